@@ -0,0 +1,75 @@
+#!/usr/bin/env perl
+# Genera src/unicode_tables.rs a partir de la base de datos Unicode de perl.
+#
+#     perl scripts/gen_unicode_tables.pl > src/unicode_tables.rs
+use strict;
+use warnings;
+use Unicode::UCD qw(prop_invlist prop_values prop_value_aliases);
+
+# Convierte una lista de inversion en rangos cerrados, sin los surrogates (no son `char`).
+sub ranges {
+    my @invlist = prop_invlist(shift);
+    push @invlist, 0x110000 if @invlist % 2;
+    my @ranges;
+    while (my ($start, $end) = splice(@invlist, 0, 2)) {
+        $end -= 1;
+        for my $piece ([$start, $end < 0xD800 ? $end : 0xD7FF], [$start > 0xDFFF ? $start : 0xE000, $end]) {
+            push @ranges, $piece if $piece->[0] <= $piece->[1];
+        }
+    }
+    return @ranges;
+}
+
+sub table {
+    my ($name, @ranges) = @_;
+    my @entries = map { sprintf("('\\u{%X}', '\\u{%X}')", @$_) } @ranges;
+    my $body = '';
+    while (my @line = splice(@entries, 0, 4)) {
+        $body .= '    ' . join(', ', @line) . ",\n";
+    }
+    return "const $name: &[(char, char)] = &[\n$body];\n";
+}
+
+my (@categories, @scripts, @tables);
+for my $short (prop_values('General_Category')) {
+    # Las categorias de una letra (y LC) se arman uniendo las de dos letras.
+    next if length($short) != 2 || $short eq 'LC';
+    my @ranges = ranges("gc=$short") or next;
+    my (undef, $long) = prop_value_aliases('gc', $short);
+    my $const = "GC_\U$short";
+    push @categories, "    (\"$short\", \"$long\", $const),\n";
+    push @tables, table($const, @ranges);
+}
+for my $short (prop_values('Script')) {
+    # Los scripts sin caracteres propios (p. ej. Hrkt) se omiten.
+    my @ranges = ranges("sc=$short") or next;
+    my (undef, $long) = prop_value_aliases('sc', $short);
+    my $const = "SC_\U$short";
+    push @scripts, "    (\"$short\", \"$long\", $const),\n";
+    push @tables, table($const, @ranges);
+}
+push @tables, table('EMOJI', ranges('Emoji'));
+
+my $version = Unicode::UCD::UnicodeVersion();
+print <<"END";
+// Generado por scripts/gen_unicode_tables.pl a partir de Unicode $version. No editar a mano.
+
+/// Version of the Unicode Character Database the tables were generated from.
+pub const UNICODE_VERSION: &str = "$version";
+
+/// A property value: its short name, its long name and its sorted ranges.
+pub type PropertyTable = (&'static str, &'static str, &'static [(char, char)]);
+
+/// Two-letter general categories.
+pub const GENERAL_CATEGORIES: &[PropertyTable] = &[
+@{[join '', @categories]}];
+
+/// Scripts.
+pub const SCRIPTS: &[PropertyTable] = &[
+@{[join '', @scripts]}];
+
+/// Characters with the `Emoji` property.
+pub const EMOJI_RANGES: &[(char, char)] = EMOJI;
+
+END
+print join("\n", @tables);
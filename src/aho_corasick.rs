@@ -0,0 +1,334 @@
+use crate::case_fold::simple_fold;
+use crate::matcher::Matcher;
+use crate::regex_match::Match;
+use crate::regex_node::RegexNode;
+use crate::regex_rep::RegexRep;
+use crate::regex_val::RegexVal;
+use std::collections::VecDeque;
+
+/// Configures and builds an `AhoCorasick` automaton.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::aho_corasick::AhoCorasickBuilder;
+/// use rgrep::matcher::Matcher;
+///
+/// let words = AhoCorasickBuilder::new()
+///     .leftmost_longest(true)
+///     .case_insensitive(true)
+///     .build(["sam", "samwise"]);
+/// assert_eq!(words.find("SAMWISE").unwrap().as_str(), "SAMWISE");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AhoCorasickBuilder {
+    leftmost_longest: bool,
+    case_insensitive: bool,
+}
+
+impl AhoCorasickBuilder {
+    pub fn new() -> Self {
+        AhoCorasickBuilder::default()
+    }
+
+    /// Among the matches that start at the leftmost position, reports the longest one instead
+    /// of the one whose pattern comes first in the list.
+    pub fn leftmost_longest(&mut self, yes: bool) -> &mut Self {
+        self.leftmost_longest = yes;
+        self
+    }
+
+    /// Ignores case, with the same simple case folding as a case-insensitive `Regex`.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Builds the automaton for a list of patterns, which are taken literally.
+    pub fn build<I, P>(&self, patterns: I) -> AhoCorasick
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let mut automaton = AhoCorasick {
+            nodes: vec![Node::default()],
+            pattern_lens: Vec::new(),
+            max_len: 0,
+            leftmost_longest: self.leftmost_longest,
+            case_insensitive: self.case_insensitive,
+        };
+        for pattern in patterns {
+            automaton.insert(pattern.as_ref());
+        }
+        automaton.link_failures();
+        automaton
+    }
+}
+
+/// Searches for any of a list of literal patterns in a single pass over the text, however many
+/// patterns there are.
+///
+/// The patterns are stored in a trie whose nodes are linked to the longest proper suffix that
+/// is also in the trie, so the search never goes back in the text. By default the reported
+/// match is leftmost-first, like an alternation of the patterns in a `Regex`: the one that
+/// starts first, and among those the one listed first. `Regex` uses this automaton by itself
+/// for patterns that are an alternation of literals, such as `cat|dog|bird`.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::aho_corasick::AhoCorasick;
+/// use rgrep::matcher::Matcher;
+///
+/// let animals = AhoCorasick::new(["cat", "dog", "bird"]);
+/// let found: Vec<_> = animals.find_iter("dog, cat").map(|m| m.as_str()).collect();
+/// assert_eq!(found, vec!["dog", "cat"]);
+/// assert_eq!(animals.find_pattern_at("a bird", 0).unwrap().0, 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AhoCorasick {
+    nodes: Vec<Node>,
+    /// Length of each pattern, in chars.
+    pattern_lens: Vec<usize>,
+    /// Length of the longest pattern, in chars.
+    max_len: usize,
+    leftmost_longest: bool,
+    case_insensitive: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// Children in the trie, sorted by char.
+    next: Vec<(char, usize)>,
+    /// Length in chars of the text this node stands for.
+    depth: usize,
+    /// The node for the longest proper suffix of this node's text that is in the trie.
+    fail: usize,
+    /// The pattern whose text is exactly this node's, the first one if it is repeated.
+    output: Option<usize>,
+    /// The nearest node along the failure links that has an output.
+    dict: Option<usize>,
+}
+
+impl Node {
+    fn child(&self, c: char) -> Option<usize> {
+        self.next
+            .binary_search_by_key(&c, |&(key, _)| key)
+            .ok()
+            .map(|i| self.next[i].1)
+    }
+}
+
+impl AhoCorasick {
+    /// Builds a leftmost-first, case-sensitive automaton; see `AhoCorasickBuilder` for the
+    /// other options.
+    pub fn new<I, P>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        AhoCorasickBuilder::new().build(patterns)
+    }
+
+    /// Builds the automaton for a pattern that is an alternation of literals, such as
+    /// `cat|dog|`, or `None` if the pattern is anything else.
+    pub(crate) fn from_alternation(node: &RegexNode) -> Option<Self> {
+        let RegexNode::Alternation(branches) = node else {
+            return None;
+        };
+        let mut patterns = Vec::with_capacity(branches.len());
+        let mut case_insensitive = None;
+        for branch in branches {
+            let steps = match branch {
+                RegexNode::Empty => &[][..],
+                RegexNode::Step(_) => std::slice::from_ref(branch),
+                RegexNode::Concat(nodes) => nodes,
+                _ => return None,
+            };
+            let mut pattern = String::new();
+            for step in steps {
+                let RegexNode::Step(step) = step else {
+                    return None;
+                };
+                let (c, folded) = match &step.val {
+                    RegexVal::Literal(c) => (*c, false),
+                    RegexVal::CaseInsensitive(val) => match **val {
+                        RegexVal::Literal(c) => (c, true),
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                // Todas las ramas tienen que coincidir en si ignoran mayusculas.
+                if step.rep != RegexRep::Exact(1)
+                    || *case_insensitive.get_or_insert(folded) != folded
+                {
+                    return None;
+                }
+                pattern.push(c);
+            }
+            patterns.push(pattern);
+        }
+        Some(
+            AhoCorasickBuilder::new()
+                .case_insensitive(case_insensitive.unwrap_or(false))
+                .build(patterns),
+        )
+    }
+
+    /// Number of patterns the automaton searches for.
+    pub fn patterns_len(&self) -> usize {
+        self.pattern_lens.len()
+    }
+
+    /// Same as `find_at`, but also returns the index of the pattern that matched.
+    pub fn find_pattern_at<'h>(
+        &self,
+        haystack: &'h str,
+        start: usize,
+    ) -> Option<(usize, Match<'h>)> {
+        if start > haystack.len() {
+            return None;
+        }
+        // Posiciones en bytes de los ultimos caracteres leidos, para ubicar el inicio de un
+        // match a partir de su largo en caracteres.
+        let mut offsets = VecDeque::with_capacity(self.max_len + 1);
+        offsets.push_back(start);
+        let mut state = 0;
+        let mut best = None;
+        self.collect(state, &offsets, &mut best);
+
+        let mut pos = start;
+        for c in haystack[start..].chars() {
+            if let Some((best_start, _, _)) = best {
+                // Ningun match posterior puede empezar antes que el mas a la izquierda.
+                if offsets[offsets.len() - 1 - self.nodes[state].depth] > best_start {
+                    break;
+                }
+            }
+            state = self.step(state, self.fold(c));
+            pos += c.len_utf8();
+            if offsets.len() > self.max_len {
+                offsets.pop_front();
+            }
+            offsets.push_back(pos);
+            self.collect(state, &offsets, &mut best);
+        }
+        best.map(|(start, end, pattern)| (pattern, Match::new(haystack, start, end)))
+    }
+
+    fn insert(&mut self, pattern: &str) {
+        let id = self.pattern_lens.len();
+        let mut node = 0;
+        let mut len = 0;
+        let chars: Vec<char> = pattern.chars().map(|c| self.fold(c)).collect();
+        for c in chars {
+            node = match self.nodes[node]
+                .next
+                .binary_search_by_key(&c, |&(key, _)| key)
+            {
+                Ok(i) => self.nodes[node].next[i].1,
+                Err(i) => {
+                    self.nodes.push(Node {
+                        depth: len + 1,
+                        ..Node::default()
+                    });
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].next.insert(i, (c, child));
+                    child
+                }
+            };
+            len += 1;
+        }
+        self.nodes[node].output.get_or_insert(id);
+        self.pattern_lens.push(len);
+        self.max_len = self.max_len.max(len);
+    }
+
+    /// Sets the failure and dictionary links, visiting the trie breadth-first so that the
+    /// links of shallower nodes are ready first.
+    fn link_failures(&mut self) {
+        let mut queue: VecDeque<usize> =
+            self.nodes[0].next.iter().map(|&(_, child)| child).collect();
+        while let Some(node) = queue.pop_front() {
+            for (c, child) in self.nodes[node].next.clone() {
+                let mut fail = self.nodes[node].fail;
+                let target = loop {
+                    match self.nodes[fail].child(c) {
+                        Some(target) => break target,
+                        None if fail == 0 => break 0,
+                        None => fail = self.nodes[fail].fail,
+                    }
+                };
+                self.nodes[child].fail = target;
+                self.nodes[child].dict = match self.nodes[target].output {
+                    Some(_) => Some(target),
+                    None => self.nodes[target].dict,
+                };
+                queue.push_back(child);
+            }
+        }
+    }
+
+    fn fold(&self, c: char) -> char {
+        if self.case_insensitive {
+            simple_fold(c)
+        } else {
+            c
+        }
+    }
+
+    /// Follows the transition for `c` from `state`, falling back along the failure links.
+    fn step(&self, mut state: usize, c: char) -> usize {
+        loop {
+            if let Some(next) = self.nodes[state].child(c) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.nodes[state].fail;
+        }
+    }
+
+    /// Compares the patterns that end at the current position with the best match so far.
+    fn collect(
+        &self,
+        state: usize,
+        offsets: &VecDeque<usize>,
+        best: &mut Option<(usize, usize, usize)>,
+    ) {
+        let end = offsets[offsets.len() - 1];
+        let mut node = match self.nodes[state].output {
+            Some(_) => Some(state),
+            None => self.nodes[state].dict,
+        };
+        while let Some(current) = node {
+            if let Some(pattern) = self.nodes[current].output {
+                let start = offsets[offsets.len() - 1 - self.pattern_lens[pattern]];
+                let better = match *best {
+                    None => true,
+                    Some((best_start, best_end, best_pattern)) => {
+                        start < best_start
+                            || (start == best_start
+                                && if self.leftmost_longest {
+                                    end > best_end || (end == best_end && pattern < best_pattern)
+                                } else {
+                                    pattern < best_pattern
+                                })
+                    }
+                };
+                if better {
+                    *best = Some((start, end, pattern));
+                }
+            }
+            node = self.nodes[current].dict;
+        }
+    }
+}
+
+impl Matcher for AhoCorasick {
+    fn find_at<'h>(&self, haystack: &'h str, start: usize) -> Option<Match<'h>> {
+        self.find_pattern_at(haystack, start)
+            .map(|(_, found)| found)
+    }
+}
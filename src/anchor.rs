@@ -0,0 +1,20 @@
+/// A zero-width assertion about the position between two characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    /// Start of the text: `\A`, or `^` outside multi-line mode.
+    Start,
+    /// End of the text: `\z`, or `$` outside multi-line mode.
+    End,
+    /// `^` in multi-line mode: start of the text or right after a `\n`.
+    LineStart,
+    /// `$` in multi-line mode: end of the text or right before a `\n`.
+    LineEnd,
+    /// `\b`: a word character on exactly one side.
+    WordBoundary,
+    /// `\B`: word characters on both sides or on neither.
+    NotWordBoundary,
+    /// `\<`: a word character after the position and none before it.
+    WordStart,
+    /// `\>`: a word character before the position and none after it.
+    WordEnd,
+}
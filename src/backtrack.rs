@@ -0,0 +1,361 @@
+use crate::case_fold::simple_fold;
+use crate::pike_vm::{chars_before, is_satisfied};
+use crate::pool::PoolGuard;
+use crate::program::{Inst, Look, Program};
+use std::time::{Duration, Instant};
+
+/// Default number of instructions a backtracking search may execute before giving up.
+pub const DEFAULT_BACKTRACK_LIMIT: usize = 10_000_000;
+
+/// Number of steps between two checks of the clock, which is much slower to read than a step.
+const STEPS_PER_CLOCK_CHECK: usize = 4096;
+
+/// The budget a backtracking search ran out of before deciding whether the pattern matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// The search executed more instructions than the step limit.
+    Steps,
+    /// The search ran for longer than the time limit.
+    Time,
+}
+
+/// Work left to do when the current path fails.
+enum Frame {
+    Explore { pc: usize, at: usize },
+    RestoreSlot { slot: usize, old: Option<usize> },
+    RestoreSplit { pc: usize, old: Option<usize> },
+}
+
+/// Runs a compiled `Program` by trying one path at a time, in priority order, and undoing the
+/// captures of a path when it fails.
+///
+/// Unlike the `PikeVm`, each path keeps its own capture positions, which is what backreferences
+/// need, but the number of paths can grow exponentially with the input. Every search therefore
+/// has a step budget, and optionally a time budget, and stops with `LimitExceeded` when it runs
+/// out of either.
+pub struct Backtracker<'a> {
+    program: &'a Program,
+    limit: usize,
+    time_limit: Option<Duration>,
+    cache: PoolGuard<'a, BacktrackerCache>,
+}
+
+/// The memory a `Backtracker` searches with, sized for one program. It is kept apart from the
+/// backtracker so that a `Regex` can hand it to the next search instead of allocating it again.
+pub(crate) struct BacktrackerCache {
+    stack: Vec<Frame>,
+    slots: Vec<Option<usize>>,
+    /// Position where the current path last went through each `Split`.
+    split_at: Vec<Option<usize>>,
+    /// The cache for the program of each `Look` instruction, indexed by its pc.
+    looks: Vec<Option<BacktrackerCache>>,
+}
+
+/// What a search has spent so far and what it may spend, shared with the lookarounds it runs.
+struct Budget {
+    steps: usize,
+    limit: usize,
+    /// When the search runs out of time, if it has a time limit.
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    /// Counts one more step, failing if that goes over the step or the time limit.
+    fn step(&mut self) -> Result<(), LimitExceeded> {
+        self.steps += 1;
+        if self.steps > self.limit {
+            return Err(LimitExceeded::Steps);
+        }
+        if self.steps.is_multiple_of(STEPS_PER_CLOCK_CHECK)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(LimitExceeded::Time);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Backtracker<'a> {
+    /// Creates a backtracker that executes at most `limit` instructions per search.
+    pub fn new(program: &'a Program, limit: usize) -> Self {
+        Backtracker::with_cache(
+            program,
+            limit,
+            PoolGuard::owned(BacktrackerCache::new(program)),
+        )
+    }
+
+    /// Creates a backtracker that searches with a cache taken from a pool, which must have
+    /// been built for `program`.
+    pub(crate) fn with_cache(
+        program: &'a Program,
+        limit: usize,
+        cache: PoolGuard<'a, BacktrackerCache>,
+    ) -> Self {
+        Backtracker {
+            program,
+            limit,
+            time_limit: None,
+            cache,
+        }
+    }
+
+    /// Makes every search give up with `LimitExceeded::Time` once it has run for `limit`.
+    pub fn time_limit(mut self, limit: Option<Duration>) -> Self {
+        self.time_limit = limit;
+        self
+    }
+
+    /// Finds the leftmost-first match starting at or after byte offset `start`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The whole line. Anchors are evaluated against it, not against `&value[start..]`.
+    /// * `start` - Byte offset where the search begins; must be a char boundary.
+    /// * `slots` - Receives the capture positions of the match; may be shorter than
+    ///   `program.slot_count()`, in which case only the first groups are reported.
+    ///
+    /// # Returns
+    ///
+    /// Whether a match was found, or `LimitExceeded` if the step or time budget ran out first.
+    pub fn search(
+        &mut self,
+        value: &str,
+        start: usize,
+        slots: &mut [Option<usize>],
+    ) -> Result<bool, LimitExceeded> {
+        let mut budget = Budget {
+            steps: 0,
+            limit: self.limit,
+            deadline: self.time_limit.map(|limit| Instant::now() + limit),
+        };
+        let cache = &mut *self.cache;
+        let mut at = start;
+        loop {
+            if self.program.anchored_start && at > 0 {
+                return Ok(false);
+            }
+            if cache.search_at(self.program, value, at, None, &[], &mut budget)? {
+                let len = slots.len().min(cache.slots.len());
+                slots[..len].copy_from_slice(&cache.slots[..len]);
+                return Ok(true);
+            }
+            match value[at..].chars().next() {
+                Some(c) => at += c.len_utf8(),
+                None => return Ok(false),
+            }
+        }
+    }
+}
+
+impl BacktrackerCache {
+    pub(crate) fn new(program: &Program) -> Self {
+        BacktrackerCache {
+            stack: Vec::new(),
+            slots: vec![None; program.slot_count()],
+            split_at: vec![None; program.len()],
+            looks: program
+                .insts
+                .iter()
+                .map(|inst| match inst {
+                    Inst::Look(look) => Some(BacktrackerCache::new(&look.program)),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Tries to match starting exactly at `start`, leaving the captures of the match in
+    /// `self.slots`.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - The program this cache was built for.
+    /// * `value` - The whole line.
+    /// * `start` - Byte offset where the match has to start.
+    /// * `end` - Byte offset where the match has to end, if any.
+    /// * `captures` - Initial capture positions, so that a lookaround can refer to the groups
+    ///   captured before it.
+    /// * `budget` - What the whole search has spent so far, including enclosing lookarounds.
+    fn search_at(
+        &mut self,
+        program: &Program,
+        value: &str,
+        start: usize,
+        end: Option<usize>,
+        captures: &[Option<usize>],
+        budget: &mut Budget,
+    ) -> Result<bool, LimitExceeded> {
+        if self.slots.len() < captures.len() {
+            self.slots.resize(captures.len(), None);
+        }
+        self.slots.fill(None);
+        self.slots[..captures.len()].copy_from_slice(captures);
+        self.split_at.fill(None);
+        self.stack.clear();
+        self.stack.push(Frame::Explore {
+            pc: program.start,
+            at: start,
+        });
+
+        while let Some(frame) = self.stack.pop() {
+            let (mut pc, mut at) = match frame {
+                Frame::Explore { pc, at } => (pc, at),
+                Frame::RestoreSlot { slot, old } => {
+                    self.slots[slot] = old;
+                    continue;
+                }
+                Frame::RestoreSplit { pc, old } => {
+                    self.split_at[pc] = old;
+                    continue;
+                }
+            };
+
+            // Se sigue el camino hasta que falla; las alternativas quedan en la pila.
+            loop {
+                budget.step()?;
+                match &program.insts[pc] {
+                    Inst::Match if end.is_some_and(|end| end != at) => break,
+                    Inst::Match => return Ok(true),
+                    Inst::Char(val) => match val.matches(&value[at..]) {
+                        0 => break,
+                        len => {
+                            at += len;
+                            pc += 1;
+                        }
+                    },
+                    Inst::Assert(anchor) => {
+                        if !is_satisfied(*anchor, at, value) {
+                            break;
+                        }
+                        pc += 1;
+                    }
+                    Inst::Save(slot) => {
+                        self.stack.push(Frame::RestoreSlot {
+                            slot: *slot,
+                            old: self.slots[*slot],
+                        });
+                        self.slots[*slot] = Some(at);
+                        pc += 1;
+                    }
+                    Inst::Jmp(target) => pc = *target,
+                    Inst::Split(first, second) => {
+                        // Volver al mismo split sin consumir nada es una vuelta vacia de un
+                        // bucle: repetirla no puede producir un match nuevo.
+                        if self.split_at[pc] == Some(at) {
+                            break;
+                        }
+                        self.stack.push(Frame::RestoreSplit {
+                            pc,
+                            old: self.split_at[pc],
+                        });
+                        self.split_at[pc] = Some(at);
+                        self.stack.push(Frame::Explore { pc: *second, at });
+                        pc = *first;
+                    }
+                    Inst::Backref {
+                        group,
+                        case_insensitive,
+                    } => match self.backref_len(*group, *case_insensitive, value, at) {
+                        Some(len) => {
+                            at += len;
+                            pc += 1;
+                        }
+                        None => break,
+                    },
+                    Inst::Look(look) => {
+                        if self.look(pc, look, value, at, budget)? == look.negate {
+                            break;
+                        }
+                        if !look.negate {
+                            self.keep_look_captures(pc);
+                        }
+                        pc += 1;
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns whether the program of a lookaround matches at `at`, ignoring its `negate` flag.
+    /// A lookbehind tries every start position within its maximum length, farthest first, so
+    /// that its groups capture the same text as with the `PikeVm`.
+    fn look(
+        &mut self,
+        pc: usize,
+        look: &Look,
+        value: &str,
+        at: usize,
+        budget: &mut Budget,
+    ) -> Result<bool, LimitExceeded> {
+        let captures = &self.slots;
+        let inner = self.looks[pc].get_or_insert_with(|| BacktrackerCache::new(&look.program));
+        if !look.behind {
+            return inner.search_at(&look.program, value, at, None, captures, budget);
+        }
+
+        let mut from = chars_before(value, at, look.max_len.unwrap_or(at));
+        loop {
+            if inner.search_at(&look.program, value, from, Some(at), captures, budget)? {
+                return Ok(true);
+            }
+            match value[from..at].chars().next() {
+                Some(c) => from += c.len_utf8(),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Copies what the groups of the lookaround at `pc` captured in its last match into the
+    /// current path, so that later backreferences and the reported captures see it. The copy
+    /// is undone when the path fails.
+    fn keep_look_captures(&mut self, pc: usize) {
+        let Some(inner) = &self.looks[pc] else {
+            return;
+        };
+        // Los slots 0 y 1 son los del match del lookaround, no los del patron.
+        for slot in 2..self.slots.len().min(inner.slots.len()) {
+            if inner.slots[slot] != self.slots[slot] {
+                self.stack.push(Frame::RestoreSlot {
+                    slot,
+                    old: self.slots[slot],
+                });
+                self.slots[slot] = inner.slots[slot];
+            }
+        }
+    }
+
+    /// Returns the length of the text at `at` that repeats what group `group` captured on the
+    /// current path, or `None` if it does not match or the group has not captured anything.
+    fn backref_len(
+        &self,
+        group: usize,
+        case_insensitive: bool,
+        value: &str,
+        at: usize,
+    ) -> Option<usize> {
+        let (start, end) = match (self.slots[2 * group], self.slots[2 * group + 1]) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => return None,
+        };
+        let captured = &value[start..end];
+        let rest = &value[at..];
+        if !case_insensitive {
+            return rest.starts_with(captured).then_some(captured.len());
+        }
+
+        let mut rest_chars = rest.chars();
+        let mut len = 0;
+        for c in captured.chars() {
+            let other = rest_chars.next()?;
+            if simple_fold(c) != simple_fold(other) {
+                return None;
+            }
+            len += other.len_utf8();
+        }
+        Some(len)
+    }
+}
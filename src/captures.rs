@@ -0,0 +1,119 @@
+use crate::engine::Engine;
+use crate::regex_error::RegexError;
+use crate::regex_match::{Match, MatchCursor};
+use crate::regex_step::Regex;
+use std::sync::Arc;
+
+/// The positions matched by every capture group of a regex in one haystack.
+///
+/// Group 0 always spans the whole match; groups that did not take part in the match (e.g. the
+/// unused branch of an alternation) have no span.
+#[derive(Debug, Clone)]
+pub struct Captures<'h> {
+    haystack: &'h str,
+    slots: Vec<Option<usize>>,
+    group_names: Arc<[Option<String>]>,
+}
+
+impl<'h> Captures<'h> {
+    pub(crate) fn new(
+        haystack: &'h str,
+        slots: Vec<Option<usize>>,
+        group_names: Arc<[Option<String>]>,
+    ) -> Self {
+        Captures {
+            haystack,
+            slots,
+            group_names,
+        }
+    }
+
+    /// Number of groups in the regex, including group 0.
+    pub fn len(&self) -> usize {
+        self.group_names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.group_names.is_empty()
+    }
+
+    /// Returns the span and text matched by group `index`.
+    pub fn get(&self, index: usize) -> Option<Match<'h>> {
+        match (self.slots.get(2 * index)?, self.slots.get(2 * index + 1)?) {
+            (Some(start), Some(end)) => Some(Match::new(self.haystack, *start, *end)),
+            _ => None,
+        }
+    }
+
+    /// Returns the span and text matched by the group called `name`.
+    pub fn name(&self, name: &str) -> Option<Match<'h>> {
+        self.get(self.index_of(name)?)
+    }
+
+    /// Returns the number of the group called `name`.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.group_names
+            .iter()
+            .position(|group_name| group_name.as_deref() == Some(name))
+    }
+}
+
+/// Iterator over the captures of the successive non-overlapping matches of a regex, created by
+/// `Regex::captures_iter`. It ends early if a backtracking search runs out of its budget.
+pub struct CaptureMatches<'r, 'h>(TryCaptureMatches<'r, 'h>);
+
+impl<'r, 'h> CaptureMatches<'r, 'h> {
+    pub(crate) fn new(regex: &'r Regex, haystack: &'h str) -> Self {
+        CaptureMatches(TryCaptureMatches::new(regex, haystack))
+    }
+}
+
+impl<'h> Iterator for CaptureMatches<'_, 'h> {
+    type Item = Captures<'h>;
+
+    fn next(&mut self) -> Option<Captures<'h>> {
+        self.0.next()?.ok()
+    }
+}
+
+/// Iterator over the captures of the successive non-overlapping matches of a regex, created by
+/// `Regex::try_captures_iter`. If a backtracking search runs out of its budget, it yields the
+/// error and then ends.
+pub struct TryCaptureMatches<'r, 'h> {
+    engine: Engine<'r>,
+    haystack: &'h str,
+    cursor: MatchCursor,
+    group_names: Arc<[Option<String>]>,
+}
+
+impl<'r, 'h> TryCaptureMatches<'r, 'h> {
+    pub(crate) fn new(regex: &'r Regex, haystack: &'h str) -> Self {
+        TryCaptureMatches {
+            engine: Engine::new(regex),
+            haystack,
+            cursor: MatchCursor::new(),
+            group_names: regex.program.group_names.clone(),
+        }
+    }
+}
+
+impl<'h> Iterator for TryCaptureMatches<'_, 'h> {
+    type Item = Result<Captures<'h>, RegexError>;
+
+    fn next(&mut self) -> Option<Result<Captures<'h>, RegexError>> {
+        let mut slots = vec![None; 2 * self.group_names.len()];
+        match self
+            .cursor
+            .advance(&mut self.engine, self.haystack, &mut slots)
+        {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(err) => return Some(Err(err.into())),
+        }
+        Some(Ok(Captures::new(
+            self.haystack,
+            slots,
+            self.group_names.clone(),
+        )))
+    }
+}
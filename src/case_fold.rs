@@ -0,0 +1,64 @@
+/// Characters whose simple case folding class has more than an uppercase and a lowercase form,
+/// listed by their folded form. The other members of each class cannot be found by converting
+/// the folded character to upper or lower case.
+const FOLD_CLASSES: &[&[char]] = &[
+    &['k', 'K', '\u{212A}'],
+    &['s', 'S', '\u{17F}'],
+    &['\u{E5}', '\u{C5}', '\u{212B}'],
+    &['\u{DF}', '\u{1E9E}'],
+    &['\u{3B2}', '\u{392}', '\u{3D0}'],
+    &['\u{3B5}', '\u{395}', '\u{3F5}'],
+    &['\u{3B8}', '\u{398}', '\u{3D1}', '\u{3F4}'],
+    &['\u{3B9}', '\u{399}', '\u{345}', '\u{1FBE}'],
+    &['\u{3BA}', '\u{39A}', '\u{3F0}'],
+    &['\u{3BC}', '\u{39C}', '\u{B5}'],
+    &['\u{3C0}', '\u{3A0}', '\u{3D6}'],
+    &['\u{3C1}', '\u{3A1}', '\u{3F1}'],
+    &['\u{3C3}', '\u{3A3}', '\u{3C2}'],
+    &['\u{3C6}', '\u{3A6}', '\u{3D5}'],
+    &['\u{3C9}', '\u{3A9}', '\u{2126}'],
+    &['\u{1E61}', '\u{1E60}', '\u{1E9B}'],
+];
+
+fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    let first = chars.next()?;
+    match chars.next() {
+        Some(_) => None,
+        None => Some(first),
+    }
+}
+
+/// Maps a character to the representative of its Unicode simple case folding class, so that
+/// two characters are equal ignoring case exactly when their folds are equal.
+///
+/// Only one-to-one mappings are used: characters whose case conversion produces several
+/// characters (e.g. `ß` → `SS`) fold to themselves, as in Unicode simple case folding.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::case_fold::simple_fold;
+/// assert_eq!(simple_fold('Ñ'), 'ñ');
+/// assert_eq!(simple_fold('ς'), 'σ');
+/// assert_eq!(simple_fold('\u{212A}'), 'k');
+/// ```
+pub fn simple_fold(c: char) -> char {
+    let upper = single(c.to_uppercase()).unwrap_or(c);
+    single(upper.to_lowercase()).unwrap_or(upper)
+}
+
+/// Returns every character that folds to the same class as `c`, including `c` itself.
+pub fn case_variants(c: char) -> Vec<char> {
+    let folded = simple_fold(c);
+    if let Some(class) = FOLD_CLASSES.iter().find(|class| class[0] == folded) {
+        return class.to_vec();
+    }
+
+    let mut variants = vec![c];
+    for variant in [folded, single(folded.to_uppercase()).unwrap_or(folded)] {
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
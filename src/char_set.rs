@@ -0,0 +1,153 @@
+use crate::case_fold::case_variants;
+use crate::class::Class;
+use crate::regex_val::RegexVal;
+
+/// The characters matched by a bracket expression: a list of ranges plus a list of classes and
+/// of negated classes, possibly negated as a whole.
+///
+/// Ranges are kept sorted and merged, so a lookup is a binary search, and membership of every
+/// ASCII character is precomputed in a bitmap.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::char_set::CharSet;
+/// use rgrep::class::Class;
+/// let set = CharSet::new(vec![('a', 'f'), ('_', '_')], vec![Class::Digit]);
+/// assert!(set.contains('c') && set.contains('7') && set.contains('_'));
+/// assert!(!set.contains('g'));
+/// assert!(set.negate().contains('g'));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharSet {
+    ranges: Vec<(char, char)>,
+    classes: Vec<Class>,
+    /// Classes whose complement belongs to the set, from `\D`, `\W` or `\S`.
+    negated_classes: Vec<Class>,
+    negated: bool,
+    /// Whether each ASCII character belongs to the ranges or classes, ignoring `negated`.
+    ascii: u128,
+}
+
+impl CharSet {
+    /// Creates the set of the characters in any of `ranges` (inclusive, in any order and
+    /// possibly overlapping) or any of `classes`.
+    pub fn new(mut ranges: Vec<(char, char)>, classes: Vec<Class>) -> Self {
+        ranges.retain(|(start, end)| start <= end);
+        ranges.sort_unstable();
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some((_, last_end)) if (*last_end as u32).saturating_add(1) >= start as u32 => {
+                    *last_end = (*last_end).max(end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+
+        let mut set = CharSet {
+            ranges: merged,
+            classes,
+            negated_classes: Vec::new(),
+            negated: false,
+            ascii: 0,
+        };
+        set.fill_ascii();
+        set
+    }
+
+    /// Adds the characters outside each of `classes` to the set, as `[\D]` or `[\W\s]` do.
+    pub fn with_negated_classes(mut self, classes: Vec<Class>) -> Self {
+        self.negated_classes = classes;
+        self.fill_ascii();
+        self
+    }
+
+    fn fill_ascii(&mut self) {
+        self.ascii = 0;
+        for byte in 0..128u8 {
+            if self.contains_unnegated(byte as char) {
+                self.ascii |= 1 << byte;
+            }
+        }
+    }
+
+    /// Returns the complement of the set, as written with a leading `^`.
+    pub fn negate(mut self) -> Self {
+        self.negated = !self.negated;
+        self
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// The sorted, non-overlapping ranges of the set, before negation.
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    /// The classes of the set, before negation.
+    pub fn classes(&self) -> &[Class] {
+        &self.classes
+    }
+
+    /// The classes whose complement belongs to the set, before negation.
+    pub fn negated_classes(&self) -> &[Class] {
+        &self.negated_classes
+    }
+
+    /// Returns whether `c` belongs to the set.
+    pub fn contains(&self, c: char) -> bool {
+        let found = if c.is_ascii() {
+            self.ascii & (1 << c as u32) != 0
+        } else {
+            self.contains_unnegated(c)
+        };
+        found != self.negated
+    }
+
+    /// Returns whether `c` belongs to the set when case is ignored: some case variant of `c`
+    /// has to be a member (for a negated set, none of them may be).
+    pub fn contains_folded(&self, c: char) -> bool {
+        let found = case_variants(c).into_iter().any(|variant| {
+            if variant.is_ascii() {
+                self.ascii & (1 << variant as u32) != 0
+            } else {
+                self.contains_unnegated(variant)
+            }
+        });
+        found != self.negated
+    }
+
+    fn contains_unnegated(&self, c: char) -> bool {
+        let in_ranges = self
+            .ranges
+            .binary_search_by(|&(start, end)| {
+                if end < c {
+                    std::cmp::Ordering::Less
+                } else if start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok();
+        in_ranges
+            || self
+                .classes
+                .iter()
+                .any(|class| RegexVal::match_class(c, class) != 0)
+            || self
+                .negated_classes
+                .iter()
+                .any(|class| RegexVal::match_class(c, class) == 0)
+    }
+}
+
+impl FromIterator<char> for CharSet {
+    /// Creates the set of the given characters.
+    fn from_iter<I: IntoIterator<Item = char>>(chars: I) -> Self {
+        CharSet::new(chars.into_iter().map(|c| (c, c)).collect(), Vec::new())
+    }
+}
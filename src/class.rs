@@ -1,10 +1,55 @@
-#[derive(Debug, Clone)]
+/// A named character class, as in `[[:alpha:]]` or `\d`.
+///
+/// Classes that POSIX defines by character properties use the Unicode version of the property,
+/// so they agree with the ASCII definitions on ASCII text and extend them to every script.
+/// `Digit`, `Xdigit` and `Punct` only match ASCII characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Class {
+    /// Letters and digits.
     Alnum,
+    /// Letters.
     Alpha,
+    /// Tab and the space separators, such as ` ` and no-break space.
+    Blank,
+    /// Control characters, such as `\n` or DEL.
+    Cntrl,
+    /// `0` to `9`.
     Digit,
+    /// Visible characters: everything but whitespace and control characters.
+    Graph,
+    /// Lowercase letters.
     Lower,
-    Upper,
-    Space,
+    /// `Graph` plus the space separators.
+    Print,
+    /// ASCII punctuation and symbols.
     Punct,
+    /// Whitespace, including line breaks.
+    Space,
+    /// Uppercase letters.
+    Upper,
+    /// Letters, digits and `_`, as matched by `\w` and `[[:word:]]`.
+    Word,
+    /// Hexadecimal digits: `0` to `9`, `a` to `f` and `A` to `F`.
+    Xdigit,
+}
+
+impl Class {
+    /// The name of the class in a `[:name:]` expression.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Class::Alnum => "alnum",
+            Class::Alpha => "alpha",
+            Class::Blank => "blank",
+            Class::Cntrl => "cntrl",
+            Class::Digit => "digit",
+            Class::Graph => "graph",
+            Class::Lower => "lower",
+            Class::Print => "print",
+            Class::Punct => "punct",
+            Class::Space => "space",
+            Class::Upper => "upper",
+            Class::Word => "word",
+            Class::Xdigit => "xdigit",
+        }
+    }
 }
@@ -0,0 +1,90 @@
+use crate::aho_corasick::AhoCorasick;
+use crate::backtrack::{Backtracker, BacktrackerCache, LimitExceeded};
+use crate::literals::RequiredLiterals;
+use crate::matcher::Matcher;
+use crate::pike_vm::{PikeVm, PikeVmCache};
+use crate::regex_step::Regex;
+
+/// Which engine runs a regex, chosen with `RegexBuilder::engine`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EngineKind {
+    /// The automaton engines when they support the pattern, otherwise the backtracker.
+    #[default]
+    Auto,
+    /// The lazy DFA and the Pike VM, which never backtrack. Patterns with backreferences or
+    /// unbounded lookbehind are rejected when compiled.
+    Automaton,
+    /// The backtracker, which supports every construct but needs a step limit.
+    Backtracking,
+}
+
+/// The engine that reports match positions for a regex.
+///
+/// The `PikeVm` runs in linear time and is used whenever it can; patterns with backreferences
+/// or unbounded lookbehind, or built with `EngineKind::Backtracking`, run on the `Backtracker`,
+/// and alternations of literals on an `AhoCorasick` automaton. Before any of them runs, the
+/// required literals of the regex skip the text that cannot match.
+pub(crate) struct Engine<'r> {
+    literals: &'r RequiredLiterals,
+    runner: Runner<'r>,
+}
+
+enum Runner<'r> {
+    PikeVm(PikeVm<'r>),
+    Backtracker(Backtracker<'r>),
+    AhoCorasick(&'r AhoCorasick),
+}
+
+impl<'r> Engine<'r> {
+    /// Takes the scratch memory of the engine from the pools of `regex`; it goes back to them
+    /// when the engine is dropped.
+    pub(crate) fn new(regex: &'r Regex) -> Self {
+        let program = &regex.program;
+        let runner = match &regex.aho_corasick {
+            _ if regex.backtrack => {
+                let cache = regex.backtrackers.get(|| BacktrackerCache::new(program));
+                Runner::Backtracker(
+                    Backtracker::with_cache(program, regex.backtrack_limit, cache)
+                        .time_limit(regex.time_limit),
+                )
+            }
+            Some(aho_corasick) => Runner::AhoCorasick(aho_corasick),
+            None => {
+                let cache = regex.pike_vms.get(|| PikeVmCache::new(program));
+                Runner::PikeVm(PikeVm::with_cache(program, cache))
+            }
+        };
+        Engine {
+            literals: &regex.literals,
+            runner,
+        }
+    }
+
+    /// Finds the leftmost-first match starting at or after byte offset `start`, with the same
+    /// arguments as `PikeVm::search`.
+    pub(crate) fn search(
+        &mut self,
+        value: &str,
+        start: usize,
+        earliest: bool,
+        slots: &mut [Option<usize>],
+    ) -> Result<bool, LimitExceeded> {
+        let Some(start) = self.literals.candidate(value, start) else {
+            return Ok(false);
+        };
+        match &mut self.runner {
+            Runner::PikeVm(vm) => Ok(vm.search(value, start, earliest, slots)),
+            Runner::Backtracker(backtracker) => backtracker.search(value, start, slots),
+            Runner::AhoCorasick(aho_corasick) => {
+                let Some(found) = aho_corasick.find_at(value, start) else {
+                    return Ok(false);
+                };
+                if let [match_start, match_end, ..] = slots {
+                    *match_start = Some(found.start());
+                    *match_end = Some(found.end());
+                }
+                Ok(true)
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::case_fold::simple_fold;
+use crate::matcher::Matcher;
+use crate::regex_match::Match;
+
+/// Searches for a fixed string, like `grep -F`: every character of the needle is taken
+/// literally, so dots, brackets and backslashes need no escaping.
+///
+/// Case-sensitive searches use the Boyer-Moore-Horspool algorithm on the UTF-8 bytes, which
+/// skips ahead by up to the length of the needle after each mismatch.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::fixed_string::FixedString;
+/// use rgrep::matcher::Matcher;
+///
+/// let needle = FixedString::new("192.168.[0]");
+/// assert_eq!(needle.find("ip 192.168.[0]").unwrap().start(), 3);
+/// assert!(!needle.is_match("ip 192.168.10"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedString {
+    needle: String,
+    /// The folded characters of the needle when the search ignores case.
+    folded: Option<Vec<char>>,
+    /// How far the window moves when its last byte is each byte value.
+    shift: [usize; 256],
+}
+
+impl FixedString {
+    pub fn new(needle: &str) -> Self {
+        let bytes = needle.as_bytes();
+        let mut shift = [bytes.len().max(1); 256];
+        for (i, &byte) in bytes.iter().enumerate().take(bytes.len().saturating_sub(1)) {
+            shift[byte as usize] = bytes.len() - 1 - i;
+        }
+        FixedString {
+            needle: needle.to_string(),
+            folded: None,
+            shift,
+        }
+    }
+
+    /// Ignores case, with the same simple case folding as a case-insensitive `Regex`.
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.folded = yes.then(|| self.needle.chars().map(simple_fold).collect());
+        self
+    }
+
+    /// The string being searched for.
+    pub fn as_str(&self) -> &str {
+        &self.needle
+    }
+
+    /// Finds the first occurrence of the needle's bytes at or after `start`.
+    fn find_bytes(&self, haystack: &[u8], start: usize) -> Option<(usize, usize)> {
+        let needle = self.needle.as_bytes();
+        let Some(last) = needle.len().checked_sub(1) else {
+            return Some((start, start));
+        };
+        let mut at = start;
+        while at + needle.len() <= haystack.len() {
+            let window = &haystack[at..at + needle.len()];
+            if window[last] == needle[last] && window == needle {
+                return Some((at, at + needle.len()));
+            }
+            at += self.shift[window[last] as usize];
+        }
+        None
+    }
+
+    /// Finds the first char boundary at or after `start` where the haystack matches the
+    /// folded needle, comparing char by char.
+    fn find_folded(&self, haystack: &str, start: usize, folded: &[char]) -> Option<(usize, usize)> {
+        let candidates = haystack[start..]
+            .char_indices()
+            .map(|(i, _)| start + i)
+            .chain(std::iter::once(haystack.len()));
+        for at in candidates {
+            let mut chars = haystack[at..].chars();
+            let mut end = at;
+            let matches = folded.iter().all(|&f| match chars.next() {
+                Some(c) if simple_fold(c) == f => {
+                    end += c.len_utf8();
+                    true
+                }
+                _ => false,
+            });
+            if matches {
+                return Some((at, end));
+            }
+        }
+        None
+    }
+}
+
+impl Matcher for FixedString {
+    fn find_at<'h>(&self, haystack: &'h str, start: usize) -> Option<Match<'h>> {
+        if start > haystack.len() {
+            return None;
+        }
+        // Como la aguja es UTF-8 valido, un match de bytes siempre cae en limites de caracter.
+        let (start, end) = match &self.folded {
+            Some(folded) => self.find_folded(haystack, start, folded)?,
+            None => self.find_bytes(haystack.as_bytes(), start)?,
+        };
+        Some(Match::new(haystack, start, end))
+    }
+}
@@ -0,0 +1,9 @@
+/// Matching options that can be set for a whole pattern from `RegexBuilder` or for part of it
+/// with an inline `(?flags)` / `(?flags:...)` group.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Flags {
+    /// `i`: compare letters ignoring case, under Unicode simple case folding.
+    pub case_insensitive: bool,
+    /// `m`: `^` and `$` also match right after and right before a `\n`.
+    pub multi_line: bool,
+}
@@ -0,0 +1,304 @@
+use crate::anchor::Anchor;
+use crate::program::{Inst, Program};
+use std::collections::HashMap;
+use std::mem::size_of;
+
+/// Default memory budget for the cached states and transitions, in bytes.
+pub const DEFAULT_CACHE_CAPACITY: usize = 2 * (1 << 20);
+
+/// Number of cache clears tolerated within a single search before giving up on the DFA.
+const MAX_CLEARS_PER_SEARCH: usize = 3;
+
+type StateId = u32;
+
+const UNKNOWN: StateId = StateId::MAX;
+
+/// Counters describing how the lazy DFA cache has been used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DfaStats {
+    /// Number of DFA states built, across every cache clear.
+    pub states_built: usize,
+    /// Number of times the cache exceeded its budget and was emptied.
+    pub cache_clears: usize,
+    /// Number of searches handed back to the NFA because the cache kept filling up.
+    pub nfa_fallbacks: usize,
+}
+
+/// A DFA state: the set of NFA instructions that are alive after following every `Jmp`/`Split`.
+struct State {
+    insts: Box<[usize]>,
+    is_match: bool,
+    ascii: Box<[StateId; 128]>,
+    other: HashMap<char, StateId>,
+}
+
+/// A DFA built on demand from a compiled `Program`.
+///
+/// States are created the first time the search reaches them and their transitions are cached,
+/// so a line is scanned doing a table lookup per character once the cache is warm. When the cache
+/// grows beyond its memory budget it is cleared; if that happens too often within one search the
+/// DFA gives up and the caller should fall back to the NFA simulation.
+pub struct LazyDfa {
+    states: Vec<State>,
+    ids: HashMap<Box<[usize]>, StateId>,
+    start: StateId,
+    capacity: usize,
+    memory_usage: usize,
+    stats: DfaStats,
+    stack: Vec<usize>,
+    seen: Vec<bool>,
+}
+
+impl LazyDfa {
+    /// Creates an empty cache that may use up to `capacity` bytes.
+    pub fn new(capacity: usize) -> Self {
+        LazyDfa {
+            states: Vec::new(),
+            ids: HashMap::new(),
+            start: UNKNOWN,
+            capacity,
+            memory_usage: 0,
+            stats: DfaStats::default(),
+            stack: Vec::new(),
+            seen: Vec::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the memory budget, dropping every cached state.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.reset();
+    }
+
+    pub fn stats(&self) -> &DfaStats {
+        &self.stats
+    }
+
+    /// Returns whether the program matches anywhere in `value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - The compiled program. It must be the same one on every call, since the
+    ///   cached states refer to its instructions.
+    /// * `value` - The line to search.
+    ///
+    /// # Returns
+    ///
+    /// `Some(result)` with the answer, or `None` if the memory budget was exceeded too many times
+    /// and the search must be repeated with the NFA.
+    pub fn is_match(&mut self, program: &Program, value: &str) -> Option<bool> {
+        let mut clears = 0;
+        if self.start == UNKNOWN {
+            self.start = self.start_state(program)?;
+        }
+
+        let mut current = self.start;
+        let mut chars = value.chars();
+        loop {
+            let state = &self.states[current as usize];
+            if state.is_match {
+                return Some(true);
+            }
+            if state.insts.is_empty() {
+                return Some(false);
+            }
+            let c = match chars.next() {
+                Some(c) => c,
+                None => {
+                    let at_start = value.is_empty();
+                    return Some(self.is_match_at_end(program, current, at_start));
+                }
+            };
+
+            let cached = if c.is_ascii() {
+                state.ascii[c as usize]
+            } else {
+                state.other.get(&c).copied().unwrap_or(UNKNOWN)
+            };
+            current = if cached != UNKNOWN {
+                cached
+            } else {
+                match self.transition(program, current, c) {
+                    Some(next) => next,
+                    None => {
+                        // El cache se lleno: se vacia y se reconstruye el estado actual.
+                        clears += 1;
+                        let insts = self.states[current as usize].insts.clone();
+                        self.clear();
+                        let next = if clears > MAX_CLEARS_PER_SEARCH {
+                            None
+                        } else {
+                            self.add_state(program, insts)
+                                .and_then(|current| self.transition(program, current, c))
+                        };
+                        match next {
+                            Some(next) => next,
+                            None => {
+                                self.stats.nfa_fallbacks += 1;
+                                return None;
+                            }
+                        }
+                    }
+                }
+            };
+        }
+    }
+
+    fn start_state(&mut self, program: &Program) -> Option<StateId> {
+        let mut insts = Vec::new();
+        self.closure(program, &[program.start], true, false, &mut insts);
+        insts.sort_unstable();
+        match self.add_state(program, insts.into_boxed_slice()) {
+            Some(id) => Some(id),
+            None => {
+                self.stats.nfa_fallbacks += 1;
+                None
+            }
+        }
+    }
+
+    /// Computes, caches and returns the state reached from `from` after reading `c`.
+    /// Returns `None` if the new state does not fit in the cache.
+    fn transition(&mut self, program: &Program, from: StateId, c: char) -> Option<StateId> {
+        let mut buf = [0; 4];
+        let encoded: &str = c.encode_utf8(&mut buf);
+
+        let mut targets = Vec::new();
+        for &pc in self.states[from as usize].insts.iter() {
+            if let Inst::Char(val) = &program.insts[pc] {
+                if val.matches(encoded) != 0 {
+                    targets.push(pc + 1);
+                }
+            }
+        }
+        if !program.anchored_start {
+            targets.push(program.start);
+        }
+
+        let mut insts = Vec::new();
+        self.closure(program, &targets, false, false, &mut insts);
+        insts.sort_unstable();
+        let to = self.add_state(program, insts.into_boxed_slice())?;
+
+        let entry_size = if c.is_ascii() {
+            0
+        } else {
+            size_of::<char>() + size_of::<StateId>()
+        };
+        if self.memory_usage + entry_size > self.capacity {
+            return None;
+        }
+        self.memory_usage += entry_size;
+
+        let state = &mut self.states[from as usize];
+        if c.is_ascii() {
+            state.ascii[c as usize] = to;
+        } else {
+            state.other.insert(c, to);
+        }
+        Some(to)
+    }
+
+    /// Returns whether the state reaches `Match` once its pending end assertions are resolved
+    /// at the end of the input.
+    fn is_match_at_end(&mut self, program: &Program, state: StateId, at_start: bool) -> bool {
+        let pending = self.states[state as usize].insts.clone();
+        let mut insts = Vec::new();
+        self.closure(program, &pending, at_start, true, &mut insts);
+        insts
+            .iter()
+            .any(|&pc| matches!(program.insts[pc], Inst::Match))
+    }
+
+    /// Collects the instructions reachable from `pcs` through `Jmp`, `Split` and the assertions
+    /// that hold, keeping only the ones that consume input or report a match. An end assertion
+    /// that cannot be decided yet is kept in the set until the next character (or the end of
+    /// the input) is seen.
+    fn closure(
+        &mut self,
+        program: &Program,
+        pcs: &[usize],
+        at_start: bool,
+        at_end: bool,
+        insts: &mut Vec<usize>,
+    ) {
+        self.seen.clear();
+        self.seen.resize(program.len(), false);
+        self.stack.extend(pcs.iter().rev());
+        while let Some(pc) = self.stack.pop() {
+            if self.seen[pc] {
+                continue;
+            }
+            self.seen[pc] = true;
+            match program.insts[pc] {
+                Inst::Jmp(target) => self.stack.push(target),
+                Inst::Save(_) => self.stack.push(pc + 1),
+                Inst::Split(first, second) => {
+                    self.stack.push(second);
+                    self.stack.push(first);
+                }
+                Inst::Assert(Anchor::Start) => {
+                    if at_start {
+                        self.stack.push(pc + 1);
+                    }
+                }
+                Inst::Assert(Anchor::End) => {
+                    if at_end {
+                        self.stack.push(pc + 1);
+                    } else {
+                        insts.push(pc);
+                    }
+                }
+                // Las demas aserciones dependen de los caracteres vecinos; los programas que
+                // las usan no pasan por el DFA.
+                Inst::Assert(_) => {}
+                Inst::Char(_) | Inst::Match => insts.push(pc),
+                // Los programas con referencias hacia atras o lookaround no usan el DFA.
+                Inst::Backref { .. } | Inst::Look(_) => {}
+            }
+        }
+    }
+
+    fn add_state(&mut self, program: &Program, insts: Box<[usize]>) -> Option<StateId> {
+        if let Some(&id) = self.ids.get(&insts) {
+            return Some(id);
+        }
+
+        let size =
+            size_of::<State>() + size_of::<[StateId; 128]>() + 2 * insts.len() * size_of::<usize>();
+        if self.memory_usage + size > self.capacity {
+            return None;
+        }
+        self.memory_usage += size;
+
+        let id = self.states.len() as StateId;
+        let is_match = insts
+            .iter()
+            .any(|&pc| matches!(program.insts[pc], Inst::Match));
+        self.ids.insert(insts.clone(), id);
+        self.states.push(State {
+            insts,
+            is_match,
+            ascii: Box::new([UNKNOWN; 128]),
+            other: HashMap::new(),
+        });
+        self.stats.states_built += 1;
+        Some(id)
+    }
+
+    fn clear(&mut self) {
+        self.reset();
+        self.stats.cache_clears += 1;
+    }
+
+    fn reset(&mut self) {
+        self.states.clear();
+        self.ids.clear();
+        self.start = UNKNOWN;
+        self.memory_usage = 0;
+    }
+}
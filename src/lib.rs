@@ -1,7 +1,32 @@
+pub mod aho_corasick;
+pub mod anchor;
+pub mod backtrack;
+pub mod captures;
+pub mod case_fold;
+pub mod char_set;
 pub mod class;
-pub mod evaluated_step;
+pub mod engine;
+pub mod fixed_string;
+pub mod flags;
+pub mod lazy_dfa;
+pub mod literals;
+pub mod matcher;
+pub mod parser;
+pub mod pike_vm;
+mod pool;
+pub mod program;
 pub mod regex;
+pub mod regex_builder;
+pub mod regex_error;
+pub mod regex_match;
+pub mod regex_node;
 pub mod regex_rep;
+pub mod regex_set;
 pub mod regex_step;
 pub mod regex_val;
+pub mod replace;
+pub mod syntax;
+pub mod unicode;
+#[rustfmt::skip]
+pub mod unicode_tables;
 pub mod utils;
@@ -0,0 +1,142 @@
+use crate::fixed_string::FixedString;
+use crate::matcher::Matcher;
+use crate::regex_node::RegexNode;
+use crate::regex_rep::RegexRep;
+use crate::regex_val::RegexVal;
+
+/// Longest run of literal characters extracted from a pattern, so that a pattern such as
+/// `a{100000}` does not produce a huge literal.
+const MAX_LITERAL_LEN: usize = 256;
+
+/// The literals that every match of a pattern contains, found when the pattern is compiled.
+///
+/// Before running an engine, searches look for `required` with a fast substring search and
+/// give up when it is missing, and jump straight to the next occurrence of `prefix`, since no
+/// match can start anywhere else. Only case-sensitive literals are extracted.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::regex_step::Regex;
+///
+/// let regex = Regex::new("ERROR: .*timeout").unwrap();
+/// let literals = regex.required_literals();
+/// assert_eq!(literals.prefix(), Some("ERROR: "));
+/// assert_eq!(literals.suffix(), Some("timeout"));
+/// assert_eq!(literals.required(), Some("ERROR: "));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequiredLiterals {
+    prefix: Option<FixedString>,
+    suffix: Option<String>,
+    required: Option<FixedString>,
+}
+
+impl RequiredLiterals {
+    /// Extracts the literals of a parsed pattern.
+    pub fn new(node: &RegexNode) -> Self {
+        let mut pieces = Vec::new();
+        collect_pieces(node, &mut pieces);
+
+        // Cada tramo de caracteres entre dos cortes aparece tal cual en todo match.
+        let runs: Vec<String> = pieces
+            .split(Option::is_none)
+            .map(|run| run.iter().flatten().collect())
+            .collect();
+        let prefix = runs.first().filter(|run| !run.is_empty());
+        let suffix = runs.last().filter(|run| !run.is_empty());
+        let required = runs
+            .iter()
+            .reduce(|longest, run| {
+                if run.len() > longest.len() {
+                    run
+                } else {
+                    longest
+                }
+            })
+            .filter(|run| !run.is_empty());
+        RequiredLiterals {
+            prefix: prefix.map(|run| FixedString::new(run)),
+            suffix: suffix.cloned(),
+            required: required.map(|run| FixedString::new(run)),
+        }
+    }
+
+    /// The literal every match starts with, if any.
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_ref().map(FixedString::as_str)
+    }
+
+    /// The literal every match ends with, if any.
+    pub fn suffix(&self) -> Option<&str> {
+        self.suffix.as_deref()
+    }
+
+    /// The longest literal every match contains, which is the one scanned for before a search.
+    pub fn required(&self) -> Option<&str> {
+        self.required.as_ref().map(FixedString::as_str)
+    }
+
+    /// Finds where an engine should start looking for a match at or after byte offset `start`.
+    ///
+    /// # Returns
+    ///
+    /// The first offset where a match may start, or `None` if `haystack` cannot contain a
+    /// match starting at or after `start`.
+    pub fn candidate(&self, haystack: &str, start: usize) -> Option<usize> {
+        if let Some(required) = &self.required {
+            if self.prefix() != Some(required.as_str()) {
+                required.find_at(haystack, start)?;
+            }
+        }
+        match &self.prefix {
+            Some(prefix) => prefix.find_at(haystack, start).map(|found| found.start()),
+            None => Some(start),
+        }
+    }
+}
+
+/// Appends what a node matches, as a sequence of literal characters and `None` breaks where
+/// the text is not known.
+fn collect_pieces(node: &RegexNode, pieces: &mut Vec<Option<char>>) {
+    match node {
+        // No consumen texto: lo que va antes y despues queda pegado en el match.
+        RegexNode::Empty | RegexNode::Anchor(_) | RegexNode::Look { .. } => {}
+        RegexNode::Step(step) => match step.val {
+            RegexVal::Literal(c) => repeat_pieces(&[Some(c)], &step.rep, pieces),
+            _ => pieces.push(None),
+        },
+        RegexNode::Concat(nodes) => {
+            for node in nodes {
+                collect_pieces(node, pieces);
+            }
+        }
+        RegexNode::Group { node, .. } => collect_pieces(node, pieces),
+        RegexNode::Repetition { node, rep } => {
+            let mut inner = Vec::new();
+            collect_pieces(node, &mut inner);
+            repeat_pieces(&inner, rep, pieces);
+        }
+        RegexNode::Alternation(_) | RegexNode::Backreference { .. } => pieces.push(None),
+    }
+}
+
+/// Appends the pieces of a repeated node: its minimum number of copies, followed by a break
+/// if it may repeat more times.
+fn repeat_pieces(inner: &[Option<char>], rep: &RegexRep, pieces: &mut Vec<Option<char>>) {
+    let (min, max) = match *rep {
+        RegexRep::Any { .. } => (0, None),
+        RegexRep::Exact(n) => (n, Some(n)),
+        RegexRep::Range { min, max, .. } => (min.unwrap_or(0), max),
+    };
+    for copy in 0..min {
+        if copy * inner.len() > MAX_LITERAL_LEN {
+            pieces.push(None);
+            return;
+        }
+        pieces.extend_from_slice(inner);
+    }
+    if max != Some(min) && !inner.is_empty() {
+        pieces.push(None);
+    }
+}
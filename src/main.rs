@@ -1,51 +1,298 @@
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
+use std::process;
+use std::time::Duration;
 
-use rgrep::regex_step::Regex;
+use rgrep::aho_corasick::AhoCorasickBuilder;
+use rgrep::backtrack::DEFAULT_BACKTRACK_LIMIT;
+use rgrep::engine::EngineKind;
+use rgrep::fixed_string::FixedString;
+use rgrep::lazy_dfa::DEFAULT_CACHE_CAPACITY;
+use rgrep::matcher::Matcher;
+use rgrep::program::DEFAULT_SIZE_LIMIT;
+use rgrep::regex_builder::RegexBuilder;
+use rgrep::syntax::{Dialect, DEFAULT_NEST_LIMIT};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 3 {
-        println!("Uso: {} <regex> <filepath>", args[0]);
+    let mut dfa_cache_size = DEFAULT_CACHE_CAPACITY;
+    let mut dfa_stats = false;
+    let mut backtrack_limit = DEFAULT_BACKTRACK_LIMIT;
+    let mut time_limit: Option<Duration> = None;
+    let mut size_limit = DEFAULT_SIZE_LIMIT;
+    let mut nest_limit = DEFAULT_NEST_LIMIT;
+    let mut debug_literals = false;
+    let mut group: Option<String> = None;
+    let mut only_matching = false;
+    let mut case_insensitive = false;
+    let mut dialect = Dialect::Extended;
+    let mut fixed_strings = false;
+    let mut pattern_file: Option<String> = None;
+    let mut engine = EngineKind::Auto;
+    let mut positional = Vec::new();
+    let mut arg_iter = args.iter().skip(1);
+    while let Some(arg) = arg_iter.next() {
+        match arg.as_str() {
+            "--dfa-cache-size" => match arg_iter.next().map(|size| size.parse::<usize>()) {
+                Some(Ok(size)) => dfa_cache_size = size,
+                _ => {
+                    eprintln!("--dfa-cache-size espera una cantidad de bytes");
+                    process::exit(2);
+                }
+            },
+            "--dfa-stats" => dfa_stats = true,
+            "--backtrack-limit" => match arg_iter.next().map(|steps| steps.parse::<usize>()) {
+                Some(Ok(steps)) => backtrack_limit = steps,
+                _ => {
+                    eprintln!("--backtrack-limit espera una cantidad de pasos");
+                    process::exit(2);
+                }
+            },
+            "--time-limit" => match arg_iter.next().map(|millis| millis.parse::<u64>()) {
+                Some(Ok(millis)) => time_limit = Some(Duration::from_millis(millis)),
+                _ => {
+                    eprintln!("--time-limit espera una cantidad de milisegundos");
+                    process::exit(2);
+                }
+            },
+            "--size-limit" => match arg_iter.next().map(|bytes| bytes.parse::<usize>()) {
+                Some(Ok(bytes)) => size_limit = bytes,
+                _ => {
+                    eprintln!("--size-limit espera una cantidad de bytes");
+                    process::exit(2);
+                }
+            },
+            "--nest-limit" => match arg_iter.next().map(|levels| levels.parse::<usize>()) {
+                Some(Ok(levels)) => nest_limit = levels,
+                _ => {
+                    eprintln!("--nest-limit espera una cantidad de niveles");
+                    process::exit(2);
+                }
+            },
+            "--debug-literals" => debug_literals = true,
+            "-o" => only_matching = true,
+            "-i" => case_insensitive = true,
+            "-G" => dialect = Dialect::Basic,
+            "-E" => dialect = Dialect::Extended,
+            "-F" => fixed_strings = true,
+            "-f" => match arg_iter.next() {
+                Some(path) => pattern_file = Some(path.clone()),
+                None => {
+                    eprintln!("-f espera un archivo de patrones");
+                    process::exit(2);
+                }
+            },
+            "--engine" => match arg_iter.next().map(String::as_str) {
+                Some("auto") => engine = EngineKind::Auto,
+                Some("automaton") => engine = EngineKind::Automaton,
+                Some("backtracking") => engine = EngineKind::Backtracking,
+                _ => {
+                    eprintln!("--engine espera auto, automaton o backtracking");
+                    process::exit(2);
+                }
+            },
+            "--group" => match arg_iter.next() {
+                Some(name) => group = Some(name.clone()),
+                None => {
+                    eprintln!("--group espera un numero o nombre de grupo");
+                    process::exit(2);
+                }
+            },
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() < if pattern_file.is_some() { 1 } else { 2 } {
+        eprintln!(
+            "Uso: {} [--dfa-cache-size <bytes>] [--dfa-stats] [--backtrack-limit <pasos>] [--time-limit <ms>] [--size-limit <bytes>] [--nest-limit <niveles>] [--debug-literals] [--engine <auto|automaton|backtracking>] [--group <n|nombre>] [-G|-E|-F] [-o] [-i] (<regex> | -f <patrones>) <filepath>",
+            args[0]
+        );
+        process::exit(2);
+    }
+
+    // Con -f cada linea del archivo es un patron, y una linea coincide si coincide alguno.
+    let (patterns, file_path) = match &pattern_file {
+        Some(path) => match read_lines(path) {
+            Some(patterns) => (patterns, positional[0]),
+            None => {
+                eprintln!("No se pudo leer el archivo de patrones {}", path);
+                process::exit(2);
+            }
+        },
+        None => (vec![positional[0].clone()], positional[1]),
+    };
+    if patterns.is_empty() {
         return;
     }
 
-    let regex_str = &args[1];
-    let file_path = &args[2];
+    if fixed_strings {
+        if group.is_some() {
+            eprintln!("--group no se puede usar con -F");
+            process::exit(2);
+        }
+        // Con -F los patrones se buscan tal cual, sin pasar por el parser de regex.
+        if let [pattern] = patterns.as_slice() {
+            let needle = FixedString::new(pattern).case_insensitive(case_insensitive);
+            search_lines(&needle, file_path, only_matching);
+        } else {
+            // Como grep -F, entre los matches que empiezan en el mismo lugar gana el mas largo.
+            let words = AhoCorasickBuilder::new()
+                .leftmost_longest(true)
+                .case_insensitive(case_insensitive)
+                .build(&patterns);
+            search_lines(&words, file_path, only_matching);
+        }
+        return;
+    }
+
+    // Cada patron se parsea por separado; si todos son literales, `Regex` los busca con
+    // Aho-Corasick.
+    let mut builder = RegexBuilder::new_many(&patterns);
+    builder
+        .case_insensitive(case_insensitive)
+        .dialect(dialect)
+        .dfa_cache_capacity(dfa_cache_size)
+        .backtrack_limit(backtrack_limit)
+        .size_limit(size_limit)
+        .nest_limit(nest_limit)
+        .engine(engine);
+    if let Some(limit) = time_limit {
+        builder.time_limit(limit);
+    }
+    let regex = match builder.build() {
+        Ok(regex) => regex,
+        Err(err) => {
+            // Los errores de un patron de -f indican la linea del archivo en la que esta.
+            let line = pattern_file.as_ref().zip(
+                patterns
+                    .iter()
+                    .position(|pattern| Some(pattern.as_str()) == err.pattern()),
+            );
+            match line {
+                Some((path, index)) => eprintln!(
+                    "Error creating regex pattern at {}:{}: {}",
+                    path,
+                    index + 1,
+                    err
+                ),
+                None => eprintln!("Error creating regex pattern: {}", err),
+            }
+            process::exit(2);
+        }
+    };
+
+    if debug_literals {
+        let literals = regex.required_literals();
+        eprintln!(
+            "literals: prefix {:?}, suffix {:?}, required {:?}",
+            literals.prefix(),
+            literals.suffix(),
+            literals.required()
+        );
+    }
 
-    let file = match File::open(file_path) {
-        Ok(file) => file,
-        Err(_) => return,
+    let Some(lines) = read_lines(file_path) else {
+        return;
     };
-    let reader = io::BufReader::new(file);
 
-    let mut lines = Vec::new();
-    for line_result in reader.lines() {
-        let line = match line_result {
-            Ok(line) => line,
-            Err(_) => return,
+    if let Some(group) = &group {
+        let exists = match group.parse::<usize>() {
+            Ok(index) => index < regex.captures_len(),
+            Err(_) => regex.capture_names().any(|name| name == Some(group)),
         };
-        lines.push(line);
+        if !exists {
+            eprintln!("El grupo {} no existe en el patron", group);
+            process::exit(2);
+        }
     }
 
     for line in lines {
-        let pattern = match Regex::new(regex_str) {
-            Ok(regex) => regex,
-            Err(err) => {
-                println!("Error creating regex pattern: {}", err);
-                continue;
+        if let Some(group) = &group {
+            let caps = match regex.try_captures(&line) {
+                Ok(caps) => caps,
+                Err(err) => {
+                    eprintln!("Error applying the regular expression pattern: {}", err);
+                    continue;
+                }
+            };
+            if let Some(text) = caps.and_then(|caps| match group.parse() {
+                Ok(index) => caps.get(index),
+                Err(_) => caps.name(group),
+            }) {
+                println!("{}", text.as_str());
             }
-        };
-        for mut regex in pattern {
-            match regex.test(&line) {
-                Ok(result) => {
-                    if !result.is_empty() {
-                        println!("{}", result)
-                    }
-                }
-                Err(err) => println!("Error applying the regular expression pattern: {}", err),
+            continue;
+        }
+        if only_matching {
+            print_matches(&regex, &line);
+            continue;
+        }
+        match regex.test(&line) {
+            Ok(result) => {
+                if !result.is_empty() {
+                    println!("{}", result)
+                }
+            }
+            Err(err) => eprintln!("Error applying the regular expression pattern: {}", err),
+        }
+    }
+
+    if dfa_stats {
+        let stats = regex.dfa_stats();
+        eprintln!(
+            "dfa: {} states built, {} cache clears, {} nfa fallbacks",
+            stats.states_built, stats.cache_clears, stats.nfa_fallbacks
+        );
+    }
+}
+
+/// Reads the lines of a file, without their line terminators.
+///
+/// The lines are read as bytes so that an invalid UTF-8 sequence does not stop the reading;
+/// those bytes are replaced by U+FFFD. Returns `None` if the file cannot be read.
+fn read_lines(file_path: &str) -> Option<Vec<String>> {
+    let reader = io::BufReader::new(File::open(file_path).ok()?);
+    let mut lines = Vec::new();
+    for line_result in reader.split(b'\n') {
+        let mut line = line_result.ok()?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        lines.push(String::from_utf8_lossy(&line).into_owned());
+    }
+    Some(lines)
+}
+
+/// Prints the lines of a file that contain a match, or only the matches with `-o`.
+fn search_lines(matcher: &impl Matcher, file_path: &str, only_matching: bool) {
+    let Some(lines) = read_lines(file_path) else {
+        return;
+    };
+    for line in lines {
+        if only_matching {
+            print_matches(matcher, &line);
+        } else {
+            match matcher.try_is_match(&line) {
+                Ok(true) => println!("{}", line),
+                Ok(false) => {}
+                Err(err) => eprintln!("Error applying the regular expression pattern: {}", err),
+            }
+        }
+    }
+}
+
+/// Prints every non-empty match in a line on its own line, as `-o` does. A search that cannot
+/// finish is reported and ends the line.
+fn print_matches(matcher: &impl Matcher, line: &str) {
+    for found in matcher.try_find_iter(line) {
+        match found {
+            Ok(found) if found.is_empty() => {}
+            Ok(found) => println!("{}", found.as_str()),
+            Err(err) => {
+                eprintln!("Error applying the regular expression pattern: {}", err);
+                return;
             }
         }
     }
@@ -0,0 +1,137 @@
+use crate::engine::Engine;
+use crate::regex_error::RegexError;
+use crate::regex_match::{Match, MatchCursor};
+use crate::regex_step::Regex;
+
+/// The searching interface shared by `Regex` and the other matchers, so callers such as the
+/// command line can search with any of them.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::fixed_string::FixedString;
+/// use rgrep::matcher::Matcher;
+/// use rgrep::regex_step::Regex;
+///
+/// fn count(matcher: &impl Matcher, haystack: &str) -> usize {
+///     matcher.find_iter(haystack).count()
+/// }
+///
+/// assert_eq!(count(&FixedString::new("1.1"), "1.1 121"), 1);
+/// assert_eq!(count(&Regex::new("1.1").unwrap(), "1.1 121"), 2);
+/// ```
+pub trait Matcher {
+    /// Finds the leftmost match that starts at or after byte offset `start`, which must lie
+    /// on a char boundary.
+    fn find_at<'h>(&self, haystack: &'h str, start: usize) -> Option<Match<'h>>;
+
+    /// Finds the leftmost match in `haystack`.
+    fn find<'h>(&self, haystack: &'h str) -> Option<Match<'h>> {
+        self.find_at(haystack, 0)
+    }
+
+    /// Checks whether there is a match anywhere in `haystack`.
+    fn is_match(&self, haystack: &str) -> bool {
+        self.find(haystack).is_some()
+    }
+
+    /// Returns an iterator over the successive non-overlapping matches in `haystack`, with the
+    /// same rule for empty matches as `Regex::find_iter`.
+    fn find_iter<'m, 'h>(&'m self, haystack: &'h str) -> FindMatches<'m, 'h, Self>
+    where
+        Self: Sized,
+    {
+        FindMatches(self.try_find_iter(haystack))
+    }
+
+    /// Same as `find_at`, but reports a search that could not finish, such as a backtracking
+    /// search that ran out of its budget, instead of treating it as no match. Matchers whose
+    /// searches always finish keep this default.
+    fn try_find_at<'h>(
+        &self,
+        haystack: &'h str,
+        start: usize,
+    ) -> Result<Option<Match<'h>>, RegexError> {
+        Ok(self.find_at(haystack, start))
+    }
+
+    /// Same as `is_match`, but reports a search that could not finish.
+    fn try_is_match(&self, haystack: &str) -> Result<bool, RegexError> {
+        Ok(self.try_find_at(haystack, 0)?.is_some())
+    }
+
+    /// Same as `find_iter`, but yields the error of a search that could not finish and then
+    /// ends.
+    fn try_find_iter<'m, 'h>(&'m self, haystack: &'h str) -> TryFindMatches<'m, 'h, Self>
+    where
+        Self: Sized,
+    {
+        TryFindMatches {
+            matcher: self,
+            haystack,
+            cursor: MatchCursor::new(),
+        }
+    }
+}
+
+impl Matcher for Regex {
+    fn find_at<'h>(&self, haystack: &'h str, start: usize) -> Option<Match<'h>> {
+        Regex::find_at(self, haystack, start)
+    }
+
+    fn is_match(&self, haystack: &str) -> bool {
+        self.try_is_match(haystack).unwrap_or(false)
+    }
+
+    fn try_find_at<'h>(
+        &self,
+        haystack: &'h str,
+        start: usize,
+    ) -> Result<Option<Match<'h>>, RegexError> {
+        Regex::try_find_at(self, haystack, start)
+    }
+
+    fn try_is_match(&self, haystack: &str) -> Result<bool, RegexError> {
+        // Basta con saber si hay match: la busqueda termina en el primero que encuentra.
+        Engine::new(self)
+            .search(haystack, 0, true, &mut [])
+            .map_err(RegexError::from)
+    }
+}
+
+/// Iterator over the successive non-overlapping matches of a `Matcher`, created by
+/// `Matcher::find_iter`. It ends early if a search cannot finish.
+pub struct FindMatches<'m, 'h, M>(TryFindMatches<'m, 'h, M>);
+
+impl<'h, M: Matcher> Iterator for FindMatches<'_, 'h, M> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        self.0.next()?.ok()
+    }
+}
+
+/// Iterator over the successive non-overlapping matches of a `Matcher`, created by
+/// `Matcher::try_find_iter`.
+pub struct TryFindMatches<'m, 'h, M> {
+    matcher: &'m M,
+    haystack: &'h str,
+    cursor: MatchCursor,
+}
+
+impl<'h, M: Matcher> Iterator for TryFindMatches<'_, 'h, M> {
+    type Item = Result<Match<'h>, RegexError>;
+
+    fn next(&mut self) -> Option<Result<Match<'h>, RegexError>> {
+        let (matcher, haystack) = (self.matcher, self.haystack);
+        let found = self.cursor.next_span(haystack, |at| {
+            let found = matcher.try_find_at(haystack, at)?;
+            Ok(found.map(|found| (found.start(), found.end())))
+        });
+        match found {
+            Ok(Some((start, end))) => Some(Ok(Match::new(haystack, start, end))),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
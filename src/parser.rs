@@ -0,0 +1,268 @@
+use crate::anchor::Anchor;
+use crate::char_set::CharSet;
+use crate::flags::Flags;
+use crate::regex_error::{ErrorKind, RegexError};
+use crate::regex_node::RegexNode;
+use crate::regex_rep::RegexRep;
+use crate::regex_step::RegexStep;
+use crate::regex_val::RegexVal;
+use crate::syntax::{
+    self, Assertion, Ast, AstKind, Backreference, Bracket, BracketItem, Dialect, GroupKind,
+};
+use crate::unicode::property_ranges;
+use std::ops::Range;
+
+/// Turns a pattern into a `RegexNode` tree: parses it into its syntax tree with
+/// `syntax::parse_with`, then applies the flags, numbers the groups and resolves backreferences.
+pub struct Parser<'a> {
+    pattern: &'a str,
+    flags: Flags,
+    dialect: Dialect,
+    nest_limit: usize,
+    /// Names of the groups numbered so far, `None` for unnamed ones.
+    group_names: Vec<Option<String>>,
+    /// Named groups with the span of their opening, e.g. `(?<name>`.
+    group_spans: Vec<(String, Range<usize>)>,
+    /// The first construct that only the backtracking engine supports, and its span.
+    backtracking_only: Option<(ErrorKind, Range<usize>)>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(exp: &'a str) -> Self {
+        Parser::with_flags(exp, Flags::default())
+    }
+
+    /// Creates a parser whose pattern starts with the given flags enabled.
+    pub fn with_flags(exp: &'a str, flags: Flags) -> Self {
+        Parser {
+            pattern: exp,
+            flags,
+            dialect: Dialect::default(),
+            nest_limit: syntax::DEFAULT_NEST_LIMIT,
+            group_names: vec![None],
+            group_spans: Vec::new(),
+            backtracking_only: None,
+        }
+    }
+
+    /// Sets the dialect the pattern is written in; extended by default.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Sets how deeply the pattern may nest, as described in `syntax::parse_with_nest_limit`.
+    pub fn nest_limit(mut self, limit: usize) -> Self {
+        self.nest_limit = limit;
+        self
+    }
+
+    /// Parses the whole pattern.
+    ///
+    /// # Returns
+    ///
+    /// The root of the tree, or an error pointing at the part of the pattern that is malformed.
+    pub fn parse(&mut self) -> Result<RegexNode, RegexError> {
+        let ast = syntax::parse_with_nest_limit(self.pattern, self.dialect, self.nest_limit)?;
+        let mut flags = self.flags;
+        Ok(self.translate(&ast, &mut flags))
+    }
+
+    /// Returns the error for the first construct in the parsed pattern that the automaton
+    /// engines cannot run (a backreference or an unbounded lookbehind), if any.
+    pub fn automaton_error(&self) -> Option<RegexError> {
+        self.backtracking_only
+            .as_ref()
+            .map(|(kind, span)| RegexError::new(*kind, self.pattern, span.clone()))
+    }
+
+    /// Returns the span of the opening of the group called `name` in the parsed pattern, e.g.
+    /// `0..5` for the `(?<n>` of `(?<n>a)`.
+    pub fn group_span(&self, name: &str) -> Option<Range<usize>> {
+        self.group_spans
+            .iter()
+            .find(|(group_name, _)| group_name == name)
+            .map(|(_, span)| span.clone())
+    }
+
+    /// Builds the node for a syntax tree node.
+    ///
+    /// # Arguments
+    ///
+    /// * `ast` - The syntax tree node.
+    /// * `flags` - The flags active before the node. A `(?flags)` group changes them for the
+    ///   rest of its enclosing group, including later branches of an alternation.
+    fn translate(&mut self, ast: &Ast, flags: &mut Flags) -> RegexNode {
+        match &ast.kind {
+            AstKind::Empty => RegexNode::Empty,
+            AstKind::SetFlags(change) => {
+                *flags = change.apply(*flags);
+                RegexNode::Empty
+            }
+            AstKind::Literal(c) => flagged_step(RegexVal::Literal(*c), flags),
+            AstKind::Dot => RegexNode::Step(RegexStep {
+                rep: RegexRep::Exact(1),
+                val: RegexVal::Wildcard,
+            }),
+            AstKind::Class { class, negated } => {
+                let val = if *negated {
+                    RegexVal::NegatedClass(*class)
+                } else {
+                    RegexVal::Class(*class)
+                };
+                flagged_step(val, flags)
+            }
+            AstKind::Property { name, negated } => {
+                // `syntax::parse` ya comprobo que la propiedad existe.
+                let ranges = property_ranges(name, *negated).unwrap_or_default();
+                flagged_step(RegexVal::Bracket(CharSet::new(ranges, Vec::new())), flags)
+            }
+            AstKind::Bracket(bracket) => flagged_step(RegexVal::Bracket(char_set(bracket)), flags),
+            AstKind::Assertion(assertion) => RegexNode::Anchor(match assertion {
+                Assertion::Start if flags.multi_line => Anchor::LineStart,
+                Assertion::End if flags.multi_line => Anchor::LineEnd,
+                Assertion::Start | Assertion::TextStart => Anchor::Start,
+                Assertion::End | Assertion::TextEnd => Anchor::End,
+                Assertion::WordBoundary => Anchor::WordBoundary,
+                Assertion::NotWordBoundary => Anchor::NotWordBoundary,
+                Assertion::WordStart => Anchor::WordStart,
+                Assertion::WordEnd => Anchor::WordEnd,
+            }),
+            AstKind::Backreference(reference) => {
+                let index = match reference {
+                    Backreference::Number(index) => *index,
+                    Backreference::Named(name) => self
+                        .group_names
+                        .iter()
+                        .position(|group_name| group_name.as_ref() == Some(name))
+                        .unwrap_or(0),
+                };
+                self.backtracking_only(ErrorKind::UnsupportedBackreference, ast);
+                RegexNode::Backreference {
+                    index,
+                    case_insensitive: flags.case_insensitive,
+                }
+            }
+            AstKind::Group { kind, ast: inner } => {
+                let saved_flags = *flags;
+                let (index, name) = match kind {
+                    GroupKind::Capturing => (Some(self.group_names.len()), None),
+                    GroupKind::Named(name) => (Some(self.group_names.len()), Some(name.clone())),
+                    GroupKind::NonCapturing(change) => {
+                        *flags = change.apply(*flags);
+                        (None, None)
+                    }
+                    GroupKind::Look { behind, negate } => {
+                        let node = self.translate(inner, flags);
+                        *flags = saved_flags;
+                        if *behind && node.max_len().is_none() {
+                            self.backtracking_only(ErrorKind::UnsupportedLookbehind, ast);
+                        }
+                        return RegexNode::Look {
+                            node: Box::new(node),
+                            behind: *behind,
+                            negate: *negate,
+                        };
+                    }
+                };
+                if index.is_some() {
+                    self.group_names.push(name.clone());
+                }
+                if let Some(name) = &name {
+                    self.group_spans
+                        .push((name.clone(), ast.span.start..inner.span.start));
+                }
+                let node = self.translate(inner, flags);
+                *flags = saved_flags;
+                RegexNode::Group {
+                    node: Box::new(node),
+                    index,
+                    name,
+                }
+            }
+            AstKind::Repetition { ast: inner, rep } => match self.translate(inner, flags) {
+                // Un paso simple guarda la repeticion en su propio `rep`.
+                RegexNode::Step(mut step) if matches!(step.rep, RegexRep::Exact(1)) => {
+                    step.rep = rep.clone();
+                    RegexNode::Step(step)
+                }
+                node => RegexNode::Repetition {
+                    node: Box::new(node),
+                    rep: rep.clone(),
+                },
+            },
+            AstKind::Concat(asts) => {
+                let mut nodes = Vec::with_capacity(asts.len());
+                for ast in asts {
+                    if let AstKind::SetFlags(change) = &ast.kind {
+                        *flags = change.apply(*flags);
+                        continue;
+                    }
+                    nodes.push(self.translate(ast, flags));
+                }
+                match nodes.len() {
+                    0 => RegexNode::Empty,
+                    1 => nodes.remove(0),
+                    _ => RegexNode::Concat(nodes),
+                }
+            }
+            AstKind::Alternation(branches) => RegexNode::Alternation(
+                branches
+                    .iter()
+                    .map(|branch| self.translate(branch, flags))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Records `ast` as needing the backtracking engine, unless an earlier construct was
+    /// already found.
+    fn backtracking_only(&mut self, kind: ErrorKind, ast: &Ast) {
+        if self.backtracking_only.is_none() {
+            self.backtracking_only = Some((kind, ast.span.clone()));
+        }
+    }
+}
+
+/// Builds the node for a single step, applying the flags active at this point.
+fn flagged_step(val: RegexVal, flags: &Flags) -> RegexNode {
+    let val = if flags.case_insensitive && !matches!(val, RegexVal::Wildcard) {
+        RegexVal::CaseInsensitive(Box::new(val))
+    } else {
+        val
+    };
+    RegexNode::Step(RegexStep {
+        rep: RegexRep::Exact(1),
+        val,
+    })
+}
+
+/// Builds the set of characters matched by a bracket expression.
+fn char_set(bracket: &Bracket) -> CharSet {
+    let mut ranges = Vec::new();
+    let mut classes = Vec::new();
+    let mut negated_classes = Vec::new();
+    for item in &bracket.items {
+        match item {
+            BracketItem::Char(c) => ranges.push((*c, *c)),
+            BracketItem::Range(start, end) => ranges.push((*start, *end)),
+            BracketItem::Class {
+                class,
+                negated: false,
+            } => classes.push(*class),
+            BracketItem::Class {
+                class,
+                negated: true,
+            } => negated_classes.push(*class),
+            BracketItem::Property { name, negated } => {
+                ranges.extend(property_ranges(name, *negated).unwrap_or_default())
+            }
+        }
+    }
+    let set = CharSet::new(ranges, classes).with_negated_classes(negated_classes);
+    if bracket.negated {
+        set.negate()
+    } else {
+        set
+    }
+}
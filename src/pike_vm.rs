@@ -0,0 +1,515 @@
+use crate::anchor::Anchor;
+use crate::pool::PoolGuard;
+use crate::program::{Inst, Program};
+use crate::unicode::is_word_char;
+
+/// A set of instruction indexes with O(1) insertion, membership and clearing.
+struct SparseSet {
+    dense: Vec<usize>,
+    sparse: Vec<usize>,
+    len: usize,
+}
+
+impl SparseSet {
+    fn new(capacity: usize) -> Self {
+        SparseSet {
+            dense: vec![0; capacity],
+            sparse: vec![0; capacity],
+            len: 0,
+        }
+    }
+
+    fn contains(&self, value: usize) -> bool {
+        let i = self.sparse[value];
+        i < self.len && self.dense[i] == value
+    }
+
+    fn insert(&mut self, value: usize) -> bool {
+        if self.contains(value) {
+            return false;
+        }
+        self.dense[self.len] = value;
+        self.sparse[value] = self.len;
+        self.len += 1;
+        true
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The threads alive at one input position, in priority order, with their capture slots.
+struct Threads {
+    set: SparseSet,
+    slots: Vec<Option<usize>>,
+    slot_count: usize,
+}
+
+impl Threads {
+    fn new(program: &Program) -> Self {
+        Threads {
+            set: SparseSet::new(program.len()),
+            slots: vec![None; program.len() * program.slot_count()],
+            slot_count: program.slot_count(),
+        }
+    }
+
+    fn slots(&self, pc: usize) -> &[Option<usize>] {
+        &self.slots[pc * self.slot_count..(pc + 1) * self.slot_count]
+    }
+
+    fn slots_mut(&mut self, pc: usize) -> &mut [Option<usize>] {
+        &mut self.slots[pc * self.slot_count..(pc + 1) * self.slot_count]
+    }
+}
+
+/// Work left to do while following the epsilon transitions of a thread.
+enum Frame {
+    Explore(usize),
+    RestoreSlot { slot: usize, old: Option<usize> },
+}
+
+/// Simulates a compiled `Program` over the input, advancing every live thread in lockstep.
+///
+/// Each instruction is visited at most once per input position, so a search takes
+/// O(program size × input size) time regardless of the pattern. Threads are kept in priority
+/// order, which gives leftmost-first match semantics when capture positions are requested.
+pub struct PikeVm<'a> {
+    program: &'a Program,
+    cache: PoolGuard<'a, PikeVmCache>,
+}
+
+/// The memory a `PikeVm` searches with, sized for one program. It is kept apart from the VM so
+/// that a `Regex` can hand it to the next search instead of allocating it again.
+pub(crate) struct PikeVmCache {
+    clist: Threads,
+    nlist: Threads,
+    stack: Vec<Frame>,
+    scratch: Vec<Option<usize>>,
+    /// Capture positions of the last match, when this cache runs the program of a lookaround.
+    captures: Vec<Option<usize>>,
+    /// The cache for the program of each `Look` instruction, indexed by its pc.
+    looks: Vec<Option<PikeVmCache>>,
+}
+
+impl<'a> PikeVm<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        PikeVm::with_cache(program, PoolGuard::owned(PikeVmCache::new(program)))
+    }
+
+    /// Creates a VM that searches with a cache taken from a pool, which must have been built
+    /// for `program`.
+    pub(crate) fn with_cache(program: &'a Program, cache: PoolGuard<'a, PikeVmCache>) -> Self {
+        PikeVm { program, cache }
+    }
+
+    /// Returns whether the program matches anywhere in `value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The line to search.
+    ///
+    /// # Returns
+    ///
+    /// `true` if some substring of `value` matches the program.
+    pub fn is_match(&mut self, value: &str) -> bool {
+        self.search(value, 0, true, &mut [])
+    }
+
+    /// Returns the byte span of the leftmost-first match starting at or after `start`.
+    pub fn find_at(&mut self, value: &str, start: usize) -> Option<(usize, usize)> {
+        let mut slots = [None, None];
+        if !self.search(value, start, false, &mut slots) {
+            return None;
+        }
+        match slots {
+            [Some(start), Some(end)] => Some((start, end)),
+            _ => None,
+        }
+    }
+
+    /// Finds the leftmost-first match starting at or after byte offset `start`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The whole line. Anchors are evaluated against it, not against `&value[start..]`.
+    /// * `start` - Byte offset where the search begins; must be a char boundary.
+    /// * `earliest` - Stop at the first match found instead of looking for the preferred one.
+    /// * `slots` - Receives the capture positions of the match; may be shorter than
+    ///   `program.slot_count()`, in which case only the first groups are reported.
+    ///
+    /// # Returns
+    ///
+    /// `true` if a match was found.
+    pub fn search(
+        &mut self,
+        value: &str,
+        start: usize,
+        earliest: bool,
+        slots: &mut [Option<usize>],
+    ) -> bool {
+        self.cache
+            .run(self.program, value, start, false, None, earliest, slots)
+    }
+
+    /// Runs a program compiled with `Program::compile_set` over `value`, finding which of its
+    /// patterns match. Unlike `search`, a match of one pattern does not stop the threads of the
+    /// other patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The line to search.
+    /// * `spans` - Has one entry per pattern, all `None`. Receives the span of a match for
+    ///   each pattern that matches.
+    /// * `leftmost` - Keep going after every pattern has matched, so that each span is the
+    ///   leftmost-first match `search` would find for the pattern alone, instead of the first
+    ///   match found for it.
+    pub fn search_set(
+        &mut self,
+        value: &str,
+        spans: &mut [Option<(usize, usize)>],
+        leftmost: bool,
+    ) {
+        self.cache.search_set(self.program, value, spans, leftmost);
+    }
+}
+
+impl PikeVmCache {
+    pub(crate) fn new(program: &Program) -> Self {
+        PikeVmCache {
+            clist: Threads::new(program),
+            nlist: Threads::new(program),
+            stack: Vec::new(),
+            scratch: vec![None; program.slot_count()],
+            captures: vec![None; program.slot_count()],
+            looks: program
+                .insts
+                .iter()
+                .map(|inst| match inst {
+                    Inst::Look(look) => Some(PikeVmCache::new(&look.program)),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Runs the search behind `PikeVm::search_set`.
+    fn search_set(
+        &mut self,
+        program: &Program,
+        value: &str,
+        spans: &mut [Option<(usize, usize)>],
+        leftmost: bool,
+    ) {
+        self.clist.set.clear();
+        self.nlist.set.clear();
+
+        let mut pending = spans.len();
+        let mut at = 0;
+        loop {
+            if !program.anchored_start || at == 0 {
+                // Los hilos nuevos van despues de los que ya estaban, que empezaron antes: al
+                // compartir una instruccion, se conserva el inicio mas a la izquierda. Un
+                // patron que ya tiene match no empieza mas hilos, como `run` tras un match.
+                self.scratch.fill(None);
+                for (pattern, span) in spans.iter().enumerate() {
+                    if span.is_some() {
+                        continue;
+                    }
+                    add_thread(
+                        program,
+                        &mut self.clist,
+                        &mut self.stack,
+                        &mut self.scratch,
+                        &mut self.looks,
+                        program.pattern_start(pattern),
+                        at,
+                        value,
+                    );
+                }
+            }
+            if self.clist.set.is_empty() {
+                break;
+            }
+
+            let rest = &value[at..];
+            let next_at = at + rest.chars().next().map_or(0, char::len_utf8);
+            for i in 0..self.clist.set.len {
+                let pc = self.clist.set.dense[i];
+                match &program.insts[pc] {
+                    Inst::Match => {
+                        let pattern = program.pattern_of(pc);
+                        if matched_here(spans[pattern], at) {
+                            continue;
+                        }
+                        if spans[pattern].is_none() {
+                            pending -= 1;
+                        }
+                        let start = self.clist.slots(pc)[0].unwrap_or(at);
+                        spans[pattern] = Some((start, at));
+                        if pending == 0 && !leftmost {
+                            return;
+                        }
+                    }
+                    Inst::Char(val) => {
+                        // Los hilos del patron que siguen a su match tienen menor prioridad: se
+                        // descartan, como en `run`.
+                        if matched_here(spans[program.pattern_of(pc)], at) {
+                            continue;
+                        }
+                        if val.matches(rest) != 0 {
+                            self.scratch.copy_from_slice(self.clist.slots(pc));
+                            add_thread(
+                                program,
+                                &mut self.nlist,
+                                &mut self.stack,
+                                &mut self.scratch,
+                                &mut self.looks,
+                                pc + 1,
+                                next_at,
+                                value,
+                            );
+                        }
+                    }
+                    Inst::Assert(_)
+                    | Inst::Save(_)
+                    | Inst::Split(..)
+                    | Inst::Jmp(_)
+                    | Inst::Backref { .. }
+                    | Inst::Look(_) => {}
+                }
+            }
+
+            if rest.is_empty() {
+                break;
+            }
+            at = next_at;
+            std::mem::swap(&mut self.clist, &mut self.nlist);
+            self.nlist.set.clear();
+        }
+    }
+
+    /// Runs the search behind `PikeVm::search`, optionally restricted to matches that start
+    /// exactly at `start` (`anchored`) or that end exactly at `end`. Lookaround assertions use
+    /// those restrictions to test their program at one position.
+    #[allow(clippy::too_many_arguments)]
+    fn run(
+        &mut self,
+        program: &Program,
+        value: &str,
+        start: usize,
+        anchored: bool,
+        end: Option<usize>,
+        earliest: bool,
+        slots: &mut [Option<usize>],
+    ) -> bool {
+        self.clist.set.clear();
+        self.nlist.set.clear();
+
+        let mut matched = false;
+        let mut at = start;
+        loop {
+            if !matched && (!anchored || at == start) && (!program.anchored_start || at == 0) {
+                self.scratch.fill(None);
+                add_thread(
+                    program,
+                    &mut self.clist,
+                    &mut self.stack,
+                    &mut self.scratch,
+                    &mut self.looks,
+                    program.start,
+                    at,
+                    value,
+                );
+            }
+            if self.clist.set.is_empty() {
+                break;
+            }
+
+            let rest = &value[at..];
+            let next_at = at + rest.chars().next().map_or(0, char::len_utf8);
+            for i in 0..self.clist.set.len {
+                let pc = self.clist.set.dense[i];
+                match &program.insts[pc] {
+                    Inst::Match if end.is_some_and(|end| end != at) => {}
+                    Inst::Match => {
+                        let len = slots.len().min(self.clist.slot_count);
+                        slots[..len].copy_from_slice(&self.clist.slots(pc)[..len]);
+                        matched = true;
+                        if earliest {
+                            return true;
+                        }
+                        // Los hilos que siguen tienen menor prioridad: se descartan.
+                        break;
+                    }
+                    Inst::Char(val) => {
+                        if val.matches(rest) != 0 {
+                            self.scratch.copy_from_slice(self.clist.slots(pc));
+                            add_thread(
+                                program,
+                                &mut self.nlist,
+                                &mut self.stack,
+                                &mut self.scratch,
+                                &mut self.looks,
+                                pc + 1,
+                                next_at,
+                                value,
+                            );
+                        }
+                    }
+                    Inst::Assert(_)
+                    | Inst::Save(_)
+                    | Inst::Split(..)
+                    | Inst::Jmp(_)
+                    | Inst::Backref { .. }
+                    | Inst::Look(_) => {}
+                }
+            }
+
+            if rest.is_empty() || Some(at) == end {
+                break;
+            }
+            at = next_at;
+            std::mem::swap(&mut self.clist, &mut self.nlist);
+            self.nlist.set.clear();
+        }
+        matched
+    }
+}
+
+/// Whether a pattern of a set found the match `span` at position `at`, that is, in the step of
+/// `search_set` that is running.
+fn matched_here(span: Option<(usize, usize)>, at: usize) -> bool {
+    span.is_some_and(|(_, end)| end == at)
+}
+
+/// Adds `pc` to `list`, following `Jmp`, `Split`, `Save` and the `Assert` and `Look`
+/// instructions that hold at position `at`, so that only instructions that consume input or
+/// report a match are followed from the list. `thread_slots` holds the captures of the thread
+/// being added and is left unchanged on return.
+#[allow(clippy::too_many_arguments)]
+fn add_thread(
+    program: &Program,
+    list: &mut Threads,
+    stack: &mut Vec<Frame>,
+    thread_slots: &mut [Option<usize>],
+    looks: &mut [Option<PikeVmCache>],
+    pc: usize,
+    at: usize,
+    value: &str,
+) {
+    stack.push(Frame::Explore(pc));
+    while let Some(frame) = stack.pop() {
+        let pc = match frame {
+            Frame::Explore(pc) => pc,
+            Frame::RestoreSlot { slot, old } => {
+                thread_slots[slot] = old;
+                continue;
+            }
+        };
+        if !list.set.insert(pc) {
+            continue;
+        }
+        match program.insts[pc] {
+            Inst::Jmp(target) => stack.push(Frame::Explore(target)),
+            Inst::Split(first, second) => {
+                // Se apila primero el de menor prioridad para visitar antes el preferido.
+                stack.push(Frame::Explore(second));
+                stack.push(Frame::Explore(first));
+            }
+            Inst::Assert(anchor) => {
+                if is_satisfied(anchor, at, value) {
+                    stack.push(Frame::Explore(pc + 1));
+                }
+            }
+            Inst::Look(ref look) => {
+                if let Some(cache) = &mut looks[pc] {
+                    // Solo hace falta el match preferido si el lookaround tiene grupos que
+                    // capturar; si no, alcanza con saber si hay alguno.
+                    let capturing = !look.negate && look.program.slot_count() > 2;
+                    let mut captures = std::mem::take(&mut cache.captures);
+                    let slots: &mut [Option<usize>] =
+                        if capturing { &mut captures } else { &mut [] };
+                    let found = if look.behind {
+                        let from = chars_before(value, at, look.max_len.unwrap_or(at));
+                        cache.run(
+                            &look.program,
+                            value,
+                            from,
+                            false,
+                            Some(at),
+                            !capturing,
+                            slots,
+                        )
+                    } else {
+                        cache.run(&look.program, value, at, true, None, !capturing, slots)
+                    };
+                    if found != look.negate {
+                        // Lo que capturaron los grupos del lookaround pasa al hilo; los slots 0 y
+                        // 1 son los del match del lookaround, no los del patron. Se restauran
+                        // despues de seguir el resto del hilo.
+                        if capturing {
+                            for (slot, &captured) in captures.iter().enumerate().skip(2) {
+                                if captured.is_some() && slot < thread_slots.len() {
+                                    stack.push(Frame::RestoreSlot {
+                                        slot,
+                                        old: thread_slots[slot],
+                                    });
+                                    thread_slots[slot] = captured;
+                                }
+                            }
+                        }
+                        stack.push(Frame::Explore(pc + 1));
+                    }
+                    cache.captures = captures;
+                }
+            }
+            Inst::Save(slot) => {
+                stack.push(Frame::RestoreSlot {
+                    slot,
+                    old: thread_slots[slot],
+                });
+                thread_slots[slot] = Some(at);
+                stack.push(Frame::Explore(pc + 1));
+            }
+            Inst::Char(_) | Inst::Match => list.slots_mut(pc).copy_from_slice(thread_slots),
+            // Las referencias hacia atras las resuelve el `Backtracker`; el VM nunca las sigue.
+            Inst::Backref { .. } => {}
+        }
+    }
+}
+
+/// Returns whether `anchor` holds at byte offset `at` of `value`.
+pub fn is_satisfied(anchor: Anchor, at: usize, value: &str) -> bool {
+    let before = value[..at].chars().next_back();
+    let after = value[at..].chars().next();
+    let word_before = before.is_some_and(is_word_char);
+    let word_after = after.is_some_and(is_word_char);
+    match anchor {
+        Anchor::Start => at == 0,
+        Anchor::End => at == value.len(),
+        Anchor::LineStart => matches!(before, None | Some('\n')),
+        Anchor::LineEnd => matches!(after, None | Some('\n')),
+        Anchor::WordBoundary => word_before != word_after,
+        Anchor::NotWordBoundary => word_before == word_after,
+        Anchor::WordStart => !word_before && word_after,
+        Anchor::WordEnd => word_before && !word_after,
+    }
+}
+
+/// Returns the byte offset `n` chars before `at`, or 0 if `value[..at]` is shorter than that.
+pub fn chars_before(value: &str, at: usize, n: usize) -> usize {
+    match n.checked_sub(1) {
+        Some(last) => value[..at]
+            .char_indices()
+            .rev()
+            .nth(last)
+            .map_or(0, |(i, _)| i),
+        None => at,
+    }
+}
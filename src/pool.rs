@@ -0,0 +1,86 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard};
+
+/// A stack of reusable values, such as search caches, shared between threads.
+///
+/// A search takes a value out of the pool, uses it without holding any lock and puts it back
+/// when it is done, so concurrent searches each work on their own value and only contend for
+/// the moment it takes to pop or push one. The pool ends up holding as many values as searches
+/// ran at the same time.
+#[derive(Debug, Default)]
+pub(crate) struct Pool<T> {
+    values: Mutex<Vec<T>>,
+}
+
+impl<T> Pool<T> {
+    pub(crate) fn new() -> Self {
+        Pool {
+            values: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Takes a value out of the pool, or creates one with `create` if the pool is empty. The
+    /// value goes back to the pool when the guard is dropped.
+    pub(crate) fn get(&self, create: impl FnOnce() -> T) -> PoolGuard<'_, T> {
+        let value = self.lock().pop().unwrap_or_else(create);
+        PoolGuard {
+            pool: Some(self),
+            value: Some(value),
+        }
+    }
+
+    /// Calls `f` on every value that is in the pool, which leaves out those in use.
+    pub(crate) fn for_each(&self, f: impl FnMut(&T)) {
+        self.lock().iter().for_each(f);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<T>> {
+        // Un panico durante una busqueda no deja valores a medio modificar en la pila.
+        self.values
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A value taken out of a `Pool`, returned to it on drop.
+pub(crate) struct PoolGuard<'p, T> {
+    /// The pool the value goes back to, or `None` if it belongs to no pool.
+    pool: Option<&'p Pool<T>>,
+    value: Option<T>,
+}
+
+impl<T> PoolGuard<'_, T> {
+    /// Wraps a value that belongs to no pool, so it is simply dropped with the guard.
+    pub(crate) fn owned(value: T) -> Self {
+        PoolGuard {
+            pool: None,
+            value: Some(value),
+        }
+    }
+}
+
+impl<T> Deref for PoolGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+            .as_ref()
+            .expect("the value is only taken on drop")
+    }
+}
+
+impl<T> DerefMut for PoolGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+            .as_mut()
+            .expect("the value is only taken on drop")
+    }
+}
+
+impl<T> Drop for PoolGuard<'_, T> {
+    fn drop(&mut self) {
+        if let (Some(pool), Some(value)) = (self.pool, self.value.take()) {
+            pool.lock().push(value);
+        }
+    }
+}
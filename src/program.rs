@@ -0,0 +1,498 @@
+use crate::anchor::Anchor;
+use crate::regex_node::RegexNode;
+use crate::regex_rep::RegexRep;
+use crate::regex_val::RegexVal;
+use std::mem::{size_of, size_of_val};
+use std::sync::Arc;
+
+/// Default maximum memory, in bytes, of a compiled program and the `PikeVm` scratch it needs,
+/// see `Program::compiled_size`.
+pub const DEFAULT_SIZE_LIMIT: usize = 10 * (1 << 20);
+
+/// A single instruction of a compiled Thompson NFA.
+#[derive(Debug, Clone)]
+pub enum Inst {
+    /// Consumes one character if it satisfies the `RegexVal`, then continues at the next instruction.
+    Char(RegexVal),
+    /// Continues at the next instruction only if the position satisfies the anchor.
+    Assert(Anchor),
+    /// Records the current position in a capture slot. Group `i` uses slots `2i` and `2i + 1`.
+    Save(usize),
+    /// Forks the current thread; the first target has priority over the second.
+    Split(usize, usize),
+    /// Continues unconditionally at the given instruction.
+    Jmp(usize),
+    /// Consumes the text last captured by a group, ignoring case if requested. Only the
+    /// `Backtracker` can run programs that use it.
+    Backref {
+        group: usize,
+        case_insensitive: bool,
+    },
+    /// Continues at the next instruction only if the lookaround holds at the current position.
+    Look(Box<Look>),
+    /// The pattern matched.
+    Match,
+}
+
+/// A lookahead or lookbehind assertion, compiled into its own program.
+#[derive(Debug, Clone)]
+pub struct Look {
+    pub program: Program,
+    pub behind: bool,
+    pub negate: bool,
+    /// Longest text, in chars, the assertion can match, or `None` if it is unbounded.
+    pub max_len: Option<usize>,
+}
+
+/// A regular expression compiled into a list of NFA instructions.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub insts: Vec<Inst>,
+    pub start: usize,
+    /// Whether every path from `start` goes through `Assert(Anchor::Start)`, so a search can
+    /// stop as soon as it moves past the first position.
+    pub anchored_start: bool,
+    /// Names of the capture groups, indexed by group number. Group 0 is the whole match.
+    pub group_names: Arc<[Option<String>]>,
+    /// Whether the program contains `Backref` instructions, which need the `Backtracker`.
+    pub has_backrefs: bool,
+    /// Position of the `Match` instruction of each pattern, in increasing order. Only programs
+    /// compiled with `compile_set` have more than one.
+    pub match_pcs: Vec<usize>,
+}
+
+impl Program {
+    /// Compiles a parsed pattern into an NFA program.
+    ///
+    /// Every `RegexVal` becomes a `Char` instruction, alternations and repetitions are expanded
+    /// into `Split`/`Jmp` instructions, so the program size is linear in the size of the pattern
+    /// (counting bounded repetitions once per copy).
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The root of the tree produced by the parser.
+    ///
+    /// # Returns
+    ///
+    /// The compiled program.
+    pub fn compile(node: &RegexNode) -> Program {
+        let program = Program::compile_set(std::slice::from_ref(node));
+        debug_assert_eq!(program.total_len(), Program::compiled_len(node));
+        program
+    }
+
+    /// Counts the instructions `compile` would produce for a pattern, including those of its
+    /// lookaround programs, without building them.
+    ///
+    /// Bounded repetitions are expanded into one copy of their body per iteration, so a short
+    /// pattern like `(a{1000}){1000}` compiles into millions of instructions; checking this
+    /// count first lets a caller reject such patterns before spending the memory. The count
+    /// saturates at `usize::MAX`.
+    pub fn compiled_len(node: &RegexNode) -> usize {
+        // save 0, patron, save 1, match
+        node_len(node).saturating_add(3)
+    }
+
+    /// Estimates the memory, in bytes, of the program `compile` would produce for a pattern
+    /// and of the `PikeVm` scratch that runs it, without building either.
+    ///
+    /// Besides the instructions themselves, this counts what they own on the heap, since every
+    /// copy of a bracket expression such as `\p{L}` carries its own list of ranges, and the
+    /// capture slots the `PikeVm` keeps for every instruction in each of its two thread lists.
+    /// The estimate saturates at `usize::MAX`.
+    pub fn compiled_size(node: &RegexNode) -> usize {
+        let mut group_names = vec![None];
+        collect_group_names(node, &mut group_names);
+        let slot_count = 2 * group_names.len();
+        // Cada lista de hilos guarda, por instruccion, sus slots y una entrada del sparse set.
+        let threads = slot_count * size_of::<Option<usize>>() + 2 * size_of::<usize>();
+        let per_inst = size_of::<Inst>() + 2 * threads;
+        Program::compiled_len(node)
+            .saturating_mul(per_inst)
+            .saturating_add(node_heap(node))
+    }
+
+    /// Compiles several parsed patterns into a single program, with a `Match` instruction for
+    /// each one so that a `PikeVm` can tell which of them match in one pass over the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `nodes` - The roots of the trees produced by the parser, in pattern order. There must
+    ///   be at least one.
+    ///
+    /// # Returns
+    ///
+    /// The compiled program. Its `match_pcs` lists the `Match` instruction of each pattern. The
+    /// patterns number their groups independently and share the capture slots, so with more
+    /// than one pattern `group_names` only has as many entries as the pattern with the most
+    /// groups, all of them unnamed.
+    pub fn compile_set(nodes: &[RegexNode]) -> Program {
+        // split L1, L2
+        // L1: save 0, patron 1, save 1, match
+        // L2: split L3, ...
+        let mut insts = Vec::new();
+        let mut match_pcs = Vec::with_capacity(nodes.len());
+        let mut group_names = vec![None];
+        for (i, node) in nodes.iter().enumerate() {
+            let split = insts.len();
+            let last = i + 1 == nodes.len();
+            if !last {
+                insts.push(Inst::Split(0, 0));
+            }
+            insts.push(Inst::Save(0));
+            compile_node(&mut insts, node);
+            insts.push(Inst::Save(1));
+            match_pcs.push(insts.len());
+            insts.push(Inst::Match);
+            if !last {
+                insts[split] = Inst::Split(split + 1, insts.len());
+            }
+            let mut names = vec![None];
+            collect_group_names(node, &mut names);
+            if nodes.len() == 1 {
+                group_names = names;
+            } else if names.len() > group_names.len() {
+                // El grupo 2 de un patron no tiene relacion con el grupo 2 de otro: solo se
+                // conserva la cantidad de slots, no los nombres.
+                group_names.resize(names.len(), None);
+            }
+        }
+
+        let anchored_start = is_anchored_start(&insts, 0);
+        let has_backrefs = insts.iter().any(|inst| match inst {
+            Inst::Backref { .. } => true,
+            Inst::Look(look) => look.program.has_backrefs,
+            _ => false,
+        });
+        Program {
+            insts,
+            start: 0,
+            anchored_start,
+            group_names: group_names.into(),
+            has_backrefs,
+            match_pcs,
+        }
+    }
+
+    /// Whether only the backtracking engine can run this program, not the automaton engines
+    /// (the `PikeVm` and the lazy DFA).
+    ///
+    /// Backreferences need per-path captures, and a lookbehind without a maximum length would
+    /// have to rescan the whole line before every position.
+    pub fn needs_backtracking(&self) -> bool {
+        self.has_backrefs
+            || self.insts.iter().any(|inst| match inst {
+                Inst::Look(look) => {
+                    (look.behind && look.max_len.is_none()) || look.program.needs_backtracking()
+                }
+                _ => false,
+            })
+    }
+
+    /// Whether the lazy DFA can answer `is_match` for this program. It has no way to evaluate
+    /// backreferences, lookaround, or assertions other than the start and end of the text.
+    pub fn dfa_compatible(&self) -> bool {
+        !self.has_backrefs
+            && self.insts.iter().all(|inst| match inst {
+                Inst::Assert(anchor) => matches!(anchor, Anchor::Start | Anchor::End),
+                Inst::Look(_) => false,
+                _ => true,
+            })
+    }
+
+    /// The instruction where the pattern at index `pattern` of a program compiled with
+    /// `compile_set` begins, after the `Split` that chooses between it and the next ones.
+    pub fn pattern_start(&self, pattern: usize) -> usize {
+        let start = match pattern {
+            0 => self.start,
+            _ => self.match_pcs[pattern - 1] + 1,
+        };
+        match self.insts[start] {
+            Inst::Split(first, _) => first,
+            _ => start,
+        }
+    }
+
+    /// The index of the pattern an instruction of a program compiled with `compile_set`
+    /// belongs to.
+    pub fn pattern_of(&self, pc: usize) -> usize {
+        self.match_pcs.partition_point(|&match_pc| match_pc < pc)
+    }
+
+    /// Number of capture slots a thread needs: two per group, including group 0.
+    pub fn slot_count(&self) -> usize {
+        2 * self.group_names.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.insts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.insts.is_empty()
+    }
+
+    /// Number of instructions, counting those of the lookaround programs.
+    fn total_len(&self) -> usize {
+        self.insts.len()
+            + self
+                .insts
+                .iter()
+                .map(|inst| match inst {
+                    Inst::Look(look) => look.program.total_len(),
+                    _ => 0,
+                })
+                .sum::<usize>()
+    }
+}
+
+fn compile_node(insts: &mut Vec<Inst>, node: &RegexNode) {
+    match node {
+        RegexNode::Empty => {}
+        RegexNode::Step(step) => compile_rep(insts, &step.rep, &|insts: &mut Vec<Inst>| {
+            insts.push(Inst::Char(step.val.clone()))
+        }),
+        RegexNode::Anchor(anchor) => insts.push(Inst::Assert(*anchor)),
+        RegexNode::Backreference {
+            index,
+            case_insensitive,
+        } => insts.push(Inst::Backref {
+            group: *index,
+            case_insensitive: *case_insensitive,
+        }),
+        RegexNode::Look {
+            node,
+            behind,
+            negate,
+        } => insts.push(Inst::Look(Box::new(Look {
+            program: Program::compile(node),
+            behind: *behind,
+            negate: *negate,
+            max_len: node.max_len(),
+        }))),
+        RegexNode::Concat(nodes) => {
+            for node in nodes {
+                compile_node(insts, node);
+            }
+        }
+        RegexNode::Alternation(branches) => {
+            // split L1, L2
+            // L1: branch 1
+            //     jmp end
+            // L2: split L3, ...
+            let mut jumps = Vec::new();
+            for (i, branch) in branches.iter().enumerate() {
+                if i + 1 == branches.len() {
+                    compile_node(insts, branch);
+                    break;
+                }
+                let split = insts.len();
+                insts.push(Inst::Split(0, 0));
+                compile_node(insts, branch);
+                jumps.push(insts.len());
+                insts.push(Inst::Jmp(0));
+                insts[split] = Inst::Split(split + 1, insts.len());
+            }
+            let end = insts.len();
+            for jump in jumps {
+                insts[jump] = Inst::Jmp(end);
+            }
+        }
+        RegexNode::Group { node, index, .. } => match index {
+            Some(index) => {
+                insts.push(Inst::Save(2 * index));
+                compile_node(insts, node);
+                insts.push(Inst::Save(2 * index + 1));
+            }
+            None => compile_node(insts, node),
+        },
+        RegexNode::Repetition { node, rep } => compile_rep(insts, rep, &|insts: &mut Vec<Inst>| {
+            compile_node(insts, node)
+        }),
+    }
+}
+
+/// Stores the name of each group of `node` at its index in `group_names`, growing it as needed.
+pub(crate) fn collect_group_names(node: &RegexNode, group_names: &mut Vec<Option<String>>) {
+    match node {
+        RegexNode::Empty
+        | RegexNode::Step(_)
+        | RegexNode::Anchor(_)
+        | RegexNode::Backreference { .. } => {}
+        RegexNode::Look { node, .. } => collect_group_names(node, group_names),
+        RegexNode::Concat(nodes) | RegexNode::Alternation(nodes) => {
+            for node in nodes {
+                collect_group_names(node, group_names);
+            }
+        }
+        RegexNode::Group { node, index, name } => {
+            if let Some(index) = *index {
+                if group_names.len() <= index {
+                    group_names.resize(index + 1, None);
+                }
+                group_names[index] = name.clone();
+            }
+            collect_group_names(node, group_names);
+        }
+        RegexNode::Repetition { node, .. } => collect_group_names(node, group_names),
+    }
+}
+
+/// Number of instructions `compile_node` emits for a node.
+fn node_len(node: &RegexNode) -> usize {
+    match node {
+        RegexNode::Empty => 0,
+        RegexNode::Step(step) => rep_len(&step.rep, 1),
+        RegexNode::Anchor(_) | RegexNode::Backreference { .. } => 1,
+        RegexNode::Look { node, .. } => Program::compiled_len(node).saturating_add(1),
+        RegexNode::Concat(nodes) => nodes
+            .iter()
+            .fold(0, |len, node| len.saturating_add(node_len(node))),
+        // Cada rama menos la ultima lleva un split y un jmp.
+        RegexNode::Alternation(branches) => branches
+            .iter()
+            .fold(2 * branches.len().saturating_sub(1), |len, branch| {
+                len.saturating_add(node_len(branch))
+            }),
+        RegexNode::Group { node, index, .. } => {
+            let saves = if index.is_some() { 2 } else { 0 };
+            node_len(node).saturating_add(saves)
+        }
+        RegexNode::Repetition { node, rep } => rep_len(rep, node_len(node)),
+    }
+}
+
+/// Bytes the instructions `compile_node` emits for a node own on the heap.
+fn node_heap(node: &RegexNode) -> usize {
+    match node {
+        RegexNode::Empty | RegexNode::Anchor(_) | RegexNode::Backreference { .. } => 0,
+        RegexNode::Step(step) => rep_copies(&step.rep).saturating_mul(val_heap(&step.val)),
+        RegexNode::Look { node, .. } => size_of::<Look>().saturating_add(node_heap(node)),
+        RegexNode::Concat(nodes) | RegexNode::Alternation(nodes) => nodes
+            .iter()
+            .fold(0, |heap, node| heap.saturating_add(node_heap(node))),
+        RegexNode::Group { node, .. } => node_heap(node),
+        RegexNode::Repetition { node, rep } => rep_copies(rep).saturating_mul(node_heap(node)),
+    }
+}
+
+/// Bytes a `RegexVal` owns on the heap.
+fn val_heap(val: &RegexVal) -> usize {
+    match val {
+        RegexVal::Bracket(set) => {
+            size_of_val(set.ranges())
+                + size_of_val(set.classes())
+                + size_of_val(set.negated_classes())
+        }
+        RegexVal::CaseInsensitive(val) => size_of::<RegexVal>() + val_heap(val),
+        RegexVal::Literal(_)
+        | RegexVal::Wildcard
+        | RegexVal::Class(_)
+        | RegexVal::NegatedClass(_) => 0,
+    }
+}
+
+/// Number of copies of its body `compile_rep` emits for a repetition.
+fn rep_copies(rep: &RegexRep) -> usize {
+    match *rep {
+        RegexRep::Any { .. } => 1,
+        RegexRep::Exact(n) => n,
+        RegexRep::Range { min, max, .. } => {
+            let min = min.unwrap_or(0);
+            match max {
+                Some(max) => min.max(max),
+                None => min.saturating_add(1),
+            }
+        }
+    }
+}
+
+/// Number of instructions `compile_rep` emits for a body of `body` instructions.
+fn rep_len(rep: &RegexRep, body: usize) -> usize {
+    let (min, max) = match *rep {
+        RegexRep::Any { .. } => (0, None),
+        RegexRep::Exact(n) => (n, Some(n)),
+        RegexRep::Range { min, max, .. } => (min.unwrap_or(0), max),
+    };
+    let optional = match max {
+        // split, cuerpo, jmp
+        None => body.saturating_add(2),
+        // un split por cada copia opcional
+        Some(max) => max
+            .saturating_sub(min)
+            .saturating_mul(body.saturating_add(1)),
+    };
+    min.saturating_mul(body).saturating_add(optional)
+}
+
+/// Emits the instructions produced by `emit` as many times as `rep` requires.
+fn compile_rep(insts: &mut Vec<Inst>, rep: &RegexRep, emit: &dyn Fn(&mut Vec<Inst>)) {
+    let (min, max) = match *rep {
+        RegexRep::Any { .. } => (0, None),
+        RegexRep::Exact(n) => (n, Some(n)),
+        RegexRep::Range { min, max, .. } => (min.unwrap_or(0), max),
+    };
+    // Un split prefiere su primer destino: las repeticiones lazy prefieren salir del bucle.
+    let make_split = |body: usize, exit: usize| {
+        if rep.is_lazy() {
+            Inst::Split(exit, body)
+        } else {
+            Inst::Split(body, exit)
+        }
+    };
+
+    for _ in 0..min {
+        emit(insts);
+    }
+
+    match max {
+        None => {
+            // L1: split L2, L3
+            // L2: body
+            //     jmp L1
+            // L3:
+            let split = insts.len();
+            insts.push(Inst::Split(0, 0));
+            emit(insts);
+            insts.push(Inst::Jmp(split));
+            insts[split] = make_split(split + 1, insts.len());
+        }
+        Some(max) => {
+            // Las repeticiones opcionales se anidan: cada split salta al final si no hay match.
+            let mut splits = Vec::new();
+            for _ in min..max {
+                splits.push(insts.len());
+                insts.push(Inst::Split(0, 0));
+                emit(insts);
+            }
+            let end = insts.len();
+            for split in splits {
+                insts[split] = make_split(split + 1, end);
+            }
+        }
+    }
+}
+
+/// Returns whether every path from `start` has to pass an `Assert(Anchor::Start)` before it can
+/// consume a character or match.
+fn is_anchored_start(insts: &[Inst], start: usize) -> bool {
+    let mut seen = vec![false; insts.len()];
+    let mut stack = vec![start];
+    while let Some(pc) = stack.pop() {
+        if seen[pc] {
+            continue;
+        }
+        seen[pc] = true;
+        match insts[pc] {
+            Inst::Assert(Anchor::Start) => {}
+            Inst::Assert(_) | Inst::Save(_) | Inst::Look(_) => stack.push(pc + 1),
+            Inst::Jmp(target) => stack.push(target),
+            Inst::Split(first, second) => {
+                stack.push(first);
+                stack.push(second);
+            }
+            Inst::Char(_) | Inst::Backref { .. } | Inst::Match => return false,
+        }
+    }
+    true
+}
@@ -1,13 +1,22 @@
+use crate::aho_corasick::AhoCorasick;
+use crate::backtrack::DEFAULT_BACKTRACK_LIMIT;
+use crate::captures::{CaptureMatches, Captures, TryCaptureMatches};
+use crate::case_fold::{case_variants, simple_fold};
 use crate::class::Class;
-use crate::evaluated_step::EvaluatedStep;
-use crate::regex_rep::RegexRep;
+use crate::engine::Engine;
+use crate::lazy_dfa::{DfaStats, LazyDfa, DEFAULT_CACHE_CAPACITY};
+use crate::literals::RequiredLiterals;
+use crate::pool::Pool;
+use crate::program::{Program, DEFAULT_SIZE_LIMIT};
+use crate::regex_builder::RegexBuilder;
+use crate::regex_error::{ErrorKind, RegexError};
+use crate::regex_match::{Match, Matches, TryMatches};
+use crate::regex_node::RegexNode;
 use crate::regex_step::{Regex, RegexStep};
 use crate::regex_val::RegexVal;
-use crate::utils::{
-    check_min_max, handle_anchoring_end, handle_anchoring_start, handle_backslash, handle_brackets,
-    handle_curly,
-};
-use std::{char, collections::VecDeque, usize::MAX};
+use crate::replace::Replacer;
+use crate::unicode::is_word_char;
+use std::borrow::Cow;
 
 impl RegexVal {
     /// Matches a character against a specified character class and returns the length of the match.
@@ -24,62 +33,33 @@ impl RegexVal {
     /// # Examples
     ///
     /// ```
-    /// use crate::structures::Class;
-    /// let value = 'A';
-    /// let class = Class::Upper;
-    /// let length = match_class(value, &class);
+    /// use rgrep::class::Class;
+    /// use rgrep::regex_val::RegexVal;
+    /// let length = RegexVal::Class(Class::Upper).matches("A");
+    /// assert_eq!(length, 1);
     /// ```
-    fn match_class(value: char, class: &Class) -> usize {
-        match class {
-            Class::Alnum => {
-                if value.is_alphanumeric() {
-                    value.len_utf8()
-                } else {
-                    0
-                }
-            }
-            Class::Alpha => {
-                if value.is_alphabetic() {
-                    value.len_utf8()
-                } else {
-                    0
-                }
-            }
-            Class::Digit => {
-                if value.is_ascii_digit() {
-                    value.len_utf8()
-                } else {
-                    0
-                }
-            }
-            Class::Lower => {
-                if value.is_lowercase() {
-                    value.len_utf8()
-                } else {
-                    0
-                }
-            }
-            Class::Upper => {
-                if value.is_uppercase() {
-                    value.len_utf8()
-                } else {
-                    0
-                }
-            }
-            Class::Space => {
-                if value.is_whitespace() {
-                    value.to_string().len()
-                } else {
-                    0
-                }
-            }
-            Class::Punct => {
-                if value.is_ascii_punctuation() {
-                    value.to_string().len()
-                } else {
-                    0
-                }
+    pub(crate) fn match_class(value: char, class: &Class) -> usize {
+        let matched = match class {
+            Class::Alnum => value.is_alphanumeric(),
+            Class::Alpha => value.is_alphabetic(),
+            Class::Blank => value == '\t' || is_space_separator(value),
+            Class::Cntrl => value.is_control(),
+            Class::Digit => value.is_ascii_digit(),
+            Class::Graph => !value.is_whitespace() && !value.is_control(),
+            Class::Lower => value.is_lowercase(),
+            Class::Print => {
+                !value.is_control() && (!value.is_whitespace() || is_space_separator(value))
             }
+            Class::Punct => value.is_ascii_punctuation(),
+            Class::Space => value.is_whitespace(),
+            Class::Upper => value.is_uppercase(),
+            Class::Word => is_word_char(value),
+            Class::Xdigit => value.is_ascii_hexdigit(),
+        };
+        if matched {
+            value.len_utf8()
+        } else {
+            0
         }
     }
 
@@ -92,7 +72,6 @@ impl RegexVal {
     /// # Returns
     ///
     /// The length of the match if the value matches the regular expression, otherwise 0.
-
     pub fn matches(&self, value: &str) -> usize {
         match self {
             RegexVal::Literal(l) => {
@@ -109,9 +88,9 @@ impl RegexVal {
                     0
                 }
             }
-            RegexVal::Bracket(chars) => {
+            RegexVal::Bracket(set) => {
                 if let Some(c) = value.chars().next() {
-                    if chars.contains(&c) {
+                    if set.contains(c) {
                         c.len_utf8()
                     } else {
                         0
@@ -120,308 +99,397 @@ impl RegexVal {
                     0
                 }
             }
-            RegexVal::NegatedBracket(chars) => {
+            RegexVal::Class(class) => {
                 if let Some(c) = value.chars().next() {
-                    if chars.contains(&c) {
-                        0
-                    } else {
+                    Self::match_class(c, class)
+                } else {
+                    0
+                }
+            }
+            RegexVal::NegatedClass(class) => {
+                if let Some(c) = value.chars().next() {
+                    if Self::match_class(c, class) == 0 {
                         c.len_utf8()
+                    } else {
+                        0
                     }
                 } else {
                     0
                 }
             }
-            RegexVal::Class(class) => {
+            RegexVal::CaseInsensitive(val) => {
                 if let Some(c) = value.chars().next() {
-                    Self::match_class(c, class)
+                    if val.matches_folded(c) {
+                        c.len_utf8()
+                    } else {
+                        0
+                    }
                 } else {
                     0
                 }
             }
         }
     }
+
+    /// Checks whether a character matches the value when case is ignored.
+    ///
+    /// Literals are compared by their simple case folding, while brackets and classes accept
+    /// the character if any of its case variants belongs to them, so `[[:upper:]]` matches
+    /// lowercase letters too, as in `grep -i`. Negated brackets and classes accept it only if
+    /// none of its case variants belongs to them.
+    fn matches_folded(&self, c: char) -> bool {
+        match self {
+            RegexVal::Literal(l) => simple_fold(*l) == simple_fold(c),
+            RegexVal::Wildcard => true,
+            RegexVal::Bracket(set) => set.contains_folded(c),
+            RegexVal::Class(class) => case_variants(c)
+                .into_iter()
+                .any(|variant| Self::match_class(variant, class) != 0),
+            RegexVal::NegatedClass(class) => case_variants(c)
+                .into_iter()
+                .all(|variant| Self::match_class(variant, class) == 0),
+            RegexVal::CaseInsensitive(val) => val.matches_folded(c),
+        }
+    }
 }
 
 impl Regex {
-    pub fn new(exp: &str) -> Result<Vec<Self>, &str> {
-        let mut regex_list: Vec<Self> = Vec::new();
+    /// Parses and compiles a pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `exp` - The pattern, which may combine groups, alternations and quantifiers at any depth.
+    ///
+    /// # Returns
+    ///
+    /// The compiled regex, or a `RegexError` with the position of the part of the pattern that
+    /// is invalid.
+    pub fn new(exp: &str) -> Result<Self, RegexError> {
+        RegexBuilder::new(exp).build()
+    }
 
-        let expressions: Vec<&str> = exp.split('|').collect();
+    /// Compiles an already parsed pattern, using the backtracking engine only if the automaton
+    /// engines cannot run it, and an `AhoCorasick` automaton if it is an alternation of
+    /// literals.
+    pub(crate) fn from_node(node: &RegexNode) -> Self {
+        let program = Program::compile(node);
+        let backtrack = program.needs_backtracking();
+        Regex {
+            program,
+            dfas: Pool::new(),
+            pike_vms: Pool::new(),
+            backtrackers: Pool::new(),
+            dfa_cache_capacity: DEFAULT_CACHE_CAPACITY,
+            literals: RequiredLiterals::new(node),
+            aho_corasick: AhoCorasick::from_alternation(node),
+            backtrack_limit: DEFAULT_BACKTRACK_LIMIT,
+            time_limit: None,
+            backtrack,
+        }
+    }
 
-        for expression in expressions {
-            let mut steps: Vec<RegexStep> = Vec::new();
-            let mut char_iter = expression.chars();
-            while let Some(c) = char_iter.next() {
-                let step = match c {
-                    '.' => Some(RegexStep {
-                        rep: RegexRep::Exact(1),
-                        val: RegexVal::Wildcard,
-                    }),
-                    'a'..='z' => Some(RegexStep {
-                        rep: RegexRep::Exact(1),
-                        val: RegexVal::Literal(c),
-                    }),
-                    '*' => {
-                        if let Some(last) = steps.last_mut() {
-                            last.rep = RegexRep::Any;
-                        } else {
-                            return Err("'*' Inesperado");
-                        }
-                        None
-                    }
-                    '+' => {
-                        if let Some(last) = steps.last_mut() {
-                            last.rep = RegexRep::Range {
-                                min: Some(2),
-                                max: Some(MAX),
-                            };
-                        } else {
-                            return Err("'+' Inesperado");
-                        }
-                        None
-                    }
-                    '\\' => match handle_backslash(&mut char_iter) {
-                        Ok(step) => step,
-                        Err(err) => return Err(err),
-                    },
+    /// Compiles a list of steps into a regex.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - The steps to match, in order.
+    ///
+    /// # Returns
+    ///
+    /// The compiled regex, with an empty DFA cache of the default capacity, or
+    /// `ErrorKind::PatternTooLarge` if the steps exceed the default size limit. The error has
+    /// no pattern text to point at, so its span is empty.
+    pub fn from_steps(steps: &[RegexStep]) -> Result<Self, RegexError> {
+        let nodes = steps.iter().cloned().map(RegexNode::Step).collect();
+        let node = RegexNode::Concat(nodes);
+        if Program::compiled_size(&node) > DEFAULT_SIZE_LIMIT {
+            return Err(RegexError::new(ErrorKind::PatternTooLarge, "", 0..0));
+        }
+        Ok(Regex::from_node(&node))
+    }
+
+    /// Returns the statistics of the lazy DFA caches used by `test`, added up over every
+    /// cache that is not in use by a search right now.
+    pub fn dfa_stats(&self) -> DfaStats {
+        let mut total = DfaStats::default();
+        self.dfas.for_each(|dfa| {
+            let stats = dfa.stats();
+            total.states_built += stats.states_built;
+            total.cache_clears += stats.cache_clears;
+            total.nfa_fallbacks += stats.nfa_fallbacks;
+        });
+        total
+    }
 
-                    ' ' | '$' | '^' | '(' | ')' | '"' | '!' | ',' | ':' | '-' => Some(RegexStep {
-                        rep: RegexRep::Exact(1),
-                        val: RegexVal::Literal(c),
-                    }),
-                    '{' => handle_curly(&mut steps, &mut char_iter),
+    /// Returns the literals found in the pattern that searches scan for before running an
+    /// engine; a debugging aid to see why a search is fast or slow.
+    pub fn required_literals(&self) -> &RequiredLiterals {
+        &self.literals
+    }
 
-                    '?' => {
-                        if let Some(last) = steps.last_mut() {
-                            last.rep = RegexRep::Range {
-                                min: Some(1),
-                                max: Some(2),
-                            }; //por como lo hice, esto representa 0 repeticiones a 1
-                        } else {
-                            return Err("'+' Inesperado");
-                        }
-                        None
-                    }
+    /// Finds the leftmost-first match in `haystack`.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - The text to search.
+    ///
+    /// # Returns
+    ///
+    /// The byte span and text of the match, or `None` if the regex does not match.
+    pub fn find<'h>(&self, haystack: &'h str) -> Option<Match<'h>> {
+        self.find_at(haystack, 0)
+    }
 
-                    '[' => match handle_brackets(&mut char_iter) {
-                        Ok(step) => Some(step),
-                        Err(err) => return Err(err),
-                    },
+    /// Finds the leftmost-first match that starts at or after byte offset `start`.
+    ///
+    /// Anchors and other context-dependent assertions still see the whole haystack, so `^a`
+    /// does not match at `start` unless `start` is 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - The text to search.
+    /// * `start` - Byte offset where the search begins. It must lie on a char boundary.
+    ///
+    /// # Returns
+    ///
+    /// The byte span and text of the match, or `None` if there is no match after `start` (or
+    /// a backtracking limit was exceeded; `try_find_at` reports that case).
+    pub fn find_at<'h>(&self, haystack: &'h str, start: usize) -> Option<Match<'h>> {
+        self.try_find_at(haystack, start).unwrap_or(None)
+    }
 
-                    _ => return Err("Caracter Inesperado"),
-                };
-                if let Some(p) = step {
-                    steps.push(p);
-                }
-            }
-            regex_list.push(Regex { steps })
+    /// Same as `find`, but returns `RegexError::BacktrackLimitExceeded` or
+    /// `RegexError::TimeLimitExceeded` when a backtracking search runs out of its budget,
+    /// instead of `None`.
+    pub fn try_find<'h>(&self, haystack: &'h str) -> Result<Option<Match<'h>>, RegexError> {
+        self.try_find_at(haystack, 0)
+    }
+
+    /// Same as `find_at`, but reports a backtracking search that runs out of its budget, like
+    /// `try_find`.
+    pub fn try_find_at<'h>(
+        &self,
+        haystack: &'h str,
+        start: usize,
+    ) -> Result<Option<Match<'h>>, RegexError> {
+        let mut slots = [None, None];
+        if !Engine::new(self).search(haystack, start, false, &mut slots)? {
+            return Ok(None);
         }
+        Ok(match slots {
+            [Some(start), Some(end)] => Some(Match::new(haystack, start, end)),
+            _ => None,
+        })
+    }
 
-        Ok(regex_list)
+    /// Returns an iterator over the successive non-overlapping matches in `haystack`.
+    ///
+    /// An empty match is never reported at the position where the previous match ended, so the
+    /// iterator always makes progress. It ends early if a backtracking search runs out of its
+    /// budget; `try_find_iter` reports that case.
+    pub fn find_iter<'r, 'h>(&'r self, haystack: &'h str) -> Matches<'r, 'h> {
+        Matches::new(self, haystack)
     }
 
-    pub fn test(&mut self, value: &str) -> Result<String, &str> {
-        if !value.is_ascii() {
-            return Err("El input no es ascii");
-        }
+    /// Same as `find_iter`, but yields the error of a backtracking search that runs out of its
+    /// budget, like `try_find`, and then ends.
+    pub fn try_find_iter<'r, 'h>(&'r self, haystack: &'h str) -> TryMatches<'r, 'h> {
+        TryMatches::new(self, haystack)
+    }
 
-        let mut queue = VecDeque::new();
-        for step in self.steps.drain(..) {
-            queue.push_back(step);
-        }
+    /// Finds the leftmost-first match in `haystack` and the text matched by each capture group.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - The text to search.
+    ///
+    /// # Returns
+    ///
+    /// The captures of the first match, or `None` if the regex does not match (or a
+    /// backtracking limit was exceeded; `try_captures` reports that case).
+    pub fn captures<'h>(&self, haystack: &'h str) -> Option<Captures<'h>> {
+        self.try_captures(haystack).unwrap_or(None)
+    }
 
-        if self.process_step(&mut queue, value) {
-            return Ok(value.to_string());
+    /// Same as `captures`, but reports a backtracking search that runs out of its budget, like
+    /// `try_find`.
+    pub fn try_captures<'h>(&self, haystack: &'h str) -> Result<Option<Captures<'h>>, RegexError> {
+        let mut slots = vec![None; self.program.slot_count()];
+        if !Engine::new(self).search(haystack, 0, false, &mut slots)? {
+            return Ok(None);
         }
+        Ok(Some(Captures::new(
+            haystack,
+            slots,
+            self.program.group_names.clone(),
+        )))
+    }
 
-        Ok("".to_string())
+    /// Returns an iterator over the captures of the successive non-overlapping matches in
+    /// `haystack`, following the same rules as `find_iter`.
+    pub fn captures_iter<'r, 'h>(&'r self, haystack: &'h str) -> CaptureMatches<'r, 'h> {
+        CaptureMatches::new(self, haystack)
     }
 
-    fn process_step(&mut self, queue: &mut VecDeque<RegexStep>, value: &str) -> bool {
-        let mut stack: Vec<EvaluatedStep> = Vec::new();
-        let mut index = 0;
+    /// Same as `captures_iter`, but yields the error of a backtracking search that runs out of
+    /// its budget, like `try_find`, and then ends.
+    pub fn try_captures_iter<'r, 'h>(&'r self, haystack: &'h str) -> TryCaptureMatches<'r, 'h> {
+        TryCaptureMatches::new(self, haystack)
+    }
 
-        let anchored_start: bool = handle_anchoring_start(queue);
-        let anchored_end: bool = handle_anchoring_end(queue);
+    /// Replaces the leftmost-first match with the replacement produced by `rep`.
+    ///
+    /// See `replacen` for the details.
+    pub fn replace<'h, R: Replacer>(&self, haystack: &'h str, rep: R) -> Cow<'h, str> {
+        self.replacen(haystack, 1, rep)
+    }
 
-        while let Some(step) = queue.pop_front() {
-            match step.rep {
-                RegexRep::Exact(_) => {
-                    if !process_exact_step(
-                        &step,
-                        queue,
-                        value,
-                        &mut index,
-                        &mut stack,
-                        anchored_start,
-                    ) {
-                        return false;
-                    }
-                }
-                RegexRep::Any => {
-                    if !process_any_step(&step, queue, value, &mut index) {
-                        return false;
-                    }
-                }
-                RegexRep::Range { min, max } => {
-                    let mut min_max: Vec<Option<usize>> = Vec::new();
-                    min_max.push(min);
-                    min_max.push(max);
-                    if !process_range_step(
-                        min_max,
-                        &step,
-                        queue,
-                        value,
-                        &mut index,
-                        &mut stack,
-                        anchored_end,
-                    ) {
-                        return false;
-                    }
-                }
-            }
-        }
-        if anchored_end && index != value.len() {
-            return false;
-        }
+    /// Replaces every non-overlapping match with the replacement produced by `rep`.
+    ///
+    /// See `replacen` for the details.
+    pub fn replace_all<'h, R: Replacer>(&self, haystack: &'h str, rep: R) -> Cow<'h, str> {
+        self.replacen(haystack, 0, rep)
+    }
 
-        true
+    /// Same as `replace`, but reports a backtracking search that runs out of its budget.
+    pub fn try_replace<'h, R: Replacer>(
+        &self,
+        haystack: &'h str,
+        rep: R,
+    ) -> Result<Cow<'h, str>, RegexError> {
+        self.try_replacen(haystack, 1, rep)
     }
-}
 
-pub fn backtrack(
-    current: &RegexStep,
-    evaluated: &mut Vec<EvaluatedStep>,
-    next: &mut VecDeque<RegexStep>,
-) -> Option<usize> {
-    let mut back_size = 0;
-    next.push_front(current.clone());
-    while let Some(e) = evaluated.pop() {
-        back_size += e.size;
-        if e.backtrackeable {
-            return Some(back_size);
-        } else {
-            next.push_front(e.step);
-        }
+    /// Same as `replace_all`, but reports a backtracking search that runs out of its budget.
+    pub fn try_replace_all<'h, R: Replacer>(
+        &self,
+        haystack: &'h str,
+        rep: R,
+    ) -> Result<Cow<'h, str>, RegexError> {
+        self.try_replacen(haystack, 0, rep)
     }
 
-    None
-}
+    /// Replaces the first `limit` non-overlapping matches with the replacement produced by `rep`.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - The text to search.
+    /// * `limit` - Maximum number of matches to replace; 0 replaces all of them.
+    /// * `rep` - A template string expanding `$1`, `$name`, `${name}` and `$$`, a closure
+    ///   receiving the `Captures` of each match, or `NoExpand` for literal text.
+    ///
+    /// # Returns
+    ///
+    /// The new text, or the haystack itself (without copying it) if nothing matched or a
+    /// backtracking search ran out of its budget; `try_replacen` reports that case.
+    pub fn replacen<'h, R: Replacer>(
+        &self,
+        haystack: &'h str,
+        limit: usize,
+        rep: R,
+    ) -> Cow<'h, str> {
+        self.try_replacen(haystack, limit, rep)
+            .unwrap_or(Cow::Borrowed(haystack))
+    }
 
-fn process_any_step(
-    step: &RegexStep,
-    queue: &mut VecDeque<RegexStep>,
-    value: &str,
-    index: &mut usize,
-) -> bool {
-    let mut keep_matching = true;
-    while keep_matching {
-        let match_size = step.val.matches(&value[*index..]);
-        if match_size != 0 {
-            *index += match_size;
-            if let Some(next_step) = queue.front() {
-                if let RegexVal::Literal(next_char) = &next_step.val {
-                    if let Some(current_char) = value.chars().nth(*index) {
-                        if current_char == *next_char {
-                            break;
-                        }
-                    }
+    /// Same as `replacen`, but returns `RegexError::BacktrackLimitExceeded` or
+    /// `RegexError::TimeLimitExceeded` when a backtracking search runs out of its budget.
+    pub fn try_replacen<'h, R: Replacer>(
+        &self,
+        haystack: &'h str,
+        limit: usize,
+        mut rep: R,
+    ) -> Result<Cow<'h, str>, RegexError> {
+        let mut new = String::new();
+        let mut last = 0;
+        let mut replaced = false;
+        if let Some(text) = rep.no_expansion() {
+            for (i, found) in self.try_find_iter(haystack).enumerate() {
+                if limit > 0 && i >= limit {
+                    break;
                 }
+                let found = found?;
+                new.push_str(&haystack[last..found.start()]);
+                new.push_str(&text);
+                last = found.end();
+                replaced = true;
             }
         } else {
-            keep_matching = false;
-        }
-    }
-    true
-}
-
-fn process_range_step(
-    min_max: Vec<Option<usize>>,
-    step: &RegexStep,
-    queue: &mut VecDeque<RegexStep>,
-    value: &str,
-    index: &mut usize,
-    stack: &mut Vec<EvaluatedStep>,
-    anchored_end: bool,
-) -> bool {
-    let mut keep_matching = true;
-    let mut counter: usize = 0;
-    while keep_matching {
-        let match_size = step.val.matches(&value[*index..]);
-        if match_size != 0 {
-            counter += 1;
-            *index += match_size;
-            if let Some(next_step) = queue.front() {
-                if let RegexVal::Literal(next_char) = &next_step.val {
-                    if let Some(current_char) = value.chars().nth(*index) {
-                        if current_char == *next_char {
-                            if !check_min_max(min_max[0], min_max[1], counter) {
-                                match backtrack(step, stack, queue) {
-                                    Some(size) => {
-                                        *index -= size;
-                                        continue;
-                                    }
-                                    None => {
-                                        if value.len() < *index + 1 {
-                                            return false;
-                                        }
-                                        *index += 1;
-                                    }
-                                }
-                            }
-                            break;
-                        }
-                    }
+            for (i, caps) in self.try_captures_iter(haystack).enumerate() {
+                if limit > 0 && i >= limit {
+                    break;
                 }
+                let caps = caps?;
+                let Some(found) = caps.get(0) else {
+                    continue;
+                };
+                new.push_str(&haystack[last..found.start()]);
+                rep.replace_append(&caps, &mut new);
+                last = found.end();
+                replaced = true;
             }
-        } else {
-            keep_matching = false;
         }
+        if !replaced {
+            return Ok(Cow::Borrowed(haystack));
+        }
+        new.push_str(&haystack[last..]);
+        Ok(Cow::Owned(new))
     }
 
-    if anchored_end && *index != value.len() {
-        return false;
+    /// Number of capture groups, including group 0.
+    pub fn captures_len(&self) -> usize {
+        self.program.group_names.len()
     }
 
-    true
-}
+    /// Names of the capture groups, indexed by group number; unnamed groups yield `None`.
+    pub fn capture_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.program.group_names.iter().map(|name| name.as_deref())
+    }
 
-fn process_exact_step(
-    step: &RegexStep,
-    queue: &mut VecDeque<RegexStep>,
-    value: &str,
-    index: &mut usize,
-    stack: &mut Vec<EvaluatedStep>,
-    anchored_start: bool,
-) -> bool {
-    let mut match_size = 0;
-    let size = step.val.matches(&value[*index..]);
-    if size == 0 {
-        if !anchored_start {
-            match backtrack(step, stack, queue) {
-                Some(size) => {
-                    *index -= size;
-                    return true;
-                }
-                None => {
-                    if value.len() < *index + 1 {
-                        return false;
-                    }
-                    *index += 1;
-                }
-            }
+    /// Checks whether the regex matches anywhere in a line.
+    ///
+    /// The line may contain any UTF-8 text: every value in the pattern matches whole code
+    /// points, never individual bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The line to search.
+    ///
+    /// # Returns
+    ///
+    /// The whole line if the regex matches it, otherwise an empty string. Patterns with
+    /// backreferences return `RegexError::BacktrackLimitExceeded` if the search exceeds the
+    /// backtracking step limit, or `RegexError::TimeLimitExceeded` if it exceeds the time limit.
+    pub fn test(&self, value: &str) -> Result<String, RegexError> {
+        let cached = if self.literals.candidate(value, 0).is_none() {
+            Some(false)
+        } else if self.backtrack || self.aho_corasick.is_some() || !self.program.dfa_compatible() {
+            None
         } else {
-            return false;
+            self.dfas
+                .get(|| LazyDfa::new(self.dfa_cache_capacity))
+                .is_match(&self.program, value)
+        };
+        let is_match = match cached {
+            Some(is_match) => is_match,
+            None => Engine::new(self)
+                .search(value, 0, true, &mut [])
+                .map_err(RegexError::from)?,
+        };
+        if is_match {
+            return Ok(value.to_string());
         }
-    } else {
-        match_size += size;
-        *index += size;
-        stack.push(EvaluatedStep {
-            step: step.clone(),
-            size: match_size,
-            backtrackeable: false,
-        });
+
+        Ok("".to_string())
     }
+}
 
-    true
+/// Returns whether `c` is a space separator (Unicode category Zs): a whitespace character that
+/// takes up room on a line, as opposed to tabs and line breaks.
+fn is_space_separator(c: char) -> bool {
+    matches!(
+        c,
+        ' ' | '\u{A0}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}'
+    )
 }
@@ -0,0 +1,254 @@
+use crate::backtrack::DEFAULT_BACKTRACK_LIMIT;
+use crate::char_set::CharSet;
+use crate::engine::EngineKind;
+use crate::flags::Flags;
+use crate::lazy_dfa::DEFAULT_CACHE_CAPACITY;
+use crate::parser::Parser;
+use crate::program::{collect_group_names, Program, DEFAULT_SIZE_LIMIT};
+use crate::regex_error::{ErrorKind, RegexError};
+use crate::regex_node::RegexNode;
+use crate::regex_rep::RegexRep;
+use crate::regex_step::{Regex, RegexStep};
+use crate::regex_val::RegexVal;
+use crate::syntax::{Dialect, DEFAULT_NEST_LIMIT};
+use std::time::Duration;
+
+/// Configures and compiles a `Regex`.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::regex_builder::RegexBuilder;
+/// let regex = RegexBuilder::new("hola").case_insensitive(true).build().unwrap();
+/// assert_eq!(regex.test("HOLA").unwrap(), "HOLA");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexBuilder {
+    /// The patterns to compile; the regex matches wherever any of them matches.
+    patterns: Vec<String>,
+    flags: Flags,
+    dialect: Dialect,
+    dfa_cache_capacity: usize,
+    backtrack_limit: usize,
+    time_limit: Option<Duration>,
+    size_limit: usize,
+    nest_limit: usize,
+    engine: EngineKind,
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str) -> Self {
+        RegexBuilder::new_many([pattern])
+    }
+
+    /// Creates a builder for a regex that matches wherever any of `patterns` matches, like the
+    /// pattern list of `grep -f`. Unlike joining them with `|`, each pattern is parsed on its
+    /// own, so an error is reported against the pattern that has it, and one pattern cannot
+    /// change how the next one is read. Groups are numbered across all the patterns, in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rgrep::regex_builder::RegexBuilder;
+    /// let regex = RegexBuilder::new_many(["(a)b", "(c)\\1"]).build().unwrap();
+    /// assert_eq!(regex.find("xcc").unwrap().as_str(), "cc");
+    /// assert_eq!(regex.captures("cc").unwrap().get(2).unwrap().as_str(), "c");
+    ///
+    /// let err = RegexBuilder::new_many(["a", "b("]).build().err().unwrap();
+    /// assert_eq!(err.pattern(), Some("b("));
+    /// ```
+    pub fn new_many<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        RegexBuilder {
+            patterns: patterns
+                .into_iter()
+                .map(|pattern| pattern.as_ref().to_string())
+                .collect(),
+            flags: Flags::default(),
+            dialect: Dialect::default(),
+            dfa_cache_capacity: DEFAULT_CACHE_CAPACITY,
+            backtrack_limit: DEFAULT_BACKTRACK_LIMIT,
+            time_limit: None,
+            size_limit: DEFAULT_SIZE_LIMIT,
+            nest_limit: DEFAULT_NEST_LIMIT,
+            engine: EngineKind::Auto,
+        }
+    }
+
+    /// Ignores case in the whole pattern, like a leading `(?i)`. Inline `(?-i)` groups can still
+    /// turn it off for part of the pattern.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.flags.case_insensitive = yes;
+        self
+    }
+
+    /// Makes `^` and `$` match at the start and end of every line inside the text, like a
+    /// leading `(?m)`. `\A` and `\z` still only match at the start and end of the whole text.
+    pub fn multi_line(&mut self, yes: bool) -> &mut Self {
+        self.flags.multi_line = yes;
+        self
+    }
+
+    /// Chooses how the pattern is written: `Dialect::Basic` like `grep -G`, or
+    /// `Dialect::Extended` (the default) like `grep -E`.
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Sets the memory budget of the lazy DFA, in bytes.
+    pub fn dfa_cache_capacity(&mut self, bytes: usize) -> &mut Self {
+        self.dfa_cache_capacity = bytes;
+        self
+    }
+
+    /// Sets how many instructions a search may execute on patterns with backreferences, which
+    /// run on a backtracking engine, before giving up.
+    pub fn backtrack_limit(&mut self, steps: usize) -> &mut Self {
+        self.backtrack_limit = steps;
+        self
+    }
+
+    /// Sets how long a search on the backtracking engine may run before giving up with
+    /// `RegexError::TimeLimitExceeded`. There is no time limit by default.
+    pub fn time_limit(&mut self, limit: Duration) -> &mut Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Sets the maximum memory, in bytes, of the compiled program and the scratch a search
+    /// needs to run it, as estimated by `Program::compiled_size`. Patterns that would take more
+    /// fail with `ErrorKind::PatternTooLarge` before they are compiled.
+    pub fn size_limit(&mut self, bytes: usize) -> &mut Self {
+        self.size_limit = bytes;
+        self
+    }
+
+    /// Sets how deeply groups and quantifiers may nest. Patterns that nest deeper fail with
+    /// `ErrorKind::NestingTooDeep`.
+    pub fn nest_limit(&mut self, levels: usize) -> &mut Self {
+        self.nest_limit = levels;
+        self
+    }
+
+    /// Chooses the engine that runs the regex. `EngineKind::Automaton` makes `build` fail on
+    /// patterns that only the backtracker supports.
+    pub fn engine(&mut self, engine: EngineKind) -> &mut Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Parses and compiles the pattern with the configured options.
+    ///
+    /// # Returns
+    ///
+    /// The compiled regex, or a `RegexError` pointing at the part of the pattern that is
+    /// malformed, that the chosen engine does not support, or that exceeds the size or nesting
+    /// limits. With several patterns, the error is that of the first one that fails, a group
+    /// name already used by an earlier pattern is an error in the later one, and if only all of
+    /// them together exceed the size limit, the error points at the last one.
+    pub fn build(&self) -> Result<Regex, RegexError> {
+        let mut branches = Vec::with_capacity(self.patterns.len());
+        let mut groups = 0;
+        let mut names = Vec::new();
+        for pattern in &self.patterns {
+            let (mut node, _) = self.parse(pattern)?;
+            let mut own_names = Vec::new();
+            collect_group_names(&node, &mut own_names);
+            for name in own_names.into_iter().flatten() {
+                if names.contains(&name) {
+                    return Err(self.duplicate_group_name(pattern, &name));
+                }
+                names.push(name);
+            }
+            // Los grupos de cada patron siguen a los de los anteriores, como si estuvieran
+            // unidos con `|`.
+            node.shift_groups(groups);
+            groups = groups.max(node.group_count());
+            branches.push(node);
+        }
+        let node = match branches.len() {
+            1 => branches.remove(0),
+            // Sin patrones no hay nada que pueda coincidir.
+            0 => RegexNode::Step(RegexStep {
+                val: RegexVal::Bracket(CharSet::new(Vec::new(), Vec::new())),
+                rep: RegexRep::Exact(1),
+            }),
+            _ => RegexNode::Alternation(branches),
+        };
+        if let [.., last] = self.patterns.as_slice() {
+            if self.patterns.len() > 1 && Program::compiled_size(&node) > self.size_limit {
+                return Err(RegexError::new(
+                    ErrorKind::PatternTooLarge,
+                    last,
+                    0..last.len(),
+                ));
+            }
+        }
+        Ok(self.compile(&node))
+    }
+
+    /// Returns the error for a pattern that reuses the name of a group of an earlier pattern,
+    /// pointing at the opening of its group like a repeated name within one pattern.
+    fn duplicate_group_name(&self, pattern: &str, name: &str) -> RegexError {
+        let mut parser = Parser::with_flags(pattern, self.flags)
+            .dialect(self.dialect)
+            .nest_limit(self.nest_limit);
+        // El patron ya se analizo sin errores en `parse`.
+        let span = match parser.parse() {
+            Ok(_) => parser.group_span(name),
+            Err(_) => None,
+        };
+        RegexError::new(
+            ErrorKind::DuplicateGroupName,
+            pattern,
+            span.unwrap_or(0..pattern.len()),
+        )
+    }
+
+    /// Parses a pattern with the configured options, which `RegexSetBuilder` also uses for
+    /// each pattern of a set.
+    ///
+    /// # Returns
+    ///
+    /// The parsed pattern and whether it has to run on the backtracking engine, either because
+    /// the automaton engines do not support it or because `EngineKind::Backtracking` was
+    /// chosen. Fails like `build` does.
+    pub(crate) fn parse(&self, pattern: &str) -> Result<(RegexNode, bool), RegexError> {
+        let mut parser = Parser::with_flags(pattern, self.flags)
+            .dialect(self.dialect)
+            .nest_limit(self.nest_limit);
+        let node = parser.parse()?;
+        if Program::compiled_size(&node) > self.size_limit {
+            return Err(RegexError::new(
+                ErrorKind::PatternTooLarge,
+                pattern,
+                0..pattern.len(),
+            ));
+        }
+        let backtrack = match self.engine {
+            EngineKind::Auto => parser.automaton_error().is_some(),
+            EngineKind::Automaton => match parser.automaton_error() {
+                Some(err) => return Err(err),
+                None => false,
+            },
+            EngineKind::Backtracking => true,
+        };
+        Ok((node, backtrack))
+    }
+
+    /// Compiles a pattern returned by `parse` into a regex with the configured options.
+    pub(crate) fn compile(&self, node: &RegexNode) -> Regex {
+        let mut regex = Regex::from_node(node);
+        if self.engine == EngineKind::Backtracking {
+            regex.backtrack = true;
+        }
+        regex.dfa_cache_capacity = self.dfa_cache_capacity;
+        regex.backtrack_limit = self.backtrack_limit;
+        regex.time_limit = self.time_limit;
+        regex
+    }
+}
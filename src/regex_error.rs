@@ -0,0 +1,212 @@
+use crate::backtrack::LimitExceeded;
+use std::fmt;
+use std::ops::Range;
+
+/// What is wrong with a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A character that cannot appear at this point of the pattern.
+    UnexpectedChar,
+    /// A `)` without a matching `(`.
+    UnmatchedCloseParen,
+    /// A `(` without a matching `)`.
+    UnclosedGroup,
+    /// A `[` without a matching `]`.
+    UnclosedBracket,
+    /// A quantifier with nothing before it to repeat, or following an assertion.
+    NothingToRepeat,
+    /// A `\{` in a basic pattern that does not start a valid `\{min,max\}` interval.
+    InvalidRepetition,
+    /// A `{min,max}` quantifier whose minimum is greater than its maximum.
+    InvalidRepetitionRange,
+    /// A bracket range whose end comes before its start, or whose ends are not characters.
+    InvalidBracketRange,
+    /// A `[:name:]` class that does not exist.
+    UnknownClass,
+    /// A `[.c.]` or `[=c=]` element that is not a single character.
+    InvalidCollatingElement,
+    /// A `\` at the end of the pattern.
+    TrailingBackslash,
+    /// An escaped letter or digit with no meaning, like `\q`.
+    UnknownEscape,
+    /// A malformed `\xHH` or `\u{...}` escape, or one that is not a valid code point.
+    InvalidEscape,
+    /// A `\p` or `\P` escape without a name.
+    InvalidProperty,
+    /// A `\p{name}` whose name is not a known Unicode property.
+    UnknownProperty,
+    /// An unknown flag or prefix after `(?`.
+    UnknownGroupFlag,
+    /// A group name that is empty, starts with a digit, has characters other than letters,
+    /// digits and `_`, or is not closed by `>`.
+    InvalidGroupName,
+    /// Two groups with the same name.
+    DuplicateGroupName,
+    /// A `\k<name>` that names no group opened before it.
+    UnknownGroupName,
+    /// A backreference to a group that does not exist.
+    InvalidBackreference,
+    /// A backreference in a pattern built for `EngineKind::Automaton`.
+    UnsupportedBackreference,
+    /// A lookbehind without a maximum length in a pattern built for `EngineKind::Automaton`.
+    UnsupportedLookbehind,
+    /// Groups nested, or quantifiers stacked on one atom, deeper than the nesting limit.
+    NestingTooDeep,
+    /// A pattern whose compiled program would take more memory than the size limit, usually
+    /// because of large bounded repetitions such as `(a{1000}){1000}`.
+    PatternTooLarge,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            ErrorKind::UnexpectedChar => "unexpected character",
+            ErrorKind::UnmatchedCloseParen => "unmatched closing parenthesis",
+            ErrorKind::UnclosedGroup => "no closing parenthesis found",
+            ErrorKind::UnclosedBracket => "no closing bracket found",
+            ErrorKind::NothingToRepeat => "quantifier has nothing to repeat",
+            ErrorKind::InvalidRepetition => "invalid interval expression",
+            ErrorKind::InvalidRepetitionRange => "invalid repetition range",
+            ErrorKind::InvalidBracketRange => "invalid range in bracket expression",
+            ErrorKind::UnknownClass => "unknown character class",
+            ErrorKind::InvalidCollatingElement => "invalid collating element",
+            ErrorKind::TrailingBackslash => "pattern ends with a backslash",
+            ErrorKind::UnknownEscape => "unknown escape sequence",
+            ErrorKind::InvalidEscape => "invalid escape, expected \\xHH or \\u{...}",
+            ErrorKind::InvalidProperty => "invalid Unicode property, expected \\p{name}",
+            ErrorKind::UnknownProperty => "unknown Unicode property",
+            ErrorKind::UnknownGroupFlag => "unknown group flag",
+            ErrorKind::InvalidGroupName => "invalid group name",
+            ErrorKind::DuplicateGroupName => "duplicate group name",
+            ErrorKind::UnknownGroupName => "unknown group name",
+            ErrorKind::InvalidBackreference => "backreference to a group that does not exist",
+            ErrorKind::UnsupportedBackreference => {
+                "backreferences are only supported by the backtracking engine"
+            }
+            ErrorKind::UnsupportedLookbehind => {
+                "unbounded lookbehind is only supported by the backtracking engine"
+            }
+            ErrorKind::NestingTooDeep => "pattern nested too deeply",
+            ErrorKind::PatternTooLarge => "compiled pattern exceeds the size limit",
+        };
+        f.write_str(message)
+    }
+}
+
+/// Error returned when a pattern cannot be compiled or a search cannot be completed.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::regex_error::ErrorKind;
+/// use rgrep::regex_step::Regex;
+/// let err = Regex::new("ab[c").err().unwrap();
+/// assert_eq!(err.kind(), Some(ErrorKind::UnclosedBracket));
+/// assert_eq!(err.span(), Some(2..4));
+/// assert_eq!(
+///     err.to_string(),
+///     "no closing bracket found at byte 2\n    ab[c\n      ^^"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegexError {
+    /// The pattern is malformed, or uses a construct the chosen engine does not support.
+    Pattern {
+        kind: ErrorKind,
+        /// The whole pattern.
+        pattern: String,
+        /// Byte range of the offending part of the pattern.
+        span: Range<usize>,
+    },
+    /// A backtracking search ran out of steps before deciding whether the pattern matches.
+    BacktrackLimitExceeded,
+    /// A backtracking search ran out of time before deciding whether the pattern matches.
+    TimeLimitExceeded,
+}
+
+impl RegexError {
+    pub fn new(kind: ErrorKind, pattern: &str, span: Range<usize>) -> Self {
+        RegexError::Pattern {
+            kind,
+            pattern: pattern.to_string(),
+            span,
+        }
+    }
+
+    /// What is wrong with the pattern, or `None` if the error happened during a search.
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            RegexError::Pattern { kind, .. } => Some(*kind),
+            RegexError::BacktrackLimitExceeded | RegexError::TimeLimitExceeded => None,
+        }
+    }
+
+    /// The pattern the error points into, or `None` if the error happened during a search.
+    pub fn pattern(&self) -> Option<&str> {
+        match self {
+            RegexError::Pattern { pattern, .. } => Some(pattern),
+            RegexError::BacktrackLimitExceeded | RegexError::TimeLimitExceeded => None,
+        }
+    }
+
+    /// Byte range of the offending part of the pattern.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            RegexError::Pattern { span, .. } => Some(span.clone()),
+            RegexError::BacktrackLimitExceeded | RegexError::TimeLimitExceeded => None,
+        }
+    }
+
+    /// Byte offset in the pattern where the offending part starts.
+    pub fn offset(&self) -> Option<usize> {
+        self.span().map(|span| span.start)
+    }
+}
+
+impl fmt::Display for RegexError {
+    /// Writes the message followed by the pattern with the offending part underlined, e.g.
+    ///
+    /// ```text
+    /// unknown escape sequence at byte 1
+    ///     a\qb
+    ///      ^^
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (kind, pattern, span) = match self {
+            RegexError::Pattern {
+                kind,
+                pattern,
+                span,
+            } => (kind, pattern, span),
+            RegexError::BacktrackLimitExceeded => {
+                return f.write_str("backtracking limit exceeded");
+            }
+            RegexError::TimeLimitExceeded => {
+                return f.write_str("backtracking time limit exceeded");
+            }
+        };
+        // Las columnas se cuentan en caracteres para que el ^ quede debajo con texto no ASCII.
+        let column = pattern[..span.start].chars().count();
+        let width = pattern[span.clone()].chars().count().max(1);
+        write!(
+            f,
+            "{} at byte {}\n    {}\n    {}{}",
+            kind,
+            span.start,
+            pattern,
+            " ".repeat(column),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl std::error::Error for RegexError {}
+
+impl From<LimitExceeded> for RegexError {
+    fn from(limit: LimitExceeded) -> Self {
+        match limit {
+            LimitExceeded::Steps => RegexError::BacktrackLimitExceeded,
+            LimitExceeded::Time => RegexError::TimeLimitExceeded,
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::backtrack::LimitExceeded;
+use crate::engine::Engine;
+use crate::regex_error::RegexError;
+use crate::regex_step::Regex;
+use std::ops::Range;
+
+/// A single match of a regex in a haystack, given by its byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'h> {
+    haystack: &'h str,
+    start: usize,
+    end: usize,
+}
+
+impl<'h> Match<'h> {
+    pub(crate) fn new(haystack: &'h str, start: usize, end: usize) -> Self {
+        Match {
+            haystack,
+            start,
+            end,
+        }
+    }
+
+    /// Byte offset where the match starts.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Byte offset right after the last byte of the match.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The matched text.
+    pub fn as_str(&self) -> &'h str {
+        &self.haystack[self.start..self.end]
+    }
+}
+
+/// Position of an iterator over successive non-overlapping matches.
+pub(crate) struct MatchCursor {
+    at: usize,
+    last_end: Option<usize>,
+}
+
+impl MatchCursor {
+    pub(crate) fn new() -> Self {
+        MatchCursor {
+            at: 0,
+            last_end: None,
+        }
+    }
+
+    /// Searches for the next match of `engine`, writing its capture positions to `slots` (which
+    /// must hold at least the two slots of group 0).
+    ///
+    /// # Returns
+    ///
+    /// `true` if another match was found, or `LimitExceeded` if a backtracking search ran out
+    /// of its budget, which also ends the iteration.
+    pub(crate) fn advance(
+        &mut self,
+        engine: &mut Engine,
+        haystack: &str,
+        slots: &mut [Option<usize>],
+    ) -> Result<bool, LimitExceeded> {
+        let found = self.next_span(haystack, |at| {
+            if !engine.search(haystack, at, false, slots)? {
+                return Ok(None);
+            }
+            Ok(slots[0].zip(slots[1]))
+        })?;
+        Ok(found.is_some())
+    }
+
+    /// Searches for the next match with any search function.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - The text being searched.
+    /// * `search` - Finds the leftmost match starting at or after the byte offset it is given,
+    ///   returning its span, or fails.
+    ///
+    /// # Returns
+    ///
+    /// The span of the next match, or `None` when there are no more. An error from `search` is
+    /// returned as is and ends the iteration.
+    pub(crate) fn next_span<E>(
+        &mut self,
+        haystack: &str,
+        mut search: impl FnMut(usize) -> Result<Option<(usize, usize)>, E>,
+    ) -> Result<Option<(usize, usize)>, E> {
+        loop {
+            if self.at > haystack.len() {
+                return Ok(None);
+            }
+            let found = search(self.at).inspect_err(|_| self.at = usize::MAX)?;
+            let Some((start, end)) = found else {
+                return Ok(None);
+            };
+            if start == end && Some(end) == self.last_end {
+                // Un match vacio justo donde termino el anterior se descarta: se avanza un
+                // caracter para no devolver el mismo match para siempre.
+                let Some(c) = haystack[end..].chars().next() else {
+                    return Ok(None);
+                };
+                self.at = end + c.len_utf8();
+                continue;
+            }
+            self.at = end;
+            self.last_end = Some(end);
+            return Ok(Some((start, end)));
+        }
+    }
+}
+
+/// Iterator over the successive non-overlapping matches of a regex, created by
+/// `Regex::find_iter`. It ends early if a backtracking search runs out of its budget.
+pub struct Matches<'r, 'h>(TryMatches<'r, 'h>);
+
+impl<'r, 'h> Matches<'r, 'h> {
+    pub(crate) fn new(regex: &'r Regex, haystack: &'h str) -> Self {
+        Matches(TryMatches::new(regex, haystack))
+    }
+}
+
+impl<'h> Iterator for Matches<'_, 'h> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        self.0.next()?.ok()
+    }
+}
+
+/// Iterator over the successive non-overlapping matches of a regex, created by
+/// `Regex::try_find_iter`. If a backtracking search runs out of its budget, it yields the
+/// error and then ends.
+pub struct TryMatches<'r, 'h> {
+    engine: Engine<'r>,
+    haystack: &'h str,
+    cursor: MatchCursor,
+}
+
+impl<'r, 'h> TryMatches<'r, 'h> {
+    pub(crate) fn new(regex: &'r Regex, haystack: &'h str) -> Self {
+        TryMatches {
+            engine: Engine::new(regex),
+            haystack,
+            cursor: MatchCursor::new(),
+        }
+    }
+}
+
+impl<'h> Iterator for TryMatches<'_, 'h> {
+    type Item = Result<Match<'h>, RegexError>;
+
+    fn next(&mut self) -> Option<Result<Match<'h>, RegexError>> {
+        let mut slots = [None, None];
+        match self
+            .cursor
+            .advance(&mut self.engine, self.haystack, &mut slots)
+        {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(err) => return Some(Err(err.into())),
+        }
+        match slots {
+            [Some(start), Some(end)] => Some(Ok(Match::new(self.haystack, start, end))),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::anchor::Anchor;
+use crate::regex_rep::RegexRep;
+use crate::regex_step::RegexStep;
+
+/// A node of the tree built by the parser.
+#[derive(Debug, Clone)]
+pub enum RegexNode {
+    /// Matches the empty string, e.g. an empty alternative in `a|`.
+    Empty,
+    /// A single value with its own repetition, e.g. `a` or `[bc]{2,4}`.
+    Step(RegexStep),
+    Anchor(Anchor),
+    Concat(Vec<RegexNode>),
+    Alternation(Vec<RegexNode>),
+    /// A parenthesized subpattern. `index` is the capture group number, or `None` for a
+    /// non-capturing `(?:...)` group.
+    Group {
+        node: Box<RegexNode>,
+        index: Option<usize>,
+        name: Option<String>,
+    },
+    /// Matches the text last captured by group `index`, e.g. `\1` or `\k<name>`.
+    Backreference {
+        index: usize,
+        case_insensitive: bool,
+    },
+    /// A zero-width lookahead or lookbehind assertion, e.g. `(?=a)` or `(?<!b)`.
+    Look {
+        node: Box<RegexNode>,
+        behind: bool,
+        negate: bool,
+    },
+    /// A quantifier applied to a whole group, e.g. `(ab)+`.
+    Repetition {
+        node: Box<RegexNode>,
+        rep: RegexRep,
+    },
+}
+
+impl RegexNode {
+    /// Adds `offset` to the number of every capture group in the node and of every
+    /// backreference, so that the node can follow the groups of another pattern.
+    pub(crate) fn shift_groups(&mut self, offset: usize) {
+        match self {
+            RegexNode::Empty | RegexNode::Step(_) | RegexNode::Anchor(_) => {}
+            RegexNode::Backreference { index, .. } => *index += offset,
+            RegexNode::Concat(nodes) | RegexNode::Alternation(nodes) => {
+                for node in nodes {
+                    node.shift_groups(offset);
+                }
+            }
+            RegexNode::Group { node, index, .. } => {
+                if let Some(index) = index {
+                    *index += offset;
+                }
+                node.shift_groups(offset);
+            }
+            RegexNode::Look { node, .. } | RegexNode::Repetition { node, .. } => {
+                node.shift_groups(offset)
+            }
+        }
+    }
+
+    /// Returns the highest capture group number in the node, or 0 if it has no groups.
+    pub(crate) fn group_count(&self) -> usize {
+        match self {
+            RegexNode::Empty
+            | RegexNode::Step(_)
+            | RegexNode::Anchor(_)
+            | RegexNode::Backreference { .. } => 0,
+            RegexNode::Concat(nodes) | RegexNode::Alternation(nodes) => {
+                nodes.iter().map(RegexNode::group_count).max().unwrap_or(0)
+            }
+            RegexNode::Group { node, index, .. } => index.unwrap_or(0).max(node.group_count()),
+            RegexNode::Look { node, .. } | RegexNode::Repetition { node, .. } => node.group_count(),
+        }
+    }
+
+    /// Returns the longest text, in chars, that the node can match, or `None` if there is no
+    /// limit.
+    pub fn max_len(&self) -> Option<usize> {
+        match self {
+            RegexNode::Empty | RegexNode::Anchor(_) | RegexNode::Look { .. } => Some(0),
+            RegexNode::Step(step) => step.rep.max_count(),
+            RegexNode::Backreference { .. } => None,
+            RegexNode::Concat(nodes) => nodes
+                .iter()
+                .try_fold(0, |len, node| node.max_len().map(|n| len + n)),
+            RegexNode::Alternation(branches) => branches
+                .iter()
+                .try_fold(0, |len, node| node.max_len().map(|n| len.max(n))),
+            RegexNode::Group { node, .. } => node.max_len(),
+            RegexNode::Repetition { node, rep } => match (node.max_len()?, rep.max_count()) {
+                (0, _) => Some(0),
+                (len, count) => count.map(|count| len * count),
+            },
+        }
+    }
+}
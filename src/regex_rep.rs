@@ -1,9 +1,50 @@
-#[derive(Debug, Clone)]
+/// How many times a step or group is repeated.
+///
+/// `Any` and `Range` are greedy unless `lazy` is set: a greedy repetition prefers to repeat as
+/// many times as possible and a lazy one as few times as possible, falling back to the other
+/// counts only if the rest of the pattern does not match.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RegexRep {
-    Any,
+    Any {
+        lazy: bool,
+    },
     Exact(usize),
     Range {
         min: Option<usize>,
         max: Option<usize>,
+        lazy: bool,
     },
 }
+
+impl RegexRep {
+    /// Returns the lazy variant of this repetition. `Exact` repeats a fixed number of times, so
+    /// it is returned unchanged.
+    pub fn lazy(self) -> Self {
+        match self {
+            RegexRep::Any { .. } => RegexRep::Any { lazy: true },
+            RegexRep::Exact(n) => RegexRep::Exact(n),
+            RegexRep::Range { min, max, .. } => RegexRep::Range {
+                min,
+                max,
+                lazy: true,
+            },
+        }
+    }
+
+    pub fn is_lazy(&self) -> bool {
+        match self {
+            RegexRep::Any { lazy } | RegexRep::Range { lazy, .. } => *lazy,
+            RegexRep::Exact(_) => false,
+        }
+    }
+
+    /// Returns the largest number of times the step or group is repeated, or `None` if it is
+    /// unbounded.
+    pub fn max_count(&self) -> Option<usize> {
+        match *self {
+            RegexRep::Any { .. } => None,
+            RegexRep::Exact(n) => Some(n),
+            RegexRep::Range { max, .. } => max,
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use crate::engine::EngineKind;
+use crate::pike_vm::{PikeVm, PikeVmCache};
+use crate::pool::Pool;
+use crate::program::Program;
+use crate::regex_builder::RegexBuilder;
+use crate::regex_error::RegexError;
+use crate::regex_match::Match;
+use crate::regex_step::Regex;
+use crate::syntax::Dialect;
+use std::time::Duration;
+
+/// A list of patterns searched for together, reporting which of them match.
+///
+/// All the patterns are compiled into one program and a single pass of the `PikeVm` over the
+/// haystack finds every pattern that matches, however many there are. By default a set
+/// rejects patterns with backreferences or unbounded lookbehind, like
+/// `EngineKind::Automaton`, and applies the default size and nesting limits to each pattern;
+/// `RegexSetBuilder` changes both.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::regex_set::RegexSet;
+///
+/// let set = RegexSet::new([r"\d+", "foo", "ba[rz]", "^x"]).unwrap();
+/// let matches = set.matches("foo 42 baz");
+/// assert_eq!(matches.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+/// assert!(!matches.matched(3));
+///
+/// let spans: Vec<_> = set
+///     .first_matches("foo 42 baz")
+///     .iter()
+///     .map(|found| found.map(|found| found.range()))
+///     .collect();
+/// assert_eq!(spans, vec![Some(4..6), Some(0..3), Some(7..10), None]);
+/// ```
+pub struct RegexSet {
+    patterns: Vec<String>,
+    /// The program of the patterns the automaton engines run, or `None` if there are none.
+    program: Option<Program>,
+    /// Index in `patterns` of each pattern of `program`, in increasing order.
+    program_patterns: Vec<usize>,
+    /// The patterns that run on the backtracking engine, each on its own, with their index in
+    /// `patterns`.
+    backtracked: Vec<(usize, Regex)>,
+    /// Scratch memory of the `PikeVm` that runs `program`, kept between searches.
+    pike_vms: Pool<PikeVmCache>,
+}
+
+impl RegexSet {
+    /// Parses and compiles a list of patterns with the default options of `RegexSetBuilder`.
+    ///
+    /// # Returns
+    ///
+    /// The compiled set, or the `RegexError` of the first pattern that is invalid, that needs
+    /// the backtracking engine or that is too large.
+    pub fn new<I, S>(patterns: I) -> Result<Self, RegexError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        RegexSetBuilder::new(patterns).build()
+    }
+
+    /// The patterns of the set, in the order they were given.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Checks whether any pattern of the set matches anywhere in `haystack`.
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.matches(haystack).matched_any()
+    }
+
+    /// Finds which patterns of the set match in `haystack`, in a single pass. A pattern whose
+    /// backtracking search runs out of its budget counts as not matching; `try_matches`
+    /// reports that case.
+    pub fn matches(&self, haystack: &str) -> SetMatches {
+        SetMatches {
+            matched: self
+                .spans(haystack, false)
+                .iter()
+                .map(|span| matches!(span, Ok(Some(_))))
+                .collect(),
+        }
+    }
+
+    /// Same as `matches`, but returns `RegexError::BacktrackLimitExceeded` or
+    /// `RegexError::TimeLimitExceeded` when a backtracking search runs out of its budget.
+    pub fn try_matches(&self, haystack: &str) -> Result<SetMatches, RegexError> {
+        let matched = self
+            .spans(haystack, false)
+            .into_iter()
+            .map(|span| span.map(|span| span.is_some()))
+            .collect::<Result<_, _>>()?;
+        Ok(SetMatches { matched })
+    }
+
+    /// Finds the first match of every pattern in `haystack`.
+    ///
+    /// # Returns
+    ///
+    /// One entry per pattern: the match `Regex::find` would report for it alone, or `None` if
+    /// the pattern does not match or its backtracking search runs out of its budget. The
+    /// patterns the automaton engines run are all found in a single pass.
+    pub fn first_matches<'h>(&self, haystack: &'h str) -> Vec<Option<Match<'h>>> {
+        self.spans(haystack, true)
+            .into_iter()
+            .map(|span| {
+                let (start, end) = span.ok()??;
+                Some(Match::new(haystack, start, end))
+            })
+            .collect()
+    }
+
+    /// Same as `first_matches`, but reports a backtracking search that runs out of its budget,
+    /// like `try_matches`.
+    pub fn try_first_matches<'h>(
+        &self,
+        haystack: &'h str,
+    ) -> Result<Vec<Option<Match<'h>>>, RegexError> {
+        self.spans(haystack, true)
+            .into_iter()
+            .map(|span| Ok(span?.map(|(start, end)| Match::new(haystack, start, end))))
+            .collect()
+    }
+
+    /// Searches for every pattern, returning for each one the span of one of its matches, the
+    /// leftmost-first one if `leftmost` is set, or the error of its backtracking search.
+    fn spans(
+        &self,
+        haystack: &str,
+        leftmost: bool,
+    ) -> Vec<Result<Option<(usize, usize)>, RegexError>> {
+        let mut spans = vec![Ok(None); self.len()];
+        if let Some(program) = &self.program {
+            let mut found = vec![None; self.program_patterns.len()];
+            let cache = self.pike_vms.get(|| PikeVmCache::new(program));
+            PikeVm::with_cache(program, cache).search_set(haystack, &mut found, leftmost);
+            for (&pattern, span) in self.program_patterns.iter().zip(found) {
+                spans[pattern] = Ok(span);
+            }
+        }
+        for (pattern, regex) in &self.backtracked {
+            spans[*pattern] = regex
+                .try_find(haystack)
+                .map(|found| found.map(|found| (found.start(), found.end())));
+        }
+        spans
+    }
+}
+
+/// Configures and compiles a `RegexSet`, with the same options as `RegexBuilder` applied to
+/// every pattern.
+///
+/// Unlike `RegexBuilder`, the engine defaults to `EngineKind::Automaton`, so that every pattern
+/// is answered by the single pass over the haystack. With `EngineKind::Auto` the patterns that
+/// need the backtracking engine are accepted too, and each of them runs on its own after that
+/// pass; with `EngineKind::Backtracking` all of them do.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::engine::EngineKind;
+/// use rgrep::regex_set::RegexSetBuilder;
+///
+/// let set = RegexSetBuilder::new([r"(\w)\1", "ab"])
+///     .engine(EngineKind::Auto)
+///     .backtrack_limit(10_000)
+///     .build()
+///     .unwrap();
+/// assert_eq!(set.matches("xaab").iter().collect::<Vec<_>>(), vec![0, 1]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexSetBuilder {
+    patterns: Vec<String>,
+    /// The options of every pattern; its own pattern is not used.
+    builder: RegexBuilder,
+}
+
+impl RegexSetBuilder {
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = RegexBuilder::new("");
+        builder.engine(EngineKind::Automaton);
+        RegexSetBuilder {
+            patterns: patterns
+                .into_iter()
+                .map(|pattern| pattern.as_ref().to_string())
+                .collect(),
+            builder,
+        }
+    }
+
+    /// Ignores case in every pattern, like `RegexBuilder::case_insensitive`.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.builder.case_insensitive(yes);
+        self
+    }
+
+    /// Makes `^` and `$` match at every line, like `RegexBuilder::multi_line`.
+    pub fn multi_line(&mut self, yes: bool) -> &mut Self {
+        self.builder.multi_line(yes);
+        self
+    }
+
+    /// Chooses how the patterns are written, like `RegexBuilder::dialect`.
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.builder.dialect(dialect);
+        self
+    }
+
+    /// Sets the step budget of each search of a pattern that runs on the backtracking engine,
+    /// like `RegexBuilder::backtrack_limit`.
+    pub fn backtrack_limit(&mut self, steps: usize) -> &mut Self {
+        self.builder.backtrack_limit(steps);
+        self
+    }
+
+    /// Sets the time budget of each search of a pattern that runs on the backtracking engine,
+    /// like `RegexBuilder::time_limit`.
+    pub fn time_limit(&mut self, limit: Duration) -> &mut Self {
+        self.builder.time_limit(limit);
+        self
+    }
+
+    /// Sets the size limit of each pattern, like `RegexBuilder::size_limit`.
+    pub fn size_limit(&mut self, bytes: usize) -> &mut Self {
+        self.builder.size_limit(bytes);
+        self
+    }
+
+    /// Sets the nesting limit of each pattern, like `RegexBuilder::nest_limit`.
+    pub fn nest_limit(&mut self, levels: usize) -> &mut Self {
+        self.builder.nest_limit(levels);
+        self
+    }
+
+    /// Chooses which patterns run in the shared pass and which on the backtracking engine.
+    pub fn engine(&mut self, engine: EngineKind) -> &mut Self {
+        self.builder.engine(engine);
+        self
+    }
+
+    /// Parses and compiles the patterns with the configured options.
+    ///
+    /// # Returns
+    ///
+    /// The compiled set, or the `RegexError` of the first pattern that is invalid, that the
+    /// chosen engine does not support or that exceeds the size or nesting limits.
+    pub fn build(&self) -> Result<RegexSet, RegexError> {
+        let mut nodes = Vec::new();
+        let mut program_patterns = Vec::new();
+        let mut backtracked = Vec::new();
+        for (index, pattern) in self.patterns.iter().enumerate() {
+            let (node, backtrack) = self.builder.parse(pattern)?;
+            if backtrack {
+                backtracked.push((index, self.builder.compile(&node)));
+            } else {
+                program_patterns.push(index);
+                nodes.push(node);
+            }
+        }
+        Ok(RegexSet {
+            patterns: self.patterns.clone(),
+            program: (!nodes.is_empty()).then(|| Program::compile_set(&nodes)),
+            program_patterns,
+            backtracked,
+            pike_vms: Pool::new(),
+        })
+    }
+}
+
+/// The patterns of a `RegexSet` that matched a haystack, returned by `RegexSet::matches`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetMatches {
+    matched: Vec<bool>,
+}
+
+impl SetMatches {
+    /// Whether any pattern matched.
+    pub fn matched_any(&self) -> bool {
+        self.matched.contains(&true)
+    }
+
+    /// Whether the pattern at `index` matched.
+    pub fn matched(&self, index: usize) -> bool {
+        self.matched.get(index).copied().unwrap_or(false)
+    }
+
+    /// Number of patterns in the set, matched or not.
+    pub fn len(&self) -> usize {
+        self.matched.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matched.is_empty()
+    }
+
+    /// The indices of the patterns that matched, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.matched
+            .iter()
+            .enumerate()
+            .filter(|(_, matched)| **matched)
+            .map(|(index, _)| index)
+    }
+}
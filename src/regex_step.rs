@@ -1,11 +1,39 @@
+use crate::aho_corasick::AhoCorasick;
+use crate::backtrack::BacktrackerCache;
+use crate::lazy_dfa::LazyDfa;
+use crate::literals::RequiredLiterals;
+use crate::pike_vm::PikeVmCache;
+use crate::pool::Pool;
+use crate::program::Program;
 use crate::regex_rep::RegexRep;
 use crate::regex_val::RegexVal;
+use std::time::Duration;
 #[derive(Debug, Clone)]
 pub struct RegexStep {
     pub val: RegexVal,
     pub rep: RegexRep,
 }
 
+/// A compiled pattern. It is immutable once built, so a single `Regex` can be shared between
+/// threads, e.g. behind an `Arc`, and searched from all of them at the same time.
 pub struct Regex {
-    pub steps: Vec<RegexStep>,
+    pub(crate) program: Program,
+    /// Lazy DFA caches for `test`, one for each search running at the same time.
+    pub(crate) dfas: Pool<LazyDfa>,
+    /// Scratch memory of the `PikeVm` and the `Backtracker`, kept between searches in the same
+    /// way as the lazy DFA caches.
+    pub(crate) pike_vms: Pool<PikeVmCache>,
+    pub(crate) backtrackers: Pool<BacktrackerCache>,
+    /// Memory budget of each lazy DFA cache, in bytes.
+    pub(crate) dfa_cache_capacity: usize,
+    /// Literals every match contains, used to skip text before running an engine.
+    pub(crate) literals: RequiredLiterals,
+    /// The automaton that runs the searches instead of the engines when the pattern is an
+    /// alternation of literals.
+    pub(crate) aho_corasick: Option<AhoCorasick>,
+    pub(crate) backtrack_limit: usize,
+    /// How long a backtracking search may run before giving up, if there is a limit.
+    pub(crate) time_limit: Option<Duration>,
+    /// Whether searches run on the `Backtracker` instead of the automaton engines.
+    pub(crate) backtrack: bool,
 }
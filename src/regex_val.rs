@@ -1,9 +1,15 @@
+use crate::char_set::CharSet;
 use crate::class::Class;
 #[derive(Debug, Clone)]
 pub enum RegexVal {
     Literal(char),
     Wildcard,
-    Bracket(Vec<char>),
-    NegatedBracket(Vec<char>),
+    /// A bracket expression such as `[a-z_]` or `[^[:space:],]`.
+    Bracket(CharSet),
     Class(Class),
+    /// Any character outside the class, e.g. `\D`.
+    NegatedClass(Class),
+    /// Matches the same characters as the wrapped value, ignoring case under Unicode simple
+    /// case folding.
+    CaseInsensitive(Box<RegexVal>),
 }
@@ -0,0 +1,142 @@
+use crate::captures::Captures;
+use std::borrow::Cow;
+
+/// Something that produces the replacement text for a match, used by `Regex::replace` and
+/// friends.
+///
+/// It is implemented for template strings (`&str`, `String`, `&String`), which expand `$1`,
+/// `$name`, `${name}` and `$$`, for closures that receive the `Captures` of the match, and for
+/// `NoExpand`, which inserts its text verbatim.
+pub trait Replacer {
+    /// Appends the replacement for the match described by `caps` to `dst`.
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String);
+
+    /// Returns the replacement text if it does not depend on the match, which lets the caller
+    /// skip computing capture groups.
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
+        None
+    }
+}
+
+/// A replacement inserted as-is, without expanding `$` references.
+#[derive(Debug, Clone, Copy)]
+pub struct NoExpand<'s>(pub &'s str);
+
+impl Replacer for &str {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
+        expand(caps, self, dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
+        if self.contains('$') {
+            None
+        } else {
+            Some(Cow::Borrowed(self))
+        }
+    }
+}
+
+impl Replacer for String {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
+        self.as_str().replace_append(caps, dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
+        if self.contains('$') {
+            None
+        } else {
+            Some(Cow::Borrowed(self.as_str()))
+        }
+    }
+}
+
+impl Replacer for &String {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
+        self.as_str().replace_append(caps, dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
+        if self.contains('$') {
+            None
+        } else {
+            Some(Cow::Borrowed(self.as_str()))
+        }
+    }
+}
+
+impl Replacer for NoExpand<'_> {
+    fn replace_append(&mut self, _: &Captures<'_>, dst: &mut String) {
+        dst.push_str(self.0);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.0))
+    }
+}
+
+impl<F, T> Replacer for F
+where
+    F: FnMut(&Captures<'_>) -> T,
+    T: AsRef<str>,
+{
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
+        dst.push_str((*self)(caps).as_ref());
+    }
+}
+
+/// Expands a replacement template for one match and appends the result to `dst`.
+///
+/// `$N` and `${N}` insert group `N`, `$name` and `${name}` insert the named group, and `$$`
+/// inserts a literal `$`. A reference to a group that does not exist or did not participate in
+/// the match expands to nothing. `$name` takes the longest run of letters, digits and `_`, so
+/// `${1}a` has to be used to put a letter right after group 1. A `$` that does not start a valid
+/// reference is copied as-is.
+///
+/// # Arguments
+///
+/// * `caps` - The captures of the match being replaced.
+/// * `template` - The replacement template.
+/// * `dst` - The string receiving the expansion.
+pub fn expand(caps: &Captures<'_>, template: &str, dst: &mut String) {
+    let mut rest = template;
+    while let Some(i) = rest.find('$') {
+        dst.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            dst.push('$');
+            rest = after;
+            continue;
+        }
+
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) if end > 0 => (&braced[..end], &braced[end + 1..]),
+                _ => {
+                    dst.push('$');
+                    continue;
+                }
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                if end == 0 {
+                    dst.push('$');
+                    continue;
+                }
+                (&rest[..end], &rest[end..])
+            }
+        };
+        rest = after;
+
+        let group = match name.parse::<usize>() {
+            Ok(index) => caps.get(index),
+            Err(_) => caps.name(name),
+        };
+        if let Some(group) = group {
+            dst.push_str(group.as_str());
+        }
+    }
+    dst.push_str(rest);
+}
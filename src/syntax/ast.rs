@@ -0,0 +1,153 @@
+use crate::class::Class;
+use crate::flags::Flags;
+use crate::regex_rep::RegexRep;
+use std::ops::Range;
+
+/// A node of the syntax tree of a pattern, with the byte range of the pattern it was parsed
+/// from.
+///
+/// Two trees are equal when they have the same structure, whatever their spans: a tree and the
+/// tree parsed back from its printed form compare equal even though the printed pattern may be
+/// written differently.
+#[derive(Debug, Clone, Eq)]
+pub struct Ast {
+    pub kind: AstKind,
+    pub span: Range<usize>,
+}
+
+impl Ast {
+    pub fn new(kind: AstKind, span: Range<usize>) -> Self {
+        Ast { kind, span }
+    }
+}
+
+impl PartialEq for Ast {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+/// What a node of the syntax tree is, as written in the pattern.
+///
+/// Unlike `RegexNode`, the tree does not depend on flags: `^` is always `Assertion::Start` and
+/// letters are not marked as case-insensitive. `(?flags)` groups stay in the tree and only take
+/// effect when it is compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AstKind {
+    /// The empty pattern, e.g. a branch of `a|`.
+    Empty,
+    /// A character, written as itself or as an escape such as `\t` or `\x41`.
+    Literal(char),
+    /// `.`
+    Dot,
+    /// `\d`, `\w` or `\s`, or their negated forms `\D`, `\W` and `\S`.
+    Class {
+        class: Class,
+        negated: bool,
+    },
+    /// `\p{name}`, or `\P{name}` when `negated`.
+    Property {
+        name: String,
+        negated: bool,
+    },
+    /// A bracket expression such as `[a-z[:digit:]]`.
+    Bracket(Bracket),
+    Assertion(Assertion),
+    Backreference(Backreference),
+    /// A parenthesized subpattern.
+    Group {
+        kind: GroupKind,
+        ast: Box<Ast>,
+    },
+    /// A `(?flags)` group, which changes the flags of the rest of the enclosing group.
+    SetFlags(FlagChange),
+    /// A quantifier and the node it repeats.
+    Repetition {
+        ast: Box<Ast>,
+        rep: RegexRep,
+    },
+    Concat(Vec<Ast>),
+    Alternation(Vec<Ast>),
+}
+
+/// The members of a bracket expression, in the order they are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bracket {
+    /// Whether the expression starts with `^`.
+    pub negated: bool,
+    pub items: Vec<BracketItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BracketItem {
+    /// A character, written as itself or as `[.c.]` or `[=c=]`.
+    Char(char),
+    /// An inclusive range such as `a-z`.
+    Range(char, char),
+    /// A `[:name:]` class, or a `\d`, `\w` or `\s` escape; negated for `\D`, `\W` and `\S`.
+    Class { class: Class, negated: bool },
+    /// A `\p{name}` or `\P{name}` property.
+    Property { name: String, negated: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assertion {
+    /// `^`: start of the text, or of a line in multi-line mode.
+    Start,
+    /// `$`: end of the text, or of a line in multi-line mode.
+    End,
+    /// `\A`
+    TextStart,
+    /// `\z`
+    TextEnd,
+    /// `\b`
+    WordBoundary,
+    /// `\B`
+    NotWordBoundary,
+    /// `\<`
+    WordStart,
+    /// `\>`
+    WordEnd,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backreference {
+    /// `\1` to `\9`.
+    Number(usize),
+    /// `\k<name>`.
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupKind {
+    /// `(...)`
+    Capturing,
+    /// `(?<name>...)` or `(?P<name>...)`.
+    Named(String),
+    /// `(?:...)`, or `(?flags:...)` when it changes flags.
+    NonCapturing(FlagChange),
+    /// `(?=...)`, `(?!...)`, `(?<=...)` or `(?<!...)`.
+    Look { behind: bool, negate: bool },
+}
+
+/// The flags turned on or off by a `(?flags)` or `(?flags:...)` group; `None` leaves a flag
+/// as it was.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlagChange {
+    pub case_insensitive: Option<bool>,
+    pub multi_line: Option<bool>,
+}
+
+impl FlagChange {
+    /// Returns `flags` with this change applied.
+    pub fn apply(&self, flags: Flags) -> Flags {
+        Flags {
+            case_insensitive: self.case_insensitive.unwrap_or(flags.case_insensitive),
+            multi_line: self.multi_line.unwrap_or(flags.multi_line),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.case_insensitive.is_none() && self.multi_line.is_none()
+    }
+}
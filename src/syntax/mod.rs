@@ -0,0 +1,19 @@
+//! The syntax tree of a pattern, separate from its compiled form.
+//!
+//! `parse` turns a pattern into an `Ast` that keeps the byte span of every node, `Display`
+//! prints a tree back as a canonical pattern, and `Visitor`/`VisitorMut` walk it. Tools can
+//! inspect and rewrite patterns with these without compiling them; `Regex::new` compiles
+//! patterns through the same parser.
+//!
+//! For every tree returned by `parse`, `parse(&ast.to_string())` returns an equal tree.
+
+pub mod ast;
+pub mod parse;
+mod print;
+pub mod visitor;
+
+pub use ast::{
+    Assertion, Ast, AstKind, Backreference, Bracket, BracketItem, FlagChange, GroupKind,
+};
+pub use parse::{parse, parse_with, parse_with_nest_limit, Dialect, DEFAULT_NEST_LIMIT};
+pub use visitor::{Visitor, VisitorMut};
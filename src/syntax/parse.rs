@@ -0,0 +1,534 @@
+use crate::regex_error::{ErrorKind, RegexError};
+use crate::regex_rep::RegexRep;
+use crate::syntax::ast::{Assertion, Ast, AstKind, Backreference, FlagChange, GroupKind};
+use crate::utils::{handle_backslash, handle_brackets, handle_curly};
+use std::ops::Range;
+use std::str::Chars;
+
+/// Default maximum nesting depth of a pattern, see `parse_with_nest_limit`.
+pub const DEFAULT_NEST_LIMIT: usize = 250;
+
+/// The syntax a pattern is written in, as chosen with `grep -G` or `grep -E`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// POSIX basic regular expressions (BRE). Groups, intervals and alternation are written
+    /// `\(...\)`, `\{m,n\}` and `\|`, and `\+` and `\?` are quantifiers, while plain `(`, `)`,
+    /// `{`, `}`, `|`, `+` and `?` are literals. A `*` at the start of the pattern, of a group or
+    /// of a branch, or right after a leading `^`, is a literal, and so are a `^` that does not
+    /// start one of those and a `$` that does not end one.
+    Basic,
+    /// POSIX extended regular expressions (ERE), plus the extensions described in `parse`.
+    #[default]
+    Extended,
+}
+
+/// Parses a pattern written in the extended dialect into its syntax tree.
+///
+/// The grammar it accepts is:
+///
+/// ```text
+/// alternation := concat ('|' concat)*
+/// concat      := (atom quantifier*)*
+/// atom        := '(' group_kind? alternation ')' | '(?' flags ')'
+///              | '[' bracket ']' | anchor | backref | '\' char | '.' | literal
+/// anchor      := '^' | '$' | '\b' | '\B' | '\<' | '\>' | '\A' | '\z'
+/// backref     := '\' [1-9] | '\k<' name '>'
+/// group_kind  := '?:' | '?P<' name '>' | '?<' name '>' | '?' flags ':'
+///              | '?=' | '?!' | '?<=' | '?<!'
+/// flags       := [im]* ('-' [im]*)?
+/// quantifier  := ('*' | '+' | '?' | '{' min? (',' max?)? '}') '?'?
+/// literal     := any character but . [ \ ( ) * + ? { | ^ $
+/// ```
+///
+/// A `{` that does not start a valid quantifier is a literal too. Group names and
+/// backreferences are checked, so every tree returned here compiles.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::syntax::{parse, AstKind};
+/// let ast = parse("ab|c").unwrap();
+/// assert!(matches!(ast.kind, AstKind::Alternation(_)));
+/// assert_eq!(ast.span, 0..4);
+/// ```
+pub fn parse(pattern: &str) -> Result<Ast, RegexError> {
+    parse_with(pattern, Dialect::Extended)
+}
+
+/// Parses a pattern written in the given dialect into its syntax tree. The tree is the same
+/// for both dialects, e.g. `a\{2\}` in `Dialect::Basic` and `a{2}` in `Dialect::Extended`.
+pub fn parse_with(pattern: &str, dialect: Dialect) -> Result<Ast, RegexError> {
+    parse_with_nest_limit(pattern, dialect, DEFAULT_NEST_LIMIT)
+}
+
+/// Same as `parse_with`, but with a custom limit on how deeply the pattern may nest.
+///
+/// Each group counts as one level for everything inside it, and each quantifier as one more
+/// level for the atom it repeats, so `a**` nests as deep as `((a))`. Patterns that nest deeper than
+/// `nest_limit` fail with `ErrorKind::NestingTooDeep`, since the parser and the compiler recurse
+/// once per level.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::regex_error::ErrorKind;
+/// use rgrep::syntax::{parse_with_nest_limit, Dialect};
+/// assert!(parse_with_nest_limit("((a))", Dialect::Extended, 2).is_ok());
+/// let err = parse_with_nest_limit("(((a)))", Dialect::Extended, 2).err().unwrap();
+/// assert_eq!(err.kind(), Some(ErrorKind::NestingTooDeep));
+/// assert_eq!(err.span(), Some(2..3));
+/// ```
+pub fn parse_with_nest_limit(
+    pattern: &str,
+    dialect: Dialect,
+    nest_limit: usize,
+) -> Result<Ast, RegexError> {
+    let mut parser = AstParser {
+        pattern,
+        char_iter: pattern.chars(),
+        dialect,
+        nest_limit,
+        depth: 0,
+        group_names: vec![None],
+        max_backref: None,
+    };
+    let ast = parser.parse_alternation()?;
+    let start = parser.pos();
+    if parser.next_token().is_some() {
+        return Err(parser.error(ErrorKind::UnmatchedCloseParen, start));
+    }
+    if let Some((index, span)) = parser.max_backref {
+        if index >= parser.group_names.len() {
+            return Err(RegexError::new(
+                ErrorKind::InvalidBackreference,
+                pattern,
+                span,
+            ));
+        }
+    }
+    Ok(ast)
+}
+
+struct AstParser<'a> {
+    pattern: &'a str,
+    char_iter: Chars<'a>,
+    dialect: Dialect,
+    nest_limit: usize,
+    /// Number of groups that enclose the current position.
+    depth: usize,
+    /// Names of the groups opened so far, `None` for unnamed ones; group 0 is the whole match.
+    group_names: Vec<Option<String>>,
+    /// Highest group number used by a backreference and its span, checked once every group is
+    /// known.
+    max_backref: Option<(usize, Range<usize>)>,
+}
+
+impl<'a> AstParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.char_iter.clone().next()
+    }
+
+    fn basic(&self) -> bool {
+        self.dialect == Dialect::Basic
+    }
+
+    fn peek_token(&self) -> Option<Token> {
+        self.read_token().0
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let (token, rest) = self.read_token();
+        self.char_iter = rest;
+        token
+    }
+
+    /// Reads the next token from a copy of the iterator, returning the token and the copy.
+    fn read_token(&self) -> (Option<Token>, Chars<'a>) {
+        let mut chars = self.char_iter.clone();
+        let token = match chars.next() {
+            None => None,
+            Some('\\') if self.basic() => match chars.clone().next() {
+                // En BRE los operadores de ERE se escriben escapados.
+                Some(c @ ('(' | ')' | '|' | '{' | '+' | '?')) => {
+                    chars.next();
+                    Some(Token::Op(c))
+                }
+                Some('}') => {
+                    chars.next();
+                    Some(Token::Literal('}'))
+                }
+                _ => Some(Token::Op('\\')),
+            },
+            Some(c @ ('*' | '.' | '[' | '^' | '$' | '\\')) => Some(Token::Op(c)),
+            Some(c @ ('(' | ')' | '|' | '{' | '+' | '?')) if !self.basic() => Some(Token::Op(c)),
+            Some(c) => Some(Token::Literal(c)),
+        };
+        (token, chars)
+    }
+
+    /// Whether a `$` just read ends the pattern, a group or a branch, which is the only place
+    /// where it is an anchor in a basic pattern.
+    fn at_basic_end(&self) -> bool {
+        let rest = self.char_iter.as_str();
+        rest.is_empty() || rest.starts_with("\\)") || rest.starts_with("\\|")
+    }
+
+    /// Byte offset of the next character to read.
+    fn pos(&self) -> usize {
+        self.pattern.len() - self.char_iter.as_str().len()
+    }
+
+    /// Builds a node for the part of the pattern between `start` and the current position.
+    fn ast(&self, kind: AstKind, start: usize) -> Ast {
+        Ast::new(kind, start..self.pos())
+    }
+
+    /// Builds an error for the part of the pattern between `start` and the current position.
+    fn error(&self, kind: ErrorKind, start: usize) -> RegexError {
+        RegexError::new(kind, self.pattern, start..self.pos())
+    }
+
+    fn parse_alternation(&mut self) -> Result<Ast, RegexError> {
+        let start = self.pos();
+        let mut branches = vec![self.parse_concat()?];
+        while self.peek_token() == Some(Token::Op('|')) {
+            self.next_token();
+            branches.push(self.parse_concat()?);
+        }
+
+        if branches.len() == 1 {
+            Ok(branches.remove(0))
+        } else {
+            Ok(self.ast(AstKind::Alternation(branches), start))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Ast, RegexError> {
+        let concat_start = self.pos();
+        let mut asts: Vec<Ast> = Vec::new();
+        while let Some(token) = self.peek_token() {
+            if matches!(token, Token::Op('|' | ')')) {
+                break;
+            }
+            let start = self.pos();
+            self.next_token();
+
+            let token = match token {
+                Token::Op('*')
+                    if self.basic()
+                        && asts.last().is_none_or(|last| {
+                            last.kind == AstKind::Assertion(Assertion::Start)
+                        }) =>
+                {
+                    Token::Literal('*')
+                }
+                Token::Op('^') if self.basic() && !asts.is_empty() => Token::Literal('^'),
+                Token::Op('$') if self.basic() && !self.at_basic_end() => Token::Literal('$'),
+                token => token,
+            };
+
+            let quantifier = match token {
+                Token::Op('*') => Some(RegexRep::Any { lazy: false }),
+                Token::Op('+') => Some(RegexRep::Range {
+                    min: Some(1),
+                    max: None,
+                    lazy: false,
+                }),
+                Token::Op('?') => Some(RegexRep::Range {
+                    min: Some(0),
+                    max: Some(1),
+                    lazy: false,
+                }),
+                Token::Op('{') => {
+                    let basic = self.basic();
+                    match handle_curly(&mut self.char_iter, basic)
+                        .map_err(|kind| self.error(kind, start))?
+                    {
+                        // `\{` siempre abre un intervalo en BRE; en ERE un `{` suelto es literal.
+                        None if basic => {
+                            return Err(self.error(ErrorKind::InvalidRepetition, start))
+                        }
+                        rep => rep,
+                    }
+                }
+                _ => None,
+            };
+            if let Some(rep) = quantifier {
+                let rep = if self.basic() {
+                    rep
+                } else {
+                    self.greediness(rep)
+                };
+                match asts.pop() {
+                    Some(ast) if self.depth + stacked_repetitions(&ast) >= self.nest_limit => {
+                        return Err(self.error(ErrorKind::NestingTooDeep, start));
+                    }
+                    Some(ast) if is_repeatable(&ast) => {
+                        let ast_start = ast.span.start;
+                        let kind = AstKind::Repetition {
+                            ast: Box::new(ast),
+                            rep,
+                        };
+                        asts.push(self.ast(kind, ast_start));
+                    }
+                    _ => return Err(self.error(ErrorKind::NothingToRepeat, start)),
+                }
+                continue;
+            }
+
+            let kind = match token {
+                Token::Op('(') => {
+                    let group = self.parse_group(start)?;
+                    asts.push(group);
+                    continue;
+                }
+                Token::Op('{') => AstKind::Literal('{'),
+                Token::Op('^') => AstKind::Assertion(Assertion::Start),
+                Token::Op('$') => AstKind::Assertion(Assertion::End),
+                Token::Op('.') => AstKind::Dot,
+                Token::Op('\\') if matches!(self.peek(), Some('1'..='9' | 'k')) => {
+                    self.parse_backreference(start)?
+                }
+                Token::Op('\\')
+                    if matches!(self.peek(), Some('b' | 'B' | '<' | '>' | 'A' | 'z')) =>
+                {
+                    let assertion = match self.char_iter.next() {
+                        Some('b') => Assertion::WordBoundary,
+                        Some('B') => Assertion::NotWordBoundary,
+                        Some('<') => Assertion::WordStart,
+                        Some('>') => Assertion::WordEnd,
+                        Some('A') => Assertion::TextStart,
+                        _ => Assertion::TextEnd,
+                    };
+                    AstKind::Assertion(assertion)
+                }
+                Token::Op('\\') => {
+                    handle_backslash(&mut self.char_iter).map_err(|kind| self.error(kind, start))?
+                }
+                Token::Op('[') => AstKind::Bracket(
+                    handle_brackets(&mut self.char_iter).map_err(|kind| self.error(kind, start))?,
+                ),
+                Token::Op(_) => return Err(self.error(ErrorKind::UnexpectedChar, start)),
+                Token::Literal(c) => AstKind::Literal(c),
+            };
+            asts.push(self.ast(kind, start));
+        }
+
+        Ok(match asts.len() {
+            0 => self.ast(AstKind::Empty, concat_start),
+            1 => asts.remove(0),
+            _ => self.ast(AstKind::Concat(asts), concat_start),
+        })
+    }
+
+    /// Parses a group after its opening `(` or `\(`, which is at byte offset `start`.
+    fn parse_group(&mut self, start: usize) -> Result<Ast, RegexError> {
+        let open_end = self.pos();
+        let kind = match self.parse_group_prefix(start)? {
+            GroupPrefix::SetFlags(change) => return Ok(self.ast(AstKind::SetFlags(change), start)),
+            GroupPrefix::Group(kind) => kind,
+        };
+        match &kind {
+            GroupKind::Capturing => self.group_names.push(None),
+            GroupKind::Named(name) => {
+                if self.group_names.iter().flatten().any(|other| other == name) {
+                    return Err(self.error(ErrorKind::DuplicateGroupName, start));
+                }
+                self.group_names.push(Some(name.clone()));
+            }
+            GroupKind::NonCapturing(_) | GroupKind::Look { .. } => {}
+        }
+
+        if self.depth == self.nest_limit {
+            return Err(RegexError::new(
+                ErrorKind::NestingTooDeep,
+                self.pattern,
+                start..open_end,
+            ));
+        }
+        self.depth += 1;
+        let inner = self.parse_alternation()?;
+        self.depth -= 1;
+        if self.next_token() != Some(Token::Op(')')) {
+            // El error senala el `(` que quedo sin cerrar.
+            return Err(RegexError::new(
+                ErrorKind::UnclosedGroup,
+                self.pattern,
+                start..open_end,
+            ));
+        }
+        Ok(self.ast(
+            AstKind::Group {
+                kind,
+                ast: Box::new(inner),
+            },
+            start,
+        ))
+    }
+
+    /// Reads the `?:`, `?P<name>`, `?<name>`, `?flags)`, `?flags:` or lookaround prefix of a
+    /// group, if any. Basic patterns have no such prefixes.
+    fn parse_group_prefix(&mut self, start: usize) -> Result<GroupPrefix, RegexError> {
+        if self.basic() || self.peek() != Some('?') {
+            return Ok(GroupPrefix::Group(GroupKind::Capturing));
+        }
+        self.char_iter.next();
+
+        match self.peek() {
+            Some(':') => {
+                self.char_iter.next();
+                return Ok(GroupPrefix::Group(GroupKind::NonCapturing(
+                    FlagChange::default(),
+                )));
+            }
+            Some('P') => {
+                self.char_iter.next();
+                if self.char_iter.next() != Some('<') {
+                    return Err(self.error(ErrorKind::UnknownGroupFlag, start));
+                }
+                let name = self.parse_group_name(start)?;
+                return Ok(GroupPrefix::Group(GroupKind::Named(name)));
+            }
+            Some(c @ ('=' | '!')) => {
+                self.char_iter.next();
+                return Ok(GroupPrefix::Group(GroupKind::Look {
+                    behind: false,
+                    negate: c == '!',
+                }));
+            }
+            Some('<') => {
+                self.char_iter.next();
+                if let Some(c @ ('=' | '!')) = self.peek() {
+                    self.char_iter.next();
+                    return Ok(GroupPrefix::Group(GroupKind::Look {
+                        behind: true,
+                        negate: c == '!',
+                    }));
+                }
+                let name = self.parse_group_name(start)?;
+                return Ok(GroupPrefix::Group(GroupKind::Named(name)));
+            }
+            _ => {}
+        }
+
+        let mut enable = true;
+        let mut change = FlagChange::default();
+        let mut empty = true;
+        loop {
+            match self.char_iter.next() {
+                Some('i') => change.case_insensitive = Some(enable),
+                Some('m') => change.multi_line = Some(enable),
+                Some('-') if enable => {
+                    enable = false;
+                    continue;
+                }
+                Some(')') if !empty || !enable => return Ok(GroupPrefix::SetFlags(change)),
+                Some(':') if !empty || !enable => {
+                    return Ok(GroupPrefix::Group(GroupKind::NonCapturing(change)));
+                }
+                _ => return Err(self.error(ErrorKind::UnknownGroupFlag, start)),
+            }
+            empty = false;
+        }
+    }
+
+    /// Parses a `\1`..`\9` or `\k<name>` backreference after its `\`, which is at byte offset
+    /// `start`. A name has to belong to a group opened earlier in the pattern.
+    fn parse_backreference(&mut self, start: usize) -> Result<AstKind, RegexError> {
+        match self.char_iter.next() {
+            Some('k') => {
+                if self.char_iter.next() != Some('<') {
+                    return Err(self.error(ErrorKind::InvalidGroupName, start));
+                }
+                let name = self.parse_group_name(start)?;
+                if !self
+                    .group_names
+                    .iter()
+                    .flatten()
+                    .any(|other| *other == name)
+                {
+                    return Err(self.error(ErrorKind::UnknownGroupName, start));
+                }
+                Ok(AstKind::Backreference(Backreference::Named(name)))
+            }
+            Some(digit @ '1'..='9') => {
+                let index = digit as usize - '0' as usize;
+                if self
+                    .max_backref
+                    .as_ref()
+                    .is_none_or(|(max, _)| index > *max)
+                {
+                    self.max_backref = Some((index, start..self.pos()));
+                }
+                Ok(AstKind::Backreference(Backreference::Number(index)))
+            }
+            _ => Err(self.error(ErrorKind::InvalidBackreference, start)),
+        }
+    }
+
+    /// Consumes the `?` that makes the quantifier just read lazy, if there is one.
+    fn greediness(&mut self, rep: RegexRep) -> RegexRep {
+        if self.peek() == Some('?') {
+            self.char_iter.next();
+            rep.lazy()
+        } else {
+            rep
+        }
+    }
+
+    /// Reads a group name up to and including its closing `>`. `start` is the byte offset of
+    /// the construct the name belongs to, used for errors.
+    fn parse_group_name(&mut self, start: usize) -> Result<String, RegexError> {
+        let mut name = String::new();
+        for c in self.char_iter.by_ref() {
+            match c {
+                '>' if name.starts_with(|first: char| !first.is_ascii_digit()) => return Ok(name),
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => name.push(c),
+                _ => break,
+            }
+        }
+        Err(self.error(ErrorKind::InvalidGroupName, start))
+    }
+}
+
+/// A character of the pattern, or an escaped pair in a basic pattern, classified by the
+/// dialect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    /// An operator, spelled as in ERE: `(`, `)`, `|`, `*`, `+`, `?`, `{`, `^`, `$`, `.`, `[`, or
+    /// the `\` that starts any other escape.
+    Op(char),
+    Literal(char),
+}
+
+/// What follows the `(` of a group.
+enum GroupPrefix {
+    Group(GroupKind),
+    /// A `(?flags)` group that only changes the flags.
+    SetFlags(FlagChange),
+}
+
+/// Number of quantifiers applied one on top of another to the innermost atom of `ast`.
+fn stacked_repetitions(mut ast: &Ast) -> usize {
+    let mut count = 0;
+    while let AstKind::Repetition { ast: inner, .. } = &ast.kind {
+        count += 1;
+        ast = inner;
+    }
+    count
+}
+
+/// Whether a quantifier can follow the node: assertions, lookarounds and `(?flags)` groups
+/// match no text, so repeating them is an error.
+fn is_repeatable(ast: &Ast) -> bool {
+    !matches!(
+        ast.kind,
+        AstKind::Empty
+            | AstKind::Assertion(_)
+            | AstKind::SetFlags(_)
+            | AstKind::Group {
+                kind: GroupKind::Look { .. },
+                ..
+            }
+    )
+}
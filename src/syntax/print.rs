@@ -0,0 +1,235 @@
+use crate::class::Class;
+use crate::regex_rep::RegexRep;
+use crate::syntax::ast::{
+    Assertion, Ast, AstKind, Backreference, Bracket, BracketItem, FlagChange, GroupKind,
+};
+use std::fmt::{self, Write};
+
+impl fmt::Display for Ast {
+    /// Prints the tree as a canonical pattern: quantifiers in their shortest form, `(?<name>`
+    /// for named groups and escapes for every character that is not a plain literal.
+    ///
+    /// Parsing the printed pattern gives back an equal tree for any tree returned by
+    /// `syntax::parse`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rgrep::syntax::parse;
+    /// let ast = parse("(?P<x>a{1,}|b{0,1}?)[a-c-]").unwrap();
+    /// assert_eq!(ast.to_string(), "(?<x>a+|b??)[a-c[.-.]]");
+    /// assert_eq!(parse(&ast.to_string()).unwrap(), ast);
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            AstKind::Empty => Ok(()),
+            AstKind::Literal(c) => write_literal(f, *c),
+            AstKind::Dot => f.write_char('.'),
+            AstKind::Class { class, negated } => write_class(f, *class, *negated),
+            AstKind::Property { name, negated } => write_property(f, name, *negated),
+            AstKind::Bracket(bracket) => write_bracket(f, bracket),
+            AstKind::Assertion(assertion) => f.write_str(match assertion {
+                Assertion::Start => "^",
+                Assertion::End => "$",
+                Assertion::TextStart => "\\A",
+                Assertion::TextEnd => "\\z",
+                Assertion::WordBoundary => "\\b",
+                Assertion::NotWordBoundary => "\\B",
+                Assertion::WordStart => "\\<",
+                Assertion::WordEnd => "\\>",
+            }),
+            AstKind::Backreference(Backreference::Number(index)) => write!(f, "\\{}", index),
+            AstKind::Backreference(Backreference::Named(name)) => write!(f, "\\k<{}>", name),
+            AstKind::Group { kind, ast } => {
+                match kind {
+                    GroupKind::Capturing => f.write_char('(')?,
+                    GroupKind::Named(name) => write!(f, "(?<{}>", name)?,
+                    GroupKind::NonCapturing(change) if change.is_empty() => f.write_str("(?:")?,
+                    GroupKind::NonCapturing(change) => {
+                        f.write_str("(?")?;
+                        write_flags(f, change)?;
+                        f.write_char(':')?;
+                    }
+                    GroupKind::Look { behind, negate } => {
+                        f.write_str(if *behind { "(?<" } else { "(?" })?;
+                        f.write_char(if *negate { '!' } else { '=' })?;
+                    }
+                }
+                write!(f, "{})", ast)
+            }
+            AstKind::SetFlags(change) => {
+                f.write_str("(?")?;
+                write_flags(f, change)?;
+                f.write_char(')')
+            }
+            AstKind::Repetition { ast, rep } => {
+                write_grouped(f, ast, |kind| {
+                    matches!(
+                        kind,
+                        AstKind::Empty | AstKind::Concat(_) | AstKind::Alternation(_)
+                    )
+                })?;
+                let after_quantifier = matches!(ast.kind, AstKind::Repetition { .. });
+                write_quantifier(f, rep, after_quantifier)
+            }
+            AstKind::Concat(asts) => {
+                for ast in asts {
+                    write_grouped(f, ast, |kind| matches!(kind, AstKind::Alternation(_)))?;
+                }
+                Ok(())
+            }
+            AstKind::Alternation(branches) => {
+                for (i, branch) in branches.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char('|')?;
+                    }
+                    write!(f, "{}", branch)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Prints `ast`, wrapped in `(?:...)` if `needs_group` says it would otherwise bind wrongly.
+/// The parser never produces such trees, but trees built or rewritten by hand may.
+fn write_grouped(
+    f: &mut fmt::Formatter,
+    ast: &Ast,
+    needs_group: impl Fn(&AstKind) -> bool,
+) -> fmt::Result {
+    if needs_group(&ast.kind) {
+        write!(f, "(?:{})", ast)
+    } else {
+        write!(f, "{}", ast)
+    }
+}
+
+/// Prints a character outside brackets: as itself unless it is an operator of extended
+/// patterns or a control character, which are escaped.
+fn write_literal(f: &mut fmt::Formatter, c: char) -> fmt::Result {
+    match c {
+        '\\' | '.' | '[' | '(' | ')' | '*' | '+' | '?' | '{' | '|' | '^' | '$' => {
+            write!(f, "\\{}", c)
+        }
+        '\t' => f.write_str("\\t"),
+        '\n' => f.write_str("\\n"),
+        '\r' => f.write_str("\\r"),
+        '\x0C' => f.write_str("\\f"),
+        '\x0B' => f.write_str("\\v"),
+        _ if c.is_ascii_control() => write!(f, "\\x{:02X}", c as u32),
+        _ if c.is_control() => write!(f, "\\u{{{:X}}}", c as u32),
+        _ => f.write_char(c),
+    }
+}
+
+fn write_class(f: &mut fmt::Formatter, class: Class, negated: bool) -> fmt::Result {
+    let letter = match class {
+        Class::Digit => 'd',
+        Class::Word => 'w',
+        Class::Space => 's',
+        // Las demas clases no tienen escape propio y se escriben entre corchetes.
+        _ => {
+            let caret = if negated { "^" } else { "" };
+            return write!(f, "[{}[:{}:]]", caret, class.name());
+        }
+    };
+    f.write_char('\\')?;
+    f.write_char(if negated {
+        letter.to_ascii_uppercase()
+    } else {
+        letter
+    })
+}
+
+fn write_property(f: &mut fmt::Formatter, name: &str, negated: bool) -> fmt::Result {
+    write!(f, "\\{}{{{}}}", if negated { 'P' } else { 'p' }, name)
+}
+
+fn write_bracket(f: &mut fmt::Formatter, bracket: &Bracket) -> fmt::Result {
+    f.write_char('[')?;
+    if bracket.negated {
+        f.write_char('^')?;
+    }
+    for item in &bracket.items {
+        match item {
+            BracketItem::Char(c) => write_bracket_char(f, *c)?,
+            BracketItem::Range(start, end) => {
+                write_bracket_char(f, *start)?;
+                f.write_char('-')?;
+                write_bracket_char(f, *end)?;
+            }
+            BracketItem::Class {
+                class,
+                negated: false,
+            } => write!(f, "[:{}:]", class.name())?,
+            BracketItem::Class {
+                class,
+                negated: true,
+            } => write_class(f, *class, true)?,
+            BracketItem::Property { name, negated } => write_property(f, name, *negated)?,
+        }
+    }
+    f.write_char(']')
+}
+
+/// Prints a character inside brackets. Characters whose meaning depends on where they are in
+/// the expression are written as a collating element, e.g. `[.].]`, so they are members
+/// anywhere.
+fn write_bracket_char(f: &mut fmt::Formatter, c: char) -> fmt::Result {
+    match c {
+        ']' | '-' | '^' | '[' | '\\' => write!(f, "[.{}.]", c),
+        _ => f.write_char(c),
+    }
+}
+
+fn write_flags(f: &mut fmt::Formatter, change: &FlagChange) -> fmt::Result {
+    let flags = [('i', change.case_insensitive), ('m', change.multi_line)];
+    for (letter, _) in flags.iter().filter(|(_, value)| *value == Some(true)) {
+        f.write_char(*letter)?;
+    }
+    let disabled: String = flags
+        .iter()
+        .filter(|(_, value)| *value == Some(false))
+        .map(|(letter, _)| *letter)
+        .collect();
+    // `(?-)` es la unica forma de escribir un grupo de flags vacio.
+    if !disabled.is_empty() || change.is_empty() {
+        write!(f, "-{}", disabled)?;
+    }
+    Ok(())
+}
+
+/// Prints a quantifier. After another quantifier, `?` would make that one lazy instead, so
+/// it is written as `{0,1}`.
+fn write_quantifier(f: &mut fmt::Formatter, rep: &RegexRep, after_quantifier: bool) -> fmt::Result {
+    match rep {
+        RegexRep::Any { .. } => f.write_char('*')?,
+        RegexRep::Exact(n) => write!(f, "{{{}}}", n)?,
+        RegexRep::Range {
+            min: Some(1),
+            max: None,
+            ..
+        } => f.write_char('+')?,
+        RegexRep::Range {
+            min: Some(0),
+            max: Some(1),
+            ..
+        } if !after_quantifier => f.write_char('?')?,
+        RegexRep::Range { min, max, .. } => {
+            f.write_char('{')?;
+            if let Some(min) = min {
+                write!(f, "{}", min)?;
+            }
+            f.write_char(',')?;
+            if let Some(max) = max {
+                write!(f, "{}", max)?;
+            }
+            f.write_char('}')?;
+        }
+    }
+    if rep.is_lazy() {
+        f.write_char('?')?;
+    }
+    Ok(())
+}
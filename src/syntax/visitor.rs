@@ -0,0 +1,93 @@
+use crate::syntax::ast::{Ast, AstKind};
+
+/// Inspects a syntax tree node by node; see `Ast::visit`.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::syntax::{parse, Ast, AstKind, Visitor};
+///
+/// struct Literals(String);
+///
+/// impl Visitor for Literals {
+///     fn visit_pre(&mut self, ast: &Ast) {
+///         if let AstKind::Literal(c) = ast.kind {
+///             self.0.push(c);
+///         }
+///     }
+/// }
+///
+/// let mut literals = Literals(String::new());
+/// parse("(ab)+|c[d]").unwrap().visit(&mut literals);
+/// assert_eq!(literals.0, "abc");
+/// ```
+pub trait Visitor {
+    /// Called on a node before its children.
+    fn visit_pre(&mut self, _ast: &Ast) {}
+
+    /// Called on a node after its children.
+    fn visit_post(&mut self, _ast: &Ast) {}
+}
+
+/// Rewrites a syntax tree node by node; see `Ast::visit_mut`.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::syntax::{parse, Ast, AstKind, VisitorMut};
+///
+/// struct AnyChar;
+///
+/// impl VisitorMut for AnyChar {
+///     fn visit_post(&mut self, ast: &mut Ast) {
+///         if let AstKind::Literal('x') = ast.kind {
+///             ast.kind = AstKind::Dot;
+///         }
+///     }
+/// }
+///
+/// let mut ast = parse("ax(x|y)").unwrap();
+/// ast.visit_mut(&mut AnyChar);
+/// assert_eq!(ast.to_string(), "a.(.|y)");
+/// ```
+pub trait VisitorMut {
+    /// Called on a node before its children. Changes made here are seen when the children are
+    /// visited.
+    fn visit_pre(&mut self, _ast: &mut Ast) {}
+
+    /// Called on a node after its children.
+    fn visit_post(&mut self, _ast: &mut Ast) {}
+}
+
+impl Ast {
+    /// Walks the tree depth-first, in the order the nodes appear in the pattern, calling
+    /// `visit_pre` and `visit_post` on every node.
+    pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+        visitor.visit_pre(self);
+        match &self.kind {
+            AstKind::Group { ast, .. } | AstKind::Repetition { ast, .. } => ast.visit(visitor),
+            AstKind::Concat(asts) | AstKind::Alternation(asts) => {
+                for ast in asts {
+                    ast.visit(visitor);
+                }
+            }
+            _ => {}
+        }
+        visitor.visit_post(self);
+    }
+
+    /// Same as `visit`, but the visitor may change the nodes.
+    pub fn visit_mut<V: VisitorMut>(&mut self, visitor: &mut V) {
+        visitor.visit_pre(self);
+        match &mut self.kind {
+            AstKind::Group { ast, .. } | AstKind::Repetition { ast, .. } => ast.visit_mut(visitor),
+            AstKind::Concat(asts) | AstKind::Alternation(asts) => {
+                for ast in asts {
+                    ast.visit_mut(visitor);
+                }
+            }
+            _ => {}
+        }
+        visitor.visit_post(self);
+    }
+}
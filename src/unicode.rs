@@ -0,0 +1,156 @@
+use crate::char_set::CharSet;
+use crate::unicode_tables::{PropertyTable, EMOJI_RANGES, GENERAL_CATEGORIES, SCRIPTS};
+use std::sync::OnceLock;
+
+/// One-letter general category groups and the long names they can be written with.
+const CATEGORY_GROUPS: &[(&str, &[&str])] = &[
+    ("C", &["Other"]),
+    ("L", &["Letter"]),
+    ("LC", &["Cased_Letter"]),
+    ("M", &["Mark", "Combining_Mark"]),
+    ("N", &["Number"]),
+    ("P", &["Punctuation", "Punct"]),
+    ("S", &["Symbol"]),
+    ("Z", &["Separator"]),
+];
+
+/// Returns the characters with the Unicode property called `name`, as used in `\p{name}`.
+///
+/// `name` may be a general category (`L`, `Lu`, `Letter`, `Uppercase_Letter`), a script
+/// (`Greek`, `Grek`, `Han`), or `Emoji`, optionally prefixed by `gc=`, `General_Category=`,
+/// `sc=` or `Script=`. Names are compared ignoring case, spaces, `_` and `-`.
+///
+/// # Arguments
+///
+/// * `name` - The property name.
+/// * `negated` - Return the characters without the property instead, as for `\P{name}`.
+///
+/// # Returns
+///
+/// The sorted, non-overlapping ranges of the property, or `None` if it is unknown.
+pub fn property_ranges(name: &str, negated: bool) -> Option<Vec<(char, char)>> {
+    let (namespace, value) = match name.split_once('=') {
+        Some((namespace, value)) => (Some(normalize(namespace)), normalize(value)),
+        None => (None, normalize(name)),
+    };
+    let allow_category = matches!(namespace.as_deref(), None | Some("gc" | "generalcategory"));
+    let allow_script = matches!(namespace.as_deref(), None | Some("sc" | "script"));
+
+    let mut ranges = Vec::new();
+    if allow_category {
+        if let Some((short, _)) = CATEGORY_GROUPS.iter().find(|(short, long)| {
+            normalize(short) == value || long.iter().any(|long| normalize(long) == value)
+        }) {
+            // `LC` son las letras con mayuscula y minuscula: Lu, Ll y Lt.
+            let members: &[&str] = match *short {
+                "LC" => &["Lu", "Ll", "Lt"],
+                _ => &[],
+            };
+            for (category, _, table) in GENERAL_CATEGORIES {
+                if category.starts_with(short) || members.contains(category) {
+                    ranges.extend_from_slice(table);
+                }
+            }
+        } else if let Some((_, _, table)) = find(GENERAL_CATEGORIES, &value) {
+            ranges.extend_from_slice(table);
+        }
+    }
+    if ranges.is_empty() && allow_script {
+        if let Some((_, _, table)) = find(SCRIPTS, &value) {
+            ranges.extend_from_slice(table);
+        }
+    }
+    if ranges.is_empty() && namespace.is_none() && value == "emoji" {
+        ranges.extend_from_slice(EMOJI_RANGES);
+    }
+    if ranges.is_empty() {
+        return None;
+    }
+
+    let ranges = CharSet::new(ranges, Vec::new()).ranges().to_vec();
+    Some(if negated { complement(&ranges) } else { ranges })
+}
+
+/// Returns whether `c` is a word character, as matched by `\w` and `[[:word:]]` and as used by
+/// `\b`, `\<` and `\>`.
+///
+/// Follows the definition of UTS #18: Alphabetic characters, marks (`M`), decimal digits
+/// (`Nd`), connector punctuation (`Pc`, such as `_`) and the two Join_Control characters. Marks
+/// matter so that a combining accent stays inside its word, as in `e\u{301}`.
+///
+/// # Examples
+///
+/// ```
+/// use rgrep::unicode::is_word_char;
+/// assert!(is_word_char('_'));
+/// assert!(is_word_char('\u{301}'));
+/// assert!(!is_word_char('\u{bd}'));
+/// ```
+pub fn is_word_char(c: char) -> bool {
+    static WORD: OnceLock<CharSet> = OnceLock::new();
+    if c.is_ascii() {
+        return c.is_ascii_alphanumeric() || c == '_';
+    }
+    // Las tablas no traen la propiedad Alphabetic; la de la biblioteca estandar es la misma.
+    c.is_alphabetic()
+        || WORD
+            .get_or_init(|| {
+                let mut ranges = vec![('\u{200C}', '\u{200D}')];
+                for (category, _, table) in GENERAL_CATEGORIES {
+                    if category.starts_with('M') || matches!(*category, "Nd" | "Pc") {
+                        ranges.extend_from_slice(table);
+                    }
+                }
+                CharSet::new(ranges, Vec::new())
+            })
+            .contains(c)
+}
+
+fn find<'t>(tables: &'t [PropertyTable], value: &str) -> Option<&'t PropertyTable> {
+    tables
+        .iter()
+        .find(|(short, long, _)| normalize(short) == value || normalize(long) == value)
+}
+
+/// Lowercases a property name and drops spaces, `_` and `-`, following the loose matching
+/// rule of Unicode (UAX #44, LM3).
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Returns the characters outside the given sorted, non-overlapping ranges.
+fn complement(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut complement = Vec::with_capacity(ranges.len() + 1);
+    let mut next = Some('\0');
+    for &(start, end) in ranges {
+        if let Some(from) = next {
+            if from < start {
+                complement.push((from, before(start)));
+            }
+        }
+        next = after(end);
+    }
+    if let Some(from) = next {
+        complement.push((from, char::MAX));
+    }
+    complement
+}
+
+/// The character right before `c`, skipping the surrogate gap.
+fn before(c: char) -> char {
+    match c {
+        '\u{E000}' => '\u{D7FF}',
+        _ => char::from_u32(c as u32 - 1).unwrap_or('\0'),
+    }
+}
+
+/// The character right after `c`, skipping the surrogate gap, or `None` after `char::MAX`.
+fn after(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        _ => char::from_u32(c as u32 + 1),
+    }
+}
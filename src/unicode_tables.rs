@@ -0,0 +1,2316 @@
+// Generado por scripts/gen_unicode_tables.pl a partir de Unicode 14.0.0. No editar a mano.
+
+/// Version of the Unicode Character Database the tables were generated from.
+pub const UNICODE_VERSION: &str = "14.0.0";
+
+/// A property value: its short name, its long name and its sorted ranges.
+pub type PropertyTable = (&'static str, &'static str, &'static [(char, char)]);
+
+/// Two-letter general categories.
+pub const GENERAL_CATEGORIES: &[PropertyTable] = &[
+    ("Cc", "Control", GC_CC),
+    ("Cf", "Format", GC_CF),
+    ("Cn", "Unassigned", GC_CN),
+    ("Co", "Private_Use", GC_CO),
+    ("Ll", "Lowercase_Letter", GC_LL),
+    ("Lm", "Modifier_Letter", GC_LM),
+    ("Lo", "Other_Letter", GC_LO),
+    ("Lt", "Titlecase_Letter", GC_LT),
+    ("Lu", "Uppercase_Letter", GC_LU),
+    ("Mc", "Spacing_Mark", GC_MC),
+    ("Me", "Enclosing_Mark", GC_ME),
+    ("Mn", "Nonspacing_Mark", GC_MN),
+    ("Nd", "Decimal_Number", GC_ND),
+    ("Nl", "Letter_Number", GC_NL),
+    ("No", "Other_Number", GC_NO),
+    ("Pc", "Connector_Punctuation", GC_PC),
+    ("Pd", "Dash_Punctuation", GC_PD),
+    ("Pe", "Close_Punctuation", GC_PE),
+    ("Pf", "Final_Punctuation", GC_PF),
+    ("Pi", "Initial_Punctuation", GC_PI),
+    ("Po", "Other_Punctuation", GC_PO),
+    ("Ps", "Open_Punctuation", GC_PS),
+    ("Sc", "Currency_Symbol", GC_SC),
+    ("Sk", "Modifier_Symbol", GC_SK),
+    ("Sm", "Math_Symbol", GC_SM),
+    ("So", "Other_Symbol", GC_SO),
+    ("Zl", "Line_Separator", GC_ZL),
+    ("Zp", "Paragraph_Separator", GC_ZP),
+    ("Zs", "Space_Separator", GC_ZS),
+];
+
+/// Scripts.
+pub const SCRIPTS: &[PropertyTable] = &[
+    ("Adlm", "Adlam", SC_ADLM),
+    ("Aghb", "Caucasian_Albanian", SC_AGHB),
+    ("Ahom", "Ahom", SC_AHOM),
+    ("Arab", "Arabic", SC_ARAB),
+    ("Armi", "Imperial_Aramaic", SC_ARMI),
+    ("Armn", "Armenian", SC_ARMN),
+    ("Avst", "Avestan", SC_AVST),
+    ("Bali", "Balinese", SC_BALI),
+    ("Bamu", "Bamum", SC_BAMU),
+    ("Bass", "Bassa_Vah", SC_BASS),
+    ("Batk", "Batak", SC_BATK),
+    ("Beng", "Bengali", SC_BENG),
+    ("Bhks", "Bhaiksuki", SC_BHKS),
+    ("Bopo", "Bopomofo", SC_BOPO),
+    ("Brah", "Brahmi", SC_BRAH),
+    ("Brai", "Braille", SC_BRAI),
+    ("Bugi", "Buginese", SC_BUGI),
+    ("Buhd", "Buhid", SC_BUHD),
+    ("Cakm", "Chakma", SC_CAKM),
+    ("Cans", "Canadian_Aboriginal", SC_CANS),
+    ("Cari", "Carian", SC_CARI),
+    ("Cham", "Cham", SC_CHAM),
+    ("Cher", "Cherokee", SC_CHER),
+    ("Chrs", "Chorasmian", SC_CHRS),
+    ("Copt", "Coptic", SC_COPT),
+    ("Cpmn", "Cypro_Minoan", SC_CPMN),
+    ("Cprt", "Cypriot", SC_CPRT),
+    ("Cyrl", "Cyrillic", SC_CYRL),
+    ("Deva", "Devanagari", SC_DEVA),
+    ("Diak", "Dives_Akuru", SC_DIAK),
+    ("Dogr", "Dogra", SC_DOGR),
+    ("Dsrt", "Deseret", SC_DSRT),
+    ("Dupl", "Duployan", SC_DUPL),
+    ("Egyp", "Egyptian_Hieroglyphs", SC_EGYP),
+    ("Elba", "Elbasan", SC_ELBA),
+    ("Elym", "Elymaic", SC_ELYM),
+    ("Ethi", "Ethiopic", SC_ETHI),
+    ("Geor", "Georgian", SC_GEOR),
+    ("Glag", "Glagolitic", SC_GLAG),
+    ("Gong", "Gunjala_Gondi", SC_GONG),
+    ("Gonm", "Masaram_Gondi", SC_GONM),
+    ("Goth", "Gothic", SC_GOTH),
+    ("Gran", "Grantha", SC_GRAN),
+    ("Grek", "Greek", SC_GREK),
+    ("Gujr", "Gujarati", SC_GUJR),
+    ("Guru", "Gurmukhi", SC_GURU),
+    ("Hang", "Hangul", SC_HANG),
+    ("Hani", "Han", SC_HANI),
+    ("Hano", "Hanunoo", SC_HANO),
+    ("Hatr", "Hatran", SC_HATR),
+    ("Hebr", "Hebrew", SC_HEBR),
+    ("Hira", "Hiragana", SC_HIRA),
+    ("Hluw", "Anatolian_Hieroglyphs", SC_HLUW),
+    ("Hmng", "Pahawh_Hmong", SC_HMNG),
+    ("Hmnp", "Nyiakeng_Puachue_Hmong", SC_HMNP),
+    ("Hung", "Old_Hungarian", SC_HUNG),
+    ("Ital", "Old_Italic", SC_ITAL),
+    ("Java", "Javanese", SC_JAVA),
+    ("Kali", "Kayah_Li", SC_KALI),
+    ("Kana", "Katakana", SC_KANA),
+    ("Khar", "Kharoshthi", SC_KHAR),
+    ("Khmr", "Khmer", SC_KHMR),
+    ("Khoj", "Khojki", SC_KHOJ),
+    ("Kits", "Khitan_Small_Script", SC_KITS),
+    ("Knda", "Kannada", SC_KNDA),
+    ("Kthi", "Kaithi", SC_KTHI),
+    ("Lana", "Tai_Tham", SC_LANA),
+    ("Laoo", "Lao", SC_LAOO),
+    ("Latn", "Latin", SC_LATN),
+    ("Lepc", "Lepcha", SC_LEPC),
+    ("Limb", "Limbu", SC_LIMB),
+    ("Lina", "Linear_A", SC_LINA),
+    ("Linb", "Linear_B", SC_LINB),
+    ("Lisu", "Lisu", SC_LISU),
+    ("Lyci", "Lycian", SC_LYCI),
+    ("Lydi", "Lydian", SC_LYDI),
+    ("Mahj", "Mahajani", SC_MAHJ),
+    ("Maka", "Makasar", SC_MAKA),
+    ("Mand", "Mandaic", SC_MAND),
+    ("Mani", "Manichaean", SC_MANI),
+    ("Marc", "Marchen", SC_MARC),
+    ("Medf", "Medefaidrin", SC_MEDF),
+    ("Mend", "Mende_Kikakui", SC_MEND),
+    ("Merc", "Meroitic_Cursive", SC_MERC),
+    ("Mero", "Meroitic_Hieroglyphs", SC_MERO),
+    ("Mlym", "Malayalam", SC_MLYM),
+    ("Modi", "Modi", SC_MODI),
+    ("Mong", "Mongolian", SC_MONG),
+    ("Mroo", "Mro", SC_MROO),
+    ("Mtei", "Meetei_Mayek", SC_MTEI),
+    ("Mult", "Multani", SC_MULT),
+    ("Mymr", "Myanmar", SC_MYMR),
+    ("Nand", "Nandinagari", SC_NAND),
+    ("Narb", "Old_North_Arabian", SC_NARB),
+    ("Nbat", "Nabataean", SC_NBAT),
+    ("Newa", "Newa", SC_NEWA),
+    ("Nkoo", "Nko", SC_NKOO),
+    ("Nshu", "Nushu", SC_NSHU),
+    ("Ogam", "Ogham", SC_OGAM),
+    ("Olck", "Ol_Chiki", SC_OLCK),
+    ("Orkh", "Old_Turkic", SC_ORKH),
+    ("Orya", "Oriya", SC_ORYA),
+    ("Osge", "Osage", SC_OSGE),
+    ("Osma", "Osmanya", SC_OSMA),
+    ("Ougr", "Old_Uyghur", SC_OUGR),
+    ("Palm", "Palmyrene", SC_PALM),
+    ("Pauc", "Pau_Cin_Hau", SC_PAUC),
+    ("Perm", "Old_Permic", SC_PERM),
+    ("Phag", "Phags_Pa", SC_PHAG),
+    ("Phli", "Inscriptional_Pahlavi", SC_PHLI),
+    ("Phlp", "Psalter_Pahlavi", SC_PHLP),
+    ("Phnx", "Phoenician", SC_PHNX),
+    ("Plrd", "Miao", SC_PLRD),
+    ("Prti", "Inscriptional_Parthian", SC_PRTI),
+    ("Rjng", "Rejang", SC_RJNG),
+    ("Rohg", "Hanifi_Rohingya", SC_ROHG),
+    ("Runr", "Runic", SC_RUNR),
+    ("Samr", "Samaritan", SC_SAMR),
+    ("Sarb", "Old_South_Arabian", SC_SARB),
+    ("Saur", "Saurashtra", SC_SAUR),
+    ("Sgnw", "SignWriting", SC_SGNW),
+    ("Shaw", "Shavian", SC_SHAW),
+    ("Shrd", "Sharada", SC_SHRD),
+    ("Sidd", "Siddham", SC_SIDD),
+    ("Sind", "Khudawadi", SC_SIND),
+    ("Sinh", "Sinhala", SC_SINH),
+    ("Sogd", "Sogdian", SC_SOGD),
+    ("Sogo", "Old_Sogdian", SC_SOGO),
+    ("Sora", "Sora_Sompeng", SC_SORA),
+    ("Soyo", "Soyombo", SC_SOYO),
+    ("Sund", "Sundanese", SC_SUND),
+    ("Sylo", "Syloti_Nagri", SC_SYLO),
+    ("Syrc", "Syriac", SC_SYRC),
+    ("Tagb", "Tagbanwa", SC_TAGB),
+    ("Takr", "Takri", SC_TAKR),
+    ("Tale", "Tai_Le", SC_TALE),
+    ("Talu", "New_Tai_Lue", SC_TALU),
+    ("Taml", "Tamil", SC_TAML),
+    ("Tang", "Tangut", SC_TANG),
+    ("Tavt", "Tai_Viet", SC_TAVT),
+    ("Telu", "Telugu", SC_TELU),
+    ("Tfng", "Tifinagh", SC_TFNG),
+    ("Tglg", "Tagalog", SC_TGLG),
+    ("Thaa", "Thaana", SC_THAA),
+    ("Thai", "Thai", SC_THAI),
+    ("Tibt", "Tibetan", SC_TIBT),
+    ("Tirh", "Tirhuta", SC_TIRH),
+    ("Tnsa", "Tangsa", SC_TNSA),
+    ("Toto", "Toto", SC_TOTO),
+    ("Ugar", "Ugaritic", SC_UGAR),
+    ("Vaii", "Vai", SC_VAII),
+    ("Vith", "Vithkuqi", SC_VITH),
+    ("Wara", "Warang_Citi", SC_WARA),
+    ("Wcho", "Wancho", SC_WCHO),
+    ("Xpeo", "Old_Persian", SC_XPEO),
+    ("Xsux", "Cuneiform", SC_XSUX),
+    ("Yezi", "Yezidi", SC_YEZI),
+    ("Yiii", "Yi", SC_YIII),
+    ("Zanb", "Zanabazar_Square", SC_ZANB),
+    ("Zinh", "Inherited", SC_ZINH),
+    ("Zyyy", "Common", SC_ZYYY),
+    ("Zzzz", "Unknown", SC_ZZZZ),
+];
+
+/// Characters with the `Emoji` property.
+pub const EMOJI_RANGES: &[(char, char)] = EMOJI;
+
+const GC_CC: &[(char, char)] = &[
+    ('\u{0}', '\u{1F}'), ('\u{7F}', '\u{9F}'),
+];
+
+const GC_CF: &[(char, char)] = &[
+    ('\u{AD}', '\u{AD}'), ('\u{600}', '\u{605}'), ('\u{61C}', '\u{61C}'), ('\u{6DD}', '\u{6DD}'),
+    ('\u{70F}', '\u{70F}'), ('\u{890}', '\u{891}'), ('\u{8E2}', '\u{8E2}'), ('\u{180E}', '\u{180E}'),
+    ('\u{200B}', '\u{200F}'), ('\u{202A}', '\u{202E}'), ('\u{2060}', '\u{2064}'), ('\u{2066}', '\u{206F}'),
+    ('\u{FEFF}', '\u{FEFF}'), ('\u{FFF9}', '\u{FFFB}'), ('\u{110BD}', '\u{110BD}'), ('\u{110CD}', '\u{110CD}'),
+    ('\u{13430}', '\u{13438}'), ('\u{1BCA0}', '\u{1BCA3}'), ('\u{1D173}', '\u{1D17A}'), ('\u{E0001}', '\u{E0001}'),
+    ('\u{E0020}', '\u{E007F}'),
+];
+
+const GC_CN: &[(char, char)] = &[
+    ('\u{378}', '\u{379}'), ('\u{380}', '\u{383}'), ('\u{38B}', '\u{38B}'), ('\u{38D}', '\u{38D}'),
+    ('\u{3A2}', '\u{3A2}'), ('\u{530}', '\u{530}'), ('\u{557}', '\u{558}'), ('\u{58B}', '\u{58C}'),
+    ('\u{590}', '\u{590}'), ('\u{5C8}', '\u{5CF}'), ('\u{5EB}', '\u{5EE}'), ('\u{5F5}', '\u{5FF}'),
+    ('\u{70E}', '\u{70E}'), ('\u{74B}', '\u{74C}'), ('\u{7B2}', '\u{7BF}'), ('\u{7FB}', '\u{7FC}'),
+    ('\u{82E}', '\u{82F}'), ('\u{83F}', '\u{83F}'), ('\u{85C}', '\u{85D}'), ('\u{85F}', '\u{85F}'),
+    ('\u{86B}', '\u{86F}'), ('\u{88F}', '\u{88F}'), ('\u{892}', '\u{897}'), ('\u{984}', '\u{984}'),
+    ('\u{98D}', '\u{98E}'), ('\u{991}', '\u{992}'), ('\u{9A9}', '\u{9A9}'), ('\u{9B1}', '\u{9B1}'),
+    ('\u{9B3}', '\u{9B5}'), ('\u{9BA}', '\u{9BB}'), ('\u{9C5}', '\u{9C6}'), ('\u{9C9}', '\u{9CA}'),
+    ('\u{9CF}', '\u{9D6}'), ('\u{9D8}', '\u{9DB}'), ('\u{9DE}', '\u{9DE}'), ('\u{9E4}', '\u{9E5}'),
+    ('\u{9FF}', '\u{A00}'), ('\u{A04}', '\u{A04}'), ('\u{A0B}', '\u{A0E}'), ('\u{A11}', '\u{A12}'),
+    ('\u{A29}', '\u{A29}'), ('\u{A31}', '\u{A31}'), ('\u{A34}', '\u{A34}'), ('\u{A37}', '\u{A37}'),
+    ('\u{A3A}', '\u{A3B}'), ('\u{A3D}', '\u{A3D}'), ('\u{A43}', '\u{A46}'), ('\u{A49}', '\u{A4A}'),
+    ('\u{A4E}', '\u{A50}'), ('\u{A52}', '\u{A58}'), ('\u{A5D}', '\u{A5D}'), ('\u{A5F}', '\u{A65}'),
+    ('\u{A77}', '\u{A80}'), ('\u{A84}', '\u{A84}'), ('\u{A8E}', '\u{A8E}'), ('\u{A92}', '\u{A92}'),
+    ('\u{AA9}', '\u{AA9}'), ('\u{AB1}', '\u{AB1}'), ('\u{AB4}', '\u{AB4}'), ('\u{ABA}', '\u{ABB}'),
+    ('\u{AC6}', '\u{AC6}'), ('\u{ACA}', '\u{ACA}'), ('\u{ACE}', '\u{ACF}'), ('\u{AD1}', '\u{ADF}'),
+    ('\u{AE4}', '\u{AE5}'), ('\u{AF2}', '\u{AF8}'), ('\u{B00}', '\u{B00}'), ('\u{B04}', '\u{B04}'),
+    ('\u{B0D}', '\u{B0E}'), ('\u{B11}', '\u{B12}'), ('\u{B29}', '\u{B29}'), ('\u{B31}', '\u{B31}'),
+    ('\u{B34}', '\u{B34}'), ('\u{B3A}', '\u{B3B}'), ('\u{B45}', '\u{B46}'), ('\u{B49}', '\u{B4A}'),
+    ('\u{B4E}', '\u{B54}'), ('\u{B58}', '\u{B5B}'), ('\u{B5E}', '\u{B5E}'), ('\u{B64}', '\u{B65}'),
+    ('\u{B78}', '\u{B81}'), ('\u{B84}', '\u{B84}'), ('\u{B8B}', '\u{B8D}'), ('\u{B91}', '\u{B91}'),
+    ('\u{B96}', '\u{B98}'), ('\u{B9B}', '\u{B9B}'), ('\u{B9D}', '\u{B9D}'), ('\u{BA0}', '\u{BA2}'),
+    ('\u{BA5}', '\u{BA7}'), ('\u{BAB}', '\u{BAD}'), ('\u{BBA}', '\u{BBD}'), ('\u{BC3}', '\u{BC5}'),
+    ('\u{BC9}', '\u{BC9}'), ('\u{BCE}', '\u{BCF}'), ('\u{BD1}', '\u{BD6}'), ('\u{BD8}', '\u{BE5}'),
+    ('\u{BFB}', '\u{BFF}'), ('\u{C0D}', '\u{C0D}'), ('\u{C11}', '\u{C11}'), ('\u{C29}', '\u{C29}'),
+    ('\u{C3A}', '\u{C3B}'), ('\u{C45}', '\u{C45}'), ('\u{C49}', '\u{C49}'), ('\u{C4E}', '\u{C54}'),
+    ('\u{C57}', '\u{C57}'), ('\u{C5B}', '\u{C5C}'), ('\u{C5E}', '\u{C5F}'), ('\u{C64}', '\u{C65}'),
+    ('\u{C70}', '\u{C76}'), ('\u{C8D}', '\u{C8D}'), ('\u{C91}', '\u{C91}'), ('\u{CA9}', '\u{CA9}'),
+    ('\u{CB4}', '\u{CB4}'), ('\u{CBA}', '\u{CBB}'), ('\u{CC5}', '\u{CC5}'), ('\u{CC9}', '\u{CC9}'),
+    ('\u{CCE}', '\u{CD4}'), ('\u{CD7}', '\u{CDC}'), ('\u{CDF}', '\u{CDF}'), ('\u{CE4}', '\u{CE5}'),
+    ('\u{CF0}', '\u{CF0}'), ('\u{CF3}', '\u{CFF}'), ('\u{D0D}', '\u{D0D}'), ('\u{D11}', '\u{D11}'),
+    ('\u{D45}', '\u{D45}'), ('\u{D49}', '\u{D49}'), ('\u{D50}', '\u{D53}'), ('\u{D64}', '\u{D65}'),
+    ('\u{D80}', '\u{D80}'), ('\u{D84}', '\u{D84}'), ('\u{D97}', '\u{D99}'), ('\u{DB2}', '\u{DB2}'),
+    ('\u{DBC}', '\u{DBC}'), ('\u{DBE}', '\u{DBF}'), ('\u{DC7}', '\u{DC9}'), ('\u{DCB}', '\u{DCE}'),
+    ('\u{DD5}', '\u{DD5}'), ('\u{DD7}', '\u{DD7}'), ('\u{DE0}', '\u{DE5}'), ('\u{DF0}', '\u{DF1}'),
+    ('\u{DF5}', '\u{E00}'), ('\u{E3B}', '\u{E3E}'), ('\u{E5C}', '\u{E80}'), ('\u{E83}', '\u{E83}'),
+    ('\u{E85}', '\u{E85}'), ('\u{E8B}', '\u{E8B}'), ('\u{EA4}', '\u{EA4}'), ('\u{EA6}', '\u{EA6}'),
+    ('\u{EBE}', '\u{EBF}'), ('\u{EC5}', '\u{EC5}'), ('\u{EC7}', '\u{EC7}'), ('\u{ECE}', '\u{ECF}'),
+    ('\u{EDA}', '\u{EDB}'), ('\u{EE0}', '\u{EFF}'), ('\u{F48}', '\u{F48}'), ('\u{F6D}', '\u{F70}'),
+    ('\u{F98}', '\u{F98}'), ('\u{FBD}', '\u{FBD}'), ('\u{FCD}', '\u{FCD}'), ('\u{FDB}', '\u{FFF}'),
+    ('\u{10C6}', '\u{10C6}'), ('\u{10C8}', '\u{10CC}'), ('\u{10CE}', '\u{10CF}'), ('\u{1249}', '\u{1249}'),
+    ('\u{124E}', '\u{124F}'), ('\u{1257}', '\u{1257}'), ('\u{1259}', '\u{1259}'), ('\u{125E}', '\u{125F}'),
+    ('\u{1289}', '\u{1289}'), ('\u{128E}', '\u{128F}'), ('\u{12B1}', '\u{12B1}'), ('\u{12B6}', '\u{12B7}'),
+    ('\u{12BF}', '\u{12BF}'), ('\u{12C1}', '\u{12C1}'), ('\u{12C6}', '\u{12C7}'), ('\u{12D7}', '\u{12D7}'),
+    ('\u{1311}', '\u{1311}'), ('\u{1316}', '\u{1317}'), ('\u{135B}', '\u{135C}'), ('\u{137D}', '\u{137F}'),
+    ('\u{139A}', '\u{139F}'), ('\u{13F6}', '\u{13F7}'), ('\u{13FE}', '\u{13FF}'), ('\u{169D}', '\u{169F}'),
+    ('\u{16F9}', '\u{16FF}'), ('\u{1716}', '\u{171E}'), ('\u{1737}', '\u{173F}'), ('\u{1754}', '\u{175F}'),
+    ('\u{176D}', '\u{176D}'), ('\u{1771}', '\u{1771}'), ('\u{1774}', '\u{177F}'), ('\u{17DE}', '\u{17DF}'),
+    ('\u{17EA}', '\u{17EF}'), ('\u{17FA}', '\u{17FF}'), ('\u{181A}', '\u{181F}'), ('\u{1879}', '\u{187F}'),
+    ('\u{18AB}', '\u{18AF}'), ('\u{18F6}', '\u{18FF}'), ('\u{191F}', '\u{191F}'), ('\u{192C}', '\u{192F}'),
+    ('\u{193C}', '\u{193F}'), ('\u{1941}', '\u{1943}'), ('\u{196E}', '\u{196F}'), ('\u{1975}', '\u{197F}'),
+    ('\u{19AC}', '\u{19AF}'), ('\u{19CA}', '\u{19CF}'), ('\u{19DB}', '\u{19DD}'), ('\u{1A1C}', '\u{1A1D}'),
+    ('\u{1A5F}', '\u{1A5F}'), ('\u{1A7D}', '\u{1A7E}'), ('\u{1A8A}', '\u{1A8F}'), ('\u{1A9A}', '\u{1A9F}'),
+    ('\u{1AAE}', '\u{1AAF}'), ('\u{1ACF}', '\u{1AFF}'), ('\u{1B4D}', '\u{1B4F}'), ('\u{1B7F}', '\u{1B7F}'),
+    ('\u{1BF4}', '\u{1BFB}'), ('\u{1C38}', '\u{1C3A}'), ('\u{1C4A}', '\u{1C4C}'), ('\u{1C89}', '\u{1C8F}'),
+    ('\u{1CBB}', '\u{1CBC}'), ('\u{1CC8}', '\u{1CCF}'), ('\u{1CFB}', '\u{1CFF}'), ('\u{1F16}', '\u{1F17}'),
+    ('\u{1F1E}', '\u{1F1F}'), ('\u{1F46}', '\u{1F47}'), ('\u{1F4E}', '\u{1F4F}'), ('\u{1F58}', '\u{1F58}'),
+    ('\u{1F5A}', '\u{1F5A}'), ('\u{1F5C}', '\u{1F5C}'), ('\u{1F5E}', '\u{1F5E}'), ('\u{1F7E}', '\u{1F7F}'),
+    ('\u{1FB5}', '\u{1FB5}'), ('\u{1FC5}', '\u{1FC5}'), ('\u{1FD4}', '\u{1FD5}'), ('\u{1FDC}', '\u{1FDC}'),
+    ('\u{1FF0}', '\u{1FF1}'), ('\u{1FF5}', '\u{1FF5}'), ('\u{1FFF}', '\u{1FFF}'), ('\u{2065}', '\u{2065}'),
+    ('\u{2072}', '\u{2073}'), ('\u{208F}', '\u{208F}'), ('\u{209D}', '\u{209F}'), ('\u{20C1}', '\u{20CF}'),
+    ('\u{20F1}', '\u{20FF}'), ('\u{218C}', '\u{218F}'), ('\u{2427}', '\u{243F}'), ('\u{244B}', '\u{245F}'),
+    ('\u{2B74}', '\u{2B75}'), ('\u{2B96}', '\u{2B96}'), ('\u{2CF4}', '\u{2CF8}'), ('\u{2D26}', '\u{2D26}'),
+    ('\u{2D28}', '\u{2D2C}'), ('\u{2D2E}', '\u{2D2F}'), ('\u{2D68}', '\u{2D6E}'), ('\u{2D71}', '\u{2D7E}'),
+    ('\u{2D97}', '\u{2D9F}'), ('\u{2DA7}', '\u{2DA7}'), ('\u{2DAF}', '\u{2DAF}'), ('\u{2DB7}', '\u{2DB7}'),
+    ('\u{2DBF}', '\u{2DBF}'), ('\u{2DC7}', '\u{2DC7}'), ('\u{2DCF}', '\u{2DCF}'), ('\u{2DD7}', '\u{2DD7}'),
+    ('\u{2DDF}', '\u{2DDF}'), ('\u{2E5E}', '\u{2E7F}'), ('\u{2E9A}', '\u{2E9A}'), ('\u{2EF4}', '\u{2EFF}'),
+    ('\u{2FD6}', '\u{2FEF}'), ('\u{2FFC}', '\u{2FFF}'), ('\u{3040}', '\u{3040}'), ('\u{3097}', '\u{3098}'),
+    ('\u{3100}', '\u{3104}'), ('\u{3130}', '\u{3130}'), ('\u{318F}', '\u{318F}'), ('\u{31E4}', '\u{31EF}'),
+    ('\u{321F}', '\u{321F}'), ('\u{A48D}', '\u{A48F}'), ('\u{A4C7}', '\u{A4CF}'), ('\u{A62C}', '\u{A63F}'),
+    ('\u{A6F8}', '\u{A6FF}'), ('\u{A7CB}', '\u{A7CF}'), ('\u{A7D2}', '\u{A7D2}'), ('\u{A7D4}', '\u{A7D4}'),
+    ('\u{A7DA}', '\u{A7F1}'), ('\u{A82D}', '\u{A82F}'), ('\u{A83A}', '\u{A83F}'), ('\u{A878}', '\u{A87F}'),
+    ('\u{A8C6}', '\u{A8CD}'), ('\u{A8DA}', '\u{A8DF}'), ('\u{A954}', '\u{A95E}'), ('\u{A97D}', '\u{A97F}'),
+    ('\u{A9CE}', '\u{A9CE}'), ('\u{A9DA}', '\u{A9DD}'), ('\u{A9FF}', '\u{A9FF}'), ('\u{AA37}', '\u{AA3F}'),
+    ('\u{AA4E}', '\u{AA4F}'), ('\u{AA5A}', '\u{AA5B}'), ('\u{AAC3}', '\u{AADA}'), ('\u{AAF7}', '\u{AB00}'),
+    ('\u{AB07}', '\u{AB08}'), ('\u{AB0F}', '\u{AB10}'), ('\u{AB17}', '\u{AB1F}'), ('\u{AB27}', '\u{AB27}'),
+    ('\u{AB2F}', '\u{AB2F}'), ('\u{AB6C}', '\u{AB6F}'), ('\u{ABEE}', '\u{ABEF}'), ('\u{ABFA}', '\u{ABFF}'),
+    ('\u{D7A4}', '\u{D7AF}'), ('\u{D7C7}', '\u{D7CA}'), ('\u{D7FC}', '\u{D7FF}'), ('\u{FA6E}', '\u{FA6F}'),
+    ('\u{FADA}', '\u{FAFF}'), ('\u{FB07}', '\u{FB12}'), ('\u{FB18}', '\u{FB1C}'), ('\u{FB37}', '\u{FB37}'),
+    ('\u{FB3D}', '\u{FB3D}'), ('\u{FB3F}', '\u{FB3F}'), ('\u{FB42}', '\u{FB42}'), ('\u{FB45}', '\u{FB45}'),
+    ('\u{FBC3}', '\u{FBD2}'), ('\u{FD90}', '\u{FD91}'), ('\u{FDC8}', '\u{FDCE}'), ('\u{FDD0}', '\u{FDEF}'),
+    ('\u{FE1A}', '\u{FE1F}'), ('\u{FE53}', '\u{FE53}'), ('\u{FE67}', '\u{FE67}'), ('\u{FE6C}', '\u{FE6F}'),
+    ('\u{FE75}', '\u{FE75}'), ('\u{FEFD}', '\u{FEFE}'), ('\u{FF00}', '\u{FF00}'), ('\u{FFBF}', '\u{FFC1}'),
+    ('\u{FFC8}', '\u{FFC9}'), ('\u{FFD0}', '\u{FFD1}'), ('\u{FFD8}', '\u{FFD9}'), ('\u{FFDD}', '\u{FFDF}'),
+    ('\u{FFE7}', '\u{FFE7}'), ('\u{FFEF}', '\u{FFF8}'), ('\u{FFFE}', '\u{FFFF}'), ('\u{1000C}', '\u{1000C}'),
+    ('\u{10027}', '\u{10027}'), ('\u{1003B}', '\u{1003B}'), ('\u{1003E}', '\u{1003E}'), ('\u{1004E}', '\u{1004F}'),
+    ('\u{1005E}', '\u{1007F}'), ('\u{100FB}', '\u{100FF}'), ('\u{10103}', '\u{10106}'), ('\u{10134}', '\u{10136}'),
+    ('\u{1018F}', '\u{1018F}'), ('\u{1019D}', '\u{1019F}'), ('\u{101A1}', '\u{101CF}'), ('\u{101FE}', '\u{1027F}'),
+    ('\u{1029D}', '\u{1029F}'), ('\u{102D1}', '\u{102DF}'), ('\u{102FC}', '\u{102FF}'), ('\u{10324}', '\u{1032C}'),
+    ('\u{1034B}', '\u{1034F}'), ('\u{1037B}', '\u{1037F}'), ('\u{1039E}', '\u{1039E}'), ('\u{103C4}', '\u{103C7}'),
+    ('\u{103D6}', '\u{103FF}'), ('\u{1049E}', '\u{1049F}'), ('\u{104AA}', '\u{104AF}'), ('\u{104D4}', '\u{104D7}'),
+    ('\u{104FC}', '\u{104FF}'), ('\u{10528}', '\u{1052F}'), ('\u{10564}', '\u{1056E}'), ('\u{1057B}', '\u{1057B}'),
+    ('\u{1058B}', '\u{1058B}'), ('\u{10593}', '\u{10593}'), ('\u{10596}', '\u{10596}'), ('\u{105A2}', '\u{105A2}'),
+    ('\u{105B2}', '\u{105B2}'), ('\u{105BA}', '\u{105BA}'), ('\u{105BD}', '\u{105FF}'), ('\u{10737}', '\u{1073F}'),
+    ('\u{10756}', '\u{1075F}'), ('\u{10768}', '\u{1077F}'), ('\u{10786}', '\u{10786}'), ('\u{107B1}', '\u{107B1}'),
+    ('\u{107BB}', '\u{107FF}'), ('\u{10806}', '\u{10807}'), ('\u{10809}', '\u{10809}'), ('\u{10836}', '\u{10836}'),
+    ('\u{10839}', '\u{1083B}'), ('\u{1083D}', '\u{1083E}'), ('\u{10856}', '\u{10856}'), ('\u{1089F}', '\u{108A6}'),
+    ('\u{108B0}', '\u{108DF}'), ('\u{108F3}', '\u{108F3}'), ('\u{108F6}', '\u{108FA}'), ('\u{1091C}', '\u{1091E}'),
+    ('\u{1093A}', '\u{1093E}'), ('\u{10940}', '\u{1097F}'), ('\u{109B8}', '\u{109BB}'), ('\u{109D0}', '\u{109D1}'),
+    ('\u{10A04}', '\u{10A04}'), ('\u{10A07}', '\u{10A0B}'), ('\u{10A14}', '\u{10A14}'), ('\u{10A18}', '\u{10A18}'),
+    ('\u{10A36}', '\u{10A37}'), ('\u{10A3B}', '\u{10A3E}'), ('\u{10A49}', '\u{10A4F}'), ('\u{10A59}', '\u{10A5F}'),
+    ('\u{10AA0}', '\u{10ABF}'), ('\u{10AE7}', '\u{10AEA}'), ('\u{10AF7}', '\u{10AFF}'), ('\u{10B36}', '\u{10B38}'),
+    ('\u{10B56}', '\u{10B57}'), ('\u{10B73}', '\u{10B77}'), ('\u{10B92}', '\u{10B98}'), ('\u{10B9D}', '\u{10BA8}'),
+    ('\u{10BB0}', '\u{10BFF}'), ('\u{10C49}', '\u{10C7F}'), ('\u{10CB3}', '\u{10CBF}'), ('\u{10CF3}', '\u{10CF9}'),
+    ('\u{10D28}', '\u{10D2F}'), ('\u{10D3A}', '\u{10E5F}'), ('\u{10E7F}', '\u{10E7F}'), ('\u{10EAA}', '\u{10EAA}'),
+    ('\u{10EAE}', '\u{10EAF}'), ('\u{10EB2}', '\u{10EFF}'), ('\u{10F28}', '\u{10F2F}'), ('\u{10F5A}', '\u{10F6F}'),
+    ('\u{10F8A}', '\u{10FAF}'), ('\u{10FCC}', '\u{10FDF}'), ('\u{10FF7}', '\u{10FFF}'), ('\u{1104E}', '\u{11051}'),
+    ('\u{11076}', '\u{1107E}'), ('\u{110C3}', '\u{110CC}'), ('\u{110CE}', '\u{110CF}'), ('\u{110E9}', '\u{110EF}'),
+    ('\u{110FA}', '\u{110FF}'), ('\u{11135}', '\u{11135}'), ('\u{11148}', '\u{1114F}'), ('\u{11177}', '\u{1117F}'),
+    ('\u{111E0}', '\u{111E0}'), ('\u{111F5}', '\u{111FF}'), ('\u{11212}', '\u{11212}'), ('\u{1123F}', '\u{1127F}'),
+    ('\u{11287}', '\u{11287}'), ('\u{11289}', '\u{11289}'), ('\u{1128E}', '\u{1128E}'), ('\u{1129E}', '\u{1129E}'),
+    ('\u{112AA}', '\u{112AF}'), ('\u{112EB}', '\u{112EF}'), ('\u{112FA}', '\u{112FF}'), ('\u{11304}', '\u{11304}'),
+    ('\u{1130D}', '\u{1130E}'), ('\u{11311}', '\u{11312}'), ('\u{11329}', '\u{11329}'), ('\u{11331}', '\u{11331}'),
+    ('\u{11334}', '\u{11334}'), ('\u{1133A}', '\u{1133A}'), ('\u{11345}', '\u{11346}'), ('\u{11349}', '\u{1134A}'),
+    ('\u{1134E}', '\u{1134F}'), ('\u{11351}', '\u{11356}'), ('\u{11358}', '\u{1135C}'), ('\u{11364}', '\u{11365}'),
+    ('\u{1136D}', '\u{1136F}'), ('\u{11375}', '\u{113FF}'), ('\u{1145C}', '\u{1145C}'), ('\u{11462}', '\u{1147F}'),
+    ('\u{114C8}', '\u{114CF}'), ('\u{114DA}', '\u{1157F}'), ('\u{115B6}', '\u{115B7}'), ('\u{115DE}', '\u{115FF}'),
+    ('\u{11645}', '\u{1164F}'), ('\u{1165A}', '\u{1165F}'), ('\u{1166D}', '\u{1167F}'), ('\u{116BA}', '\u{116BF}'),
+    ('\u{116CA}', '\u{116FF}'), ('\u{1171B}', '\u{1171C}'), ('\u{1172C}', '\u{1172F}'), ('\u{11747}', '\u{117FF}'),
+    ('\u{1183C}', '\u{1189F}'), ('\u{118F3}', '\u{118FE}'), ('\u{11907}', '\u{11908}'), ('\u{1190A}', '\u{1190B}'),
+    ('\u{11914}', '\u{11914}'), ('\u{11917}', '\u{11917}'), ('\u{11936}', '\u{11936}'), ('\u{11939}', '\u{1193A}'),
+    ('\u{11947}', '\u{1194F}'), ('\u{1195A}', '\u{1199F}'), ('\u{119A8}', '\u{119A9}'), ('\u{119D8}', '\u{119D9}'),
+    ('\u{119E5}', '\u{119FF}'), ('\u{11A48}', '\u{11A4F}'), ('\u{11AA3}', '\u{11AAF}'), ('\u{11AF9}', '\u{11BFF}'),
+    ('\u{11C09}', '\u{11C09}'), ('\u{11C37}', '\u{11C37}'), ('\u{11C46}', '\u{11C4F}'), ('\u{11C6D}', '\u{11C6F}'),
+    ('\u{11C90}', '\u{11C91}'), ('\u{11CA8}', '\u{11CA8}'), ('\u{11CB7}', '\u{11CFF}'), ('\u{11D07}', '\u{11D07}'),
+    ('\u{11D0A}', '\u{11D0A}'), ('\u{11D37}', '\u{11D39}'), ('\u{11D3B}', '\u{11D3B}'), ('\u{11D3E}', '\u{11D3E}'),
+    ('\u{11D48}', '\u{11D4F}'), ('\u{11D5A}', '\u{11D5F}'), ('\u{11D66}', '\u{11D66}'), ('\u{11D69}', '\u{11D69}'),
+    ('\u{11D8F}', '\u{11D8F}'), ('\u{11D92}', '\u{11D92}'), ('\u{11D99}', '\u{11D9F}'), ('\u{11DAA}', '\u{11EDF}'),
+    ('\u{11EF9}', '\u{11FAF}'), ('\u{11FB1}', '\u{11FBF}'), ('\u{11FF2}', '\u{11FFE}'), ('\u{1239A}', '\u{123FF}'),
+    ('\u{1246F}', '\u{1246F}'), ('\u{12475}', '\u{1247F}'), ('\u{12544}', '\u{12F8F}'), ('\u{12FF3}', '\u{12FFF}'),
+    ('\u{1342F}', '\u{1342F}'), ('\u{13439}', '\u{143FF}'), ('\u{14647}', '\u{167FF}'), ('\u{16A39}', '\u{16A3F}'),
+    ('\u{16A5F}', '\u{16A5F}'), ('\u{16A6A}', '\u{16A6D}'), ('\u{16ABF}', '\u{16ABF}'), ('\u{16ACA}', '\u{16ACF}'),
+    ('\u{16AEE}', '\u{16AEF}'), ('\u{16AF6}', '\u{16AFF}'), ('\u{16B46}', '\u{16B4F}'), ('\u{16B5A}', '\u{16B5A}'),
+    ('\u{16B62}', '\u{16B62}'), ('\u{16B78}', '\u{16B7C}'), ('\u{16B90}', '\u{16E3F}'), ('\u{16E9B}', '\u{16EFF}'),
+    ('\u{16F4B}', '\u{16F4E}'), ('\u{16F88}', '\u{16F8E}'), ('\u{16FA0}', '\u{16FDF}'), ('\u{16FE5}', '\u{16FEF}'),
+    ('\u{16FF2}', '\u{16FFF}'), ('\u{187F8}', '\u{187FF}'), ('\u{18CD6}', '\u{18CFF}'), ('\u{18D09}', '\u{1AFEF}'),
+    ('\u{1AFF4}', '\u{1AFF4}'), ('\u{1AFFC}', '\u{1AFFC}'), ('\u{1AFFF}', '\u{1AFFF}'), ('\u{1B123}', '\u{1B14F}'),
+    ('\u{1B153}', '\u{1B163}'), ('\u{1B168}', '\u{1B16F}'), ('\u{1B2FC}', '\u{1BBFF}'), ('\u{1BC6B}', '\u{1BC6F}'),
+    ('\u{1BC7D}', '\u{1BC7F}'), ('\u{1BC89}', '\u{1BC8F}'), ('\u{1BC9A}', '\u{1BC9B}'), ('\u{1BCA4}', '\u{1CEFF}'),
+    ('\u{1CF2E}', '\u{1CF2F}'), ('\u{1CF47}', '\u{1CF4F}'), ('\u{1CFC4}', '\u{1CFFF}'), ('\u{1D0F6}', '\u{1D0FF}'),
+    ('\u{1D127}', '\u{1D128}'), ('\u{1D1EB}', '\u{1D1FF}'), ('\u{1D246}', '\u{1D2DF}'), ('\u{1D2F4}', '\u{1D2FF}'),
+    ('\u{1D357}', '\u{1D35F}'), ('\u{1D379}', '\u{1D3FF}'), ('\u{1D455}', '\u{1D455}'), ('\u{1D49D}', '\u{1D49D}'),
+    ('\u{1D4A0}', '\u{1D4A1}'), ('\u{1D4A3}', '\u{1D4A4}'), ('\u{1D4A7}', '\u{1D4A8}'), ('\u{1D4AD}', '\u{1D4AD}'),
+    ('\u{1D4BA}', '\u{1D4BA}'), ('\u{1D4BC}', '\u{1D4BC}'), ('\u{1D4C4}', '\u{1D4C4}'), ('\u{1D506}', '\u{1D506}'),
+    ('\u{1D50B}', '\u{1D50C}'), ('\u{1D515}', '\u{1D515}'), ('\u{1D51D}', '\u{1D51D}'), ('\u{1D53A}', '\u{1D53A}'),
+    ('\u{1D53F}', '\u{1D53F}'), ('\u{1D545}', '\u{1D545}'), ('\u{1D547}', '\u{1D549}'), ('\u{1D551}', '\u{1D551}'),
+    ('\u{1D6A6}', '\u{1D6A7}'), ('\u{1D7CC}', '\u{1D7CD}'), ('\u{1DA8C}', '\u{1DA9A}'), ('\u{1DAA0}', '\u{1DAA0}'),
+    ('\u{1DAB0}', '\u{1DEFF}'), ('\u{1DF1F}', '\u{1DFFF}'), ('\u{1E007}', '\u{1E007}'), ('\u{1E019}', '\u{1E01A}'),
+    ('\u{1E022}', '\u{1E022}'), ('\u{1E025}', '\u{1E025}'), ('\u{1E02B}', '\u{1E0FF}'), ('\u{1E12D}', '\u{1E12F}'),
+    ('\u{1E13E}', '\u{1E13F}'), ('\u{1E14A}', '\u{1E14D}'), ('\u{1E150}', '\u{1E28F}'), ('\u{1E2AF}', '\u{1E2BF}'),
+    ('\u{1E2FA}', '\u{1E2FE}'), ('\u{1E300}', '\u{1E7DF}'), ('\u{1E7E7}', '\u{1E7E7}'), ('\u{1E7EC}', '\u{1E7EC}'),
+    ('\u{1E7EF}', '\u{1E7EF}'), ('\u{1E7FF}', '\u{1E7FF}'), ('\u{1E8C5}', '\u{1E8C6}'), ('\u{1E8D7}', '\u{1E8FF}'),
+    ('\u{1E94C}', '\u{1E94F}'), ('\u{1E95A}', '\u{1E95D}'), ('\u{1E960}', '\u{1EC70}'), ('\u{1ECB5}', '\u{1ED00}'),
+    ('\u{1ED3E}', '\u{1EDFF}'), ('\u{1EE04}', '\u{1EE04}'), ('\u{1EE20}', '\u{1EE20}'), ('\u{1EE23}', '\u{1EE23}'),
+    ('\u{1EE25}', '\u{1EE26}'), ('\u{1EE28}', '\u{1EE28}'), ('\u{1EE33}', '\u{1EE33}'), ('\u{1EE38}', '\u{1EE38}'),
+    ('\u{1EE3A}', '\u{1EE3A}'), ('\u{1EE3C}', '\u{1EE41}'), ('\u{1EE43}', '\u{1EE46}'), ('\u{1EE48}', '\u{1EE48}'),
+    ('\u{1EE4A}', '\u{1EE4A}'), ('\u{1EE4C}', '\u{1EE4C}'), ('\u{1EE50}', '\u{1EE50}'), ('\u{1EE53}', '\u{1EE53}'),
+    ('\u{1EE55}', '\u{1EE56}'), ('\u{1EE58}', '\u{1EE58}'), ('\u{1EE5A}', '\u{1EE5A}'), ('\u{1EE5C}', '\u{1EE5C}'),
+    ('\u{1EE5E}', '\u{1EE5E}'), ('\u{1EE60}', '\u{1EE60}'), ('\u{1EE63}', '\u{1EE63}'), ('\u{1EE65}', '\u{1EE66}'),
+    ('\u{1EE6B}', '\u{1EE6B}'), ('\u{1EE73}', '\u{1EE73}'), ('\u{1EE78}', '\u{1EE78}'), ('\u{1EE7D}', '\u{1EE7D}'),
+    ('\u{1EE7F}', '\u{1EE7F}'), ('\u{1EE8A}', '\u{1EE8A}'), ('\u{1EE9C}', '\u{1EEA0}'), ('\u{1EEA4}', '\u{1EEA4}'),
+    ('\u{1EEAA}', '\u{1EEAA}'), ('\u{1EEBC}', '\u{1EEEF}'), ('\u{1EEF2}', '\u{1EFFF}'), ('\u{1F02C}', '\u{1F02F}'),
+    ('\u{1F094}', '\u{1F09F}'), ('\u{1F0AF}', '\u{1F0B0}'), ('\u{1F0C0}', '\u{1F0C0}'), ('\u{1F0D0}', '\u{1F0D0}'),
+    ('\u{1F0F6}', '\u{1F0FF}'), ('\u{1F1AE}', '\u{1F1E5}'), ('\u{1F203}', '\u{1F20F}'), ('\u{1F23C}', '\u{1F23F}'),
+    ('\u{1F249}', '\u{1F24F}'), ('\u{1F252}', '\u{1F25F}'), ('\u{1F266}', '\u{1F2FF}'), ('\u{1F6D8}', '\u{1F6DC}'),
+    ('\u{1F6ED}', '\u{1F6EF}'), ('\u{1F6FD}', '\u{1F6FF}'), ('\u{1F774}', '\u{1F77F}'), ('\u{1F7D9}', '\u{1F7DF}'),
+    ('\u{1F7EC}', '\u{1F7EF}'), ('\u{1F7F1}', '\u{1F7FF}'), ('\u{1F80C}', '\u{1F80F}'), ('\u{1F848}', '\u{1F84F}'),
+    ('\u{1F85A}', '\u{1F85F}'), ('\u{1F888}', '\u{1F88F}'), ('\u{1F8AE}', '\u{1F8AF}'), ('\u{1F8B2}', '\u{1F8FF}'),
+    ('\u{1FA54}', '\u{1FA5F}'), ('\u{1FA6E}', '\u{1FA6F}'), ('\u{1FA75}', '\u{1FA77}'), ('\u{1FA7D}', '\u{1FA7F}'),
+    ('\u{1FA87}', '\u{1FA8F}'), ('\u{1FAAD}', '\u{1FAAF}'), ('\u{1FABB}', '\u{1FABF}'), ('\u{1FAC6}', '\u{1FACF}'),
+    ('\u{1FADA}', '\u{1FADF}'), ('\u{1FAE8}', '\u{1FAEF}'), ('\u{1FAF7}', '\u{1FAFF}'), ('\u{1FB93}', '\u{1FB93}'),
+    ('\u{1FBCB}', '\u{1FBEF}'), ('\u{1FBFA}', '\u{1FFFF}'), ('\u{2A6E0}', '\u{2A6FF}'), ('\u{2B739}', '\u{2B73F}'),
+    ('\u{2B81E}', '\u{2B81F}'), ('\u{2CEA2}', '\u{2CEAF}'), ('\u{2EBE1}', '\u{2F7FF}'), ('\u{2FA1E}', '\u{2FFFF}'),
+    ('\u{3134B}', '\u{E0000}'), ('\u{E0002}', '\u{E001F}'), ('\u{E0080}', '\u{E00FF}'), ('\u{E01F0}', '\u{EFFFF}'),
+    ('\u{FFFFE}', '\u{FFFFF}'), ('\u{10FFFE}', '\u{10FFFF}'),
+];
+
+const GC_CO: &[(char, char)] = &[
+    ('\u{E000}', '\u{F8FF}'), ('\u{F0000}', '\u{FFFFD}'), ('\u{100000}', '\u{10FFFD}'),
+];
+
+const GC_LL: &[(char, char)] = &[
+    ('\u{61}', '\u{7A}'), ('\u{B5}', '\u{B5}'), ('\u{DF}', '\u{F6}'), ('\u{F8}', '\u{FF}'),
+    ('\u{101}', '\u{101}'), ('\u{103}', '\u{103}'), ('\u{105}', '\u{105}'), ('\u{107}', '\u{107}'),
+    ('\u{109}', '\u{109}'), ('\u{10B}', '\u{10B}'), ('\u{10D}', '\u{10D}'), ('\u{10F}', '\u{10F}'),
+    ('\u{111}', '\u{111}'), ('\u{113}', '\u{113}'), ('\u{115}', '\u{115}'), ('\u{117}', '\u{117}'),
+    ('\u{119}', '\u{119}'), ('\u{11B}', '\u{11B}'), ('\u{11D}', '\u{11D}'), ('\u{11F}', '\u{11F}'),
+    ('\u{121}', '\u{121}'), ('\u{123}', '\u{123}'), ('\u{125}', '\u{125}'), ('\u{127}', '\u{127}'),
+    ('\u{129}', '\u{129}'), ('\u{12B}', '\u{12B}'), ('\u{12D}', '\u{12D}'), ('\u{12F}', '\u{12F}'),
+    ('\u{131}', '\u{131}'), ('\u{133}', '\u{133}'), ('\u{135}', '\u{135}'), ('\u{137}', '\u{138}'),
+    ('\u{13A}', '\u{13A}'), ('\u{13C}', '\u{13C}'), ('\u{13E}', '\u{13E}'), ('\u{140}', '\u{140}'),
+    ('\u{142}', '\u{142}'), ('\u{144}', '\u{144}'), ('\u{146}', '\u{146}'), ('\u{148}', '\u{149}'),
+    ('\u{14B}', '\u{14B}'), ('\u{14D}', '\u{14D}'), ('\u{14F}', '\u{14F}'), ('\u{151}', '\u{151}'),
+    ('\u{153}', '\u{153}'), ('\u{155}', '\u{155}'), ('\u{157}', '\u{157}'), ('\u{159}', '\u{159}'),
+    ('\u{15B}', '\u{15B}'), ('\u{15D}', '\u{15D}'), ('\u{15F}', '\u{15F}'), ('\u{161}', '\u{161}'),
+    ('\u{163}', '\u{163}'), ('\u{165}', '\u{165}'), ('\u{167}', '\u{167}'), ('\u{169}', '\u{169}'),
+    ('\u{16B}', '\u{16B}'), ('\u{16D}', '\u{16D}'), ('\u{16F}', '\u{16F}'), ('\u{171}', '\u{171}'),
+    ('\u{173}', '\u{173}'), ('\u{175}', '\u{175}'), ('\u{177}', '\u{177}'), ('\u{17A}', '\u{17A}'),
+    ('\u{17C}', '\u{17C}'), ('\u{17E}', '\u{180}'), ('\u{183}', '\u{183}'), ('\u{185}', '\u{185}'),
+    ('\u{188}', '\u{188}'), ('\u{18C}', '\u{18D}'), ('\u{192}', '\u{192}'), ('\u{195}', '\u{195}'),
+    ('\u{199}', '\u{19B}'), ('\u{19E}', '\u{19E}'), ('\u{1A1}', '\u{1A1}'), ('\u{1A3}', '\u{1A3}'),
+    ('\u{1A5}', '\u{1A5}'), ('\u{1A8}', '\u{1A8}'), ('\u{1AA}', '\u{1AB}'), ('\u{1AD}', '\u{1AD}'),
+    ('\u{1B0}', '\u{1B0}'), ('\u{1B4}', '\u{1B4}'), ('\u{1B6}', '\u{1B6}'), ('\u{1B9}', '\u{1BA}'),
+    ('\u{1BD}', '\u{1BF}'), ('\u{1C6}', '\u{1C6}'), ('\u{1C9}', '\u{1C9}'), ('\u{1CC}', '\u{1CC}'),
+    ('\u{1CE}', '\u{1CE}'), ('\u{1D0}', '\u{1D0}'), ('\u{1D2}', '\u{1D2}'), ('\u{1D4}', '\u{1D4}'),
+    ('\u{1D6}', '\u{1D6}'), ('\u{1D8}', '\u{1D8}'), ('\u{1DA}', '\u{1DA}'), ('\u{1DC}', '\u{1DD}'),
+    ('\u{1DF}', '\u{1DF}'), ('\u{1E1}', '\u{1E1}'), ('\u{1E3}', '\u{1E3}'), ('\u{1E5}', '\u{1E5}'),
+    ('\u{1E7}', '\u{1E7}'), ('\u{1E9}', '\u{1E9}'), ('\u{1EB}', '\u{1EB}'), ('\u{1ED}', '\u{1ED}'),
+    ('\u{1EF}', '\u{1F0}'), ('\u{1F3}', '\u{1F3}'), ('\u{1F5}', '\u{1F5}'), ('\u{1F9}', '\u{1F9}'),
+    ('\u{1FB}', '\u{1FB}'), ('\u{1FD}', '\u{1FD}'), ('\u{1FF}', '\u{1FF}'), ('\u{201}', '\u{201}'),
+    ('\u{203}', '\u{203}'), ('\u{205}', '\u{205}'), ('\u{207}', '\u{207}'), ('\u{209}', '\u{209}'),
+    ('\u{20B}', '\u{20B}'), ('\u{20D}', '\u{20D}'), ('\u{20F}', '\u{20F}'), ('\u{211}', '\u{211}'),
+    ('\u{213}', '\u{213}'), ('\u{215}', '\u{215}'), ('\u{217}', '\u{217}'), ('\u{219}', '\u{219}'),
+    ('\u{21B}', '\u{21B}'), ('\u{21D}', '\u{21D}'), ('\u{21F}', '\u{21F}'), ('\u{221}', '\u{221}'),
+    ('\u{223}', '\u{223}'), ('\u{225}', '\u{225}'), ('\u{227}', '\u{227}'), ('\u{229}', '\u{229}'),
+    ('\u{22B}', '\u{22B}'), ('\u{22D}', '\u{22D}'), ('\u{22F}', '\u{22F}'), ('\u{231}', '\u{231}'),
+    ('\u{233}', '\u{239}'), ('\u{23C}', '\u{23C}'), ('\u{23F}', '\u{240}'), ('\u{242}', '\u{242}'),
+    ('\u{247}', '\u{247}'), ('\u{249}', '\u{249}'), ('\u{24B}', '\u{24B}'), ('\u{24D}', '\u{24D}'),
+    ('\u{24F}', '\u{293}'), ('\u{295}', '\u{2AF}'), ('\u{371}', '\u{371}'), ('\u{373}', '\u{373}'),
+    ('\u{377}', '\u{377}'), ('\u{37B}', '\u{37D}'), ('\u{390}', '\u{390}'), ('\u{3AC}', '\u{3CE}'),
+    ('\u{3D0}', '\u{3D1}'), ('\u{3D5}', '\u{3D7}'), ('\u{3D9}', '\u{3D9}'), ('\u{3DB}', '\u{3DB}'),
+    ('\u{3DD}', '\u{3DD}'), ('\u{3DF}', '\u{3DF}'), ('\u{3E1}', '\u{3E1}'), ('\u{3E3}', '\u{3E3}'),
+    ('\u{3E5}', '\u{3E5}'), ('\u{3E7}', '\u{3E7}'), ('\u{3E9}', '\u{3E9}'), ('\u{3EB}', '\u{3EB}'),
+    ('\u{3ED}', '\u{3ED}'), ('\u{3EF}', '\u{3F3}'), ('\u{3F5}', '\u{3F5}'), ('\u{3F8}', '\u{3F8}'),
+    ('\u{3FB}', '\u{3FC}'), ('\u{430}', '\u{45F}'), ('\u{461}', '\u{461}'), ('\u{463}', '\u{463}'),
+    ('\u{465}', '\u{465}'), ('\u{467}', '\u{467}'), ('\u{469}', '\u{469}'), ('\u{46B}', '\u{46B}'),
+    ('\u{46D}', '\u{46D}'), ('\u{46F}', '\u{46F}'), ('\u{471}', '\u{471}'), ('\u{473}', '\u{473}'),
+    ('\u{475}', '\u{475}'), ('\u{477}', '\u{477}'), ('\u{479}', '\u{479}'), ('\u{47B}', '\u{47B}'),
+    ('\u{47D}', '\u{47D}'), ('\u{47F}', '\u{47F}'), ('\u{481}', '\u{481}'), ('\u{48B}', '\u{48B}'),
+    ('\u{48D}', '\u{48D}'), ('\u{48F}', '\u{48F}'), ('\u{491}', '\u{491}'), ('\u{493}', '\u{493}'),
+    ('\u{495}', '\u{495}'), ('\u{497}', '\u{497}'), ('\u{499}', '\u{499}'), ('\u{49B}', '\u{49B}'),
+    ('\u{49D}', '\u{49D}'), ('\u{49F}', '\u{49F}'), ('\u{4A1}', '\u{4A1}'), ('\u{4A3}', '\u{4A3}'),
+    ('\u{4A5}', '\u{4A5}'), ('\u{4A7}', '\u{4A7}'), ('\u{4A9}', '\u{4A9}'), ('\u{4AB}', '\u{4AB}'),
+    ('\u{4AD}', '\u{4AD}'), ('\u{4AF}', '\u{4AF}'), ('\u{4B1}', '\u{4B1}'), ('\u{4B3}', '\u{4B3}'),
+    ('\u{4B5}', '\u{4B5}'), ('\u{4B7}', '\u{4B7}'), ('\u{4B9}', '\u{4B9}'), ('\u{4BB}', '\u{4BB}'),
+    ('\u{4BD}', '\u{4BD}'), ('\u{4BF}', '\u{4BF}'), ('\u{4C2}', '\u{4C2}'), ('\u{4C4}', '\u{4C4}'),
+    ('\u{4C6}', '\u{4C6}'), ('\u{4C8}', '\u{4C8}'), ('\u{4CA}', '\u{4CA}'), ('\u{4CC}', '\u{4CC}'),
+    ('\u{4CE}', '\u{4CF}'), ('\u{4D1}', '\u{4D1}'), ('\u{4D3}', '\u{4D3}'), ('\u{4D5}', '\u{4D5}'),
+    ('\u{4D7}', '\u{4D7}'), ('\u{4D9}', '\u{4D9}'), ('\u{4DB}', '\u{4DB}'), ('\u{4DD}', '\u{4DD}'),
+    ('\u{4DF}', '\u{4DF}'), ('\u{4E1}', '\u{4E1}'), ('\u{4E3}', '\u{4E3}'), ('\u{4E5}', '\u{4E5}'),
+    ('\u{4E7}', '\u{4E7}'), ('\u{4E9}', '\u{4E9}'), ('\u{4EB}', '\u{4EB}'), ('\u{4ED}', '\u{4ED}'),
+    ('\u{4EF}', '\u{4EF}'), ('\u{4F1}', '\u{4F1}'), ('\u{4F3}', '\u{4F3}'), ('\u{4F5}', '\u{4F5}'),
+    ('\u{4F7}', '\u{4F7}'), ('\u{4F9}', '\u{4F9}'), ('\u{4FB}', '\u{4FB}'), ('\u{4FD}', '\u{4FD}'),
+    ('\u{4FF}', '\u{4FF}'), ('\u{501}', '\u{501}'), ('\u{503}', '\u{503}'), ('\u{505}', '\u{505}'),
+    ('\u{507}', '\u{507}'), ('\u{509}', '\u{509}'), ('\u{50B}', '\u{50B}'), ('\u{50D}', '\u{50D}'),
+    ('\u{50F}', '\u{50F}'), ('\u{511}', '\u{511}'), ('\u{513}', '\u{513}'), ('\u{515}', '\u{515}'),
+    ('\u{517}', '\u{517}'), ('\u{519}', '\u{519}'), ('\u{51B}', '\u{51B}'), ('\u{51D}', '\u{51D}'),
+    ('\u{51F}', '\u{51F}'), ('\u{521}', '\u{521}'), ('\u{523}', '\u{523}'), ('\u{525}', '\u{525}'),
+    ('\u{527}', '\u{527}'), ('\u{529}', '\u{529}'), ('\u{52B}', '\u{52B}'), ('\u{52D}', '\u{52D}'),
+    ('\u{52F}', '\u{52F}'), ('\u{560}', '\u{588}'), ('\u{10D0}', '\u{10FA}'), ('\u{10FD}', '\u{10FF}'),
+    ('\u{13F8}', '\u{13FD}'), ('\u{1C80}', '\u{1C88}'), ('\u{1D00}', '\u{1D2B}'), ('\u{1D6B}', '\u{1D77}'),
+    ('\u{1D79}', '\u{1D9A}'), ('\u{1E01}', '\u{1E01}'), ('\u{1E03}', '\u{1E03}'), ('\u{1E05}', '\u{1E05}'),
+    ('\u{1E07}', '\u{1E07}'), ('\u{1E09}', '\u{1E09}'), ('\u{1E0B}', '\u{1E0B}'), ('\u{1E0D}', '\u{1E0D}'),
+    ('\u{1E0F}', '\u{1E0F}'), ('\u{1E11}', '\u{1E11}'), ('\u{1E13}', '\u{1E13}'), ('\u{1E15}', '\u{1E15}'),
+    ('\u{1E17}', '\u{1E17}'), ('\u{1E19}', '\u{1E19}'), ('\u{1E1B}', '\u{1E1B}'), ('\u{1E1D}', '\u{1E1D}'),
+    ('\u{1E1F}', '\u{1E1F}'), ('\u{1E21}', '\u{1E21}'), ('\u{1E23}', '\u{1E23}'), ('\u{1E25}', '\u{1E25}'),
+    ('\u{1E27}', '\u{1E27}'), ('\u{1E29}', '\u{1E29}'), ('\u{1E2B}', '\u{1E2B}'), ('\u{1E2D}', '\u{1E2D}'),
+    ('\u{1E2F}', '\u{1E2F}'), ('\u{1E31}', '\u{1E31}'), ('\u{1E33}', '\u{1E33}'), ('\u{1E35}', '\u{1E35}'),
+    ('\u{1E37}', '\u{1E37}'), ('\u{1E39}', '\u{1E39}'), ('\u{1E3B}', '\u{1E3B}'), ('\u{1E3D}', '\u{1E3D}'),
+    ('\u{1E3F}', '\u{1E3F}'), ('\u{1E41}', '\u{1E41}'), ('\u{1E43}', '\u{1E43}'), ('\u{1E45}', '\u{1E45}'),
+    ('\u{1E47}', '\u{1E47}'), ('\u{1E49}', '\u{1E49}'), ('\u{1E4B}', '\u{1E4B}'), ('\u{1E4D}', '\u{1E4D}'),
+    ('\u{1E4F}', '\u{1E4F}'), ('\u{1E51}', '\u{1E51}'), ('\u{1E53}', '\u{1E53}'), ('\u{1E55}', '\u{1E55}'),
+    ('\u{1E57}', '\u{1E57}'), ('\u{1E59}', '\u{1E59}'), ('\u{1E5B}', '\u{1E5B}'), ('\u{1E5D}', '\u{1E5D}'),
+    ('\u{1E5F}', '\u{1E5F}'), ('\u{1E61}', '\u{1E61}'), ('\u{1E63}', '\u{1E63}'), ('\u{1E65}', '\u{1E65}'),
+    ('\u{1E67}', '\u{1E67}'), ('\u{1E69}', '\u{1E69}'), ('\u{1E6B}', '\u{1E6B}'), ('\u{1E6D}', '\u{1E6D}'),
+    ('\u{1E6F}', '\u{1E6F}'), ('\u{1E71}', '\u{1E71}'), ('\u{1E73}', '\u{1E73}'), ('\u{1E75}', '\u{1E75}'),
+    ('\u{1E77}', '\u{1E77}'), ('\u{1E79}', '\u{1E79}'), ('\u{1E7B}', '\u{1E7B}'), ('\u{1E7D}', '\u{1E7D}'),
+    ('\u{1E7F}', '\u{1E7F}'), ('\u{1E81}', '\u{1E81}'), ('\u{1E83}', '\u{1E83}'), ('\u{1E85}', '\u{1E85}'),
+    ('\u{1E87}', '\u{1E87}'), ('\u{1E89}', '\u{1E89}'), ('\u{1E8B}', '\u{1E8B}'), ('\u{1E8D}', '\u{1E8D}'),
+    ('\u{1E8F}', '\u{1E8F}'), ('\u{1E91}', '\u{1E91}'), ('\u{1E93}', '\u{1E93}'), ('\u{1E95}', '\u{1E9D}'),
+    ('\u{1E9F}', '\u{1E9F}'), ('\u{1EA1}', '\u{1EA1}'), ('\u{1EA3}', '\u{1EA3}'), ('\u{1EA5}', '\u{1EA5}'),
+    ('\u{1EA7}', '\u{1EA7}'), ('\u{1EA9}', '\u{1EA9}'), ('\u{1EAB}', '\u{1EAB}'), ('\u{1EAD}', '\u{1EAD}'),
+    ('\u{1EAF}', '\u{1EAF}'), ('\u{1EB1}', '\u{1EB1}'), ('\u{1EB3}', '\u{1EB3}'), ('\u{1EB5}', '\u{1EB5}'),
+    ('\u{1EB7}', '\u{1EB7}'), ('\u{1EB9}', '\u{1EB9}'), ('\u{1EBB}', '\u{1EBB}'), ('\u{1EBD}', '\u{1EBD}'),
+    ('\u{1EBF}', '\u{1EBF}'), ('\u{1EC1}', '\u{1EC1}'), ('\u{1EC3}', '\u{1EC3}'), ('\u{1EC5}', '\u{1EC5}'),
+    ('\u{1EC7}', '\u{1EC7}'), ('\u{1EC9}', '\u{1EC9}'), ('\u{1ECB}', '\u{1ECB}'), ('\u{1ECD}', '\u{1ECD}'),
+    ('\u{1ECF}', '\u{1ECF}'), ('\u{1ED1}', '\u{1ED1}'), ('\u{1ED3}', '\u{1ED3}'), ('\u{1ED5}', '\u{1ED5}'),
+    ('\u{1ED7}', '\u{1ED7}'), ('\u{1ED9}', '\u{1ED9}'), ('\u{1EDB}', '\u{1EDB}'), ('\u{1EDD}', '\u{1EDD}'),
+    ('\u{1EDF}', '\u{1EDF}'), ('\u{1EE1}', '\u{1EE1}'), ('\u{1EE3}', '\u{1EE3}'), ('\u{1EE5}', '\u{1EE5}'),
+    ('\u{1EE7}', '\u{1EE7}'), ('\u{1EE9}', '\u{1EE9}'), ('\u{1EEB}', '\u{1EEB}'), ('\u{1EED}', '\u{1EED}'),
+    ('\u{1EEF}', '\u{1EEF}'), ('\u{1EF1}', '\u{1EF1}'), ('\u{1EF3}', '\u{1EF3}'), ('\u{1EF5}', '\u{1EF5}'),
+    ('\u{1EF7}', '\u{1EF7}'), ('\u{1EF9}', '\u{1EF9}'), ('\u{1EFB}', '\u{1EFB}'), ('\u{1EFD}', '\u{1EFD}'),
+    ('\u{1EFF}', '\u{1F07}'), ('\u{1F10}', '\u{1F15}'), ('\u{1F20}', '\u{1F27}'), ('\u{1F30}', '\u{1F37}'),
+    ('\u{1F40}', '\u{1F45}'), ('\u{1F50}', '\u{1F57}'), ('\u{1F60}', '\u{1F67}'), ('\u{1F70}', '\u{1F7D}'),
+    ('\u{1F80}', '\u{1F87}'), ('\u{1F90}', '\u{1F97}'), ('\u{1FA0}', '\u{1FA7}'), ('\u{1FB0}', '\u{1FB4}'),
+    ('\u{1FB6}', '\u{1FB7}'), ('\u{1FBE}', '\u{1FBE}'), ('\u{1FC2}', '\u{1FC4}'), ('\u{1FC6}', '\u{1FC7}'),
+    ('\u{1FD0}', '\u{1FD3}'), ('\u{1FD6}', '\u{1FD7}'), ('\u{1FE0}', '\u{1FE7}'), ('\u{1FF2}', '\u{1FF4}'),
+    ('\u{1FF6}', '\u{1FF7}'), ('\u{210A}', '\u{210A}'), ('\u{210E}', '\u{210F}'), ('\u{2113}', '\u{2113}'),
+    ('\u{212F}', '\u{212F}'), ('\u{2134}', '\u{2134}'), ('\u{2139}', '\u{2139}'), ('\u{213C}', '\u{213D}'),
+    ('\u{2146}', '\u{2149}'), ('\u{214E}', '\u{214E}'), ('\u{2184}', '\u{2184}'), ('\u{2C30}', '\u{2C5F}'),
+    ('\u{2C61}', '\u{2C61}'), ('\u{2C65}', '\u{2C66}'), ('\u{2C68}', '\u{2C68}'), ('\u{2C6A}', '\u{2C6A}'),
+    ('\u{2C6C}', '\u{2C6C}'), ('\u{2C71}', '\u{2C71}'), ('\u{2C73}', '\u{2C74}'), ('\u{2C76}', '\u{2C7B}'),
+    ('\u{2C81}', '\u{2C81}'), ('\u{2C83}', '\u{2C83}'), ('\u{2C85}', '\u{2C85}'), ('\u{2C87}', '\u{2C87}'),
+    ('\u{2C89}', '\u{2C89}'), ('\u{2C8B}', '\u{2C8B}'), ('\u{2C8D}', '\u{2C8D}'), ('\u{2C8F}', '\u{2C8F}'),
+    ('\u{2C91}', '\u{2C91}'), ('\u{2C93}', '\u{2C93}'), ('\u{2C95}', '\u{2C95}'), ('\u{2C97}', '\u{2C97}'),
+    ('\u{2C99}', '\u{2C99}'), ('\u{2C9B}', '\u{2C9B}'), ('\u{2C9D}', '\u{2C9D}'), ('\u{2C9F}', '\u{2C9F}'),
+    ('\u{2CA1}', '\u{2CA1}'), ('\u{2CA3}', '\u{2CA3}'), ('\u{2CA5}', '\u{2CA5}'), ('\u{2CA7}', '\u{2CA7}'),
+    ('\u{2CA9}', '\u{2CA9}'), ('\u{2CAB}', '\u{2CAB}'), ('\u{2CAD}', '\u{2CAD}'), ('\u{2CAF}', '\u{2CAF}'),
+    ('\u{2CB1}', '\u{2CB1}'), ('\u{2CB3}', '\u{2CB3}'), ('\u{2CB5}', '\u{2CB5}'), ('\u{2CB7}', '\u{2CB7}'),
+    ('\u{2CB9}', '\u{2CB9}'), ('\u{2CBB}', '\u{2CBB}'), ('\u{2CBD}', '\u{2CBD}'), ('\u{2CBF}', '\u{2CBF}'),
+    ('\u{2CC1}', '\u{2CC1}'), ('\u{2CC3}', '\u{2CC3}'), ('\u{2CC5}', '\u{2CC5}'), ('\u{2CC7}', '\u{2CC7}'),
+    ('\u{2CC9}', '\u{2CC9}'), ('\u{2CCB}', '\u{2CCB}'), ('\u{2CCD}', '\u{2CCD}'), ('\u{2CCF}', '\u{2CCF}'),
+    ('\u{2CD1}', '\u{2CD1}'), ('\u{2CD3}', '\u{2CD3}'), ('\u{2CD5}', '\u{2CD5}'), ('\u{2CD7}', '\u{2CD7}'),
+    ('\u{2CD9}', '\u{2CD9}'), ('\u{2CDB}', '\u{2CDB}'), ('\u{2CDD}', '\u{2CDD}'), ('\u{2CDF}', '\u{2CDF}'),
+    ('\u{2CE1}', '\u{2CE1}'), ('\u{2CE3}', '\u{2CE4}'), ('\u{2CEC}', '\u{2CEC}'), ('\u{2CEE}', '\u{2CEE}'),
+    ('\u{2CF3}', '\u{2CF3}'), ('\u{2D00}', '\u{2D25}'), ('\u{2D27}', '\u{2D27}'), ('\u{2D2D}', '\u{2D2D}'),
+    ('\u{A641}', '\u{A641}'), ('\u{A643}', '\u{A643}'), ('\u{A645}', '\u{A645}'), ('\u{A647}', '\u{A647}'),
+    ('\u{A649}', '\u{A649}'), ('\u{A64B}', '\u{A64B}'), ('\u{A64D}', '\u{A64D}'), ('\u{A64F}', '\u{A64F}'),
+    ('\u{A651}', '\u{A651}'), ('\u{A653}', '\u{A653}'), ('\u{A655}', '\u{A655}'), ('\u{A657}', '\u{A657}'),
+    ('\u{A659}', '\u{A659}'), ('\u{A65B}', '\u{A65B}'), ('\u{A65D}', '\u{A65D}'), ('\u{A65F}', '\u{A65F}'),
+    ('\u{A661}', '\u{A661}'), ('\u{A663}', '\u{A663}'), ('\u{A665}', '\u{A665}'), ('\u{A667}', '\u{A667}'),
+    ('\u{A669}', '\u{A669}'), ('\u{A66B}', '\u{A66B}'), ('\u{A66D}', '\u{A66D}'), ('\u{A681}', '\u{A681}'),
+    ('\u{A683}', '\u{A683}'), ('\u{A685}', '\u{A685}'), ('\u{A687}', '\u{A687}'), ('\u{A689}', '\u{A689}'),
+    ('\u{A68B}', '\u{A68B}'), ('\u{A68D}', '\u{A68D}'), ('\u{A68F}', '\u{A68F}'), ('\u{A691}', '\u{A691}'),
+    ('\u{A693}', '\u{A693}'), ('\u{A695}', '\u{A695}'), ('\u{A697}', '\u{A697}'), ('\u{A699}', '\u{A699}'),
+    ('\u{A69B}', '\u{A69B}'), ('\u{A723}', '\u{A723}'), ('\u{A725}', '\u{A725}'), ('\u{A727}', '\u{A727}'),
+    ('\u{A729}', '\u{A729}'), ('\u{A72B}', '\u{A72B}'), ('\u{A72D}', '\u{A72D}'), ('\u{A72F}', '\u{A731}'),
+    ('\u{A733}', '\u{A733}'), ('\u{A735}', '\u{A735}'), ('\u{A737}', '\u{A737}'), ('\u{A739}', '\u{A739}'),
+    ('\u{A73B}', '\u{A73B}'), ('\u{A73D}', '\u{A73D}'), ('\u{A73F}', '\u{A73F}'), ('\u{A741}', '\u{A741}'),
+    ('\u{A743}', '\u{A743}'), ('\u{A745}', '\u{A745}'), ('\u{A747}', '\u{A747}'), ('\u{A749}', '\u{A749}'),
+    ('\u{A74B}', '\u{A74B}'), ('\u{A74D}', '\u{A74D}'), ('\u{A74F}', '\u{A74F}'), ('\u{A751}', '\u{A751}'),
+    ('\u{A753}', '\u{A753}'), ('\u{A755}', '\u{A755}'), ('\u{A757}', '\u{A757}'), ('\u{A759}', '\u{A759}'),
+    ('\u{A75B}', '\u{A75B}'), ('\u{A75D}', '\u{A75D}'), ('\u{A75F}', '\u{A75F}'), ('\u{A761}', '\u{A761}'),
+    ('\u{A763}', '\u{A763}'), ('\u{A765}', '\u{A765}'), ('\u{A767}', '\u{A767}'), ('\u{A769}', '\u{A769}'),
+    ('\u{A76B}', '\u{A76B}'), ('\u{A76D}', '\u{A76D}'), ('\u{A76F}', '\u{A76F}'), ('\u{A771}', '\u{A778}'),
+    ('\u{A77A}', '\u{A77A}'), ('\u{A77C}', '\u{A77C}'), ('\u{A77F}', '\u{A77F}'), ('\u{A781}', '\u{A781}'),
+    ('\u{A783}', '\u{A783}'), ('\u{A785}', '\u{A785}'), ('\u{A787}', '\u{A787}'), ('\u{A78C}', '\u{A78C}'),
+    ('\u{A78E}', '\u{A78E}'), ('\u{A791}', '\u{A791}'), ('\u{A793}', '\u{A795}'), ('\u{A797}', '\u{A797}'),
+    ('\u{A799}', '\u{A799}'), ('\u{A79B}', '\u{A79B}'), ('\u{A79D}', '\u{A79D}'), ('\u{A79F}', '\u{A79F}'),
+    ('\u{A7A1}', '\u{A7A1}'), ('\u{A7A3}', '\u{A7A3}'), ('\u{A7A5}', '\u{A7A5}'), ('\u{A7A7}', '\u{A7A7}'),
+    ('\u{A7A9}', '\u{A7A9}'), ('\u{A7AF}', '\u{A7AF}'), ('\u{A7B5}', '\u{A7B5}'), ('\u{A7B7}', '\u{A7B7}'),
+    ('\u{A7B9}', '\u{A7B9}'), ('\u{A7BB}', '\u{A7BB}'), ('\u{A7BD}', '\u{A7BD}'), ('\u{A7BF}', '\u{A7BF}'),
+    ('\u{A7C1}', '\u{A7C1}'), ('\u{A7C3}', '\u{A7C3}'), ('\u{A7C8}', '\u{A7C8}'), ('\u{A7CA}', '\u{A7CA}'),
+    ('\u{A7D1}', '\u{A7D1}'), ('\u{A7D3}', '\u{A7D3}'), ('\u{A7D5}', '\u{A7D5}'), ('\u{A7D7}', '\u{A7D7}'),
+    ('\u{A7D9}', '\u{A7D9}'), ('\u{A7F6}', '\u{A7F6}'), ('\u{A7FA}', '\u{A7FA}'), ('\u{AB30}', '\u{AB5A}'),
+    ('\u{AB60}', '\u{AB68}'), ('\u{AB70}', '\u{ABBF}'), ('\u{FB00}', '\u{FB06}'), ('\u{FB13}', '\u{FB17}'),
+    ('\u{FF41}', '\u{FF5A}'), ('\u{10428}', '\u{1044F}'), ('\u{104D8}', '\u{104FB}'), ('\u{10597}', '\u{105A1}'),
+    ('\u{105A3}', '\u{105B1}'), ('\u{105B3}', '\u{105B9}'), ('\u{105BB}', '\u{105BC}'), ('\u{10CC0}', '\u{10CF2}'),
+    ('\u{118C0}', '\u{118DF}'), ('\u{16E60}', '\u{16E7F}'), ('\u{1D41A}', '\u{1D433}'), ('\u{1D44E}', '\u{1D454}'),
+    ('\u{1D456}', '\u{1D467}'), ('\u{1D482}', '\u{1D49B}'), ('\u{1D4B6}', '\u{1D4B9}'), ('\u{1D4BB}', '\u{1D4BB}'),
+    ('\u{1D4BD}', '\u{1D4C3}'), ('\u{1D4C5}', '\u{1D4CF}'), ('\u{1D4EA}', '\u{1D503}'), ('\u{1D51E}', '\u{1D537}'),
+    ('\u{1D552}', '\u{1D56B}'), ('\u{1D586}', '\u{1D59F}'), ('\u{1D5BA}', '\u{1D5D3}'), ('\u{1D5EE}', '\u{1D607}'),
+    ('\u{1D622}', '\u{1D63B}'), ('\u{1D656}', '\u{1D66F}'), ('\u{1D68A}', '\u{1D6A5}'), ('\u{1D6C2}', '\u{1D6DA}'),
+    ('\u{1D6DC}', '\u{1D6E1}'), ('\u{1D6FC}', '\u{1D714}'), ('\u{1D716}', '\u{1D71B}'), ('\u{1D736}', '\u{1D74E}'),
+    ('\u{1D750}', '\u{1D755}'), ('\u{1D770}', '\u{1D788}'), ('\u{1D78A}', '\u{1D78F}'), ('\u{1D7AA}', '\u{1D7C2}'),
+    ('\u{1D7C4}', '\u{1D7C9}'), ('\u{1D7CB}', '\u{1D7CB}'), ('\u{1DF00}', '\u{1DF09}'), ('\u{1DF0B}', '\u{1DF1E}'),
+    ('\u{1E922}', '\u{1E943}'),
+];
+
+const GC_LM: &[(char, char)] = &[
+    ('\u{2B0}', '\u{2C1}'), ('\u{2C6}', '\u{2D1}'), ('\u{2E0}', '\u{2E4}'), ('\u{2EC}', '\u{2EC}'),
+    ('\u{2EE}', '\u{2EE}'), ('\u{374}', '\u{374}'), ('\u{37A}', '\u{37A}'), ('\u{559}', '\u{559}'),
+    ('\u{640}', '\u{640}'), ('\u{6E5}', '\u{6E6}'), ('\u{7F4}', '\u{7F5}'), ('\u{7FA}', '\u{7FA}'),
+    ('\u{81A}', '\u{81A}'), ('\u{824}', '\u{824}'), ('\u{828}', '\u{828}'), ('\u{8C9}', '\u{8C9}'),
+    ('\u{971}', '\u{971}'), ('\u{E46}', '\u{E46}'), ('\u{EC6}', '\u{EC6}'), ('\u{10FC}', '\u{10FC}'),
+    ('\u{17D7}', '\u{17D7}'), ('\u{1843}', '\u{1843}'), ('\u{1AA7}', '\u{1AA7}'), ('\u{1C78}', '\u{1C7D}'),
+    ('\u{1D2C}', '\u{1D6A}'), ('\u{1D78}', '\u{1D78}'), ('\u{1D9B}', '\u{1DBF}'), ('\u{2071}', '\u{2071}'),
+    ('\u{207F}', '\u{207F}'), ('\u{2090}', '\u{209C}'), ('\u{2C7C}', '\u{2C7D}'), ('\u{2D6F}', '\u{2D6F}'),
+    ('\u{2E2F}', '\u{2E2F}'), ('\u{3005}', '\u{3005}'), ('\u{3031}', '\u{3035}'), ('\u{303B}', '\u{303B}'),
+    ('\u{309D}', '\u{309E}'), ('\u{30FC}', '\u{30FE}'), ('\u{A015}', '\u{A015}'), ('\u{A4F8}', '\u{A4FD}'),
+    ('\u{A60C}', '\u{A60C}'), ('\u{A67F}', '\u{A67F}'), ('\u{A69C}', '\u{A69D}'), ('\u{A717}', '\u{A71F}'),
+    ('\u{A770}', '\u{A770}'), ('\u{A788}', '\u{A788}'), ('\u{A7F2}', '\u{A7F4}'), ('\u{A7F8}', '\u{A7F9}'),
+    ('\u{A9CF}', '\u{A9CF}'), ('\u{A9E6}', '\u{A9E6}'), ('\u{AA70}', '\u{AA70}'), ('\u{AADD}', '\u{AADD}'),
+    ('\u{AAF3}', '\u{AAF4}'), ('\u{AB5C}', '\u{AB5F}'), ('\u{AB69}', '\u{AB69}'), ('\u{FF70}', '\u{FF70}'),
+    ('\u{FF9E}', '\u{FF9F}'), ('\u{10780}', '\u{10785}'), ('\u{10787}', '\u{107B0}'), ('\u{107B2}', '\u{107BA}'),
+    ('\u{16B40}', '\u{16B43}'), ('\u{16F93}', '\u{16F9F}'), ('\u{16FE0}', '\u{16FE1}'), ('\u{16FE3}', '\u{16FE3}'),
+    ('\u{1AFF0}', '\u{1AFF3}'), ('\u{1AFF5}', '\u{1AFFB}'), ('\u{1AFFD}', '\u{1AFFE}'), ('\u{1E137}', '\u{1E13D}'),
+    ('\u{1E94B}', '\u{1E94B}'),
+];
+
+const GC_LO: &[(char, char)] = &[
+    ('\u{AA}', '\u{AA}'), ('\u{BA}', '\u{BA}'), ('\u{1BB}', '\u{1BB}'), ('\u{1C0}', '\u{1C3}'),
+    ('\u{294}', '\u{294}'), ('\u{5D0}', '\u{5EA}'), ('\u{5EF}', '\u{5F2}'), ('\u{620}', '\u{63F}'),
+    ('\u{641}', '\u{64A}'), ('\u{66E}', '\u{66F}'), ('\u{671}', '\u{6D3}'), ('\u{6D5}', '\u{6D5}'),
+    ('\u{6EE}', '\u{6EF}'), ('\u{6FA}', '\u{6FC}'), ('\u{6FF}', '\u{6FF}'), ('\u{710}', '\u{710}'),
+    ('\u{712}', '\u{72F}'), ('\u{74D}', '\u{7A5}'), ('\u{7B1}', '\u{7B1}'), ('\u{7CA}', '\u{7EA}'),
+    ('\u{800}', '\u{815}'), ('\u{840}', '\u{858}'), ('\u{860}', '\u{86A}'), ('\u{870}', '\u{887}'),
+    ('\u{889}', '\u{88E}'), ('\u{8A0}', '\u{8C8}'), ('\u{904}', '\u{939}'), ('\u{93D}', '\u{93D}'),
+    ('\u{950}', '\u{950}'), ('\u{958}', '\u{961}'), ('\u{972}', '\u{980}'), ('\u{985}', '\u{98C}'),
+    ('\u{98F}', '\u{990}'), ('\u{993}', '\u{9A8}'), ('\u{9AA}', '\u{9B0}'), ('\u{9B2}', '\u{9B2}'),
+    ('\u{9B6}', '\u{9B9}'), ('\u{9BD}', '\u{9BD}'), ('\u{9CE}', '\u{9CE}'), ('\u{9DC}', '\u{9DD}'),
+    ('\u{9DF}', '\u{9E1}'), ('\u{9F0}', '\u{9F1}'), ('\u{9FC}', '\u{9FC}'), ('\u{A05}', '\u{A0A}'),
+    ('\u{A0F}', '\u{A10}'), ('\u{A13}', '\u{A28}'), ('\u{A2A}', '\u{A30}'), ('\u{A32}', '\u{A33}'),
+    ('\u{A35}', '\u{A36}'), ('\u{A38}', '\u{A39}'), ('\u{A59}', '\u{A5C}'), ('\u{A5E}', '\u{A5E}'),
+    ('\u{A72}', '\u{A74}'), ('\u{A85}', '\u{A8D}'), ('\u{A8F}', '\u{A91}'), ('\u{A93}', '\u{AA8}'),
+    ('\u{AAA}', '\u{AB0}'), ('\u{AB2}', '\u{AB3}'), ('\u{AB5}', '\u{AB9}'), ('\u{ABD}', '\u{ABD}'),
+    ('\u{AD0}', '\u{AD0}'), ('\u{AE0}', '\u{AE1}'), ('\u{AF9}', '\u{AF9}'), ('\u{B05}', '\u{B0C}'),
+    ('\u{B0F}', '\u{B10}'), ('\u{B13}', '\u{B28}'), ('\u{B2A}', '\u{B30}'), ('\u{B32}', '\u{B33}'),
+    ('\u{B35}', '\u{B39}'), ('\u{B3D}', '\u{B3D}'), ('\u{B5C}', '\u{B5D}'), ('\u{B5F}', '\u{B61}'),
+    ('\u{B71}', '\u{B71}'), ('\u{B83}', '\u{B83}'), ('\u{B85}', '\u{B8A}'), ('\u{B8E}', '\u{B90}'),
+    ('\u{B92}', '\u{B95}'), ('\u{B99}', '\u{B9A}'), ('\u{B9C}', '\u{B9C}'), ('\u{B9E}', '\u{B9F}'),
+    ('\u{BA3}', '\u{BA4}'), ('\u{BA8}', '\u{BAA}'), ('\u{BAE}', '\u{BB9}'), ('\u{BD0}', '\u{BD0}'),
+    ('\u{C05}', '\u{C0C}'), ('\u{C0E}', '\u{C10}'), ('\u{C12}', '\u{C28}'), ('\u{C2A}', '\u{C39}'),
+    ('\u{C3D}', '\u{C3D}'), ('\u{C58}', '\u{C5A}'), ('\u{C5D}', '\u{C5D}'), ('\u{C60}', '\u{C61}'),
+    ('\u{C80}', '\u{C80}'), ('\u{C85}', '\u{C8C}'), ('\u{C8E}', '\u{C90}'), ('\u{C92}', '\u{CA8}'),
+    ('\u{CAA}', '\u{CB3}'), ('\u{CB5}', '\u{CB9}'), ('\u{CBD}', '\u{CBD}'), ('\u{CDD}', '\u{CDE}'),
+    ('\u{CE0}', '\u{CE1}'), ('\u{CF1}', '\u{CF2}'), ('\u{D04}', '\u{D0C}'), ('\u{D0E}', '\u{D10}'),
+    ('\u{D12}', '\u{D3A}'), ('\u{D3D}', '\u{D3D}'), ('\u{D4E}', '\u{D4E}'), ('\u{D54}', '\u{D56}'),
+    ('\u{D5F}', '\u{D61}'), ('\u{D7A}', '\u{D7F}'), ('\u{D85}', '\u{D96}'), ('\u{D9A}', '\u{DB1}'),
+    ('\u{DB3}', '\u{DBB}'), ('\u{DBD}', '\u{DBD}'), ('\u{DC0}', '\u{DC6}'), ('\u{E01}', '\u{E30}'),
+    ('\u{E32}', '\u{E33}'), ('\u{E40}', '\u{E45}'), ('\u{E81}', '\u{E82}'), ('\u{E84}', '\u{E84}'),
+    ('\u{E86}', '\u{E8A}'), ('\u{E8C}', '\u{EA3}'), ('\u{EA5}', '\u{EA5}'), ('\u{EA7}', '\u{EB0}'),
+    ('\u{EB2}', '\u{EB3}'), ('\u{EBD}', '\u{EBD}'), ('\u{EC0}', '\u{EC4}'), ('\u{EDC}', '\u{EDF}'),
+    ('\u{F00}', '\u{F00}'), ('\u{F40}', '\u{F47}'), ('\u{F49}', '\u{F6C}'), ('\u{F88}', '\u{F8C}'),
+    ('\u{1000}', '\u{102A}'), ('\u{103F}', '\u{103F}'), ('\u{1050}', '\u{1055}'), ('\u{105A}', '\u{105D}'),
+    ('\u{1061}', '\u{1061}'), ('\u{1065}', '\u{1066}'), ('\u{106E}', '\u{1070}'), ('\u{1075}', '\u{1081}'),
+    ('\u{108E}', '\u{108E}'), ('\u{1100}', '\u{1248}'), ('\u{124A}', '\u{124D}'), ('\u{1250}', '\u{1256}'),
+    ('\u{1258}', '\u{1258}'), ('\u{125A}', '\u{125D}'), ('\u{1260}', '\u{1288}'), ('\u{128A}', '\u{128D}'),
+    ('\u{1290}', '\u{12B0}'), ('\u{12B2}', '\u{12B5}'), ('\u{12B8}', '\u{12BE}'), ('\u{12C0}', '\u{12C0}'),
+    ('\u{12C2}', '\u{12C5}'), ('\u{12C8}', '\u{12D6}'), ('\u{12D8}', '\u{1310}'), ('\u{1312}', '\u{1315}'),
+    ('\u{1318}', '\u{135A}'), ('\u{1380}', '\u{138F}'), ('\u{1401}', '\u{166C}'), ('\u{166F}', '\u{167F}'),
+    ('\u{1681}', '\u{169A}'), ('\u{16A0}', '\u{16EA}'), ('\u{16F1}', '\u{16F8}'), ('\u{1700}', '\u{1711}'),
+    ('\u{171F}', '\u{1731}'), ('\u{1740}', '\u{1751}'), ('\u{1760}', '\u{176C}'), ('\u{176E}', '\u{1770}'),
+    ('\u{1780}', '\u{17B3}'), ('\u{17DC}', '\u{17DC}'), ('\u{1820}', '\u{1842}'), ('\u{1844}', '\u{1878}'),
+    ('\u{1880}', '\u{1884}'), ('\u{1887}', '\u{18A8}'), ('\u{18AA}', '\u{18AA}'), ('\u{18B0}', '\u{18F5}'),
+    ('\u{1900}', '\u{191E}'), ('\u{1950}', '\u{196D}'), ('\u{1970}', '\u{1974}'), ('\u{1980}', '\u{19AB}'),
+    ('\u{19B0}', '\u{19C9}'), ('\u{1A00}', '\u{1A16}'), ('\u{1A20}', '\u{1A54}'), ('\u{1B05}', '\u{1B33}'),
+    ('\u{1B45}', '\u{1B4C}'), ('\u{1B83}', '\u{1BA0}'), ('\u{1BAE}', '\u{1BAF}'), ('\u{1BBA}', '\u{1BE5}'),
+    ('\u{1C00}', '\u{1C23}'), ('\u{1C4D}', '\u{1C4F}'), ('\u{1C5A}', '\u{1C77}'), ('\u{1CE9}', '\u{1CEC}'),
+    ('\u{1CEE}', '\u{1CF3}'), ('\u{1CF5}', '\u{1CF6}'), ('\u{1CFA}', '\u{1CFA}'), ('\u{2135}', '\u{2138}'),
+    ('\u{2D30}', '\u{2D67}'), ('\u{2D80}', '\u{2D96}'), ('\u{2DA0}', '\u{2DA6}'), ('\u{2DA8}', '\u{2DAE}'),
+    ('\u{2DB0}', '\u{2DB6}'), ('\u{2DB8}', '\u{2DBE}'), ('\u{2DC0}', '\u{2DC6}'), ('\u{2DC8}', '\u{2DCE}'),
+    ('\u{2DD0}', '\u{2DD6}'), ('\u{2DD8}', '\u{2DDE}'), ('\u{3006}', '\u{3006}'), ('\u{303C}', '\u{303C}'),
+    ('\u{3041}', '\u{3096}'), ('\u{309F}', '\u{309F}'), ('\u{30A1}', '\u{30FA}'), ('\u{30FF}', '\u{30FF}'),
+    ('\u{3105}', '\u{312F}'), ('\u{3131}', '\u{318E}'), ('\u{31A0}', '\u{31BF}'), ('\u{31F0}', '\u{31FF}'),
+    ('\u{3400}', '\u{4DBF}'), ('\u{4E00}', '\u{A014}'), ('\u{A016}', '\u{A48C}'), ('\u{A4D0}', '\u{A4F7}'),
+    ('\u{A500}', '\u{A60B}'), ('\u{A610}', '\u{A61F}'), ('\u{A62A}', '\u{A62B}'), ('\u{A66E}', '\u{A66E}'),
+    ('\u{A6A0}', '\u{A6E5}'), ('\u{A78F}', '\u{A78F}'), ('\u{A7F7}', '\u{A7F7}'), ('\u{A7FB}', '\u{A801}'),
+    ('\u{A803}', '\u{A805}'), ('\u{A807}', '\u{A80A}'), ('\u{A80C}', '\u{A822}'), ('\u{A840}', '\u{A873}'),
+    ('\u{A882}', '\u{A8B3}'), ('\u{A8F2}', '\u{A8F7}'), ('\u{A8FB}', '\u{A8FB}'), ('\u{A8FD}', '\u{A8FE}'),
+    ('\u{A90A}', '\u{A925}'), ('\u{A930}', '\u{A946}'), ('\u{A960}', '\u{A97C}'), ('\u{A984}', '\u{A9B2}'),
+    ('\u{A9E0}', '\u{A9E4}'), ('\u{A9E7}', '\u{A9EF}'), ('\u{A9FA}', '\u{A9FE}'), ('\u{AA00}', '\u{AA28}'),
+    ('\u{AA40}', '\u{AA42}'), ('\u{AA44}', '\u{AA4B}'), ('\u{AA60}', '\u{AA6F}'), ('\u{AA71}', '\u{AA76}'),
+    ('\u{AA7A}', '\u{AA7A}'), ('\u{AA7E}', '\u{AAAF}'), ('\u{AAB1}', '\u{AAB1}'), ('\u{AAB5}', '\u{AAB6}'),
+    ('\u{AAB9}', '\u{AABD}'), ('\u{AAC0}', '\u{AAC0}'), ('\u{AAC2}', '\u{AAC2}'), ('\u{AADB}', '\u{AADC}'),
+    ('\u{AAE0}', '\u{AAEA}'), ('\u{AAF2}', '\u{AAF2}'), ('\u{AB01}', '\u{AB06}'), ('\u{AB09}', '\u{AB0E}'),
+    ('\u{AB11}', '\u{AB16}'), ('\u{AB20}', '\u{AB26}'), ('\u{AB28}', '\u{AB2E}'), ('\u{ABC0}', '\u{ABE2}'),
+    ('\u{AC00}', '\u{D7A3}'), ('\u{D7B0}', '\u{D7C6}'), ('\u{D7CB}', '\u{D7FB}'), ('\u{F900}', '\u{FA6D}'),
+    ('\u{FA70}', '\u{FAD9}'), ('\u{FB1D}', '\u{FB1D}'), ('\u{FB1F}', '\u{FB28}'), ('\u{FB2A}', '\u{FB36}'),
+    ('\u{FB38}', '\u{FB3C}'), ('\u{FB3E}', '\u{FB3E}'), ('\u{FB40}', '\u{FB41}'), ('\u{FB43}', '\u{FB44}'),
+    ('\u{FB46}', '\u{FBB1}'), ('\u{FBD3}', '\u{FD3D}'), ('\u{FD50}', '\u{FD8F}'), ('\u{FD92}', '\u{FDC7}'),
+    ('\u{FDF0}', '\u{FDFB}'), ('\u{FE70}', '\u{FE74}'), ('\u{FE76}', '\u{FEFC}'), ('\u{FF66}', '\u{FF6F}'),
+    ('\u{FF71}', '\u{FF9D}'), ('\u{FFA0}', '\u{FFBE}'), ('\u{FFC2}', '\u{FFC7}'), ('\u{FFCA}', '\u{FFCF}'),
+    ('\u{FFD2}', '\u{FFD7}'), ('\u{FFDA}', '\u{FFDC}'), ('\u{10000}', '\u{1000B}'), ('\u{1000D}', '\u{10026}'),
+    ('\u{10028}', '\u{1003A}'), ('\u{1003C}', '\u{1003D}'), ('\u{1003F}', '\u{1004D}'), ('\u{10050}', '\u{1005D}'),
+    ('\u{10080}', '\u{100FA}'), ('\u{10280}', '\u{1029C}'), ('\u{102A0}', '\u{102D0}'), ('\u{10300}', '\u{1031F}'),
+    ('\u{1032D}', '\u{10340}'), ('\u{10342}', '\u{10349}'), ('\u{10350}', '\u{10375}'), ('\u{10380}', '\u{1039D}'),
+    ('\u{103A0}', '\u{103C3}'), ('\u{103C8}', '\u{103CF}'), ('\u{10450}', '\u{1049D}'), ('\u{10500}', '\u{10527}'),
+    ('\u{10530}', '\u{10563}'), ('\u{10600}', '\u{10736}'), ('\u{10740}', '\u{10755}'), ('\u{10760}', '\u{10767}'),
+    ('\u{10800}', '\u{10805}'), ('\u{10808}', '\u{10808}'), ('\u{1080A}', '\u{10835}'), ('\u{10837}', '\u{10838}'),
+    ('\u{1083C}', '\u{1083C}'), ('\u{1083F}', '\u{10855}'), ('\u{10860}', '\u{10876}'), ('\u{10880}', '\u{1089E}'),
+    ('\u{108E0}', '\u{108F2}'), ('\u{108F4}', '\u{108F5}'), ('\u{10900}', '\u{10915}'), ('\u{10920}', '\u{10939}'),
+    ('\u{10980}', '\u{109B7}'), ('\u{109BE}', '\u{109BF}'), ('\u{10A00}', '\u{10A00}'), ('\u{10A10}', '\u{10A13}'),
+    ('\u{10A15}', '\u{10A17}'), ('\u{10A19}', '\u{10A35}'), ('\u{10A60}', '\u{10A7C}'), ('\u{10A80}', '\u{10A9C}'),
+    ('\u{10AC0}', '\u{10AC7}'), ('\u{10AC9}', '\u{10AE4}'), ('\u{10B00}', '\u{10B35}'), ('\u{10B40}', '\u{10B55}'),
+    ('\u{10B60}', '\u{10B72}'), ('\u{10B80}', '\u{10B91}'), ('\u{10C00}', '\u{10C48}'), ('\u{10D00}', '\u{10D23}'),
+    ('\u{10E80}', '\u{10EA9}'), ('\u{10EB0}', '\u{10EB1}'), ('\u{10F00}', '\u{10F1C}'), ('\u{10F27}', '\u{10F27}'),
+    ('\u{10F30}', '\u{10F45}'), ('\u{10F70}', '\u{10F81}'), ('\u{10FB0}', '\u{10FC4}'), ('\u{10FE0}', '\u{10FF6}'),
+    ('\u{11003}', '\u{11037}'), ('\u{11071}', '\u{11072}'), ('\u{11075}', '\u{11075}'), ('\u{11083}', '\u{110AF}'),
+    ('\u{110D0}', '\u{110E8}'), ('\u{11103}', '\u{11126}'), ('\u{11144}', '\u{11144}'), ('\u{11147}', '\u{11147}'),
+    ('\u{11150}', '\u{11172}'), ('\u{11176}', '\u{11176}'), ('\u{11183}', '\u{111B2}'), ('\u{111C1}', '\u{111C4}'),
+    ('\u{111DA}', '\u{111DA}'), ('\u{111DC}', '\u{111DC}'), ('\u{11200}', '\u{11211}'), ('\u{11213}', '\u{1122B}'),
+    ('\u{11280}', '\u{11286}'), ('\u{11288}', '\u{11288}'), ('\u{1128A}', '\u{1128D}'), ('\u{1128F}', '\u{1129D}'),
+    ('\u{1129F}', '\u{112A8}'), ('\u{112B0}', '\u{112DE}'), ('\u{11305}', '\u{1130C}'), ('\u{1130F}', '\u{11310}'),
+    ('\u{11313}', '\u{11328}'), ('\u{1132A}', '\u{11330}'), ('\u{11332}', '\u{11333}'), ('\u{11335}', '\u{11339}'),
+    ('\u{1133D}', '\u{1133D}'), ('\u{11350}', '\u{11350}'), ('\u{1135D}', '\u{11361}'), ('\u{11400}', '\u{11434}'),
+    ('\u{11447}', '\u{1144A}'), ('\u{1145F}', '\u{11461}'), ('\u{11480}', '\u{114AF}'), ('\u{114C4}', '\u{114C5}'),
+    ('\u{114C7}', '\u{114C7}'), ('\u{11580}', '\u{115AE}'), ('\u{115D8}', '\u{115DB}'), ('\u{11600}', '\u{1162F}'),
+    ('\u{11644}', '\u{11644}'), ('\u{11680}', '\u{116AA}'), ('\u{116B8}', '\u{116B8}'), ('\u{11700}', '\u{1171A}'),
+    ('\u{11740}', '\u{11746}'), ('\u{11800}', '\u{1182B}'), ('\u{118FF}', '\u{11906}'), ('\u{11909}', '\u{11909}'),
+    ('\u{1190C}', '\u{11913}'), ('\u{11915}', '\u{11916}'), ('\u{11918}', '\u{1192F}'), ('\u{1193F}', '\u{1193F}'),
+    ('\u{11941}', '\u{11941}'), ('\u{119A0}', '\u{119A7}'), ('\u{119AA}', '\u{119D0}'), ('\u{119E1}', '\u{119E1}'),
+    ('\u{119E3}', '\u{119E3}'), ('\u{11A00}', '\u{11A00}'), ('\u{11A0B}', '\u{11A32}'), ('\u{11A3A}', '\u{11A3A}'),
+    ('\u{11A50}', '\u{11A50}'), ('\u{11A5C}', '\u{11A89}'), ('\u{11A9D}', '\u{11A9D}'), ('\u{11AB0}', '\u{11AF8}'),
+    ('\u{11C00}', '\u{11C08}'), ('\u{11C0A}', '\u{11C2E}'), ('\u{11C40}', '\u{11C40}'), ('\u{11C72}', '\u{11C8F}'),
+    ('\u{11D00}', '\u{11D06}'), ('\u{11D08}', '\u{11D09}'), ('\u{11D0B}', '\u{11D30}'), ('\u{11D46}', '\u{11D46}'),
+    ('\u{11D60}', '\u{11D65}'), ('\u{11D67}', '\u{11D68}'), ('\u{11D6A}', '\u{11D89}'), ('\u{11D98}', '\u{11D98}'),
+    ('\u{11EE0}', '\u{11EF2}'), ('\u{11FB0}', '\u{11FB0}'), ('\u{12000}', '\u{12399}'), ('\u{12480}', '\u{12543}'),
+    ('\u{12F90}', '\u{12FF0}'), ('\u{13000}', '\u{1342E}'), ('\u{14400}', '\u{14646}'), ('\u{16800}', '\u{16A38}'),
+    ('\u{16A40}', '\u{16A5E}'), ('\u{16A70}', '\u{16ABE}'), ('\u{16AD0}', '\u{16AED}'), ('\u{16B00}', '\u{16B2F}'),
+    ('\u{16B63}', '\u{16B77}'), ('\u{16B7D}', '\u{16B8F}'), ('\u{16F00}', '\u{16F4A}'), ('\u{16F50}', '\u{16F50}'),
+    ('\u{17000}', '\u{187F7}'), ('\u{18800}', '\u{18CD5}'), ('\u{18D00}', '\u{18D08}'), ('\u{1B000}', '\u{1B122}'),
+    ('\u{1B150}', '\u{1B152}'), ('\u{1B164}', '\u{1B167}'), ('\u{1B170}', '\u{1B2FB}'), ('\u{1BC00}', '\u{1BC6A}'),
+    ('\u{1BC70}', '\u{1BC7C}'), ('\u{1BC80}', '\u{1BC88}'), ('\u{1BC90}', '\u{1BC99}'), ('\u{1DF0A}', '\u{1DF0A}'),
+    ('\u{1E100}', '\u{1E12C}'), ('\u{1E14E}', '\u{1E14E}'), ('\u{1E290}', '\u{1E2AD}'), ('\u{1E2C0}', '\u{1E2EB}'),
+    ('\u{1E7E0}', '\u{1E7E6}'), ('\u{1E7E8}', '\u{1E7EB}'), ('\u{1E7ED}', '\u{1E7EE}'), ('\u{1E7F0}', '\u{1E7FE}'),
+    ('\u{1E800}', '\u{1E8C4}'), ('\u{1EE00}', '\u{1EE03}'), ('\u{1EE05}', '\u{1EE1F}'), ('\u{1EE21}', '\u{1EE22}'),
+    ('\u{1EE24}', '\u{1EE24}'), ('\u{1EE27}', '\u{1EE27}'), ('\u{1EE29}', '\u{1EE32}'), ('\u{1EE34}', '\u{1EE37}'),
+    ('\u{1EE39}', '\u{1EE39}'), ('\u{1EE3B}', '\u{1EE3B}'), ('\u{1EE42}', '\u{1EE42}'), ('\u{1EE47}', '\u{1EE47}'),
+    ('\u{1EE49}', '\u{1EE49}'), ('\u{1EE4B}', '\u{1EE4B}'), ('\u{1EE4D}', '\u{1EE4F}'), ('\u{1EE51}', '\u{1EE52}'),
+    ('\u{1EE54}', '\u{1EE54}'), ('\u{1EE57}', '\u{1EE57}'), ('\u{1EE59}', '\u{1EE59}'), ('\u{1EE5B}', '\u{1EE5B}'),
+    ('\u{1EE5D}', '\u{1EE5D}'), ('\u{1EE5F}', '\u{1EE5F}'), ('\u{1EE61}', '\u{1EE62}'), ('\u{1EE64}', '\u{1EE64}'),
+    ('\u{1EE67}', '\u{1EE6A}'), ('\u{1EE6C}', '\u{1EE72}'), ('\u{1EE74}', '\u{1EE77}'), ('\u{1EE79}', '\u{1EE7C}'),
+    ('\u{1EE7E}', '\u{1EE7E}'), ('\u{1EE80}', '\u{1EE89}'), ('\u{1EE8B}', '\u{1EE9B}'), ('\u{1EEA1}', '\u{1EEA3}'),
+    ('\u{1EEA5}', '\u{1EEA9}'), ('\u{1EEAB}', '\u{1EEBB}'), ('\u{20000}', '\u{2A6DF}'), ('\u{2A700}', '\u{2B738}'),
+    ('\u{2B740}', '\u{2B81D}'), ('\u{2B820}', '\u{2CEA1}'), ('\u{2CEB0}', '\u{2EBE0}'), ('\u{2F800}', '\u{2FA1D}'),
+    ('\u{30000}', '\u{3134A}'),
+];
+
+const GC_LT: &[(char, char)] = &[
+    ('\u{1C5}', '\u{1C5}'), ('\u{1C8}', '\u{1C8}'), ('\u{1CB}', '\u{1CB}'), ('\u{1F2}', '\u{1F2}'),
+    ('\u{1F88}', '\u{1F8F}'), ('\u{1F98}', '\u{1F9F}'), ('\u{1FA8}', '\u{1FAF}'), ('\u{1FBC}', '\u{1FBC}'),
+    ('\u{1FCC}', '\u{1FCC}'), ('\u{1FFC}', '\u{1FFC}'),
+];
+
+const GC_LU: &[(char, char)] = &[
+    ('\u{41}', '\u{5A}'), ('\u{C0}', '\u{D6}'), ('\u{D8}', '\u{DE}'), ('\u{100}', '\u{100}'),
+    ('\u{102}', '\u{102}'), ('\u{104}', '\u{104}'), ('\u{106}', '\u{106}'), ('\u{108}', '\u{108}'),
+    ('\u{10A}', '\u{10A}'), ('\u{10C}', '\u{10C}'), ('\u{10E}', '\u{10E}'), ('\u{110}', '\u{110}'),
+    ('\u{112}', '\u{112}'), ('\u{114}', '\u{114}'), ('\u{116}', '\u{116}'), ('\u{118}', '\u{118}'),
+    ('\u{11A}', '\u{11A}'), ('\u{11C}', '\u{11C}'), ('\u{11E}', '\u{11E}'), ('\u{120}', '\u{120}'),
+    ('\u{122}', '\u{122}'), ('\u{124}', '\u{124}'), ('\u{126}', '\u{126}'), ('\u{128}', '\u{128}'),
+    ('\u{12A}', '\u{12A}'), ('\u{12C}', '\u{12C}'), ('\u{12E}', '\u{12E}'), ('\u{130}', '\u{130}'),
+    ('\u{132}', '\u{132}'), ('\u{134}', '\u{134}'), ('\u{136}', '\u{136}'), ('\u{139}', '\u{139}'),
+    ('\u{13B}', '\u{13B}'), ('\u{13D}', '\u{13D}'), ('\u{13F}', '\u{13F}'), ('\u{141}', '\u{141}'),
+    ('\u{143}', '\u{143}'), ('\u{145}', '\u{145}'), ('\u{147}', '\u{147}'), ('\u{14A}', '\u{14A}'),
+    ('\u{14C}', '\u{14C}'), ('\u{14E}', '\u{14E}'), ('\u{150}', '\u{150}'), ('\u{152}', '\u{152}'),
+    ('\u{154}', '\u{154}'), ('\u{156}', '\u{156}'), ('\u{158}', '\u{158}'), ('\u{15A}', '\u{15A}'),
+    ('\u{15C}', '\u{15C}'), ('\u{15E}', '\u{15E}'), ('\u{160}', '\u{160}'), ('\u{162}', '\u{162}'),
+    ('\u{164}', '\u{164}'), ('\u{166}', '\u{166}'), ('\u{168}', '\u{168}'), ('\u{16A}', '\u{16A}'),
+    ('\u{16C}', '\u{16C}'), ('\u{16E}', '\u{16E}'), ('\u{170}', '\u{170}'), ('\u{172}', '\u{172}'),
+    ('\u{174}', '\u{174}'), ('\u{176}', '\u{176}'), ('\u{178}', '\u{179}'), ('\u{17B}', '\u{17B}'),
+    ('\u{17D}', '\u{17D}'), ('\u{181}', '\u{182}'), ('\u{184}', '\u{184}'), ('\u{186}', '\u{187}'),
+    ('\u{189}', '\u{18B}'), ('\u{18E}', '\u{191}'), ('\u{193}', '\u{194}'), ('\u{196}', '\u{198}'),
+    ('\u{19C}', '\u{19D}'), ('\u{19F}', '\u{1A0}'), ('\u{1A2}', '\u{1A2}'), ('\u{1A4}', '\u{1A4}'),
+    ('\u{1A6}', '\u{1A7}'), ('\u{1A9}', '\u{1A9}'), ('\u{1AC}', '\u{1AC}'), ('\u{1AE}', '\u{1AF}'),
+    ('\u{1B1}', '\u{1B3}'), ('\u{1B5}', '\u{1B5}'), ('\u{1B7}', '\u{1B8}'), ('\u{1BC}', '\u{1BC}'),
+    ('\u{1C4}', '\u{1C4}'), ('\u{1C7}', '\u{1C7}'), ('\u{1CA}', '\u{1CA}'), ('\u{1CD}', '\u{1CD}'),
+    ('\u{1CF}', '\u{1CF}'), ('\u{1D1}', '\u{1D1}'), ('\u{1D3}', '\u{1D3}'), ('\u{1D5}', '\u{1D5}'),
+    ('\u{1D7}', '\u{1D7}'), ('\u{1D9}', '\u{1D9}'), ('\u{1DB}', '\u{1DB}'), ('\u{1DE}', '\u{1DE}'),
+    ('\u{1E0}', '\u{1E0}'), ('\u{1E2}', '\u{1E2}'), ('\u{1E4}', '\u{1E4}'), ('\u{1E6}', '\u{1E6}'),
+    ('\u{1E8}', '\u{1E8}'), ('\u{1EA}', '\u{1EA}'), ('\u{1EC}', '\u{1EC}'), ('\u{1EE}', '\u{1EE}'),
+    ('\u{1F1}', '\u{1F1}'), ('\u{1F4}', '\u{1F4}'), ('\u{1F6}', '\u{1F8}'), ('\u{1FA}', '\u{1FA}'),
+    ('\u{1FC}', '\u{1FC}'), ('\u{1FE}', '\u{1FE}'), ('\u{200}', '\u{200}'), ('\u{202}', '\u{202}'),
+    ('\u{204}', '\u{204}'), ('\u{206}', '\u{206}'), ('\u{208}', '\u{208}'), ('\u{20A}', '\u{20A}'),
+    ('\u{20C}', '\u{20C}'), ('\u{20E}', '\u{20E}'), ('\u{210}', '\u{210}'), ('\u{212}', '\u{212}'),
+    ('\u{214}', '\u{214}'), ('\u{216}', '\u{216}'), ('\u{218}', '\u{218}'), ('\u{21A}', '\u{21A}'),
+    ('\u{21C}', '\u{21C}'), ('\u{21E}', '\u{21E}'), ('\u{220}', '\u{220}'), ('\u{222}', '\u{222}'),
+    ('\u{224}', '\u{224}'), ('\u{226}', '\u{226}'), ('\u{228}', '\u{228}'), ('\u{22A}', '\u{22A}'),
+    ('\u{22C}', '\u{22C}'), ('\u{22E}', '\u{22E}'), ('\u{230}', '\u{230}'), ('\u{232}', '\u{232}'),
+    ('\u{23A}', '\u{23B}'), ('\u{23D}', '\u{23E}'), ('\u{241}', '\u{241}'), ('\u{243}', '\u{246}'),
+    ('\u{248}', '\u{248}'), ('\u{24A}', '\u{24A}'), ('\u{24C}', '\u{24C}'), ('\u{24E}', '\u{24E}'),
+    ('\u{370}', '\u{370}'), ('\u{372}', '\u{372}'), ('\u{376}', '\u{376}'), ('\u{37F}', '\u{37F}'),
+    ('\u{386}', '\u{386}'), ('\u{388}', '\u{38A}'), ('\u{38C}', '\u{38C}'), ('\u{38E}', '\u{38F}'),
+    ('\u{391}', '\u{3A1}'), ('\u{3A3}', '\u{3AB}'), ('\u{3CF}', '\u{3CF}'), ('\u{3D2}', '\u{3D4}'),
+    ('\u{3D8}', '\u{3D8}'), ('\u{3DA}', '\u{3DA}'), ('\u{3DC}', '\u{3DC}'), ('\u{3DE}', '\u{3DE}'),
+    ('\u{3E0}', '\u{3E0}'), ('\u{3E2}', '\u{3E2}'), ('\u{3E4}', '\u{3E4}'), ('\u{3E6}', '\u{3E6}'),
+    ('\u{3E8}', '\u{3E8}'), ('\u{3EA}', '\u{3EA}'), ('\u{3EC}', '\u{3EC}'), ('\u{3EE}', '\u{3EE}'),
+    ('\u{3F4}', '\u{3F4}'), ('\u{3F7}', '\u{3F7}'), ('\u{3F9}', '\u{3FA}'), ('\u{3FD}', '\u{42F}'),
+    ('\u{460}', '\u{460}'), ('\u{462}', '\u{462}'), ('\u{464}', '\u{464}'), ('\u{466}', '\u{466}'),
+    ('\u{468}', '\u{468}'), ('\u{46A}', '\u{46A}'), ('\u{46C}', '\u{46C}'), ('\u{46E}', '\u{46E}'),
+    ('\u{470}', '\u{470}'), ('\u{472}', '\u{472}'), ('\u{474}', '\u{474}'), ('\u{476}', '\u{476}'),
+    ('\u{478}', '\u{478}'), ('\u{47A}', '\u{47A}'), ('\u{47C}', '\u{47C}'), ('\u{47E}', '\u{47E}'),
+    ('\u{480}', '\u{480}'), ('\u{48A}', '\u{48A}'), ('\u{48C}', '\u{48C}'), ('\u{48E}', '\u{48E}'),
+    ('\u{490}', '\u{490}'), ('\u{492}', '\u{492}'), ('\u{494}', '\u{494}'), ('\u{496}', '\u{496}'),
+    ('\u{498}', '\u{498}'), ('\u{49A}', '\u{49A}'), ('\u{49C}', '\u{49C}'), ('\u{49E}', '\u{49E}'),
+    ('\u{4A0}', '\u{4A0}'), ('\u{4A2}', '\u{4A2}'), ('\u{4A4}', '\u{4A4}'), ('\u{4A6}', '\u{4A6}'),
+    ('\u{4A8}', '\u{4A8}'), ('\u{4AA}', '\u{4AA}'), ('\u{4AC}', '\u{4AC}'), ('\u{4AE}', '\u{4AE}'),
+    ('\u{4B0}', '\u{4B0}'), ('\u{4B2}', '\u{4B2}'), ('\u{4B4}', '\u{4B4}'), ('\u{4B6}', '\u{4B6}'),
+    ('\u{4B8}', '\u{4B8}'), ('\u{4BA}', '\u{4BA}'), ('\u{4BC}', '\u{4BC}'), ('\u{4BE}', '\u{4BE}'),
+    ('\u{4C0}', '\u{4C1}'), ('\u{4C3}', '\u{4C3}'), ('\u{4C5}', '\u{4C5}'), ('\u{4C7}', '\u{4C7}'),
+    ('\u{4C9}', '\u{4C9}'), ('\u{4CB}', '\u{4CB}'), ('\u{4CD}', '\u{4CD}'), ('\u{4D0}', '\u{4D0}'),
+    ('\u{4D2}', '\u{4D2}'), ('\u{4D4}', '\u{4D4}'), ('\u{4D6}', '\u{4D6}'), ('\u{4D8}', '\u{4D8}'),
+    ('\u{4DA}', '\u{4DA}'), ('\u{4DC}', '\u{4DC}'), ('\u{4DE}', '\u{4DE}'), ('\u{4E0}', '\u{4E0}'),
+    ('\u{4E2}', '\u{4E2}'), ('\u{4E4}', '\u{4E4}'), ('\u{4E6}', '\u{4E6}'), ('\u{4E8}', '\u{4E8}'),
+    ('\u{4EA}', '\u{4EA}'), ('\u{4EC}', '\u{4EC}'), ('\u{4EE}', '\u{4EE}'), ('\u{4F0}', '\u{4F0}'),
+    ('\u{4F2}', '\u{4F2}'), ('\u{4F4}', '\u{4F4}'), ('\u{4F6}', '\u{4F6}'), ('\u{4F8}', '\u{4F8}'),
+    ('\u{4FA}', '\u{4FA}'), ('\u{4FC}', '\u{4FC}'), ('\u{4FE}', '\u{4FE}'), ('\u{500}', '\u{500}'),
+    ('\u{502}', '\u{502}'), ('\u{504}', '\u{504}'), ('\u{506}', '\u{506}'), ('\u{508}', '\u{508}'),
+    ('\u{50A}', '\u{50A}'), ('\u{50C}', '\u{50C}'), ('\u{50E}', '\u{50E}'), ('\u{510}', '\u{510}'),
+    ('\u{512}', '\u{512}'), ('\u{514}', '\u{514}'), ('\u{516}', '\u{516}'), ('\u{518}', '\u{518}'),
+    ('\u{51A}', '\u{51A}'), ('\u{51C}', '\u{51C}'), ('\u{51E}', '\u{51E}'), ('\u{520}', '\u{520}'),
+    ('\u{522}', '\u{522}'), ('\u{524}', '\u{524}'), ('\u{526}', '\u{526}'), ('\u{528}', '\u{528}'),
+    ('\u{52A}', '\u{52A}'), ('\u{52C}', '\u{52C}'), ('\u{52E}', '\u{52E}'), ('\u{531}', '\u{556}'),
+    ('\u{10A0}', '\u{10C5}'), ('\u{10C7}', '\u{10C7}'), ('\u{10CD}', '\u{10CD}'), ('\u{13A0}', '\u{13F5}'),
+    ('\u{1C90}', '\u{1CBA}'), ('\u{1CBD}', '\u{1CBF}'), ('\u{1E00}', '\u{1E00}'), ('\u{1E02}', '\u{1E02}'),
+    ('\u{1E04}', '\u{1E04}'), ('\u{1E06}', '\u{1E06}'), ('\u{1E08}', '\u{1E08}'), ('\u{1E0A}', '\u{1E0A}'),
+    ('\u{1E0C}', '\u{1E0C}'), ('\u{1E0E}', '\u{1E0E}'), ('\u{1E10}', '\u{1E10}'), ('\u{1E12}', '\u{1E12}'),
+    ('\u{1E14}', '\u{1E14}'), ('\u{1E16}', '\u{1E16}'), ('\u{1E18}', '\u{1E18}'), ('\u{1E1A}', '\u{1E1A}'),
+    ('\u{1E1C}', '\u{1E1C}'), ('\u{1E1E}', '\u{1E1E}'), ('\u{1E20}', '\u{1E20}'), ('\u{1E22}', '\u{1E22}'),
+    ('\u{1E24}', '\u{1E24}'), ('\u{1E26}', '\u{1E26}'), ('\u{1E28}', '\u{1E28}'), ('\u{1E2A}', '\u{1E2A}'),
+    ('\u{1E2C}', '\u{1E2C}'), ('\u{1E2E}', '\u{1E2E}'), ('\u{1E30}', '\u{1E30}'), ('\u{1E32}', '\u{1E32}'),
+    ('\u{1E34}', '\u{1E34}'), ('\u{1E36}', '\u{1E36}'), ('\u{1E38}', '\u{1E38}'), ('\u{1E3A}', '\u{1E3A}'),
+    ('\u{1E3C}', '\u{1E3C}'), ('\u{1E3E}', '\u{1E3E}'), ('\u{1E40}', '\u{1E40}'), ('\u{1E42}', '\u{1E42}'),
+    ('\u{1E44}', '\u{1E44}'), ('\u{1E46}', '\u{1E46}'), ('\u{1E48}', '\u{1E48}'), ('\u{1E4A}', '\u{1E4A}'),
+    ('\u{1E4C}', '\u{1E4C}'), ('\u{1E4E}', '\u{1E4E}'), ('\u{1E50}', '\u{1E50}'), ('\u{1E52}', '\u{1E52}'),
+    ('\u{1E54}', '\u{1E54}'), ('\u{1E56}', '\u{1E56}'), ('\u{1E58}', '\u{1E58}'), ('\u{1E5A}', '\u{1E5A}'),
+    ('\u{1E5C}', '\u{1E5C}'), ('\u{1E5E}', '\u{1E5E}'), ('\u{1E60}', '\u{1E60}'), ('\u{1E62}', '\u{1E62}'),
+    ('\u{1E64}', '\u{1E64}'), ('\u{1E66}', '\u{1E66}'), ('\u{1E68}', '\u{1E68}'), ('\u{1E6A}', '\u{1E6A}'),
+    ('\u{1E6C}', '\u{1E6C}'), ('\u{1E6E}', '\u{1E6E}'), ('\u{1E70}', '\u{1E70}'), ('\u{1E72}', '\u{1E72}'),
+    ('\u{1E74}', '\u{1E74}'), ('\u{1E76}', '\u{1E76}'), ('\u{1E78}', '\u{1E78}'), ('\u{1E7A}', '\u{1E7A}'),
+    ('\u{1E7C}', '\u{1E7C}'), ('\u{1E7E}', '\u{1E7E}'), ('\u{1E80}', '\u{1E80}'), ('\u{1E82}', '\u{1E82}'),
+    ('\u{1E84}', '\u{1E84}'), ('\u{1E86}', '\u{1E86}'), ('\u{1E88}', '\u{1E88}'), ('\u{1E8A}', '\u{1E8A}'),
+    ('\u{1E8C}', '\u{1E8C}'), ('\u{1E8E}', '\u{1E8E}'), ('\u{1E90}', '\u{1E90}'), ('\u{1E92}', '\u{1E92}'),
+    ('\u{1E94}', '\u{1E94}'), ('\u{1E9E}', '\u{1E9E}'), ('\u{1EA0}', '\u{1EA0}'), ('\u{1EA2}', '\u{1EA2}'),
+    ('\u{1EA4}', '\u{1EA4}'), ('\u{1EA6}', '\u{1EA6}'), ('\u{1EA8}', '\u{1EA8}'), ('\u{1EAA}', '\u{1EAA}'),
+    ('\u{1EAC}', '\u{1EAC}'), ('\u{1EAE}', '\u{1EAE}'), ('\u{1EB0}', '\u{1EB0}'), ('\u{1EB2}', '\u{1EB2}'),
+    ('\u{1EB4}', '\u{1EB4}'), ('\u{1EB6}', '\u{1EB6}'), ('\u{1EB8}', '\u{1EB8}'), ('\u{1EBA}', '\u{1EBA}'),
+    ('\u{1EBC}', '\u{1EBC}'), ('\u{1EBE}', '\u{1EBE}'), ('\u{1EC0}', '\u{1EC0}'), ('\u{1EC2}', '\u{1EC2}'),
+    ('\u{1EC4}', '\u{1EC4}'), ('\u{1EC6}', '\u{1EC6}'), ('\u{1EC8}', '\u{1EC8}'), ('\u{1ECA}', '\u{1ECA}'),
+    ('\u{1ECC}', '\u{1ECC}'), ('\u{1ECE}', '\u{1ECE}'), ('\u{1ED0}', '\u{1ED0}'), ('\u{1ED2}', '\u{1ED2}'),
+    ('\u{1ED4}', '\u{1ED4}'), ('\u{1ED6}', '\u{1ED6}'), ('\u{1ED8}', '\u{1ED8}'), ('\u{1EDA}', '\u{1EDA}'),
+    ('\u{1EDC}', '\u{1EDC}'), ('\u{1EDE}', '\u{1EDE}'), ('\u{1EE0}', '\u{1EE0}'), ('\u{1EE2}', '\u{1EE2}'),
+    ('\u{1EE4}', '\u{1EE4}'), ('\u{1EE6}', '\u{1EE6}'), ('\u{1EE8}', '\u{1EE8}'), ('\u{1EEA}', '\u{1EEA}'),
+    ('\u{1EEC}', '\u{1EEC}'), ('\u{1EEE}', '\u{1EEE}'), ('\u{1EF0}', '\u{1EF0}'), ('\u{1EF2}', '\u{1EF2}'),
+    ('\u{1EF4}', '\u{1EF4}'), ('\u{1EF6}', '\u{1EF6}'), ('\u{1EF8}', '\u{1EF8}'), ('\u{1EFA}', '\u{1EFA}'),
+    ('\u{1EFC}', '\u{1EFC}'), ('\u{1EFE}', '\u{1EFE}'), ('\u{1F08}', '\u{1F0F}'), ('\u{1F18}', '\u{1F1D}'),
+    ('\u{1F28}', '\u{1F2F}'), ('\u{1F38}', '\u{1F3F}'), ('\u{1F48}', '\u{1F4D}'), ('\u{1F59}', '\u{1F59}'),
+    ('\u{1F5B}', '\u{1F5B}'), ('\u{1F5D}', '\u{1F5D}'), ('\u{1F5F}', '\u{1F5F}'), ('\u{1F68}', '\u{1F6F}'),
+    ('\u{1FB8}', '\u{1FBB}'), ('\u{1FC8}', '\u{1FCB}'), ('\u{1FD8}', '\u{1FDB}'), ('\u{1FE8}', '\u{1FEC}'),
+    ('\u{1FF8}', '\u{1FFB}'), ('\u{2102}', '\u{2102}'), ('\u{2107}', '\u{2107}'), ('\u{210B}', '\u{210D}'),
+    ('\u{2110}', '\u{2112}'), ('\u{2115}', '\u{2115}'), ('\u{2119}', '\u{211D}'), ('\u{2124}', '\u{2124}'),
+    ('\u{2126}', '\u{2126}'), ('\u{2128}', '\u{2128}'), ('\u{212A}', '\u{212D}'), ('\u{2130}', '\u{2133}'),
+    ('\u{213E}', '\u{213F}'), ('\u{2145}', '\u{2145}'), ('\u{2183}', '\u{2183}'), ('\u{2C00}', '\u{2C2F}'),
+    ('\u{2C60}', '\u{2C60}'), ('\u{2C62}', '\u{2C64}'), ('\u{2C67}', '\u{2C67}'), ('\u{2C69}', '\u{2C69}'),
+    ('\u{2C6B}', '\u{2C6B}'), ('\u{2C6D}', '\u{2C70}'), ('\u{2C72}', '\u{2C72}'), ('\u{2C75}', '\u{2C75}'),
+    ('\u{2C7E}', '\u{2C80}'), ('\u{2C82}', '\u{2C82}'), ('\u{2C84}', '\u{2C84}'), ('\u{2C86}', '\u{2C86}'),
+    ('\u{2C88}', '\u{2C88}'), ('\u{2C8A}', '\u{2C8A}'), ('\u{2C8C}', '\u{2C8C}'), ('\u{2C8E}', '\u{2C8E}'),
+    ('\u{2C90}', '\u{2C90}'), ('\u{2C92}', '\u{2C92}'), ('\u{2C94}', '\u{2C94}'), ('\u{2C96}', '\u{2C96}'),
+    ('\u{2C98}', '\u{2C98}'), ('\u{2C9A}', '\u{2C9A}'), ('\u{2C9C}', '\u{2C9C}'), ('\u{2C9E}', '\u{2C9E}'),
+    ('\u{2CA0}', '\u{2CA0}'), ('\u{2CA2}', '\u{2CA2}'), ('\u{2CA4}', '\u{2CA4}'), ('\u{2CA6}', '\u{2CA6}'),
+    ('\u{2CA8}', '\u{2CA8}'), ('\u{2CAA}', '\u{2CAA}'), ('\u{2CAC}', '\u{2CAC}'), ('\u{2CAE}', '\u{2CAE}'),
+    ('\u{2CB0}', '\u{2CB0}'), ('\u{2CB2}', '\u{2CB2}'), ('\u{2CB4}', '\u{2CB4}'), ('\u{2CB6}', '\u{2CB6}'),
+    ('\u{2CB8}', '\u{2CB8}'), ('\u{2CBA}', '\u{2CBA}'), ('\u{2CBC}', '\u{2CBC}'), ('\u{2CBE}', '\u{2CBE}'),
+    ('\u{2CC0}', '\u{2CC0}'), ('\u{2CC2}', '\u{2CC2}'), ('\u{2CC4}', '\u{2CC4}'), ('\u{2CC6}', '\u{2CC6}'),
+    ('\u{2CC8}', '\u{2CC8}'), ('\u{2CCA}', '\u{2CCA}'), ('\u{2CCC}', '\u{2CCC}'), ('\u{2CCE}', '\u{2CCE}'),
+    ('\u{2CD0}', '\u{2CD0}'), ('\u{2CD2}', '\u{2CD2}'), ('\u{2CD4}', '\u{2CD4}'), ('\u{2CD6}', '\u{2CD6}'),
+    ('\u{2CD8}', '\u{2CD8}'), ('\u{2CDA}', '\u{2CDA}'), ('\u{2CDC}', '\u{2CDC}'), ('\u{2CDE}', '\u{2CDE}'),
+    ('\u{2CE0}', '\u{2CE0}'), ('\u{2CE2}', '\u{2CE2}'), ('\u{2CEB}', '\u{2CEB}'), ('\u{2CED}', '\u{2CED}'),
+    ('\u{2CF2}', '\u{2CF2}'), ('\u{A640}', '\u{A640}'), ('\u{A642}', '\u{A642}'), ('\u{A644}', '\u{A644}'),
+    ('\u{A646}', '\u{A646}'), ('\u{A648}', '\u{A648}'), ('\u{A64A}', '\u{A64A}'), ('\u{A64C}', '\u{A64C}'),
+    ('\u{A64E}', '\u{A64E}'), ('\u{A650}', '\u{A650}'), ('\u{A652}', '\u{A652}'), ('\u{A654}', '\u{A654}'),
+    ('\u{A656}', '\u{A656}'), ('\u{A658}', '\u{A658}'), ('\u{A65A}', '\u{A65A}'), ('\u{A65C}', '\u{A65C}'),
+    ('\u{A65E}', '\u{A65E}'), ('\u{A660}', '\u{A660}'), ('\u{A662}', '\u{A662}'), ('\u{A664}', '\u{A664}'),
+    ('\u{A666}', '\u{A666}'), ('\u{A668}', '\u{A668}'), ('\u{A66A}', '\u{A66A}'), ('\u{A66C}', '\u{A66C}'),
+    ('\u{A680}', '\u{A680}'), ('\u{A682}', '\u{A682}'), ('\u{A684}', '\u{A684}'), ('\u{A686}', '\u{A686}'),
+    ('\u{A688}', '\u{A688}'), ('\u{A68A}', '\u{A68A}'), ('\u{A68C}', '\u{A68C}'), ('\u{A68E}', '\u{A68E}'),
+    ('\u{A690}', '\u{A690}'), ('\u{A692}', '\u{A692}'), ('\u{A694}', '\u{A694}'), ('\u{A696}', '\u{A696}'),
+    ('\u{A698}', '\u{A698}'), ('\u{A69A}', '\u{A69A}'), ('\u{A722}', '\u{A722}'), ('\u{A724}', '\u{A724}'),
+    ('\u{A726}', '\u{A726}'), ('\u{A728}', '\u{A728}'), ('\u{A72A}', '\u{A72A}'), ('\u{A72C}', '\u{A72C}'),
+    ('\u{A72E}', '\u{A72E}'), ('\u{A732}', '\u{A732}'), ('\u{A734}', '\u{A734}'), ('\u{A736}', '\u{A736}'),
+    ('\u{A738}', '\u{A738}'), ('\u{A73A}', '\u{A73A}'), ('\u{A73C}', '\u{A73C}'), ('\u{A73E}', '\u{A73E}'),
+    ('\u{A740}', '\u{A740}'), ('\u{A742}', '\u{A742}'), ('\u{A744}', '\u{A744}'), ('\u{A746}', '\u{A746}'),
+    ('\u{A748}', '\u{A748}'), ('\u{A74A}', '\u{A74A}'), ('\u{A74C}', '\u{A74C}'), ('\u{A74E}', '\u{A74E}'),
+    ('\u{A750}', '\u{A750}'), ('\u{A752}', '\u{A752}'), ('\u{A754}', '\u{A754}'), ('\u{A756}', '\u{A756}'),
+    ('\u{A758}', '\u{A758}'), ('\u{A75A}', '\u{A75A}'), ('\u{A75C}', '\u{A75C}'), ('\u{A75E}', '\u{A75E}'),
+    ('\u{A760}', '\u{A760}'), ('\u{A762}', '\u{A762}'), ('\u{A764}', '\u{A764}'), ('\u{A766}', '\u{A766}'),
+    ('\u{A768}', '\u{A768}'), ('\u{A76A}', '\u{A76A}'), ('\u{A76C}', '\u{A76C}'), ('\u{A76E}', '\u{A76E}'),
+    ('\u{A779}', '\u{A779}'), ('\u{A77B}', '\u{A77B}'), ('\u{A77D}', '\u{A77E}'), ('\u{A780}', '\u{A780}'),
+    ('\u{A782}', '\u{A782}'), ('\u{A784}', '\u{A784}'), ('\u{A786}', '\u{A786}'), ('\u{A78B}', '\u{A78B}'),
+    ('\u{A78D}', '\u{A78D}'), ('\u{A790}', '\u{A790}'), ('\u{A792}', '\u{A792}'), ('\u{A796}', '\u{A796}'),
+    ('\u{A798}', '\u{A798}'), ('\u{A79A}', '\u{A79A}'), ('\u{A79C}', '\u{A79C}'), ('\u{A79E}', '\u{A79E}'),
+    ('\u{A7A0}', '\u{A7A0}'), ('\u{A7A2}', '\u{A7A2}'), ('\u{A7A4}', '\u{A7A4}'), ('\u{A7A6}', '\u{A7A6}'),
+    ('\u{A7A8}', '\u{A7A8}'), ('\u{A7AA}', '\u{A7AE}'), ('\u{A7B0}', '\u{A7B4}'), ('\u{A7B6}', '\u{A7B6}'),
+    ('\u{A7B8}', '\u{A7B8}'), ('\u{A7BA}', '\u{A7BA}'), ('\u{A7BC}', '\u{A7BC}'), ('\u{A7BE}', '\u{A7BE}'),
+    ('\u{A7C0}', '\u{A7C0}'), ('\u{A7C2}', '\u{A7C2}'), ('\u{A7C4}', '\u{A7C7}'), ('\u{A7C9}', '\u{A7C9}'),
+    ('\u{A7D0}', '\u{A7D0}'), ('\u{A7D6}', '\u{A7D6}'), ('\u{A7D8}', '\u{A7D8}'), ('\u{A7F5}', '\u{A7F5}'),
+    ('\u{FF21}', '\u{FF3A}'), ('\u{10400}', '\u{10427}'), ('\u{104B0}', '\u{104D3}'), ('\u{10570}', '\u{1057A}'),
+    ('\u{1057C}', '\u{1058A}'), ('\u{1058C}', '\u{10592}'), ('\u{10594}', '\u{10595}'), ('\u{10C80}', '\u{10CB2}'),
+    ('\u{118A0}', '\u{118BF}'), ('\u{16E40}', '\u{16E5F}'), ('\u{1D400}', '\u{1D419}'), ('\u{1D434}', '\u{1D44D}'),
+    ('\u{1D468}', '\u{1D481}'), ('\u{1D49C}', '\u{1D49C}'), ('\u{1D49E}', '\u{1D49F}'), ('\u{1D4A2}', '\u{1D4A2}'),
+    ('\u{1D4A5}', '\u{1D4A6}'), ('\u{1D4A9}', '\u{1D4AC}'), ('\u{1D4AE}', '\u{1D4B5}'), ('\u{1D4D0}', '\u{1D4E9}'),
+    ('\u{1D504}', '\u{1D505}'), ('\u{1D507}', '\u{1D50A}'), ('\u{1D50D}', '\u{1D514}'), ('\u{1D516}', '\u{1D51C}'),
+    ('\u{1D538}', '\u{1D539}'), ('\u{1D53B}', '\u{1D53E}'), ('\u{1D540}', '\u{1D544}'), ('\u{1D546}', '\u{1D546}'),
+    ('\u{1D54A}', '\u{1D550}'), ('\u{1D56C}', '\u{1D585}'), ('\u{1D5A0}', '\u{1D5B9}'), ('\u{1D5D4}', '\u{1D5ED}'),
+    ('\u{1D608}', '\u{1D621}'), ('\u{1D63C}', '\u{1D655}'), ('\u{1D670}', '\u{1D689}'), ('\u{1D6A8}', '\u{1D6C0}'),
+    ('\u{1D6E2}', '\u{1D6FA}'), ('\u{1D71C}', '\u{1D734}'), ('\u{1D756}', '\u{1D76E}'), ('\u{1D790}', '\u{1D7A8}'),
+    ('\u{1D7CA}', '\u{1D7CA}'), ('\u{1E900}', '\u{1E921}'),
+];
+
+const GC_MC: &[(char, char)] = &[
+    ('\u{903}', '\u{903}'), ('\u{93B}', '\u{93B}'), ('\u{93E}', '\u{940}'), ('\u{949}', '\u{94C}'),
+    ('\u{94E}', '\u{94F}'), ('\u{982}', '\u{983}'), ('\u{9BE}', '\u{9C0}'), ('\u{9C7}', '\u{9C8}'),
+    ('\u{9CB}', '\u{9CC}'), ('\u{9D7}', '\u{9D7}'), ('\u{A03}', '\u{A03}'), ('\u{A3E}', '\u{A40}'),
+    ('\u{A83}', '\u{A83}'), ('\u{ABE}', '\u{AC0}'), ('\u{AC9}', '\u{AC9}'), ('\u{ACB}', '\u{ACC}'),
+    ('\u{B02}', '\u{B03}'), ('\u{B3E}', '\u{B3E}'), ('\u{B40}', '\u{B40}'), ('\u{B47}', '\u{B48}'),
+    ('\u{B4B}', '\u{B4C}'), ('\u{B57}', '\u{B57}'), ('\u{BBE}', '\u{BBF}'), ('\u{BC1}', '\u{BC2}'),
+    ('\u{BC6}', '\u{BC8}'), ('\u{BCA}', '\u{BCC}'), ('\u{BD7}', '\u{BD7}'), ('\u{C01}', '\u{C03}'),
+    ('\u{C41}', '\u{C44}'), ('\u{C82}', '\u{C83}'), ('\u{CBE}', '\u{CBE}'), ('\u{CC0}', '\u{CC4}'),
+    ('\u{CC7}', '\u{CC8}'), ('\u{CCA}', '\u{CCB}'), ('\u{CD5}', '\u{CD6}'), ('\u{D02}', '\u{D03}'),
+    ('\u{D3E}', '\u{D40}'), ('\u{D46}', '\u{D48}'), ('\u{D4A}', '\u{D4C}'), ('\u{D57}', '\u{D57}'),
+    ('\u{D82}', '\u{D83}'), ('\u{DCF}', '\u{DD1}'), ('\u{DD8}', '\u{DDF}'), ('\u{DF2}', '\u{DF3}'),
+    ('\u{F3E}', '\u{F3F}'), ('\u{F7F}', '\u{F7F}'), ('\u{102B}', '\u{102C}'), ('\u{1031}', '\u{1031}'),
+    ('\u{1038}', '\u{1038}'), ('\u{103B}', '\u{103C}'), ('\u{1056}', '\u{1057}'), ('\u{1062}', '\u{1064}'),
+    ('\u{1067}', '\u{106D}'), ('\u{1083}', '\u{1084}'), ('\u{1087}', '\u{108C}'), ('\u{108F}', '\u{108F}'),
+    ('\u{109A}', '\u{109C}'), ('\u{1715}', '\u{1715}'), ('\u{1734}', '\u{1734}'), ('\u{17B6}', '\u{17B6}'),
+    ('\u{17BE}', '\u{17C5}'), ('\u{17C7}', '\u{17C8}'), ('\u{1923}', '\u{1926}'), ('\u{1929}', '\u{192B}'),
+    ('\u{1930}', '\u{1931}'), ('\u{1933}', '\u{1938}'), ('\u{1A19}', '\u{1A1A}'), ('\u{1A55}', '\u{1A55}'),
+    ('\u{1A57}', '\u{1A57}'), ('\u{1A61}', '\u{1A61}'), ('\u{1A63}', '\u{1A64}'), ('\u{1A6D}', '\u{1A72}'),
+    ('\u{1B04}', '\u{1B04}'), ('\u{1B35}', '\u{1B35}'), ('\u{1B3B}', '\u{1B3B}'), ('\u{1B3D}', '\u{1B41}'),
+    ('\u{1B43}', '\u{1B44}'), ('\u{1B82}', '\u{1B82}'), ('\u{1BA1}', '\u{1BA1}'), ('\u{1BA6}', '\u{1BA7}'),
+    ('\u{1BAA}', '\u{1BAA}'), ('\u{1BE7}', '\u{1BE7}'), ('\u{1BEA}', '\u{1BEC}'), ('\u{1BEE}', '\u{1BEE}'),
+    ('\u{1BF2}', '\u{1BF3}'), ('\u{1C24}', '\u{1C2B}'), ('\u{1C34}', '\u{1C35}'), ('\u{1CE1}', '\u{1CE1}'),
+    ('\u{1CF7}', '\u{1CF7}'), ('\u{302E}', '\u{302F}'), ('\u{A823}', '\u{A824}'), ('\u{A827}', '\u{A827}'),
+    ('\u{A880}', '\u{A881}'), ('\u{A8B4}', '\u{A8C3}'), ('\u{A952}', '\u{A953}'), ('\u{A983}', '\u{A983}'),
+    ('\u{A9B4}', '\u{A9B5}'), ('\u{A9BA}', '\u{A9BB}'), ('\u{A9BE}', '\u{A9C0}'), ('\u{AA2F}', '\u{AA30}'),
+    ('\u{AA33}', '\u{AA34}'), ('\u{AA4D}', '\u{AA4D}'), ('\u{AA7B}', '\u{AA7B}'), ('\u{AA7D}', '\u{AA7D}'),
+    ('\u{AAEB}', '\u{AAEB}'), ('\u{AAEE}', '\u{AAEF}'), ('\u{AAF5}', '\u{AAF5}'), ('\u{ABE3}', '\u{ABE4}'),
+    ('\u{ABE6}', '\u{ABE7}'), ('\u{ABE9}', '\u{ABEA}'), ('\u{ABEC}', '\u{ABEC}'), ('\u{11000}', '\u{11000}'),
+    ('\u{11002}', '\u{11002}'), ('\u{11082}', '\u{11082}'), ('\u{110B0}', '\u{110B2}'), ('\u{110B7}', '\u{110B8}'),
+    ('\u{1112C}', '\u{1112C}'), ('\u{11145}', '\u{11146}'), ('\u{11182}', '\u{11182}'), ('\u{111B3}', '\u{111B5}'),
+    ('\u{111BF}', '\u{111C0}'), ('\u{111CE}', '\u{111CE}'), ('\u{1122C}', '\u{1122E}'), ('\u{11232}', '\u{11233}'),
+    ('\u{11235}', '\u{11235}'), ('\u{112E0}', '\u{112E2}'), ('\u{11302}', '\u{11303}'), ('\u{1133E}', '\u{1133F}'),
+    ('\u{11341}', '\u{11344}'), ('\u{11347}', '\u{11348}'), ('\u{1134B}', '\u{1134D}'), ('\u{11357}', '\u{11357}'),
+    ('\u{11362}', '\u{11363}'), ('\u{11435}', '\u{11437}'), ('\u{11440}', '\u{11441}'), ('\u{11445}', '\u{11445}'),
+    ('\u{114B0}', '\u{114B2}'), ('\u{114B9}', '\u{114B9}'), ('\u{114BB}', '\u{114BE}'), ('\u{114C1}', '\u{114C1}'),
+    ('\u{115AF}', '\u{115B1}'), ('\u{115B8}', '\u{115BB}'), ('\u{115BE}', '\u{115BE}'), ('\u{11630}', '\u{11632}'),
+    ('\u{1163B}', '\u{1163C}'), ('\u{1163E}', '\u{1163E}'), ('\u{116AC}', '\u{116AC}'), ('\u{116AE}', '\u{116AF}'),
+    ('\u{116B6}', '\u{116B6}'), ('\u{11720}', '\u{11721}'), ('\u{11726}', '\u{11726}'), ('\u{1182C}', '\u{1182E}'),
+    ('\u{11838}', '\u{11838}'), ('\u{11930}', '\u{11935}'), ('\u{11937}', '\u{11938}'), ('\u{1193D}', '\u{1193D}'),
+    ('\u{11940}', '\u{11940}'), ('\u{11942}', '\u{11942}'), ('\u{119D1}', '\u{119D3}'), ('\u{119DC}', '\u{119DF}'),
+    ('\u{119E4}', '\u{119E4}'), ('\u{11A39}', '\u{11A39}'), ('\u{11A57}', '\u{11A58}'), ('\u{11A97}', '\u{11A97}'),
+    ('\u{11C2F}', '\u{11C2F}'), ('\u{11C3E}', '\u{11C3E}'), ('\u{11CA9}', '\u{11CA9}'), ('\u{11CB1}', '\u{11CB1}'),
+    ('\u{11CB4}', '\u{11CB4}'), ('\u{11D8A}', '\u{11D8E}'), ('\u{11D93}', '\u{11D94}'), ('\u{11D96}', '\u{11D96}'),
+    ('\u{11EF5}', '\u{11EF6}'), ('\u{16F51}', '\u{16F87}'), ('\u{16FF0}', '\u{16FF1}'), ('\u{1D165}', '\u{1D166}'),
+    ('\u{1D16D}', '\u{1D172}'),
+];
+
+const GC_ME: &[(char, char)] = &[
+    ('\u{488}', '\u{489}'), ('\u{1ABE}', '\u{1ABE}'), ('\u{20DD}', '\u{20E0}'), ('\u{20E2}', '\u{20E4}'),
+    ('\u{A670}', '\u{A672}'),
+];
+
+const GC_MN: &[(char, char)] = &[
+    ('\u{300}', '\u{36F}'), ('\u{483}', '\u{487}'), ('\u{591}', '\u{5BD}'), ('\u{5BF}', '\u{5BF}'),
+    ('\u{5C1}', '\u{5C2}'), ('\u{5C4}', '\u{5C5}'), ('\u{5C7}', '\u{5C7}'), ('\u{610}', '\u{61A}'),
+    ('\u{64B}', '\u{65F}'), ('\u{670}', '\u{670}'), ('\u{6D6}', '\u{6DC}'), ('\u{6DF}', '\u{6E4}'),
+    ('\u{6E7}', '\u{6E8}'), ('\u{6EA}', '\u{6ED}'), ('\u{711}', '\u{711}'), ('\u{730}', '\u{74A}'),
+    ('\u{7A6}', '\u{7B0}'), ('\u{7EB}', '\u{7F3}'), ('\u{7FD}', '\u{7FD}'), ('\u{816}', '\u{819}'),
+    ('\u{81B}', '\u{823}'), ('\u{825}', '\u{827}'), ('\u{829}', '\u{82D}'), ('\u{859}', '\u{85B}'),
+    ('\u{898}', '\u{89F}'), ('\u{8CA}', '\u{8E1}'), ('\u{8E3}', '\u{902}'), ('\u{93A}', '\u{93A}'),
+    ('\u{93C}', '\u{93C}'), ('\u{941}', '\u{948}'), ('\u{94D}', '\u{94D}'), ('\u{951}', '\u{957}'),
+    ('\u{962}', '\u{963}'), ('\u{981}', '\u{981}'), ('\u{9BC}', '\u{9BC}'), ('\u{9C1}', '\u{9C4}'),
+    ('\u{9CD}', '\u{9CD}'), ('\u{9E2}', '\u{9E3}'), ('\u{9FE}', '\u{9FE}'), ('\u{A01}', '\u{A02}'),
+    ('\u{A3C}', '\u{A3C}'), ('\u{A41}', '\u{A42}'), ('\u{A47}', '\u{A48}'), ('\u{A4B}', '\u{A4D}'),
+    ('\u{A51}', '\u{A51}'), ('\u{A70}', '\u{A71}'), ('\u{A75}', '\u{A75}'), ('\u{A81}', '\u{A82}'),
+    ('\u{ABC}', '\u{ABC}'), ('\u{AC1}', '\u{AC5}'), ('\u{AC7}', '\u{AC8}'), ('\u{ACD}', '\u{ACD}'),
+    ('\u{AE2}', '\u{AE3}'), ('\u{AFA}', '\u{AFF}'), ('\u{B01}', '\u{B01}'), ('\u{B3C}', '\u{B3C}'),
+    ('\u{B3F}', '\u{B3F}'), ('\u{B41}', '\u{B44}'), ('\u{B4D}', '\u{B4D}'), ('\u{B55}', '\u{B56}'),
+    ('\u{B62}', '\u{B63}'), ('\u{B82}', '\u{B82}'), ('\u{BC0}', '\u{BC0}'), ('\u{BCD}', '\u{BCD}'),
+    ('\u{C00}', '\u{C00}'), ('\u{C04}', '\u{C04}'), ('\u{C3C}', '\u{C3C}'), ('\u{C3E}', '\u{C40}'),
+    ('\u{C46}', '\u{C48}'), ('\u{C4A}', '\u{C4D}'), ('\u{C55}', '\u{C56}'), ('\u{C62}', '\u{C63}'),
+    ('\u{C81}', '\u{C81}'), ('\u{CBC}', '\u{CBC}'), ('\u{CBF}', '\u{CBF}'), ('\u{CC6}', '\u{CC6}'),
+    ('\u{CCC}', '\u{CCD}'), ('\u{CE2}', '\u{CE3}'), ('\u{D00}', '\u{D01}'), ('\u{D3B}', '\u{D3C}'),
+    ('\u{D41}', '\u{D44}'), ('\u{D4D}', '\u{D4D}'), ('\u{D62}', '\u{D63}'), ('\u{D81}', '\u{D81}'),
+    ('\u{DCA}', '\u{DCA}'), ('\u{DD2}', '\u{DD4}'), ('\u{DD6}', '\u{DD6}'), ('\u{E31}', '\u{E31}'),
+    ('\u{E34}', '\u{E3A}'), ('\u{E47}', '\u{E4E}'), ('\u{EB1}', '\u{EB1}'), ('\u{EB4}', '\u{EBC}'),
+    ('\u{EC8}', '\u{ECD}'), ('\u{F18}', '\u{F19}'), ('\u{F35}', '\u{F35}'), ('\u{F37}', '\u{F37}'),
+    ('\u{F39}', '\u{F39}'), ('\u{F71}', '\u{F7E}'), ('\u{F80}', '\u{F84}'), ('\u{F86}', '\u{F87}'),
+    ('\u{F8D}', '\u{F97}'), ('\u{F99}', '\u{FBC}'), ('\u{FC6}', '\u{FC6}'), ('\u{102D}', '\u{1030}'),
+    ('\u{1032}', '\u{1037}'), ('\u{1039}', '\u{103A}'), ('\u{103D}', '\u{103E}'), ('\u{1058}', '\u{1059}'),
+    ('\u{105E}', '\u{1060}'), ('\u{1071}', '\u{1074}'), ('\u{1082}', '\u{1082}'), ('\u{1085}', '\u{1086}'),
+    ('\u{108D}', '\u{108D}'), ('\u{109D}', '\u{109D}'), ('\u{135D}', '\u{135F}'), ('\u{1712}', '\u{1714}'),
+    ('\u{1732}', '\u{1733}'), ('\u{1752}', '\u{1753}'), ('\u{1772}', '\u{1773}'), ('\u{17B4}', '\u{17B5}'),
+    ('\u{17B7}', '\u{17BD}'), ('\u{17C6}', '\u{17C6}'), ('\u{17C9}', '\u{17D3}'), ('\u{17DD}', '\u{17DD}'),
+    ('\u{180B}', '\u{180D}'), ('\u{180F}', '\u{180F}'), ('\u{1885}', '\u{1886}'), ('\u{18A9}', '\u{18A9}'),
+    ('\u{1920}', '\u{1922}'), ('\u{1927}', '\u{1928}'), ('\u{1932}', '\u{1932}'), ('\u{1939}', '\u{193B}'),
+    ('\u{1A17}', '\u{1A18}'), ('\u{1A1B}', '\u{1A1B}'), ('\u{1A56}', '\u{1A56}'), ('\u{1A58}', '\u{1A5E}'),
+    ('\u{1A60}', '\u{1A60}'), ('\u{1A62}', '\u{1A62}'), ('\u{1A65}', '\u{1A6C}'), ('\u{1A73}', '\u{1A7C}'),
+    ('\u{1A7F}', '\u{1A7F}'), ('\u{1AB0}', '\u{1ABD}'), ('\u{1ABF}', '\u{1ACE}'), ('\u{1B00}', '\u{1B03}'),
+    ('\u{1B34}', '\u{1B34}'), ('\u{1B36}', '\u{1B3A}'), ('\u{1B3C}', '\u{1B3C}'), ('\u{1B42}', '\u{1B42}'),
+    ('\u{1B6B}', '\u{1B73}'), ('\u{1B80}', '\u{1B81}'), ('\u{1BA2}', '\u{1BA5}'), ('\u{1BA8}', '\u{1BA9}'),
+    ('\u{1BAB}', '\u{1BAD}'), ('\u{1BE6}', '\u{1BE6}'), ('\u{1BE8}', '\u{1BE9}'), ('\u{1BED}', '\u{1BED}'),
+    ('\u{1BEF}', '\u{1BF1}'), ('\u{1C2C}', '\u{1C33}'), ('\u{1C36}', '\u{1C37}'), ('\u{1CD0}', '\u{1CD2}'),
+    ('\u{1CD4}', '\u{1CE0}'), ('\u{1CE2}', '\u{1CE8}'), ('\u{1CED}', '\u{1CED}'), ('\u{1CF4}', '\u{1CF4}'),
+    ('\u{1CF8}', '\u{1CF9}'), ('\u{1DC0}', '\u{1DFF}'), ('\u{20D0}', '\u{20DC}'), ('\u{20E1}', '\u{20E1}'),
+    ('\u{20E5}', '\u{20F0}'), ('\u{2CEF}', '\u{2CF1}'), ('\u{2D7F}', '\u{2D7F}'), ('\u{2DE0}', '\u{2DFF}'),
+    ('\u{302A}', '\u{302D}'), ('\u{3099}', '\u{309A}'), ('\u{A66F}', '\u{A66F}'), ('\u{A674}', '\u{A67D}'),
+    ('\u{A69E}', '\u{A69F}'), ('\u{A6F0}', '\u{A6F1}'), ('\u{A802}', '\u{A802}'), ('\u{A806}', '\u{A806}'),
+    ('\u{A80B}', '\u{A80B}'), ('\u{A825}', '\u{A826}'), ('\u{A82C}', '\u{A82C}'), ('\u{A8C4}', '\u{A8C5}'),
+    ('\u{A8E0}', '\u{A8F1}'), ('\u{A8FF}', '\u{A8FF}'), ('\u{A926}', '\u{A92D}'), ('\u{A947}', '\u{A951}'),
+    ('\u{A980}', '\u{A982}'), ('\u{A9B3}', '\u{A9B3}'), ('\u{A9B6}', '\u{A9B9}'), ('\u{A9BC}', '\u{A9BD}'),
+    ('\u{A9E5}', '\u{A9E5}'), ('\u{AA29}', '\u{AA2E}'), ('\u{AA31}', '\u{AA32}'), ('\u{AA35}', '\u{AA36}'),
+    ('\u{AA43}', '\u{AA43}'), ('\u{AA4C}', '\u{AA4C}'), ('\u{AA7C}', '\u{AA7C}'), ('\u{AAB0}', '\u{AAB0}'),
+    ('\u{AAB2}', '\u{AAB4}'), ('\u{AAB7}', '\u{AAB8}'), ('\u{AABE}', '\u{AABF}'), ('\u{AAC1}', '\u{AAC1}'),
+    ('\u{AAEC}', '\u{AAED}'), ('\u{AAF6}', '\u{AAF6}'), ('\u{ABE5}', '\u{ABE5}'), ('\u{ABE8}', '\u{ABE8}'),
+    ('\u{ABED}', '\u{ABED}'), ('\u{FB1E}', '\u{FB1E}'), ('\u{FE00}', '\u{FE0F}'), ('\u{FE20}', '\u{FE2F}'),
+    ('\u{101FD}', '\u{101FD}'), ('\u{102E0}', '\u{102E0}'), ('\u{10376}', '\u{1037A}'), ('\u{10A01}', '\u{10A03}'),
+    ('\u{10A05}', '\u{10A06}'), ('\u{10A0C}', '\u{10A0F}'), ('\u{10A38}', '\u{10A3A}'), ('\u{10A3F}', '\u{10A3F}'),
+    ('\u{10AE5}', '\u{10AE6}'), ('\u{10D24}', '\u{10D27}'), ('\u{10EAB}', '\u{10EAC}'), ('\u{10F46}', '\u{10F50}'),
+    ('\u{10F82}', '\u{10F85}'), ('\u{11001}', '\u{11001}'), ('\u{11038}', '\u{11046}'), ('\u{11070}', '\u{11070}'),
+    ('\u{11073}', '\u{11074}'), ('\u{1107F}', '\u{11081}'), ('\u{110B3}', '\u{110B6}'), ('\u{110B9}', '\u{110BA}'),
+    ('\u{110C2}', '\u{110C2}'), ('\u{11100}', '\u{11102}'), ('\u{11127}', '\u{1112B}'), ('\u{1112D}', '\u{11134}'),
+    ('\u{11173}', '\u{11173}'), ('\u{11180}', '\u{11181}'), ('\u{111B6}', '\u{111BE}'), ('\u{111C9}', '\u{111CC}'),
+    ('\u{111CF}', '\u{111CF}'), ('\u{1122F}', '\u{11231}'), ('\u{11234}', '\u{11234}'), ('\u{11236}', '\u{11237}'),
+    ('\u{1123E}', '\u{1123E}'), ('\u{112DF}', '\u{112DF}'), ('\u{112E3}', '\u{112EA}'), ('\u{11300}', '\u{11301}'),
+    ('\u{1133B}', '\u{1133C}'), ('\u{11340}', '\u{11340}'), ('\u{11366}', '\u{1136C}'), ('\u{11370}', '\u{11374}'),
+    ('\u{11438}', '\u{1143F}'), ('\u{11442}', '\u{11444}'), ('\u{11446}', '\u{11446}'), ('\u{1145E}', '\u{1145E}'),
+    ('\u{114B3}', '\u{114B8}'), ('\u{114BA}', '\u{114BA}'), ('\u{114BF}', '\u{114C0}'), ('\u{114C2}', '\u{114C3}'),
+    ('\u{115B2}', '\u{115B5}'), ('\u{115BC}', '\u{115BD}'), ('\u{115BF}', '\u{115C0}'), ('\u{115DC}', '\u{115DD}'),
+    ('\u{11633}', '\u{1163A}'), ('\u{1163D}', '\u{1163D}'), ('\u{1163F}', '\u{11640}'), ('\u{116AB}', '\u{116AB}'),
+    ('\u{116AD}', '\u{116AD}'), ('\u{116B0}', '\u{116B5}'), ('\u{116B7}', '\u{116B7}'), ('\u{1171D}', '\u{1171F}'),
+    ('\u{11722}', '\u{11725}'), ('\u{11727}', '\u{1172B}'), ('\u{1182F}', '\u{11837}'), ('\u{11839}', '\u{1183A}'),
+    ('\u{1193B}', '\u{1193C}'), ('\u{1193E}', '\u{1193E}'), ('\u{11943}', '\u{11943}'), ('\u{119D4}', '\u{119D7}'),
+    ('\u{119DA}', '\u{119DB}'), ('\u{119E0}', '\u{119E0}'), ('\u{11A01}', '\u{11A0A}'), ('\u{11A33}', '\u{11A38}'),
+    ('\u{11A3B}', '\u{11A3E}'), ('\u{11A47}', '\u{11A47}'), ('\u{11A51}', '\u{11A56}'), ('\u{11A59}', '\u{11A5B}'),
+    ('\u{11A8A}', '\u{11A96}'), ('\u{11A98}', '\u{11A99}'), ('\u{11C30}', '\u{11C36}'), ('\u{11C38}', '\u{11C3D}'),
+    ('\u{11C3F}', '\u{11C3F}'), ('\u{11C92}', '\u{11CA7}'), ('\u{11CAA}', '\u{11CB0}'), ('\u{11CB2}', '\u{11CB3}'),
+    ('\u{11CB5}', '\u{11CB6}'), ('\u{11D31}', '\u{11D36}'), ('\u{11D3A}', '\u{11D3A}'), ('\u{11D3C}', '\u{11D3D}'),
+    ('\u{11D3F}', '\u{11D45}'), ('\u{11D47}', '\u{11D47}'), ('\u{11D90}', '\u{11D91}'), ('\u{11D95}', '\u{11D95}'),
+    ('\u{11D97}', '\u{11D97}'), ('\u{11EF3}', '\u{11EF4}'), ('\u{16AF0}', '\u{16AF4}'), ('\u{16B30}', '\u{16B36}'),
+    ('\u{16F4F}', '\u{16F4F}'), ('\u{16F8F}', '\u{16F92}'), ('\u{16FE4}', '\u{16FE4}'), ('\u{1BC9D}', '\u{1BC9E}'),
+    ('\u{1CF00}', '\u{1CF2D}'), ('\u{1CF30}', '\u{1CF46}'), ('\u{1D167}', '\u{1D169}'), ('\u{1D17B}', '\u{1D182}'),
+    ('\u{1D185}', '\u{1D18B}'), ('\u{1D1AA}', '\u{1D1AD}'), ('\u{1D242}', '\u{1D244}'), ('\u{1DA00}', '\u{1DA36}'),
+    ('\u{1DA3B}', '\u{1DA6C}'), ('\u{1DA75}', '\u{1DA75}'), ('\u{1DA84}', '\u{1DA84}'), ('\u{1DA9B}', '\u{1DA9F}'),
+    ('\u{1DAA1}', '\u{1DAAF}'), ('\u{1E000}', '\u{1E006}'), ('\u{1E008}', '\u{1E018}'), ('\u{1E01B}', '\u{1E021}'),
+    ('\u{1E023}', '\u{1E024}'), ('\u{1E026}', '\u{1E02A}'), ('\u{1E130}', '\u{1E136}'), ('\u{1E2AE}', '\u{1E2AE}'),
+    ('\u{1E2EC}', '\u{1E2EF}'), ('\u{1E8D0}', '\u{1E8D6}'), ('\u{1E944}', '\u{1E94A}'), ('\u{E0100}', '\u{E01EF}'),
+];
+
+const GC_ND: &[(char, char)] = &[
+    ('\u{30}', '\u{39}'), ('\u{660}', '\u{669}'), ('\u{6F0}', '\u{6F9}'), ('\u{7C0}', '\u{7C9}'),
+    ('\u{966}', '\u{96F}'), ('\u{9E6}', '\u{9EF}'), ('\u{A66}', '\u{A6F}'), ('\u{AE6}', '\u{AEF}'),
+    ('\u{B66}', '\u{B6F}'), ('\u{BE6}', '\u{BEF}'), ('\u{C66}', '\u{C6F}'), ('\u{CE6}', '\u{CEF}'),
+    ('\u{D66}', '\u{D6F}'), ('\u{DE6}', '\u{DEF}'), ('\u{E50}', '\u{E59}'), ('\u{ED0}', '\u{ED9}'),
+    ('\u{F20}', '\u{F29}'), ('\u{1040}', '\u{1049}'), ('\u{1090}', '\u{1099}'), ('\u{17E0}', '\u{17E9}'),
+    ('\u{1810}', '\u{1819}'), ('\u{1946}', '\u{194F}'), ('\u{19D0}', '\u{19D9}'), ('\u{1A80}', '\u{1A89}'),
+    ('\u{1A90}', '\u{1A99}'), ('\u{1B50}', '\u{1B59}'), ('\u{1BB0}', '\u{1BB9}'), ('\u{1C40}', '\u{1C49}'),
+    ('\u{1C50}', '\u{1C59}'), ('\u{A620}', '\u{A629}'), ('\u{A8D0}', '\u{A8D9}'), ('\u{A900}', '\u{A909}'),
+    ('\u{A9D0}', '\u{A9D9}'), ('\u{A9F0}', '\u{A9F9}'), ('\u{AA50}', '\u{AA59}'), ('\u{ABF0}', '\u{ABF9}'),
+    ('\u{FF10}', '\u{FF19}'), ('\u{104A0}', '\u{104A9}'), ('\u{10D30}', '\u{10D39}'), ('\u{11066}', '\u{1106F}'),
+    ('\u{110F0}', '\u{110F9}'), ('\u{11136}', '\u{1113F}'), ('\u{111D0}', '\u{111D9}'), ('\u{112F0}', '\u{112F9}'),
+    ('\u{11450}', '\u{11459}'), ('\u{114D0}', '\u{114D9}'), ('\u{11650}', '\u{11659}'), ('\u{116C0}', '\u{116C9}'),
+    ('\u{11730}', '\u{11739}'), ('\u{118E0}', '\u{118E9}'), ('\u{11950}', '\u{11959}'), ('\u{11C50}', '\u{11C59}'),
+    ('\u{11D50}', '\u{11D59}'), ('\u{11DA0}', '\u{11DA9}'), ('\u{16A60}', '\u{16A69}'), ('\u{16AC0}', '\u{16AC9}'),
+    ('\u{16B50}', '\u{16B59}'), ('\u{1D7CE}', '\u{1D7FF}'), ('\u{1E140}', '\u{1E149}'), ('\u{1E2F0}', '\u{1E2F9}'),
+    ('\u{1E950}', '\u{1E959}'), ('\u{1FBF0}', '\u{1FBF9}'),
+];
+
+const GC_NL: &[(char, char)] = &[
+    ('\u{16EE}', '\u{16F0}'), ('\u{2160}', '\u{2182}'), ('\u{2185}', '\u{2188}'), ('\u{3007}', '\u{3007}'),
+    ('\u{3021}', '\u{3029}'), ('\u{3038}', '\u{303A}'), ('\u{A6E6}', '\u{A6EF}'), ('\u{10140}', '\u{10174}'),
+    ('\u{10341}', '\u{10341}'), ('\u{1034A}', '\u{1034A}'), ('\u{103D1}', '\u{103D5}'), ('\u{12400}', '\u{1246E}'),
+];
+
+const GC_NO: &[(char, char)] = &[
+    ('\u{B2}', '\u{B3}'), ('\u{B9}', '\u{B9}'), ('\u{BC}', '\u{BE}'), ('\u{9F4}', '\u{9F9}'),
+    ('\u{B72}', '\u{B77}'), ('\u{BF0}', '\u{BF2}'), ('\u{C78}', '\u{C7E}'), ('\u{D58}', '\u{D5E}'),
+    ('\u{D70}', '\u{D78}'), ('\u{F2A}', '\u{F33}'), ('\u{1369}', '\u{137C}'), ('\u{17F0}', '\u{17F9}'),
+    ('\u{19DA}', '\u{19DA}'), ('\u{2070}', '\u{2070}'), ('\u{2074}', '\u{2079}'), ('\u{2080}', '\u{2089}'),
+    ('\u{2150}', '\u{215F}'), ('\u{2189}', '\u{2189}'), ('\u{2460}', '\u{249B}'), ('\u{24EA}', '\u{24FF}'),
+    ('\u{2776}', '\u{2793}'), ('\u{2CFD}', '\u{2CFD}'), ('\u{3192}', '\u{3195}'), ('\u{3220}', '\u{3229}'),
+    ('\u{3248}', '\u{324F}'), ('\u{3251}', '\u{325F}'), ('\u{3280}', '\u{3289}'), ('\u{32B1}', '\u{32BF}'),
+    ('\u{A830}', '\u{A835}'), ('\u{10107}', '\u{10133}'), ('\u{10175}', '\u{10178}'), ('\u{1018A}', '\u{1018B}'),
+    ('\u{102E1}', '\u{102FB}'), ('\u{10320}', '\u{10323}'), ('\u{10858}', '\u{1085F}'), ('\u{10879}', '\u{1087F}'),
+    ('\u{108A7}', '\u{108AF}'), ('\u{108FB}', '\u{108FF}'), ('\u{10916}', '\u{1091B}'), ('\u{109BC}', '\u{109BD}'),
+    ('\u{109C0}', '\u{109CF}'), ('\u{109D2}', '\u{109FF}'), ('\u{10A40}', '\u{10A48}'), ('\u{10A7D}', '\u{10A7E}'),
+    ('\u{10A9D}', '\u{10A9F}'), ('\u{10AEB}', '\u{10AEF}'), ('\u{10B58}', '\u{10B5F}'), ('\u{10B78}', '\u{10B7F}'),
+    ('\u{10BA9}', '\u{10BAF}'), ('\u{10CFA}', '\u{10CFF}'), ('\u{10E60}', '\u{10E7E}'), ('\u{10F1D}', '\u{10F26}'),
+    ('\u{10F51}', '\u{10F54}'), ('\u{10FC5}', '\u{10FCB}'), ('\u{11052}', '\u{11065}'), ('\u{111E1}', '\u{111F4}'),
+    ('\u{1173A}', '\u{1173B}'), ('\u{118EA}', '\u{118F2}'), ('\u{11C5A}', '\u{11C6C}'), ('\u{11FC0}', '\u{11FD4}'),
+    ('\u{16B5B}', '\u{16B61}'), ('\u{16E80}', '\u{16E96}'), ('\u{1D2E0}', '\u{1D2F3}'), ('\u{1D360}', '\u{1D378}'),
+    ('\u{1E8C7}', '\u{1E8CF}'), ('\u{1EC71}', '\u{1ECAB}'), ('\u{1ECAD}', '\u{1ECAF}'), ('\u{1ECB1}', '\u{1ECB4}'),
+    ('\u{1ED01}', '\u{1ED2D}'), ('\u{1ED2F}', '\u{1ED3D}'), ('\u{1F100}', '\u{1F10C}'),
+];
+
+const GC_PC: &[(char, char)] = &[
+    ('\u{5F}', '\u{5F}'), ('\u{203F}', '\u{2040}'), ('\u{2054}', '\u{2054}'), ('\u{FE33}', '\u{FE34}'),
+    ('\u{FE4D}', '\u{FE4F}'), ('\u{FF3F}', '\u{FF3F}'),
+];
+
+const GC_PD: &[(char, char)] = &[
+    ('\u{2D}', '\u{2D}'), ('\u{58A}', '\u{58A}'), ('\u{5BE}', '\u{5BE}'), ('\u{1400}', '\u{1400}'),
+    ('\u{1806}', '\u{1806}'), ('\u{2010}', '\u{2015}'), ('\u{2E17}', '\u{2E17}'), ('\u{2E1A}', '\u{2E1A}'),
+    ('\u{2E3A}', '\u{2E3B}'), ('\u{2E40}', '\u{2E40}'), ('\u{2E5D}', '\u{2E5D}'), ('\u{301C}', '\u{301C}'),
+    ('\u{3030}', '\u{3030}'), ('\u{30A0}', '\u{30A0}'), ('\u{FE31}', '\u{FE32}'), ('\u{FE58}', '\u{FE58}'),
+    ('\u{FE63}', '\u{FE63}'), ('\u{FF0D}', '\u{FF0D}'), ('\u{10EAD}', '\u{10EAD}'),
+];
+
+const GC_PE: &[(char, char)] = &[
+    ('\u{29}', '\u{29}'), ('\u{5D}', '\u{5D}'), ('\u{7D}', '\u{7D}'), ('\u{F3B}', '\u{F3B}'),
+    ('\u{F3D}', '\u{F3D}'), ('\u{169C}', '\u{169C}'), ('\u{2046}', '\u{2046}'), ('\u{207E}', '\u{207E}'),
+    ('\u{208E}', '\u{208E}'), ('\u{2309}', '\u{2309}'), ('\u{230B}', '\u{230B}'), ('\u{232A}', '\u{232A}'),
+    ('\u{2769}', '\u{2769}'), ('\u{276B}', '\u{276B}'), ('\u{276D}', '\u{276D}'), ('\u{276F}', '\u{276F}'),
+    ('\u{2771}', '\u{2771}'), ('\u{2773}', '\u{2773}'), ('\u{2775}', '\u{2775}'), ('\u{27C6}', '\u{27C6}'),
+    ('\u{27E7}', '\u{27E7}'), ('\u{27E9}', '\u{27E9}'), ('\u{27EB}', '\u{27EB}'), ('\u{27ED}', '\u{27ED}'),
+    ('\u{27EF}', '\u{27EF}'), ('\u{2984}', '\u{2984}'), ('\u{2986}', '\u{2986}'), ('\u{2988}', '\u{2988}'),
+    ('\u{298A}', '\u{298A}'), ('\u{298C}', '\u{298C}'), ('\u{298E}', '\u{298E}'), ('\u{2990}', '\u{2990}'),
+    ('\u{2992}', '\u{2992}'), ('\u{2994}', '\u{2994}'), ('\u{2996}', '\u{2996}'), ('\u{2998}', '\u{2998}'),
+    ('\u{29D9}', '\u{29D9}'), ('\u{29DB}', '\u{29DB}'), ('\u{29FD}', '\u{29FD}'), ('\u{2E23}', '\u{2E23}'),
+    ('\u{2E25}', '\u{2E25}'), ('\u{2E27}', '\u{2E27}'), ('\u{2E29}', '\u{2E29}'), ('\u{2E56}', '\u{2E56}'),
+    ('\u{2E58}', '\u{2E58}'), ('\u{2E5A}', '\u{2E5A}'), ('\u{2E5C}', '\u{2E5C}'), ('\u{3009}', '\u{3009}'),
+    ('\u{300B}', '\u{300B}'), ('\u{300D}', '\u{300D}'), ('\u{300F}', '\u{300F}'), ('\u{3011}', '\u{3011}'),
+    ('\u{3015}', '\u{3015}'), ('\u{3017}', '\u{3017}'), ('\u{3019}', '\u{3019}'), ('\u{301B}', '\u{301B}'),
+    ('\u{301E}', '\u{301F}'), ('\u{FD3E}', '\u{FD3E}'), ('\u{FE18}', '\u{FE18}'), ('\u{FE36}', '\u{FE36}'),
+    ('\u{FE38}', '\u{FE38}'), ('\u{FE3A}', '\u{FE3A}'), ('\u{FE3C}', '\u{FE3C}'), ('\u{FE3E}', '\u{FE3E}'),
+    ('\u{FE40}', '\u{FE40}'), ('\u{FE42}', '\u{FE42}'), ('\u{FE44}', '\u{FE44}'), ('\u{FE48}', '\u{FE48}'),
+    ('\u{FE5A}', '\u{FE5A}'), ('\u{FE5C}', '\u{FE5C}'), ('\u{FE5E}', '\u{FE5E}'), ('\u{FF09}', '\u{FF09}'),
+    ('\u{FF3D}', '\u{FF3D}'), ('\u{FF5D}', '\u{FF5D}'), ('\u{FF60}', '\u{FF60}'), ('\u{FF63}', '\u{FF63}'),
+];
+
+const GC_PF: &[(char, char)] = &[
+    ('\u{BB}', '\u{BB}'), ('\u{2019}', '\u{2019}'), ('\u{201D}', '\u{201D}'), ('\u{203A}', '\u{203A}'),
+    ('\u{2E03}', '\u{2E03}'), ('\u{2E05}', '\u{2E05}'), ('\u{2E0A}', '\u{2E0A}'), ('\u{2E0D}', '\u{2E0D}'),
+    ('\u{2E1D}', '\u{2E1D}'), ('\u{2E21}', '\u{2E21}'),
+];
+
+const GC_PI: &[(char, char)] = &[
+    ('\u{AB}', '\u{AB}'), ('\u{2018}', '\u{2018}'), ('\u{201B}', '\u{201C}'), ('\u{201F}', '\u{201F}'),
+    ('\u{2039}', '\u{2039}'), ('\u{2E02}', '\u{2E02}'), ('\u{2E04}', '\u{2E04}'), ('\u{2E09}', '\u{2E09}'),
+    ('\u{2E0C}', '\u{2E0C}'), ('\u{2E1C}', '\u{2E1C}'), ('\u{2E20}', '\u{2E20}'),
+];
+
+const GC_PO: &[(char, char)] = &[
+    ('\u{21}', '\u{23}'), ('\u{25}', '\u{27}'), ('\u{2A}', '\u{2A}'), ('\u{2C}', '\u{2C}'),
+    ('\u{2E}', '\u{2F}'), ('\u{3A}', '\u{3B}'), ('\u{3F}', '\u{40}'), ('\u{5C}', '\u{5C}'),
+    ('\u{A1}', '\u{A1}'), ('\u{A7}', '\u{A7}'), ('\u{B6}', '\u{B7}'), ('\u{BF}', '\u{BF}'),
+    ('\u{37E}', '\u{37E}'), ('\u{387}', '\u{387}'), ('\u{55A}', '\u{55F}'), ('\u{589}', '\u{589}'),
+    ('\u{5C0}', '\u{5C0}'), ('\u{5C3}', '\u{5C3}'), ('\u{5C6}', '\u{5C6}'), ('\u{5F3}', '\u{5F4}'),
+    ('\u{609}', '\u{60A}'), ('\u{60C}', '\u{60D}'), ('\u{61B}', '\u{61B}'), ('\u{61D}', '\u{61F}'),
+    ('\u{66A}', '\u{66D}'), ('\u{6D4}', '\u{6D4}'), ('\u{700}', '\u{70D}'), ('\u{7F7}', '\u{7F9}'),
+    ('\u{830}', '\u{83E}'), ('\u{85E}', '\u{85E}'), ('\u{964}', '\u{965}'), ('\u{970}', '\u{970}'),
+    ('\u{9FD}', '\u{9FD}'), ('\u{A76}', '\u{A76}'), ('\u{AF0}', '\u{AF0}'), ('\u{C77}', '\u{C77}'),
+    ('\u{C84}', '\u{C84}'), ('\u{DF4}', '\u{DF4}'), ('\u{E4F}', '\u{E4F}'), ('\u{E5A}', '\u{E5B}'),
+    ('\u{F04}', '\u{F12}'), ('\u{F14}', '\u{F14}'), ('\u{F85}', '\u{F85}'), ('\u{FD0}', '\u{FD4}'),
+    ('\u{FD9}', '\u{FDA}'), ('\u{104A}', '\u{104F}'), ('\u{10FB}', '\u{10FB}'), ('\u{1360}', '\u{1368}'),
+    ('\u{166E}', '\u{166E}'), ('\u{16EB}', '\u{16ED}'), ('\u{1735}', '\u{1736}'), ('\u{17D4}', '\u{17D6}'),
+    ('\u{17D8}', '\u{17DA}'), ('\u{1800}', '\u{1805}'), ('\u{1807}', '\u{180A}'), ('\u{1944}', '\u{1945}'),
+    ('\u{1A1E}', '\u{1A1F}'), ('\u{1AA0}', '\u{1AA6}'), ('\u{1AA8}', '\u{1AAD}'), ('\u{1B5A}', '\u{1B60}'),
+    ('\u{1B7D}', '\u{1B7E}'), ('\u{1BFC}', '\u{1BFF}'), ('\u{1C3B}', '\u{1C3F}'), ('\u{1C7E}', '\u{1C7F}'),
+    ('\u{1CC0}', '\u{1CC7}'), ('\u{1CD3}', '\u{1CD3}'), ('\u{2016}', '\u{2017}'), ('\u{2020}', '\u{2027}'),
+    ('\u{2030}', '\u{2038}'), ('\u{203B}', '\u{203E}'), ('\u{2041}', '\u{2043}'), ('\u{2047}', '\u{2051}'),
+    ('\u{2053}', '\u{2053}'), ('\u{2055}', '\u{205E}'), ('\u{2CF9}', '\u{2CFC}'), ('\u{2CFE}', '\u{2CFF}'),
+    ('\u{2D70}', '\u{2D70}'), ('\u{2E00}', '\u{2E01}'), ('\u{2E06}', '\u{2E08}'), ('\u{2E0B}', '\u{2E0B}'),
+    ('\u{2E0E}', '\u{2E16}'), ('\u{2E18}', '\u{2E19}'), ('\u{2E1B}', '\u{2E1B}'), ('\u{2E1E}', '\u{2E1F}'),
+    ('\u{2E2A}', '\u{2E2E}'), ('\u{2E30}', '\u{2E39}'), ('\u{2E3C}', '\u{2E3F}'), ('\u{2E41}', '\u{2E41}'),
+    ('\u{2E43}', '\u{2E4F}'), ('\u{2E52}', '\u{2E54}'), ('\u{3001}', '\u{3003}'), ('\u{303D}', '\u{303D}'),
+    ('\u{30FB}', '\u{30FB}'), ('\u{A4FE}', '\u{A4FF}'), ('\u{A60D}', '\u{A60F}'), ('\u{A673}', '\u{A673}'),
+    ('\u{A67E}', '\u{A67E}'), ('\u{A6F2}', '\u{A6F7}'), ('\u{A874}', '\u{A877}'), ('\u{A8CE}', '\u{A8CF}'),
+    ('\u{A8F8}', '\u{A8FA}'), ('\u{A8FC}', '\u{A8FC}'), ('\u{A92E}', '\u{A92F}'), ('\u{A95F}', '\u{A95F}'),
+    ('\u{A9C1}', '\u{A9CD}'), ('\u{A9DE}', '\u{A9DF}'), ('\u{AA5C}', '\u{AA5F}'), ('\u{AADE}', '\u{AADF}'),
+    ('\u{AAF0}', '\u{AAF1}'), ('\u{ABEB}', '\u{ABEB}'), ('\u{FE10}', '\u{FE16}'), ('\u{FE19}', '\u{FE19}'),
+    ('\u{FE30}', '\u{FE30}'), ('\u{FE45}', '\u{FE46}'), ('\u{FE49}', '\u{FE4C}'), ('\u{FE50}', '\u{FE52}'),
+    ('\u{FE54}', '\u{FE57}'), ('\u{FE5F}', '\u{FE61}'), ('\u{FE68}', '\u{FE68}'), ('\u{FE6A}', '\u{FE6B}'),
+    ('\u{FF01}', '\u{FF03}'), ('\u{FF05}', '\u{FF07}'), ('\u{FF0A}', '\u{FF0A}'), ('\u{FF0C}', '\u{FF0C}'),
+    ('\u{FF0E}', '\u{FF0F}'), ('\u{FF1A}', '\u{FF1B}'), ('\u{FF1F}', '\u{FF20}'), ('\u{FF3C}', '\u{FF3C}'),
+    ('\u{FF61}', '\u{FF61}'), ('\u{FF64}', '\u{FF65}'), ('\u{10100}', '\u{10102}'), ('\u{1039F}', '\u{1039F}'),
+    ('\u{103D0}', '\u{103D0}'), ('\u{1056F}', '\u{1056F}'), ('\u{10857}', '\u{10857}'), ('\u{1091F}', '\u{1091F}'),
+    ('\u{1093F}', '\u{1093F}'), ('\u{10A50}', '\u{10A58}'), ('\u{10A7F}', '\u{10A7F}'), ('\u{10AF0}', '\u{10AF6}'),
+    ('\u{10B39}', '\u{10B3F}'), ('\u{10B99}', '\u{10B9C}'), ('\u{10F55}', '\u{10F59}'), ('\u{10F86}', '\u{10F89}'),
+    ('\u{11047}', '\u{1104D}'), ('\u{110BB}', '\u{110BC}'), ('\u{110BE}', '\u{110C1}'), ('\u{11140}', '\u{11143}'),
+    ('\u{11174}', '\u{11175}'), ('\u{111C5}', '\u{111C8}'), ('\u{111CD}', '\u{111CD}'), ('\u{111DB}', '\u{111DB}'),
+    ('\u{111DD}', '\u{111DF}'), ('\u{11238}', '\u{1123D}'), ('\u{112A9}', '\u{112A9}'), ('\u{1144B}', '\u{1144F}'),
+    ('\u{1145A}', '\u{1145B}'), ('\u{1145D}', '\u{1145D}'), ('\u{114C6}', '\u{114C6}'), ('\u{115C1}', '\u{115D7}'),
+    ('\u{11641}', '\u{11643}'), ('\u{11660}', '\u{1166C}'), ('\u{116B9}', '\u{116B9}'), ('\u{1173C}', '\u{1173E}'),
+    ('\u{1183B}', '\u{1183B}'), ('\u{11944}', '\u{11946}'), ('\u{119E2}', '\u{119E2}'), ('\u{11A3F}', '\u{11A46}'),
+    ('\u{11A9A}', '\u{11A9C}'), ('\u{11A9E}', '\u{11AA2}'), ('\u{11C41}', '\u{11C45}'), ('\u{11C70}', '\u{11C71}'),
+    ('\u{11EF7}', '\u{11EF8}'), ('\u{11FFF}', '\u{11FFF}'), ('\u{12470}', '\u{12474}'), ('\u{12FF1}', '\u{12FF2}'),
+    ('\u{16A6E}', '\u{16A6F}'), ('\u{16AF5}', '\u{16AF5}'), ('\u{16B37}', '\u{16B3B}'), ('\u{16B44}', '\u{16B44}'),
+    ('\u{16E97}', '\u{16E9A}'), ('\u{16FE2}', '\u{16FE2}'), ('\u{1BC9F}', '\u{1BC9F}'), ('\u{1DA87}', '\u{1DA8B}'),
+    ('\u{1E95E}', '\u{1E95F}'),
+];
+
+const GC_PS: &[(char, char)] = &[
+    ('\u{28}', '\u{28}'), ('\u{5B}', '\u{5B}'), ('\u{7B}', '\u{7B}'), ('\u{F3A}', '\u{F3A}'),
+    ('\u{F3C}', '\u{F3C}'), ('\u{169B}', '\u{169B}'), ('\u{201A}', '\u{201A}'), ('\u{201E}', '\u{201E}'),
+    ('\u{2045}', '\u{2045}'), ('\u{207D}', '\u{207D}'), ('\u{208D}', '\u{208D}'), ('\u{2308}', '\u{2308}'),
+    ('\u{230A}', '\u{230A}'), ('\u{2329}', '\u{2329}'), ('\u{2768}', '\u{2768}'), ('\u{276A}', '\u{276A}'),
+    ('\u{276C}', '\u{276C}'), ('\u{276E}', '\u{276E}'), ('\u{2770}', '\u{2770}'), ('\u{2772}', '\u{2772}'),
+    ('\u{2774}', '\u{2774}'), ('\u{27C5}', '\u{27C5}'), ('\u{27E6}', '\u{27E6}'), ('\u{27E8}', '\u{27E8}'),
+    ('\u{27EA}', '\u{27EA}'), ('\u{27EC}', '\u{27EC}'), ('\u{27EE}', '\u{27EE}'), ('\u{2983}', '\u{2983}'),
+    ('\u{2985}', '\u{2985}'), ('\u{2987}', '\u{2987}'), ('\u{2989}', '\u{2989}'), ('\u{298B}', '\u{298B}'),
+    ('\u{298D}', '\u{298D}'), ('\u{298F}', '\u{298F}'), ('\u{2991}', '\u{2991}'), ('\u{2993}', '\u{2993}'),
+    ('\u{2995}', '\u{2995}'), ('\u{2997}', '\u{2997}'), ('\u{29D8}', '\u{29D8}'), ('\u{29DA}', '\u{29DA}'),
+    ('\u{29FC}', '\u{29FC}'), ('\u{2E22}', '\u{2E22}'), ('\u{2E24}', '\u{2E24}'), ('\u{2E26}', '\u{2E26}'),
+    ('\u{2E28}', '\u{2E28}'), ('\u{2E42}', '\u{2E42}'), ('\u{2E55}', '\u{2E55}'), ('\u{2E57}', '\u{2E57}'),
+    ('\u{2E59}', '\u{2E59}'), ('\u{2E5B}', '\u{2E5B}'), ('\u{3008}', '\u{3008}'), ('\u{300A}', '\u{300A}'),
+    ('\u{300C}', '\u{300C}'), ('\u{300E}', '\u{300E}'), ('\u{3010}', '\u{3010}'), ('\u{3014}', '\u{3014}'),
+    ('\u{3016}', '\u{3016}'), ('\u{3018}', '\u{3018}'), ('\u{301A}', '\u{301A}'), ('\u{301D}', '\u{301D}'),
+    ('\u{FD3F}', '\u{FD3F}'), ('\u{FE17}', '\u{FE17}'), ('\u{FE35}', '\u{FE35}'), ('\u{FE37}', '\u{FE37}'),
+    ('\u{FE39}', '\u{FE39}'), ('\u{FE3B}', '\u{FE3B}'), ('\u{FE3D}', '\u{FE3D}'), ('\u{FE3F}', '\u{FE3F}'),
+    ('\u{FE41}', '\u{FE41}'), ('\u{FE43}', '\u{FE43}'), ('\u{FE47}', '\u{FE47}'), ('\u{FE59}', '\u{FE59}'),
+    ('\u{FE5B}', '\u{FE5B}'), ('\u{FE5D}', '\u{FE5D}'), ('\u{FF08}', '\u{FF08}'), ('\u{FF3B}', '\u{FF3B}'),
+    ('\u{FF5B}', '\u{FF5B}'), ('\u{FF5F}', '\u{FF5F}'), ('\u{FF62}', '\u{FF62}'),
+];
+
+const GC_SC: &[(char, char)] = &[
+    ('\u{24}', '\u{24}'), ('\u{A2}', '\u{A5}'), ('\u{58F}', '\u{58F}'), ('\u{60B}', '\u{60B}'),
+    ('\u{7FE}', '\u{7FF}'), ('\u{9F2}', '\u{9F3}'), ('\u{9FB}', '\u{9FB}'), ('\u{AF1}', '\u{AF1}'),
+    ('\u{BF9}', '\u{BF9}'), ('\u{E3F}', '\u{E3F}'), ('\u{17DB}', '\u{17DB}'), ('\u{20A0}', '\u{20C0}'),
+    ('\u{A838}', '\u{A838}'), ('\u{FDFC}', '\u{FDFC}'), ('\u{FE69}', '\u{FE69}'), ('\u{FF04}', '\u{FF04}'),
+    ('\u{FFE0}', '\u{FFE1}'), ('\u{FFE5}', '\u{FFE6}'), ('\u{11FDD}', '\u{11FE0}'), ('\u{1E2FF}', '\u{1E2FF}'),
+    ('\u{1ECB0}', '\u{1ECB0}'),
+];
+
+const GC_SK: &[(char, char)] = &[
+    ('\u{5E}', '\u{5E}'), ('\u{60}', '\u{60}'), ('\u{A8}', '\u{A8}'), ('\u{AF}', '\u{AF}'),
+    ('\u{B4}', '\u{B4}'), ('\u{B8}', '\u{B8}'), ('\u{2C2}', '\u{2C5}'), ('\u{2D2}', '\u{2DF}'),
+    ('\u{2E5}', '\u{2EB}'), ('\u{2ED}', '\u{2ED}'), ('\u{2EF}', '\u{2FF}'), ('\u{375}', '\u{375}'),
+    ('\u{384}', '\u{385}'), ('\u{888}', '\u{888}'), ('\u{1FBD}', '\u{1FBD}'), ('\u{1FBF}', '\u{1FC1}'),
+    ('\u{1FCD}', '\u{1FCF}'), ('\u{1FDD}', '\u{1FDF}'), ('\u{1FED}', '\u{1FEF}'), ('\u{1FFD}', '\u{1FFE}'),
+    ('\u{309B}', '\u{309C}'), ('\u{A700}', '\u{A716}'), ('\u{A720}', '\u{A721}'), ('\u{A789}', '\u{A78A}'),
+    ('\u{AB5B}', '\u{AB5B}'), ('\u{AB6A}', '\u{AB6B}'), ('\u{FBB2}', '\u{FBC2}'), ('\u{FF3E}', '\u{FF3E}'),
+    ('\u{FF40}', '\u{FF40}'), ('\u{FFE3}', '\u{FFE3}'), ('\u{1F3FB}', '\u{1F3FF}'),
+];
+
+const GC_SM: &[(char, char)] = &[
+    ('\u{2B}', '\u{2B}'), ('\u{3C}', '\u{3E}'), ('\u{7C}', '\u{7C}'), ('\u{7E}', '\u{7E}'),
+    ('\u{AC}', '\u{AC}'), ('\u{B1}', '\u{B1}'), ('\u{D7}', '\u{D7}'), ('\u{F7}', '\u{F7}'),
+    ('\u{3F6}', '\u{3F6}'), ('\u{606}', '\u{608}'), ('\u{2044}', '\u{2044}'), ('\u{2052}', '\u{2052}'),
+    ('\u{207A}', '\u{207C}'), ('\u{208A}', '\u{208C}'), ('\u{2118}', '\u{2118}'), ('\u{2140}', '\u{2144}'),
+    ('\u{214B}', '\u{214B}'), ('\u{2190}', '\u{2194}'), ('\u{219A}', '\u{219B}'), ('\u{21A0}', '\u{21A0}'),
+    ('\u{21A3}', '\u{21A3}'), ('\u{21A6}', '\u{21A6}'), ('\u{21AE}', '\u{21AE}'), ('\u{21CE}', '\u{21CF}'),
+    ('\u{21D2}', '\u{21D2}'), ('\u{21D4}', '\u{21D4}'), ('\u{21F4}', '\u{22FF}'), ('\u{2320}', '\u{2321}'),
+    ('\u{237C}', '\u{237C}'), ('\u{239B}', '\u{23B3}'), ('\u{23DC}', '\u{23E1}'), ('\u{25B7}', '\u{25B7}'),
+    ('\u{25C1}', '\u{25C1}'), ('\u{25F8}', '\u{25FF}'), ('\u{266F}', '\u{266F}'), ('\u{27C0}', '\u{27C4}'),
+    ('\u{27C7}', '\u{27E5}'), ('\u{27F0}', '\u{27FF}'), ('\u{2900}', '\u{2982}'), ('\u{2999}', '\u{29D7}'),
+    ('\u{29DC}', '\u{29FB}'), ('\u{29FE}', '\u{2AFF}'), ('\u{2B30}', '\u{2B44}'), ('\u{2B47}', '\u{2B4C}'),
+    ('\u{FB29}', '\u{FB29}'), ('\u{FE62}', '\u{FE62}'), ('\u{FE64}', '\u{FE66}'), ('\u{FF0B}', '\u{FF0B}'),
+    ('\u{FF1C}', '\u{FF1E}'), ('\u{FF5C}', '\u{FF5C}'), ('\u{FF5E}', '\u{FF5E}'), ('\u{FFE2}', '\u{FFE2}'),
+    ('\u{FFE9}', '\u{FFEC}'), ('\u{1D6C1}', '\u{1D6C1}'), ('\u{1D6DB}', '\u{1D6DB}'), ('\u{1D6FB}', '\u{1D6FB}'),
+    ('\u{1D715}', '\u{1D715}'), ('\u{1D735}', '\u{1D735}'), ('\u{1D74F}', '\u{1D74F}'), ('\u{1D76F}', '\u{1D76F}'),
+    ('\u{1D789}', '\u{1D789}'), ('\u{1D7A9}', '\u{1D7A9}'), ('\u{1D7C3}', '\u{1D7C3}'), ('\u{1EEF0}', '\u{1EEF1}'),
+];
+
+const GC_SO: &[(char, char)] = &[
+    ('\u{A6}', '\u{A6}'), ('\u{A9}', '\u{A9}'), ('\u{AE}', '\u{AE}'), ('\u{B0}', '\u{B0}'),
+    ('\u{482}', '\u{482}'), ('\u{58D}', '\u{58E}'), ('\u{60E}', '\u{60F}'), ('\u{6DE}', '\u{6DE}'),
+    ('\u{6E9}', '\u{6E9}'), ('\u{6FD}', '\u{6FE}'), ('\u{7F6}', '\u{7F6}'), ('\u{9FA}', '\u{9FA}'),
+    ('\u{B70}', '\u{B70}'), ('\u{BF3}', '\u{BF8}'), ('\u{BFA}', '\u{BFA}'), ('\u{C7F}', '\u{C7F}'),
+    ('\u{D4F}', '\u{D4F}'), ('\u{D79}', '\u{D79}'), ('\u{F01}', '\u{F03}'), ('\u{F13}', '\u{F13}'),
+    ('\u{F15}', '\u{F17}'), ('\u{F1A}', '\u{F1F}'), ('\u{F34}', '\u{F34}'), ('\u{F36}', '\u{F36}'),
+    ('\u{F38}', '\u{F38}'), ('\u{FBE}', '\u{FC5}'), ('\u{FC7}', '\u{FCC}'), ('\u{FCE}', '\u{FCF}'),
+    ('\u{FD5}', '\u{FD8}'), ('\u{109E}', '\u{109F}'), ('\u{1390}', '\u{1399}'), ('\u{166D}', '\u{166D}'),
+    ('\u{1940}', '\u{1940}'), ('\u{19DE}', '\u{19FF}'), ('\u{1B61}', '\u{1B6A}'), ('\u{1B74}', '\u{1B7C}'),
+    ('\u{2100}', '\u{2101}'), ('\u{2103}', '\u{2106}'), ('\u{2108}', '\u{2109}'), ('\u{2114}', '\u{2114}'),
+    ('\u{2116}', '\u{2117}'), ('\u{211E}', '\u{2123}'), ('\u{2125}', '\u{2125}'), ('\u{2127}', '\u{2127}'),
+    ('\u{2129}', '\u{2129}'), ('\u{212E}', '\u{212E}'), ('\u{213A}', '\u{213B}'), ('\u{214A}', '\u{214A}'),
+    ('\u{214C}', '\u{214D}'), ('\u{214F}', '\u{214F}'), ('\u{218A}', '\u{218B}'), ('\u{2195}', '\u{2199}'),
+    ('\u{219C}', '\u{219F}'), ('\u{21A1}', '\u{21A2}'), ('\u{21A4}', '\u{21A5}'), ('\u{21A7}', '\u{21AD}'),
+    ('\u{21AF}', '\u{21CD}'), ('\u{21D0}', '\u{21D1}'), ('\u{21D3}', '\u{21D3}'), ('\u{21D5}', '\u{21F3}'),
+    ('\u{2300}', '\u{2307}'), ('\u{230C}', '\u{231F}'), ('\u{2322}', '\u{2328}'), ('\u{232B}', '\u{237B}'),
+    ('\u{237D}', '\u{239A}'), ('\u{23B4}', '\u{23DB}'), ('\u{23E2}', '\u{2426}'), ('\u{2440}', '\u{244A}'),
+    ('\u{249C}', '\u{24E9}'), ('\u{2500}', '\u{25B6}'), ('\u{25B8}', '\u{25C0}'), ('\u{25C2}', '\u{25F7}'),
+    ('\u{2600}', '\u{266E}'), ('\u{2670}', '\u{2767}'), ('\u{2794}', '\u{27BF}'), ('\u{2800}', '\u{28FF}'),
+    ('\u{2B00}', '\u{2B2F}'), ('\u{2B45}', '\u{2B46}'), ('\u{2B4D}', '\u{2B73}'), ('\u{2B76}', '\u{2B95}'),
+    ('\u{2B97}', '\u{2BFF}'), ('\u{2CE5}', '\u{2CEA}'), ('\u{2E50}', '\u{2E51}'), ('\u{2E80}', '\u{2E99}'),
+    ('\u{2E9B}', '\u{2EF3}'), ('\u{2F00}', '\u{2FD5}'), ('\u{2FF0}', '\u{2FFB}'), ('\u{3004}', '\u{3004}'),
+    ('\u{3012}', '\u{3013}'), ('\u{3020}', '\u{3020}'), ('\u{3036}', '\u{3037}'), ('\u{303E}', '\u{303F}'),
+    ('\u{3190}', '\u{3191}'), ('\u{3196}', '\u{319F}'), ('\u{31C0}', '\u{31E3}'), ('\u{3200}', '\u{321E}'),
+    ('\u{322A}', '\u{3247}'), ('\u{3250}', '\u{3250}'), ('\u{3260}', '\u{327F}'), ('\u{328A}', '\u{32B0}'),
+    ('\u{32C0}', '\u{33FF}'), ('\u{4DC0}', '\u{4DFF}'), ('\u{A490}', '\u{A4C6}'), ('\u{A828}', '\u{A82B}'),
+    ('\u{A836}', '\u{A837}'), ('\u{A839}', '\u{A839}'), ('\u{AA77}', '\u{AA79}'), ('\u{FD40}', '\u{FD4F}'),
+    ('\u{FDCF}', '\u{FDCF}'), ('\u{FDFD}', '\u{FDFF}'), ('\u{FFE4}', '\u{FFE4}'), ('\u{FFE8}', '\u{FFE8}'),
+    ('\u{FFED}', '\u{FFEE}'), ('\u{FFFC}', '\u{FFFD}'), ('\u{10137}', '\u{1013F}'), ('\u{10179}', '\u{10189}'),
+    ('\u{1018C}', '\u{1018E}'), ('\u{10190}', '\u{1019C}'), ('\u{101A0}', '\u{101A0}'), ('\u{101D0}', '\u{101FC}'),
+    ('\u{10877}', '\u{10878}'), ('\u{10AC8}', '\u{10AC8}'), ('\u{1173F}', '\u{1173F}'), ('\u{11FD5}', '\u{11FDC}'),
+    ('\u{11FE1}', '\u{11FF1}'), ('\u{16B3C}', '\u{16B3F}'), ('\u{16B45}', '\u{16B45}'), ('\u{1BC9C}', '\u{1BC9C}'),
+    ('\u{1CF50}', '\u{1CFC3}'), ('\u{1D000}', '\u{1D0F5}'), ('\u{1D100}', '\u{1D126}'), ('\u{1D129}', '\u{1D164}'),
+    ('\u{1D16A}', '\u{1D16C}'), ('\u{1D183}', '\u{1D184}'), ('\u{1D18C}', '\u{1D1A9}'), ('\u{1D1AE}', '\u{1D1EA}'),
+    ('\u{1D200}', '\u{1D241}'), ('\u{1D245}', '\u{1D245}'), ('\u{1D300}', '\u{1D356}'), ('\u{1D800}', '\u{1D9FF}'),
+    ('\u{1DA37}', '\u{1DA3A}'), ('\u{1DA6D}', '\u{1DA74}'), ('\u{1DA76}', '\u{1DA83}'), ('\u{1DA85}', '\u{1DA86}'),
+    ('\u{1E14F}', '\u{1E14F}'), ('\u{1ECAC}', '\u{1ECAC}'), ('\u{1ED2E}', '\u{1ED2E}'), ('\u{1F000}', '\u{1F02B}'),
+    ('\u{1F030}', '\u{1F093}'), ('\u{1F0A0}', '\u{1F0AE}'), ('\u{1F0B1}', '\u{1F0BF}'), ('\u{1F0C1}', '\u{1F0CF}'),
+    ('\u{1F0D1}', '\u{1F0F5}'), ('\u{1F10D}', '\u{1F1AD}'), ('\u{1F1E6}', '\u{1F202}'), ('\u{1F210}', '\u{1F23B}'),
+    ('\u{1F240}', '\u{1F248}'), ('\u{1F250}', '\u{1F251}'), ('\u{1F260}', '\u{1F265}'), ('\u{1F300}', '\u{1F3FA}'),
+    ('\u{1F400}', '\u{1F6D7}'), ('\u{1F6DD}', '\u{1F6EC}'), ('\u{1F6F0}', '\u{1F6FC}'), ('\u{1F700}', '\u{1F773}'),
+    ('\u{1F780}', '\u{1F7D8}'), ('\u{1F7E0}', '\u{1F7EB}'), ('\u{1F7F0}', '\u{1F7F0}'), ('\u{1F800}', '\u{1F80B}'),
+    ('\u{1F810}', '\u{1F847}'), ('\u{1F850}', '\u{1F859}'), ('\u{1F860}', '\u{1F887}'), ('\u{1F890}', '\u{1F8AD}'),
+    ('\u{1F8B0}', '\u{1F8B1}'), ('\u{1F900}', '\u{1FA53}'), ('\u{1FA60}', '\u{1FA6D}'), ('\u{1FA70}', '\u{1FA74}'),
+    ('\u{1FA78}', '\u{1FA7C}'), ('\u{1FA80}', '\u{1FA86}'), ('\u{1FA90}', '\u{1FAAC}'), ('\u{1FAB0}', '\u{1FABA}'),
+    ('\u{1FAC0}', '\u{1FAC5}'), ('\u{1FAD0}', '\u{1FAD9}'), ('\u{1FAE0}', '\u{1FAE7}'), ('\u{1FAF0}', '\u{1FAF6}'),
+    ('\u{1FB00}', '\u{1FB92}'), ('\u{1FB94}', '\u{1FBCA}'),
+];
+
+const GC_ZL: &[(char, char)] = &[
+    ('\u{2028}', '\u{2028}'),
+];
+
+const GC_ZP: &[(char, char)] = &[
+    ('\u{2029}', '\u{2029}'),
+];
+
+const GC_ZS: &[(char, char)] = &[
+    ('\u{20}', '\u{20}'), ('\u{A0}', '\u{A0}'), ('\u{1680}', '\u{1680}'), ('\u{2000}', '\u{200A}'),
+    ('\u{202F}', '\u{202F}'), ('\u{205F}', '\u{205F}'), ('\u{3000}', '\u{3000}'),
+];
+
+const SC_ADLM: &[(char, char)] = &[
+    ('\u{1E900}', '\u{1E94B}'), ('\u{1E950}', '\u{1E959}'), ('\u{1E95E}', '\u{1E95F}'),
+];
+
+const SC_AGHB: &[(char, char)] = &[
+    ('\u{10530}', '\u{10563}'), ('\u{1056F}', '\u{1056F}'),
+];
+
+const SC_AHOM: &[(char, char)] = &[
+    ('\u{11700}', '\u{1171A}'), ('\u{1171D}', '\u{1172B}'), ('\u{11730}', '\u{11746}'),
+];
+
+const SC_ARAB: &[(char, char)] = &[
+    ('\u{600}', '\u{604}'), ('\u{606}', '\u{60B}'), ('\u{60D}', '\u{61A}'), ('\u{61C}', '\u{61E}'),
+    ('\u{620}', '\u{63F}'), ('\u{641}', '\u{64A}'), ('\u{656}', '\u{66F}'), ('\u{671}', '\u{6DC}'),
+    ('\u{6DE}', '\u{6FF}'), ('\u{750}', '\u{77F}'), ('\u{870}', '\u{88E}'), ('\u{890}', '\u{891}'),
+    ('\u{898}', '\u{8E1}'), ('\u{8E3}', '\u{8FF}'), ('\u{FB50}', '\u{FBC2}'), ('\u{FBD3}', '\u{FD3D}'),
+    ('\u{FD40}', '\u{FD8F}'), ('\u{FD92}', '\u{FDC7}'), ('\u{FDCF}', '\u{FDCF}'), ('\u{FDF0}', '\u{FDFF}'),
+    ('\u{FE70}', '\u{FE74}'), ('\u{FE76}', '\u{FEFC}'), ('\u{10E60}', '\u{10E7E}'), ('\u{1EE00}', '\u{1EE03}'),
+    ('\u{1EE05}', '\u{1EE1F}'), ('\u{1EE21}', '\u{1EE22}'), ('\u{1EE24}', '\u{1EE24}'), ('\u{1EE27}', '\u{1EE27}'),
+    ('\u{1EE29}', '\u{1EE32}'), ('\u{1EE34}', '\u{1EE37}'), ('\u{1EE39}', '\u{1EE39}'), ('\u{1EE3B}', '\u{1EE3B}'),
+    ('\u{1EE42}', '\u{1EE42}'), ('\u{1EE47}', '\u{1EE47}'), ('\u{1EE49}', '\u{1EE49}'), ('\u{1EE4B}', '\u{1EE4B}'),
+    ('\u{1EE4D}', '\u{1EE4F}'), ('\u{1EE51}', '\u{1EE52}'), ('\u{1EE54}', '\u{1EE54}'), ('\u{1EE57}', '\u{1EE57}'),
+    ('\u{1EE59}', '\u{1EE59}'), ('\u{1EE5B}', '\u{1EE5B}'), ('\u{1EE5D}', '\u{1EE5D}'), ('\u{1EE5F}', '\u{1EE5F}'),
+    ('\u{1EE61}', '\u{1EE62}'), ('\u{1EE64}', '\u{1EE64}'), ('\u{1EE67}', '\u{1EE6A}'), ('\u{1EE6C}', '\u{1EE72}'),
+    ('\u{1EE74}', '\u{1EE77}'), ('\u{1EE79}', '\u{1EE7C}'), ('\u{1EE7E}', '\u{1EE7E}'), ('\u{1EE80}', '\u{1EE89}'),
+    ('\u{1EE8B}', '\u{1EE9B}'), ('\u{1EEA1}', '\u{1EEA3}'), ('\u{1EEA5}', '\u{1EEA9}'), ('\u{1EEAB}', '\u{1EEBB}'),
+    ('\u{1EEF0}', '\u{1EEF1}'),
+];
+
+const SC_ARMI: &[(char, char)] = &[
+    ('\u{10840}', '\u{10855}'), ('\u{10857}', '\u{1085F}'),
+];
+
+const SC_ARMN: &[(char, char)] = &[
+    ('\u{531}', '\u{556}'), ('\u{559}', '\u{58A}'), ('\u{58D}', '\u{58F}'), ('\u{FB13}', '\u{FB17}'),
+];
+
+const SC_AVST: &[(char, char)] = &[
+    ('\u{10B00}', '\u{10B35}'), ('\u{10B39}', '\u{10B3F}'),
+];
+
+const SC_BALI: &[(char, char)] = &[
+    ('\u{1B00}', '\u{1B4C}'), ('\u{1B50}', '\u{1B7E}'),
+];
+
+const SC_BAMU: &[(char, char)] = &[
+    ('\u{A6A0}', '\u{A6F7}'), ('\u{16800}', '\u{16A38}'),
+];
+
+const SC_BASS: &[(char, char)] = &[
+    ('\u{16AD0}', '\u{16AED}'), ('\u{16AF0}', '\u{16AF5}'),
+];
+
+const SC_BATK: &[(char, char)] = &[
+    ('\u{1BC0}', '\u{1BF3}'), ('\u{1BFC}', '\u{1BFF}'),
+];
+
+const SC_BENG: &[(char, char)] = &[
+    ('\u{980}', '\u{983}'), ('\u{985}', '\u{98C}'), ('\u{98F}', '\u{990}'), ('\u{993}', '\u{9A8}'),
+    ('\u{9AA}', '\u{9B0}'), ('\u{9B2}', '\u{9B2}'), ('\u{9B6}', '\u{9B9}'), ('\u{9BC}', '\u{9C4}'),
+    ('\u{9C7}', '\u{9C8}'), ('\u{9CB}', '\u{9CE}'), ('\u{9D7}', '\u{9D7}'), ('\u{9DC}', '\u{9DD}'),
+    ('\u{9DF}', '\u{9E3}'), ('\u{9E6}', '\u{9FE}'),
+];
+
+const SC_BHKS: &[(char, char)] = &[
+    ('\u{11C00}', '\u{11C08}'), ('\u{11C0A}', '\u{11C36}'), ('\u{11C38}', '\u{11C45}'), ('\u{11C50}', '\u{11C6C}'),
+];
+
+const SC_BOPO: &[(char, char)] = &[
+    ('\u{2EA}', '\u{2EB}'), ('\u{3105}', '\u{312F}'), ('\u{31A0}', '\u{31BF}'),
+];
+
+const SC_BRAH: &[(char, char)] = &[
+    ('\u{11000}', '\u{1104D}'), ('\u{11052}', '\u{11075}'), ('\u{1107F}', '\u{1107F}'),
+];
+
+const SC_BRAI: &[(char, char)] = &[
+    ('\u{2800}', '\u{28FF}'),
+];
+
+const SC_BUGI: &[(char, char)] = &[
+    ('\u{1A00}', '\u{1A1B}'), ('\u{1A1E}', '\u{1A1F}'),
+];
+
+const SC_BUHD: &[(char, char)] = &[
+    ('\u{1740}', '\u{1753}'),
+];
+
+const SC_CAKM: &[(char, char)] = &[
+    ('\u{11100}', '\u{11134}'), ('\u{11136}', '\u{11147}'),
+];
+
+const SC_CANS: &[(char, char)] = &[
+    ('\u{1400}', '\u{167F}'), ('\u{18B0}', '\u{18F5}'), ('\u{11AB0}', '\u{11ABF}'),
+];
+
+const SC_CARI: &[(char, char)] = &[
+    ('\u{102A0}', '\u{102D0}'),
+];
+
+const SC_CHAM: &[(char, char)] = &[
+    ('\u{AA00}', '\u{AA36}'), ('\u{AA40}', '\u{AA4D}'), ('\u{AA50}', '\u{AA59}'), ('\u{AA5C}', '\u{AA5F}'),
+];
+
+const SC_CHER: &[(char, char)] = &[
+    ('\u{13A0}', '\u{13F5}'), ('\u{13F8}', '\u{13FD}'), ('\u{AB70}', '\u{ABBF}'),
+];
+
+const SC_CHRS: &[(char, char)] = &[
+    ('\u{10FB0}', '\u{10FCB}'),
+];
+
+const SC_COPT: &[(char, char)] = &[
+    ('\u{3E2}', '\u{3EF}'), ('\u{2C80}', '\u{2CF3}'), ('\u{2CF9}', '\u{2CFF}'),
+];
+
+const SC_CPMN: &[(char, char)] = &[
+    ('\u{12F90}', '\u{12FF2}'),
+];
+
+const SC_CPRT: &[(char, char)] = &[
+    ('\u{10800}', '\u{10805}'), ('\u{10808}', '\u{10808}'), ('\u{1080A}', '\u{10835}'), ('\u{10837}', '\u{10838}'),
+    ('\u{1083C}', '\u{1083C}'), ('\u{1083F}', '\u{1083F}'),
+];
+
+const SC_CYRL: &[(char, char)] = &[
+    ('\u{400}', '\u{484}'), ('\u{487}', '\u{52F}'), ('\u{1C80}', '\u{1C88}'), ('\u{1D2B}', '\u{1D2B}'),
+    ('\u{1D78}', '\u{1D78}'), ('\u{2DE0}', '\u{2DFF}'), ('\u{A640}', '\u{A69F}'), ('\u{FE2E}', '\u{FE2F}'),
+];
+
+const SC_DEVA: &[(char, char)] = &[
+    ('\u{900}', '\u{950}'), ('\u{955}', '\u{963}'), ('\u{966}', '\u{97F}'), ('\u{A8E0}', '\u{A8FF}'),
+];
+
+const SC_DIAK: &[(char, char)] = &[
+    ('\u{11900}', '\u{11906}'), ('\u{11909}', '\u{11909}'), ('\u{1190C}', '\u{11913}'), ('\u{11915}', '\u{11916}'),
+    ('\u{11918}', '\u{11935}'), ('\u{11937}', '\u{11938}'), ('\u{1193B}', '\u{11946}'), ('\u{11950}', '\u{11959}'),
+];
+
+const SC_DOGR: &[(char, char)] = &[
+    ('\u{11800}', '\u{1183B}'),
+];
+
+const SC_DSRT: &[(char, char)] = &[
+    ('\u{10400}', '\u{1044F}'),
+];
+
+const SC_DUPL: &[(char, char)] = &[
+    ('\u{1BC00}', '\u{1BC6A}'), ('\u{1BC70}', '\u{1BC7C}'), ('\u{1BC80}', '\u{1BC88}'), ('\u{1BC90}', '\u{1BC99}'),
+    ('\u{1BC9C}', '\u{1BC9F}'),
+];
+
+const SC_EGYP: &[(char, char)] = &[
+    ('\u{13000}', '\u{1342E}'), ('\u{13430}', '\u{13438}'),
+];
+
+const SC_ELBA: &[(char, char)] = &[
+    ('\u{10500}', '\u{10527}'),
+];
+
+const SC_ELYM: &[(char, char)] = &[
+    ('\u{10FE0}', '\u{10FF6}'),
+];
+
+const SC_ETHI: &[(char, char)] = &[
+    ('\u{1200}', '\u{1248}'), ('\u{124A}', '\u{124D}'), ('\u{1250}', '\u{1256}'), ('\u{1258}', '\u{1258}'),
+    ('\u{125A}', '\u{125D}'), ('\u{1260}', '\u{1288}'), ('\u{128A}', '\u{128D}'), ('\u{1290}', '\u{12B0}'),
+    ('\u{12B2}', '\u{12B5}'), ('\u{12B8}', '\u{12BE}'), ('\u{12C0}', '\u{12C0}'), ('\u{12C2}', '\u{12C5}'),
+    ('\u{12C8}', '\u{12D6}'), ('\u{12D8}', '\u{1310}'), ('\u{1312}', '\u{1315}'), ('\u{1318}', '\u{135A}'),
+    ('\u{135D}', '\u{137C}'), ('\u{1380}', '\u{1399}'), ('\u{2D80}', '\u{2D96}'), ('\u{2DA0}', '\u{2DA6}'),
+    ('\u{2DA8}', '\u{2DAE}'), ('\u{2DB0}', '\u{2DB6}'), ('\u{2DB8}', '\u{2DBE}'), ('\u{2DC0}', '\u{2DC6}'),
+    ('\u{2DC8}', '\u{2DCE}'), ('\u{2DD0}', '\u{2DD6}'), ('\u{2DD8}', '\u{2DDE}'), ('\u{AB01}', '\u{AB06}'),
+    ('\u{AB09}', '\u{AB0E}'), ('\u{AB11}', '\u{AB16}'), ('\u{AB20}', '\u{AB26}'), ('\u{AB28}', '\u{AB2E}'),
+    ('\u{1E7E0}', '\u{1E7E6}'), ('\u{1E7E8}', '\u{1E7EB}'), ('\u{1E7ED}', '\u{1E7EE}'), ('\u{1E7F0}', '\u{1E7FE}'),
+];
+
+const SC_GEOR: &[(char, char)] = &[
+    ('\u{10A0}', '\u{10C5}'), ('\u{10C7}', '\u{10C7}'), ('\u{10CD}', '\u{10CD}'), ('\u{10D0}', '\u{10FA}'),
+    ('\u{10FC}', '\u{10FF}'), ('\u{1C90}', '\u{1CBA}'), ('\u{1CBD}', '\u{1CBF}'), ('\u{2D00}', '\u{2D25}'),
+    ('\u{2D27}', '\u{2D27}'), ('\u{2D2D}', '\u{2D2D}'),
+];
+
+const SC_GLAG: &[(char, char)] = &[
+    ('\u{2C00}', '\u{2C5F}'), ('\u{1E000}', '\u{1E006}'), ('\u{1E008}', '\u{1E018}'), ('\u{1E01B}', '\u{1E021}'),
+    ('\u{1E023}', '\u{1E024}'), ('\u{1E026}', '\u{1E02A}'),
+];
+
+const SC_GONG: &[(char, char)] = &[
+    ('\u{11D60}', '\u{11D65}'), ('\u{11D67}', '\u{11D68}'), ('\u{11D6A}', '\u{11D8E}'), ('\u{11D90}', '\u{11D91}'),
+    ('\u{11D93}', '\u{11D98}'), ('\u{11DA0}', '\u{11DA9}'),
+];
+
+const SC_GONM: &[(char, char)] = &[
+    ('\u{11D00}', '\u{11D06}'), ('\u{11D08}', '\u{11D09}'), ('\u{11D0B}', '\u{11D36}'), ('\u{11D3A}', '\u{11D3A}'),
+    ('\u{11D3C}', '\u{11D3D}'), ('\u{11D3F}', '\u{11D47}'), ('\u{11D50}', '\u{11D59}'),
+];
+
+const SC_GOTH: &[(char, char)] = &[
+    ('\u{10330}', '\u{1034A}'),
+];
+
+const SC_GRAN: &[(char, char)] = &[
+    ('\u{11300}', '\u{11303}'), ('\u{11305}', '\u{1130C}'), ('\u{1130F}', '\u{11310}'), ('\u{11313}', '\u{11328}'),
+    ('\u{1132A}', '\u{11330}'), ('\u{11332}', '\u{11333}'), ('\u{11335}', '\u{11339}'), ('\u{1133C}', '\u{11344}'),
+    ('\u{11347}', '\u{11348}'), ('\u{1134B}', '\u{1134D}'), ('\u{11350}', '\u{11350}'), ('\u{11357}', '\u{11357}'),
+    ('\u{1135D}', '\u{11363}'), ('\u{11366}', '\u{1136C}'), ('\u{11370}', '\u{11374}'),
+];
+
+const SC_GREK: &[(char, char)] = &[
+    ('\u{370}', '\u{373}'), ('\u{375}', '\u{377}'), ('\u{37A}', '\u{37D}'), ('\u{37F}', '\u{37F}'),
+    ('\u{384}', '\u{384}'), ('\u{386}', '\u{386}'), ('\u{388}', '\u{38A}'), ('\u{38C}', '\u{38C}'),
+    ('\u{38E}', '\u{3A1}'), ('\u{3A3}', '\u{3E1}'), ('\u{3F0}', '\u{3FF}'), ('\u{1D26}', '\u{1D2A}'),
+    ('\u{1D5D}', '\u{1D61}'), ('\u{1D66}', '\u{1D6A}'), ('\u{1DBF}', '\u{1DBF}'), ('\u{1F00}', '\u{1F15}'),
+    ('\u{1F18}', '\u{1F1D}'), ('\u{1F20}', '\u{1F45}'), ('\u{1F48}', '\u{1F4D}'), ('\u{1F50}', '\u{1F57}'),
+    ('\u{1F59}', '\u{1F59}'), ('\u{1F5B}', '\u{1F5B}'), ('\u{1F5D}', '\u{1F5D}'), ('\u{1F5F}', '\u{1F7D}'),
+    ('\u{1F80}', '\u{1FB4}'), ('\u{1FB6}', '\u{1FC4}'), ('\u{1FC6}', '\u{1FD3}'), ('\u{1FD6}', '\u{1FDB}'),
+    ('\u{1FDD}', '\u{1FEF}'), ('\u{1FF2}', '\u{1FF4}'), ('\u{1FF6}', '\u{1FFE}'), ('\u{2126}', '\u{2126}'),
+    ('\u{AB65}', '\u{AB65}'), ('\u{10140}', '\u{1018E}'), ('\u{101A0}', '\u{101A0}'), ('\u{1D200}', '\u{1D245}'),
+];
+
+const SC_GUJR: &[(char, char)] = &[
+    ('\u{A81}', '\u{A83}'), ('\u{A85}', '\u{A8D}'), ('\u{A8F}', '\u{A91}'), ('\u{A93}', '\u{AA8}'),
+    ('\u{AAA}', '\u{AB0}'), ('\u{AB2}', '\u{AB3}'), ('\u{AB5}', '\u{AB9}'), ('\u{ABC}', '\u{AC5}'),
+    ('\u{AC7}', '\u{AC9}'), ('\u{ACB}', '\u{ACD}'), ('\u{AD0}', '\u{AD0}'), ('\u{AE0}', '\u{AE3}'),
+    ('\u{AE6}', '\u{AF1}'), ('\u{AF9}', '\u{AFF}'),
+];
+
+const SC_GURU: &[(char, char)] = &[
+    ('\u{A01}', '\u{A03}'), ('\u{A05}', '\u{A0A}'), ('\u{A0F}', '\u{A10}'), ('\u{A13}', '\u{A28}'),
+    ('\u{A2A}', '\u{A30}'), ('\u{A32}', '\u{A33}'), ('\u{A35}', '\u{A36}'), ('\u{A38}', '\u{A39}'),
+    ('\u{A3C}', '\u{A3C}'), ('\u{A3E}', '\u{A42}'), ('\u{A47}', '\u{A48}'), ('\u{A4B}', '\u{A4D}'),
+    ('\u{A51}', '\u{A51}'), ('\u{A59}', '\u{A5C}'), ('\u{A5E}', '\u{A5E}'), ('\u{A66}', '\u{A76}'),
+];
+
+const SC_HANG: &[(char, char)] = &[
+    ('\u{1100}', '\u{11FF}'), ('\u{302E}', '\u{302F}'), ('\u{3131}', '\u{318E}'), ('\u{3200}', '\u{321E}'),
+    ('\u{3260}', '\u{327E}'), ('\u{A960}', '\u{A97C}'), ('\u{AC00}', '\u{D7A3}'), ('\u{D7B0}', '\u{D7C6}'),
+    ('\u{D7CB}', '\u{D7FB}'), ('\u{FFA0}', '\u{FFBE}'), ('\u{FFC2}', '\u{FFC7}'), ('\u{FFCA}', '\u{FFCF}'),
+    ('\u{FFD2}', '\u{FFD7}'), ('\u{FFDA}', '\u{FFDC}'),
+];
+
+const SC_HANI: &[(char, char)] = &[
+    ('\u{2E80}', '\u{2E99}'), ('\u{2E9B}', '\u{2EF3}'), ('\u{2F00}', '\u{2FD5}'), ('\u{3005}', '\u{3005}'),
+    ('\u{3007}', '\u{3007}'), ('\u{3021}', '\u{3029}'), ('\u{3038}', '\u{303B}'), ('\u{3400}', '\u{4DBF}'),
+    ('\u{4E00}', '\u{9FFF}'), ('\u{F900}', '\u{FA6D}'), ('\u{FA70}', '\u{FAD9}'), ('\u{16FE2}', '\u{16FE3}'),
+    ('\u{16FF0}', '\u{16FF1}'), ('\u{20000}', '\u{2A6DF}'), ('\u{2A700}', '\u{2B738}'), ('\u{2B740}', '\u{2B81D}'),
+    ('\u{2B820}', '\u{2CEA1}'), ('\u{2CEB0}', '\u{2EBE0}'), ('\u{2F800}', '\u{2FA1D}'), ('\u{30000}', '\u{3134A}'),
+];
+
+const SC_HANO: &[(char, char)] = &[
+    ('\u{1720}', '\u{1734}'),
+];
+
+const SC_HATR: &[(char, char)] = &[
+    ('\u{108E0}', '\u{108F2}'), ('\u{108F4}', '\u{108F5}'), ('\u{108FB}', '\u{108FF}'),
+];
+
+const SC_HEBR: &[(char, char)] = &[
+    ('\u{591}', '\u{5C7}'), ('\u{5D0}', '\u{5EA}'), ('\u{5EF}', '\u{5F4}'), ('\u{FB1D}', '\u{FB36}'),
+    ('\u{FB38}', '\u{FB3C}'), ('\u{FB3E}', '\u{FB3E}'), ('\u{FB40}', '\u{FB41}'), ('\u{FB43}', '\u{FB44}'),
+    ('\u{FB46}', '\u{FB4F}'),
+];
+
+const SC_HIRA: &[(char, char)] = &[
+    ('\u{3041}', '\u{3096}'), ('\u{309D}', '\u{309F}'), ('\u{1B001}', '\u{1B11F}'), ('\u{1B150}', '\u{1B152}'),
+    ('\u{1F200}', '\u{1F200}'),
+];
+
+const SC_HLUW: &[(char, char)] = &[
+    ('\u{14400}', '\u{14646}'),
+];
+
+const SC_HMNG: &[(char, char)] = &[
+    ('\u{16B00}', '\u{16B45}'), ('\u{16B50}', '\u{16B59}'), ('\u{16B5B}', '\u{16B61}'), ('\u{16B63}', '\u{16B77}'),
+    ('\u{16B7D}', '\u{16B8F}'),
+];
+
+const SC_HMNP: &[(char, char)] = &[
+    ('\u{1E100}', '\u{1E12C}'), ('\u{1E130}', '\u{1E13D}'), ('\u{1E140}', '\u{1E149}'), ('\u{1E14E}', '\u{1E14F}'),
+];
+
+const SC_HUNG: &[(char, char)] = &[
+    ('\u{10C80}', '\u{10CB2}'), ('\u{10CC0}', '\u{10CF2}'), ('\u{10CFA}', '\u{10CFF}'),
+];
+
+const SC_ITAL: &[(char, char)] = &[
+    ('\u{10300}', '\u{10323}'), ('\u{1032D}', '\u{1032F}'),
+];
+
+const SC_JAVA: &[(char, char)] = &[
+    ('\u{A980}', '\u{A9CD}'), ('\u{A9D0}', '\u{A9D9}'), ('\u{A9DE}', '\u{A9DF}'),
+];
+
+const SC_KALI: &[(char, char)] = &[
+    ('\u{A900}', '\u{A92D}'), ('\u{A92F}', '\u{A92F}'),
+];
+
+const SC_KANA: &[(char, char)] = &[
+    ('\u{30A1}', '\u{30FA}'), ('\u{30FD}', '\u{30FF}'), ('\u{31F0}', '\u{31FF}'), ('\u{32D0}', '\u{32FE}'),
+    ('\u{3300}', '\u{3357}'), ('\u{FF66}', '\u{FF6F}'), ('\u{FF71}', '\u{FF9D}'), ('\u{1AFF0}', '\u{1AFF3}'),
+    ('\u{1AFF5}', '\u{1AFFB}'), ('\u{1AFFD}', '\u{1AFFE}'), ('\u{1B000}', '\u{1B000}'), ('\u{1B120}', '\u{1B122}'),
+    ('\u{1B164}', '\u{1B167}'),
+];
+
+const SC_KHAR: &[(char, char)] = &[
+    ('\u{10A00}', '\u{10A03}'), ('\u{10A05}', '\u{10A06}'), ('\u{10A0C}', '\u{10A13}'), ('\u{10A15}', '\u{10A17}'),
+    ('\u{10A19}', '\u{10A35}'), ('\u{10A38}', '\u{10A3A}'), ('\u{10A3F}', '\u{10A48}'), ('\u{10A50}', '\u{10A58}'),
+];
+
+const SC_KHMR: &[(char, char)] = &[
+    ('\u{1780}', '\u{17DD}'), ('\u{17E0}', '\u{17E9}'), ('\u{17F0}', '\u{17F9}'), ('\u{19E0}', '\u{19FF}'),
+];
+
+const SC_KHOJ: &[(char, char)] = &[
+    ('\u{11200}', '\u{11211}'), ('\u{11213}', '\u{1123E}'),
+];
+
+const SC_KITS: &[(char, char)] = &[
+    ('\u{16FE4}', '\u{16FE4}'), ('\u{18B00}', '\u{18CD5}'),
+];
+
+const SC_KNDA: &[(char, char)] = &[
+    ('\u{C80}', '\u{C8C}'), ('\u{C8E}', '\u{C90}'), ('\u{C92}', '\u{CA8}'), ('\u{CAA}', '\u{CB3}'),
+    ('\u{CB5}', '\u{CB9}'), ('\u{CBC}', '\u{CC4}'), ('\u{CC6}', '\u{CC8}'), ('\u{CCA}', '\u{CCD}'),
+    ('\u{CD5}', '\u{CD6}'), ('\u{CDD}', '\u{CDE}'), ('\u{CE0}', '\u{CE3}'), ('\u{CE6}', '\u{CEF}'),
+    ('\u{CF1}', '\u{CF2}'),
+];
+
+const SC_KTHI: &[(char, char)] = &[
+    ('\u{11080}', '\u{110C2}'), ('\u{110CD}', '\u{110CD}'),
+];
+
+const SC_LANA: &[(char, char)] = &[
+    ('\u{1A20}', '\u{1A5E}'), ('\u{1A60}', '\u{1A7C}'), ('\u{1A7F}', '\u{1A89}'), ('\u{1A90}', '\u{1A99}'),
+    ('\u{1AA0}', '\u{1AAD}'),
+];
+
+const SC_LAOO: &[(char, char)] = &[
+    ('\u{E81}', '\u{E82}'), ('\u{E84}', '\u{E84}'), ('\u{E86}', '\u{E8A}'), ('\u{E8C}', '\u{EA3}'),
+    ('\u{EA5}', '\u{EA5}'), ('\u{EA7}', '\u{EBD}'), ('\u{EC0}', '\u{EC4}'), ('\u{EC6}', '\u{EC6}'),
+    ('\u{EC8}', '\u{ECD}'), ('\u{ED0}', '\u{ED9}'), ('\u{EDC}', '\u{EDF}'),
+];
+
+const SC_LATN: &[(char, char)] = &[
+    ('\u{41}', '\u{5A}'), ('\u{61}', '\u{7A}'), ('\u{AA}', '\u{AA}'), ('\u{BA}', '\u{BA}'),
+    ('\u{C0}', '\u{D6}'), ('\u{D8}', '\u{F6}'), ('\u{F8}', '\u{2B8}'), ('\u{2E0}', '\u{2E4}'),
+    ('\u{1D00}', '\u{1D25}'), ('\u{1D2C}', '\u{1D5C}'), ('\u{1D62}', '\u{1D65}'), ('\u{1D6B}', '\u{1D77}'),
+    ('\u{1D79}', '\u{1DBE}'), ('\u{1E00}', '\u{1EFF}'), ('\u{2071}', '\u{2071}'), ('\u{207F}', '\u{207F}'),
+    ('\u{2090}', '\u{209C}'), ('\u{212A}', '\u{212B}'), ('\u{2132}', '\u{2132}'), ('\u{214E}', '\u{214E}'),
+    ('\u{2160}', '\u{2188}'), ('\u{2C60}', '\u{2C7F}'), ('\u{A722}', '\u{A787}'), ('\u{A78B}', '\u{A7CA}'),
+    ('\u{A7D0}', '\u{A7D1}'), ('\u{A7D3}', '\u{A7D3}'), ('\u{A7D5}', '\u{A7D9}'), ('\u{A7F2}', '\u{A7FF}'),
+    ('\u{AB30}', '\u{AB5A}'), ('\u{AB5C}', '\u{AB64}'), ('\u{AB66}', '\u{AB69}'), ('\u{FB00}', '\u{FB06}'),
+    ('\u{FF21}', '\u{FF3A}'), ('\u{FF41}', '\u{FF5A}'), ('\u{10780}', '\u{10785}'), ('\u{10787}', '\u{107B0}'),
+    ('\u{107B2}', '\u{107BA}'), ('\u{1DF00}', '\u{1DF1E}'),
+];
+
+const SC_LEPC: &[(char, char)] = &[
+    ('\u{1C00}', '\u{1C37}'), ('\u{1C3B}', '\u{1C49}'), ('\u{1C4D}', '\u{1C4F}'),
+];
+
+const SC_LIMB: &[(char, char)] = &[
+    ('\u{1900}', '\u{191E}'), ('\u{1920}', '\u{192B}'), ('\u{1930}', '\u{193B}'), ('\u{1940}', '\u{1940}'),
+    ('\u{1944}', '\u{194F}'),
+];
+
+const SC_LINA: &[(char, char)] = &[
+    ('\u{10600}', '\u{10736}'), ('\u{10740}', '\u{10755}'), ('\u{10760}', '\u{10767}'),
+];
+
+const SC_LINB: &[(char, char)] = &[
+    ('\u{10000}', '\u{1000B}'), ('\u{1000D}', '\u{10026}'), ('\u{10028}', '\u{1003A}'), ('\u{1003C}', '\u{1003D}'),
+    ('\u{1003F}', '\u{1004D}'), ('\u{10050}', '\u{1005D}'), ('\u{10080}', '\u{100FA}'),
+];
+
+const SC_LISU: &[(char, char)] = &[
+    ('\u{A4D0}', '\u{A4FF}'), ('\u{11FB0}', '\u{11FB0}'),
+];
+
+const SC_LYCI: &[(char, char)] = &[
+    ('\u{10280}', '\u{1029C}'),
+];
+
+const SC_LYDI: &[(char, char)] = &[
+    ('\u{10920}', '\u{10939}'), ('\u{1093F}', '\u{1093F}'),
+];
+
+const SC_MAHJ: &[(char, char)] = &[
+    ('\u{11150}', '\u{11176}'),
+];
+
+const SC_MAKA: &[(char, char)] = &[
+    ('\u{11EE0}', '\u{11EF8}'),
+];
+
+const SC_MAND: &[(char, char)] = &[
+    ('\u{840}', '\u{85B}'), ('\u{85E}', '\u{85E}'),
+];
+
+const SC_MANI: &[(char, char)] = &[
+    ('\u{10AC0}', '\u{10AE6}'), ('\u{10AEB}', '\u{10AF6}'),
+];
+
+const SC_MARC: &[(char, char)] = &[
+    ('\u{11C70}', '\u{11C8F}'), ('\u{11C92}', '\u{11CA7}'), ('\u{11CA9}', '\u{11CB6}'),
+];
+
+const SC_MEDF: &[(char, char)] = &[
+    ('\u{16E40}', '\u{16E9A}'),
+];
+
+const SC_MEND: &[(char, char)] = &[
+    ('\u{1E800}', '\u{1E8C4}'), ('\u{1E8C7}', '\u{1E8D6}'),
+];
+
+const SC_MERC: &[(char, char)] = &[
+    ('\u{109A0}', '\u{109B7}'), ('\u{109BC}', '\u{109CF}'), ('\u{109D2}', '\u{109FF}'),
+];
+
+const SC_MERO: &[(char, char)] = &[
+    ('\u{10980}', '\u{1099F}'),
+];
+
+const SC_MLYM: &[(char, char)] = &[
+    ('\u{D00}', '\u{D0C}'), ('\u{D0E}', '\u{D10}'), ('\u{D12}', '\u{D44}'), ('\u{D46}', '\u{D48}'),
+    ('\u{D4A}', '\u{D4F}'), ('\u{D54}', '\u{D63}'), ('\u{D66}', '\u{D7F}'),
+];
+
+const SC_MODI: &[(char, char)] = &[
+    ('\u{11600}', '\u{11644}'), ('\u{11650}', '\u{11659}'),
+];
+
+const SC_MONG: &[(char, char)] = &[
+    ('\u{1800}', '\u{1801}'), ('\u{1804}', '\u{1804}'), ('\u{1806}', '\u{1819}'), ('\u{1820}', '\u{1878}'),
+    ('\u{1880}', '\u{18AA}'), ('\u{11660}', '\u{1166C}'),
+];
+
+const SC_MROO: &[(char, char)] = &[
+    ('\u{16A40}', '\u{16A5E}'), ('\u{16A60}', '\u{16A69}'), ('\u{16A6E}', '\u{16A6F}'),
+];
+
+const SC_MTEI: &[(char, char)] = &[
+    ('\u{AAE0}', '\u{AAF6}'), ('\u{ABC0}', '\u{ABED}'), ('\u{ABF0}', '\u{ABF9}'),
+];
+
+const SC_MULT: &[(char, char)] = &[
+    ('\u{11280}', '\u{11286}'), ('\u{11288}', '\u{11288}'), ('\u{1128A}', '\u{1128D}'), ('\u{1128F}', '\u{1129D}'),
+    ('\u{1129F}', '\u{112A9}'),
+];
+
+const SC_MYMR: &[(char, char)] = &[
+    ('\u{1000}', '\u{109F}'), ('\u{A9E0}', '\u{A9FE}'), ('\u{AA60}', '\u{AA7F}'),
+];
+
+const SC_NAND: &[(char, char)] = &[
+    ('\u{119A0}', '\u{119A7}'), ('\u{119AA}', '\u{119D7}'), ('\u{119DA}', '\u{119E4}'),
+];
+
+const SC_NARB: &[(char, char)] = &[
+    ('\u{10A80}', '\u{10A9F}'),
+];
+
+const SC_NBAT: &[(char, char)] = &[
+    ('\u{10880}', '\u{1089E}'), ('\u{108A7}', '\u{108AF}'),
+];
+
+const SC_NEWA: &[(char, char)] = &[
+    ('\u{11400}', '\u{1145B}'), ('\u{1145D}', '\u{11461}'),
+];
+
+const SC_NKOO: &[(char, char)] = &[
+    ('\u{7C0}', '\u{7FA}'), ('\u{7FD}', '\u{7FF}'),
+];
+
+const SC_NSHU: &[(char, char)] = &[
+    ('\u{16FE1}', '\u{16FE1}'), ('\u{1B170}', '\u{1B2FB}'),
+];
+
+const SC_OGAM: &[(char, char)] = &[
+    ('\u{1680}', '\u{169C}'),
+];
+
+const SC_OLCK: &[(char, char)] = &[
+    ('\u{1C50}', '\u{1C7F}'),
+];
+
+const SC_ORKH: &[(char, char)] = &[
+    ('\u{10C00}', '\u{10C48}'),
+];
+
+const SC_ORYA: &[(char, char)] = &[
+    ('\u{B01}', '\u{B03}'), ('\u{B05}', '\u{B0C}'), ('\u{B0F}', '\u{B10}'), ('\u{B13}', '\u{B28}'),
+    ('\u{B2A}', '\u{B30}'), ('\u{B32}', '\u{B33}'), ('\u{B35}', '\u{B39}'), ('\u{B3C}', '\u{B44}'),
+    ('\u{B47}', '\u{B48}'), ('\u{B4B}', '\u{B4D}'), ('\u{B55}', '\u{B57}'), ('\u{B5C}', '\u{B5D}'),
+    ('\u{B5F}', '\u{B63}'), ('\u{B66}', '\u{B77}'),
+];
+
+const SC_OSGE: &[(char, char)] = &[
+    ('\u{104B0}', '\u{104D3}'), ('\u{104D8}', '\u{104FB}'),
+];
+
+const SC_OSMA: &[(char, char)] = &[
+    ('\u{10480}', '\u{1049D}'), ('\u{104A0}', '\u{104A9}'),
+];
+
+const SC_OUGR: &[(char, char)] = &[
+    ('\u{10F70}', '\u{10F89}'),
+];
+
+const SC_PALM: &[(char, char)] = &[
+    ('\u{10860}', '\u{1087F}'),
+];
+
+const SC_PAUC: &[(char, char)] = &[
+    ('\u{11AC0}', '\u{11AF8}'),
+];
+
+const SC_PERM: &[(char, char)] = &[
+    ('\u{10350}', '\u{1037A}'),
+];
+
+const SC_PHAG: &[(char, char)] = &[
+    ('\u{A840}', '\u{A877}'),
+];
+
+const SC_PHLI: &[(char, char)] = &[
+    ('\u{10B60}', '\u{10B72}'), ('\u{10B78}', '\u{10B7F}'),
+];
+
+const SC_PHLP: &[(char, char)] = &[
+    ('\u{10B80}', '\u{10B91}'), ('\u{10B99}', '\u{10B9C}'), ('\u{10BA9}', '\u{10BAF}'),
+];
+
+const SC_PHNX: &[(char, char)] = &[
+    ('\u{10900}', '\u{1091B}'), ('\u{1091F}', '\u{1091F}'),
+];
+
+const SC_PLRD: &[(char, char)] = &[
+    ('\u{16F00}', '\u{16F4A}'), ('\u{16F4F}', '\u{16F87}'), ('\u{16F8F}', '\u{16F9F}'),
+];
+
+const SC_PRTI: &[(char, char)] = &[
+    ('\u{10B40}', '\u{10B55}'), ('\u{10B58}', '\u{10B5F}'),
+];
+
+const SC_RJNG: &[(char, char)] = &[
+    ('\u{A930}', '\u{A953}'), ('\u{A95F}', '\u{A95F}'),
+];
+
+const SC_ROHG: &[(char, char)] = &[
+    ('\u{10D00}', '\u{10D27}'), ('\u{10D30}', '\u{10D39}'),
+];
+
+const SC_RUNR: &[(char, char)] = &[
+    ('\u{16A0}', '\u{16EA}'), ('\u{16EE}', '\u{16F8}'),
+];
+
+const SC_SAMR: &[(char, char)] = &[
+    ('\u{800}', '\u{82D}'), ('\u{830}', '\u{83E}'),
+];
+
+const SC_SARB: &[(char, char)] = &[
+    ('\u{10A60}', '\u{10A7F}'),
+];
+
+const SC_SAUR: &[(char, char)] = &[
+    ('\u{A880}', '\u{A8C5}'), ('\u{A8CE}', '\u{A8D9}'),
+];
+
+const SC_SGNW: &[(char, char)] = &[
+    ('\u{1D800}', '\u{1DA8B}'), ('\u{1DA9B}', '\u{1DA9F}'), ('\u{1DAA1}', '\u{1DAAF}'),
+];
+
+const SC_SHAW: &[(char, char)] = &[
+    ('\u{10450}', '\u{1047F}'),
+];
+
+const SC_SHRD: &[(char, char)] = &[
+    ('\u{11180}', '\u{111DF}'),
+];
+
+const SC_SIDD: &[(char, char)] = &[
+    ('\u{11580}', '\u{115B5}'), ('\u{115B8}', '\u{115DD}'),
+];
+
+const SC_SIND: &[(char, char)] = &[
+    ('\u{112B0}', '\u{112EA}'), ('\u{112F0}', '\u{112F9}'),
+];
+
+const SC_SINH: &[(char, char)] = &[
+    ('\u{D81}', '\u{D83}'), ('\u{D85}', '\u{D96}'), ('\u{D9A}', '\u{DB1}'), ('\u{DB3}', '\u{DBB}'),
+    ('\u{DBD}', '\u{DBD}'), ('\u{DC0}', '\u{DC6}'), ('\u{DCA}', '\u{DCA}'), ('\u{DCF}', '\u{DD4}'),
+    ('\u{DD6}', '\u{DD6}'), ('\u{DD8}', '\u{DDF}'), ('\u{DE6}', '\u{DEF}'), ('\u{DF2}', '\u{DF4}'),
+    ('\u{111E1}', '\u{111F4}'),
+];
+
+const SC_SOGD: &[(char, char)] = &[
+    ('\u{10F30}', '\u{10F59}'),
+];
+
+const SC_SOGO: &[(char, char)] = &[
+    ('\u{10F00}', '\u{10F27}'),
+];
+
+const SC_SORA: &[(char, char)] = &[
+    ('\u{110D0}', '\u{110E8}'), ('\u{110F0}', '\u{110F9}'),
+];
+
+const SC_SOYO: &[(char, char)] = &[
+    ('\u{11A50}', '\u{11AA2}'),
+];
+
+const SC_SUND: &[(char, char)] = &[
+    ('\u{1B80}', '\u{1BBF}'), ('\u{1CC0}', '\u{1CC7}'),
+];
+
+const SC_SYLO: &[(char, char)] = &[
+    ('\u{A800}', '\u{A82C}'),
+];
+
+const SC_SYRC: &[(char, char)] = &[
+    ('\u{700}', '\u{70D}'), ('\u{70F}', '\u{74A}'), ('\u{74D}', '\u{74F}'), ('\u{860}', '\u{86A}'),
+];
+
+const SC_TAGB: &[(char, char)] = &[
+    ('\u{1760}', '\u{176C}'), ('\u{176E}', '\u{1770}'), ('\u{1772}', '\u{1773}'),
+];
+
+const SC_TAKR: &[(char, char)] = &[
+    ('\u{11680}', '\u{116B9}'), ('\u{116C0}', '\u{116C9}'),
+];
+
+const SC_TALE: &[(char, char)] = &[
+    ('\u{1950}', '\u{196D}'), ('\u{1970}', '\u{1974}'),
+];
+
+const SC_TALU: &[(char, char)] = &[
+    ('\u{1980}', '\u{19AB}'), ('\u{19B0}', '\u{19C9}'), ('\u{19D0}', '\u{19DA}'), ('\u{19DE}', '\u{19DF}'),
+];
+
+const SC_TAML: &[(char, char)] = &[
+    ('\u{B82}', '\u{B83}'), ('\u{B85}', '\u{B8A}'), ('\u{B8E}', '\u{B90}'), ('\u{B92}', '\u{B95}'),
+    ('\u{B99}', '\u{B9A}'), ('\u{B9C}', '\u{B9C}'), ('\u{B9E}', '\u{B9F}'), ('\u{BA3}', '\u{BA4}'),
+    ('\u{BA8}', '\u{BAA}'), ('\u{BAE}', '\u{BB9}'), ('\u{BBE}', '\u{BC2}'), ('\u{BC6}', '\u{BC8}'),
+    ('\u{BCA}', '\u{BCD}'), ('\u{BD0}', '\u{BD0}'), ('\u{BD7}', '\u{BD7}'), ('\u{BE6}', '\u{BFA}'),
+    ('\u{11FC0}', '\u{11FF1}'), ('\u{11FFF}', '\u{11FFF}'),
+];
+
+const SC_TANG: &[(char, char)] = &[
+    ('\u{16FE0}', '\u{16FE0}'), ('\u{17000}', '\u{187F7}'), ('\u{18800}', '\u{18AFF}'), ('\u{18D00}', '\u{18D08}'),
+];
+
+const SC_TAVT: &[(char, char)] = &[
+    ('\u{AA80}', '\u{AAC2}'), ('\u{AADB}', '\u{AADF}'),
+];
+
+const SC_TELU: &[(char, char)] = &[
+    ('\u{C00}', '\u{C0C}'), ('\u{C0E}', '\u{C10}'), ('\u{C12}', '\u{C28}'), ('\u{C2A}', '\u{C39}'),
+    ('\u{C3C}', '\u{C44}'), ('\u{C46}', '\u{C48}'), ('\u{C4A}', '\u{C4D}'), ('\u{C55}', '\u{C56}'),
+    ('\u{C58}', '\u{C5A}'), ('\u{C5D}', '\u{C5D}'), ('\u{C60}', '\u{C63}'), ('\u{C66}', '\u{C6F}'),
+    ('\u{C77}', '\u{C7F}'),
+];
+
+const SC_TFNG: &[(char, char)] = &[
+    ('\u{2D30}', '\u{2D67}'), ('\u{2D6F}', '\u{2D70}'), ('\u{2D7F}', '\u{2D7F}'),
+];
+
+const SC_TGLG: &[(char, char)] = &[
+    ('\u{1700}', '\u{1715}'), ('\u{171F}', '\u{171F}'),
+];
+
+const SC_THAA: &[(char, char)] = &[
+    ('\u{780}', '\u{7B1}'),
+];
+
+const SC_THAI: &[(char, char)] = &[
+    ('\u{E01}', '\u{E3A}'), ('\u{E40}', '\u{E5B}'),
+];
+
+const SC_TIBT: &[(char, char)] = &[
+    ('\u{F00}', '\u{F47}'), ('\u{F49}', '\u{F6C}'), ('\u{F71}', '\u{F97}'), ('\u{F99}', '\u{FBC}'),
+    ('\u{FBE}', '\u{FCC}'), ('\u{FCE}', '\u{FD4}'), ('\u{FD9}', '\u{FDA}'),
+];
+
+const SC_TIRH: &[(char, char)] = &[
+    ('\u{11480}', '\u{114C7}'), ('\u{114D0}', '\u{114D9}'),
+];
+
+const SC_TNSA: &[(char, char)] = &[
+    ('\u{16A70}', '\u{16ABE}'), ('\u{16AC0}', '\u{16AC9}'),
+];
+
+const SC_TOTO: &[(char, char)] = &[
+    ('\u{1E290}', '\u{1E2AE}'),
+];
+
+const SC_UGAR: &[(char, char)] = &[
+    ('\u{10380}', '\u{1039D}'), ('\u{1039F}', '\u{1039F}'),
+];
+
+const SC_VAII: &[(char, char)] = &[
+    ('\u{A500}', '\u{A62B}'),
+];
+
+const SC_VITH: &[(char, char)] = &[
+    ('\u{10570}', '\u{1057A}'), ('\u{1057C}', '\u{1058A}'), ('\u{1058C}', '\u{10592}'), ('\u{10594}', '\u{10595}'),
+    ('\u{10597}', '\u{105A1}'), ('\u{105A3}', '\u{105B1}'), ('\u{105B3}', '\u{105B9}'), ('\u{105BB}', '\u{105BC}'),
+];
+
+const SC_WARA: &[(char, char)] = &[
+    ('\u{118A0}', '\u{118F2}'), ('\u{118FF}', '\u{118FF}'),
+];
+
+const SC_WCHO: &[(char, char)] = &[
+    ('\u{1E2C0}', '\u{1E2F9}'), ('\u{1E2FF}', '\u{1E2FF}'),
+];
+
+const SC_XPEO: &[(char, char)] = &[
+    ('\u{103A0}', '\u{103C3}'), ('\u{103C8}', '\u{103D5}'),
+];
+
+const SC_XSUX: &[(char, char)] = &[
+    ('\u{12000}', '\u{12399}'), ('\u{12400}', '\u{1246E}'), ('\u{12470}', '\u{12474}'), ('\u{12480}', '\u{12543}'),
+];
+
+const SC_YEZI: &[(char, char)] = &[
+    ('\u{10E80}', '\u{10EA9}'), ('\u{10EAB}', '\u{10EAD}'), ('\u{10EB0}', '\u{10EB1}'),
+];
+
+const SC_YIII: &[(char, char)] = &[
+    ('\u{A000}', '\u{A48C}'), ('\u{A490}', '\u{A4C6}'),
+];
+
+const SC_ZANB: &[(char, char)] = &[
+    ('\u{11A00}', '\u{11A47}'),
+];
+
+const SC_ZINH: &[(char, char)] = &[
+    ('\u{300}', '\u{36F}'), ('\u{485}', '\u{486}'), ('\u{64B}', '\u{655}'), ('\u{670}', '\u{670}'),
+    ('\u{951}', '\u{954}'), ('\u{1AB0}', '\u{1ACE}'), ('\u{1CD0}', '\u{1CD2}'), ('\u{1CD4}', '\u{1CE0}'),
+    ('\u{1CE2}', '\u{1CE8}'), ('\u{1CED}', '\u{1CED}'), ('\u{1CF4}', '\u{1CF4}'), ('\u{1CF8}', '\u{1CF9}'),
+    ('\u{1DC0}', '\u{1DFF}'), ('\u{200C}', '\u{200D}'), ('\u{20D0}', '\u{20F0}'), ('\u{302A}', '\u{302D}'),
+    ('\u{3099}', '\u{309A}'), ('\u{FE00}', '\u{FE0F}'), ('\u{FE20}', '\u{FE2D}'), ('\u{101FD}', '\u{101FD}'),
+    ('\u{102E0}', '\u{102E0}'), ('\u{1133B}', '\u{1133B}'), ('\u{1CF00}', '\u{1CF2D}'), ('\u{1CF30}', '\u{1CF46}'),
+    ('\u{1D167}', '\u{1D169}'), ('\u{1D17B}', '\u{1D182}'), ('\u{1D185}', '\u{1D18B}'), ('\u{1D1AA}', '\u{1D1AD}'),
+    ('\u{E0100}', '\u{E01EF}'),
+];
+
+const SC_ZYYY: &[(char, char)] = &[
+    ('\u{0}', '\u{40}'), ('\u{5B}', '\u{60}'), ('\u{7B}', '\u{A9}'), ('\u{AB}', '\u{B9}'),
+    ('\u{BB}', '\u{BF}'), ('\u{D7}', '\u{D7}'), ('\u{F7}', '\u{F7}'), ('\u{2B9}', '\u{2DF}'),
+    ('\u{2E5}', '\u{2E9}'), ('\u{2EC}', '\u{2FF}'), ('\u{374}', '\u{374}'), ('\u{37E}', '\u{37E}'),
+    ('\u{385}', '\u{385}'), ('\u{387}', '\u{387}'), ('\u{605}', '\u{605}'), ('\u{60C}', '\u{60C}'),
+    ('\u{61B}', '\u{61B}'), ('\u{61F}', '\u{61F}'), ('\u{640}', '\u{640}'), ('\u{6DD}', '\u{6DD}'),
+    ('\u{8E2}', '\u{8E2}'), ('\u{964}', '\u{965}'), ('\u{E3F}', '\u{E3F}'), ('\u{FD5}', '\u{FD8}'),
+    ('\u{10FB}', '\u{10FB}'), ('\u{16EB}', '\u{16ED}'), ('\u{1735}', '\u{1736}'), ('\u{1802}', '\u{1803}'),
+    ('\u{1805}', '\u{1805}'), ('\u{1CD3}', '\u{1CD3}'), ('\u{1CE1}', '\u{1CE1}'), ('\u{1CE9}', '\u{1CEC}'),
+    ('\u{1CEE}', '\u{1CF3}'), ('\u{1CF5}', '\u{1CF7}'), ('\u{1CFA}', '\u{1CFA}'), ('\u{2000}', '\u{200B}'),
+    ('\u{200E}', '\u{2064}'), ('\u{2066}', '\u{2070}'), ('\u{2074}', '\u{207E}'), ('\u{2080}', '\u{208E}'),
+    ('\u{20A0}', '\u{20C0}'), ('\u{2100}', '\u{2125}'), ('\u{2127}', '\u{2129}'), ('\u{212C}', '\u{2131}'),
+    ('\u{2133}', '\u{214D}'), ('\u{214F}', '\u{215F}'), ('\u{2189}', '\u{218B}'), ('\u{2190}', '\u{2426}'),
+    ('\u{2440}', '\u{244A}'), ('\u{2460}', '\u{27FF}'), ('\u{2900}', '\u{2B73}'), ('\u{2B76}', '\u{2B95}'),
+    ('\u{2B97}', '\u{2BFF}'), ('\u{2E00}', '\u{2E5D}'), ('\u{2FF0}', '\u{2FFB}'), ('\u{3000}', '\u{3004}'),
+    ('\u{3006}', '\u{3006}'), ('\u{3008}', '\u{3020}'), ('\u{3030}', '\u{3037}'), ('\u{303C}', '\u{303F}'),
+    ('\u{309B}', '\u{309C}'), ('\u{30A0}', '\u{30A0}'), ('\u{30FB}', '\u{30FC}'), ('\u{3190}', '\u{319F}'),
+    ('\u{31C0}', '\u{31E3}'), ('\u{3220}', '\u{325F}'), ('\u{327F}', '\u{32CF}'), ('\u{32FF}', '\u{32FF}'),
+    ('\u{3358}', '\u{33FF}'), ('\u{4DC0}', '\u{4DFF}'), ('\u{A700}', '\u{A721}'), ('\u{A788}', '\u{A78A}'),
+    ('\u{A830}', '\u{A839}'), ('\u{A92E}', '\u{A92E}'), ('\u{A9CF}', '\u{A9CF}'), ('\u{AB5B}', '\u{AB5B}'),
+    ('\u{AB6A}', '\u{AB6B}'), ('\u{FD3E}', '\u{FD3F}'), ('\u{FE10}', '\u{FE19}'), ('\u{FE30}', '\u{FE52}'),
+    ('\u{FE54}', '\u{FE66}'), ('\u{FE68}', '\u{FE6B}'), ('\u{FEFF}', '\u{FEFF}'), ('\u{FF01}', '\u{FF20}'),
+    ('\u{FF3B}', '\u{FF40}'), ('\u{FF5B}', '\u{FF65}'), ('\u{FF70}', '\u{FF70}'), ('\u{FF9E}', '\u{FF9F}'),
+    ('\u{FFE0}', '\u{FFE6}'), ('\u{FFE8}', '\u{FFEE}'), ('\u{FFF9}', '\u{FFFD}'), ('\u{10100}', '\u{10102}'),
+    ('\u{10107}', '\u{10133}'), ('\u{10137}', '\u{1013F}'), ('\u{10190}', '\u{1019C}'), ('\u{101D0}', '\u{101FC}'),
+    ('\u{102E1}', '\u{102FB}'), ('\u{1BCA0}', '\u{1BCA3}'), ('\u{1CF50}', '\u{1CFC3}'), ('\u{1D000}', '\u{1D0F5}'),
+    ('\u{1D100}', '\u{1D126}'), ('\u{1D129}', '\u{1D166}'), ('\u{1D16A}', '\u{1D17A}'), ('\u{1D183}', '\u{1D184}'),
+    ('\u{1D18C}', '\u{1D1A9}'), ('\u{1D1AE}', '\u{1D1EA}'), ('\u{1D2E0}', '\u{1D2F3}'), ('\u{1D300}', '\u{1D356}'),
+    ('\u{1D360}', '\u{1D378}'), ('\u{1D400}', '\u{1D454}'), ('\u{1D456}', '\u{1D49C}'), ('\u{1D49E}', '\u{1D49F}'),
+    ('\u{1D4A2}', '\u{1D4A2}'), ('\u{1D4A5}', '\u{1D4A6}'), ('\u{1D4A9}', '\u{1D4AC}'), ('\u{1D4AE}', '\u{1D4B9}'),
+    ('\u{1D4BB}', '\u{1D4BB}'), ('\u{1D4BD}', '\u{1D4C3}'), ('\u{1D4C5}', '\u{1D505}'), ('\u{1D507}', '\u{1D50A}'),
+    ('\u{1D50D}', '\u{1D514}'), ('\u{1D516}', '\u{1D51C}'), ('\u{1D51E}', '\u{1D539}'), ('\u{1D53B}', '\u{1D53E}'),
+    ('\u{1D540}', '\u{1D544}'), ('\u{1D546}', '\u{1D546}'), ('\u{1D54A}', '\u{1D550}'), ('\u{1D552}', '\u{1D6A5}'),
+    ('\u{1D6A8}', '\u{1D7CB}'), ('\u{1D7CE}', '\u{1D7FF}'), ('\u{1EC71}', '\u{1ECB4}'), ('\u{1ED01}', '\u{1ED3D}'),
+    ('\u{1F000}', '\u{1F02B}'), ('\u{1F030}', '\u{1F093}'), ('\u{1F0A0}', '\u{1F0AE}'), ('\u{1F0B1}', '\u{1F0BF}'),
+    ('\u{1F0C1}', '\u{1F0CF}'), ('\u{1F0D1}', '\u{1F0F5}'), ('\u{1F100}', '\u{1F1AD}'), ('\u{1F1E6}', '\u{1F1FF}'),
+    ('\u{1F201}', '\u{1F202}'), ('\u{1F210}', '\u{1F23B}'), ('\u{1F240}', '\u{1F248}'), ('\u{1F250}', '\u{1F251}'),
+    ('\u{1F260}', '\u{1F265}'), ('\u{1F300}', '\u{1F6D7}'), ('\u{1F6DD}', '\u{1F6EC}'), ('\u{1F6F0}', '\u{1F6FC}'),
+    ('\u{1F700}', '\u{1F773}'), ('\u{1F780}', '\u{1F7D8}'), ('\u{1F7E0}', '\u{1F7EB}'), ('\u{1F7F0}', '\u{1F7F0}'),
+    ('\u{1F800}', '\u{1F80B}'), ('\u{1F810}', '\u{1F847}'), ('\u{1F850}', '\u{1F859}'), ('\u{1F860}', '\u{1F887}'),
+    ('\u{1F890}', '\u{1F8AD}'), ('\u{1F8B0}', '\u{1F8B1}'), ('\u{1F900}', '\u{1FA53}'), ('\u{1FA60}', '\u{1FA6D}'),
+    ('\u{1FA70}', '\u{1FA74}'), ('\u{1FA78}', '\u{1FA7C}'), ('\u{1FA80}', '\u{1FA86}'), ('\u{1FA90}', '\u{1FAAC}'),
+    ('\u{1FAB0}', '\u{1FABA}'), ('\u{1FAC0}', '\u{1FAC5}'), ('\u{1FAD0}', '\u{1FAD9}'), ('\u{1FAE0}', '\u{1FAE7}'),
+    ('\u{1FAF0}', '\u{1FAF6}'), ('\u{1FB00}', '\u{1FB92}'), ('\u{1FB94}', '\u{1FBCA}'), ('\u{1FBF0}', '\u{1FBF9}'),
+    ('\u{E0001}', '\u{E0001}'), ('\u{E0020}', '\u{E007F}'),
+];
+
+const SC_ZZZZ: &[(char, char)] = &[
+    ('\u{378}', '\u{379}'), ('\u{380}', '\u{383}'), ('\u{38B}', '\u{38B}'), ('\u{38D}', '\u{38D}'),
+    ('\u{3A2}', '\u{3A2}'), ('\u{530}', '\u{530}'), ('\u{557}', '\u{558}'), ('\u{58B}', '\u{58C}'),
+    ('\u{590}', '\u{590}'), ('\u{5C8}', '\u{5CF}'), ('\u{5EB}', '\u{5EE}'), ('\u{5F5}', '\u{5FF}'),
+    ('\u{70E}', '\u{70E}'), ('\u{74B}', '\u{74C}'), ('\u{7B2}', '\u{7BF}'), ('\u{7FB}', '\u{7FC}'),
+    ('\u{82E}', '\u{82F}'), ('\u{83F}', '\u{83F}'), ('\u{85C}', '\u{85D}'), ('\u{85F}', '\u{85F}'),
+    ('\u{86B}', '\u{86F}'), ('\u{88F}', '\u{88F}'), ('\u{892}', '\u{897}'), ('\u{984}', '\u{984}'),
+    ('\u{98D}', '\u{98E}'), ('\u{991}', '\u{992}'), ('\u{9A9}', '\u{9A9}'), ('\u{9B1}', '\u{9B1}'),
+    ('\u{9B3}', '\u{9B5}'), ('\u{9BA}', '\u{9BB}'), ('\u{9C5}', '\u{9C6}'), ('\u{9C9}', '\u{9CA}'),
+    ('\u{9CF}', '\u{9D6}'), ('\u{9D8}', '\u{9DB}'), ('\u{9DE}', '\u{9DE}'), ('\u{9E4}', '\u{9E5}'),
+    ('\u{9FF}', '\u{A00}'), ('\u{A04}', '\u{A04}'), ('\u{A0B}', '\u{A0E}'), ('\u{A11}', '\u{A12}'),
+    ('\u{A29}', '\u{A29}'), ('\u{A31}', '\u{A31}'), ('\u{A34}', '\u{A34}'), ('\u{A37}', '\u{A37}'),
+    ('\u{A3A}', '\u{A3B}'), ('\u{A3D}', '\u{A3D}'), ('\u{A43}', '\u{A46}'), ('\u{A49}', '\u{A4A}'),
+    ('\u{A4E}', '\u{A50}'), ('\u{A52}', '\u{A58}'), ('\u{A5D}', '\u{A5D}'), ('\u{A5F}', '\u{A65}'),
+    ('\u{A77}', '\u{A80}'), ('\u{A84}', '\u{A84}'), ('\u{A8E}', '\u{A8E}'), ('\u{A92}', '\u{A92}'),
+    ('\u{AA9}', '\u{AA9}'), ('\u{AB1}', '\u{AB1}'), ('\u{AB4}', '\u{AB4}'), ('\u{ABA}', '\u{ABB}'),
+    ('\u{AC6}', '\u{AC6}'), ('\u{ACA}', '\u{ACA}'), ('\u{ACE}', '\u{ACF}'), ('\u{AD1}', '\u{ADF}'),
+    ('\u{AE4}', '\u{AE5}'), ('\u{AF2}', '\u{AF8}'), ('\u{B00}', '\u{B00}'), ('\u{B04}', '\u{B04}'),
+    ('\u{B0D}', '\u{B0E}'), ('\u{B11}', '\u{B12}'), ('\u{B29}', '\u{B29}'), ('\u{B31}', '\u{B31}'),
+    ('\u{B34}', '\u{B34}'), ('\u{B3A}', '\u{B3B}'), ('\u{B45}', '\u{B46}'), ('\u{B49}', '\u{B4A}'),
+    ('\u{B4E}', '\u{B54}'), ('\u{B58}', '\u{B5B}'), ('\u{B5E}', '\u{B5E}'), ('\u{B64}', '\u{B65}'),
+    ('\u{B78}', '\u{B81}'), ('\u{B84}', '\u{B84}'), ('\u{B8B}', '\u{B8D}'), ('\u{B91}', '\u{B91}'),
+    ('\u{B96}', '\u{B98}'), ('\u{B9B}', '\u{B9B}'), ('\u{B9D}', '\u{B9D}'), ('\u{BA0}', '\u{BA2}'),
+    ('\u{BA5}', '\u{BA7}'), ('\u{BAB}', '\u{BAD}'), ('\u{BBA}', '\u{BBD}'), ('\u{BC3}', '\u{BC5}'),
+    ('\u{BC9}', '\u{BC9}'), ('\u{BCE}', '\u{BCF}'), ('\u{BD1}', '\u{BD6}'), ('\u{BD8}', '\u{BE5}'),
+    ('\u{BFB}', '\u{BFF}'), ('\u{C0D}', '\u{C0D}'), ('\u{C11}', '\u{C11}'), ('\u{C29}', '\u{C29}'),
+    ('\u{C3A}', '\u{C3B}'), ('\u{C45}', '\u{C45}'), ('\u{C49}', '\u{C49}'), ('\u{C4E}', '\u{C54}'),
+    ('\u{C57}', '\u{C57}'), ('\u{C5B}', '\u{C5C}'), ('\u{C5E}', '\u{C5F}'), ('\u{C64}', '\u{C65}'),
+    ('\u{C70}', '\u{C76}'), ('\u{C8D}', '\u{C8D}'), ('\u{C91}', '\u{C91}'), ('\u{CA9}', '\u{CA9}'),
+    ('\u{CB4}', '\u{CB4}'), ('\u{CBA}', '\u{CBB}'), ('\u{CC5}', '\u{CC5}'), ('\u{CC9}', '\u{CC9}'),
+    ('\u{CCE}', '\u{CD4}'), ('\u{CD7}', '\u{CDC}'), ('\u{CDF}', '\u{CDF}'), ('\u{CE4}', '\u{CE5}'),
+    ('\u{CF0}', '\u{CF0}'), ('\u{CF3}', '\u{CFF}'), ('\u{D0D}', '\u{D0D}'), ('\u{D11}', '\u{D11}'),
+    ('\u{D45}', '\u{D45}'), ('\u{D49}', '\u{D49}'), ('\u{D50}', '\u{D53}'), ('\u{D64}', '\u{D65}'),
+    ('\u{D80}', '\u{D80}'), ('\u{D84}', '\u{D84}'), ('\u{D97}', '\u{D99}'), ('\u{DB2}', '\u{DB2}'),
+    ('\u{DBC}', '\u{DBC}'), ('\u{DBE}', '\u{DBF}'), ('\u{DC7}', '\u{DC9}'), ('\u{DCB}', '\u{DCE}'),
+    ('\u{DD5}', '\u{DD5}'), ('\u{DD7}', '\u{DD7}'), ('\u{DE0}', '\u{DE5}'), ('\u{DF0}', '\u{DF1}'),
+    ('\u{DF5}', '\u{E00}'), ('\u{E3B}', '\u{E3E}'), ('\u{E5C}', '\u{E80}'), ('\u{E83}', '\u{E83}'),
+    ('\u{E85}', '\u{E85}'), ('\u{E8B}', '\u{E8B}'), ('\u{EA4}', '\u{EA4}'), ('\u{EA6}', '\u{EA6}'),
+    ('\u{EBE}', '\u{EBF}'), ('\u{EC5}', '\u{EC5}'), ('\u{EC7}', '\u{EC7}'), ('\u{ECE}', '\u{ECF}'),
+    ('\u{EDA}', '\u{EDB}'), ('\u{EE0}', '\u{EFF}'), ('\u{F48}', '\u{F48}'), ('\u{F6D}', '\u{F70}'),
+    ('\u{F98}', '\u{F98}'), ('\u{FBD}', '\u{FBD}'), ('\u{FCD}', '\u{FCD}'), ('\u{FDB}', '\u{FFF}'),
+    ('\u{10C6}', '\u{10C6}'), ('\u{10C8}', '\u{10CC}'), ('\u{10CE}', '\u{10CF}'), ('\u{1249}', '\u{1249}'),
+    ('\u{124E}', '\u{124F}'), ('\u{1257}', '\u{1257}'), ('\u{1259}', '\u{1259}'), ('\u{125E}', '\u{125F}'),
+    ('\u{1289}', '\u{1289}'), ('\u{128E}', '\u{128F}'), ('\u{12B1}', '\u{12B1}'), ('\u{12B6}', '\u{12B7}'),
+    ('\u{12BF}', '\u{12BF}'), ('\u{12C1}', '\u{12C1}'), ('\u{12C6}', '\u{12C7}'), ('\u{12D7}', '\u{12D7}'),
+    ('\u{1311}', '\u{1311}'), ('\u{1316}', '\u{1317}'), ('\u{135B}', '\u{135C}'), ('\u{137D}', '\u{137F}'),
+    ('\u{139A}', '\u{139F}'), ('\u{13F6}', '\u{13F7}'), ('\u{13FE}', '\u{13FF}'), ('\u{169D}', '\u{169F}'),
+    ('\u{16F9}', '\u{16FF}'), ('\u{1716}', '\u{171E}'), ('\u{1737}', '\u{173F}'), ('\u{1754}', '\u{175F}'),
+    ('\u{176D}', '\u{176D}'), ('\u{1771}', '\u{1771}'), ('\u{1774}', '\u{177F}'), ('\u{17DE}', '\u{17DF}'),
+    ('\u{17EA}', '\u{17EF}'), ('\u{17FA}', '\u{17FF}'), ('\u{181A}', '\u{181F}'), ('\u{1879}', '\u{187F}'),
+    ('\u{18AB}', '\u{18AF}'), ('\u{18F6}', '\u{18FF}'), ('\u{191F}', '\u{191F}'), ('\u{192C}', '\u{192F}'),
+    ('\u{193C}', '\u{193F}'), ('\u{1941}', '\u{1943}'), ('\u{196E}', '\u{196F}'), ('\u{1975}', '\u{197F}'),
+    ('\u{19AC}', '\u{19AF}'), ('\u{19CA}', '\u{19CF}'), ('\u{19DB}', '\u{19DD}'), ('\u{1A1C}', '\u{1A1D}'),
+    ('\u{1A5F}', '\u{1A5F}'), ('\u{1A7D}', '\u{1A7E}'), ('\u{1A8A}', '\u{1A8F}'), ('\u{1A9A}', '\u{1A9F}'),
+    ('\u{1AAE}', '\u{1AAF}'), ('\u{1ACF}', '\u{1AFF}'), ('\u{1B4D}', '\u{1B4F}'), ('\u{1B7F}', '\u{1B7F}'),
+    ('\u{1BF4}', '\u{1BFB}'), ('\u{1C38}', '\u{1C3A}'), ('\u{1C4A}', '\u{1C4C}'), ('\u{1C89}', '\u{1C8F}'),
+    ('\u{1CBB}', '\u{1CBC}'), ('\u{1CC8}', '\u{1CCF}'), ('\u{1CFB}', '\u{1CFF}'), ('\u{1F16}', '\u{1F17}'),
+    ('\u{1F1E}', '\u{1F1F}'), ('\u{1F46}', '\u{1F47}'), ('\u{1F4E}', '\u{1F4F}'), ('\u{1F58}', '\u{1F58}'),
+    ('\u{1F5A}', '\u{1F5A}'), ('\u{1F5C}', '\u{1F5C}'), ('\u{1F5E}', '\u{1F5E}'), ('\u{1F7E}', '\u{1F7F}'),
+    ('\u{1FB5}', '\u{1FB5}'), ('\u{1FC5}', '\u{1FC5}'), ('\u{1FD4}', '\u{1FD5}'), ('\u{1FDC}', '\u{1FDC}'),
+    ('\u{1FF0}', '\u{1FF1}'), ('\u{1FF5}', '\u{1FF5}'), ('\u{1FFF}', '\u{1FFF}'), ('\u{2065}', '\u{2065}'),
+    ('\u{2072}', '\u{2073}'), ('\u{208F}', '\u{208F}'), ('\u{209D}', '\u{209F}'), ('\u{20C1}', '\u{20CF}'),
+    ('\u{20F1}', '\u{20FF}'), ('\u{218C}', '\u{218F}'), ('\u{2427}', '\u{243F}'), ('\u{244B}', '\u{245F}'),
+    ('\u{2B74}', '\u{2B75}'), ('\u{2B96}', '\u{2B96}'), ('\u{2CF4}', '\u{2CF8}'), ('\u{2D26}', '\u{2D26}'),
+    ('\u{2D28}', '\u{2D2C}'), ('\u{2D2E}', '\u{2D2F}'), ('\u{2D68}', '\u{2D6E}'), ('\u{2D71}', '\u{2D7E}'),
+    ('\u{2D97}', '\u{2D9F}'), ('\u{2DA7}', '\u{2DA7}'), ('\u{2DAF}', '\u{2DAF}'), ('\u{2DB7}', '\u{2DB7}'),
+    ('\u{2DBF}', '\u{2DBF}'), ('\u{2DC7}', '\u{2DC7}'), ('\u{2DCF}', '\u{2DCF}'), ('\u{2DD7}', '\u{2DD7}'),
+    ('\u{2DDF}', '\u{2DDF}'), ('\u{2E5E}', '\u{2E7F}'), ('\u{2E9A}', '\u{2E9A}'), ('\u{2EF4}', '\u{2EFF}'),
+    ('\u{2FD6}', '\u{2FEF}'), ('\u{2FFC}', '\u{2FFF}'), ('\u{3040}', '\u{3040}'), ('\u{3097}', '\u{3098}'),
+    ('\u{3100}', '\u{3104}'), ('\u{3130}', '\u{3130}'), ('\u{318F}', '\u{318F}'), ('\u{31E4}', '\u{31EF}'),
+    ('\u{321F}', '\u{321F}'), ('\u{A48D}', '\u{A48F}'), ('\u{A4C7}', '\u{A4CF}'), ('\u{A62C}', '\u{A63F}'),
+    ('\u{A6F8}', '\u{A6FF}'), ('\u{A7CB}', '\u{A7CF}'), ('\u{A7D2}', '\u{A7D2}'), ('\u{A7D4}', '\u{A7D4}'),
+    ('\u{A7DA}', '\u{A7F1}'), ('\u{A82D}', '\u{A82F}'), ('\u{A83A}', '\u{A83F}'), ('\u{A878}', '\u{A87F}'),
+    ('\u{A8C6}', '\u{A8CD}'), ('\u{A8DA}', '\u{A8DF}'), ('\u{A954}', '\u{A95E}'), ('\u{A97D}', '\u{A97F}'),
+    ('\u{A9CE}', '\u{A9CE}'), ('\u{A9DA}', '\u{A9DD}'), ('\u{A9FF}', '\u{A9FF}'), ('\u{AA37}', '\u{AA3F}'),
+    ('\u{AA4E}', '\u{AA4F}'), ('\u{AA5A}', '\u{AA5B}'), ('\u{AAC3}', '\u{AADA}'), ('\u{AAF7}', '\u{AB00}'),
+    ('\u{AB07}', '\u{AB08}'), ('\u{AB0F}', '\u{AB10}'), ('\u{AB17}', '\u{AB1F}'), ('\u{AB27}', '\u{AB27}'),
+    ('\u{AB2F}', '\u{AB2F}'), ('\u{AB6C}', '\u{AB6F}'), ('\u{ABEE}', '\u{ABEF}'), ('\u{ABFA}', '\u{ABFF}'),
+    ('\u{D7A4}', '\u{D7AF}'), ('\u{D7C7}', '\u{D7CA}'), ('\u{D7FC}', '\u{D7FF}'), ('\u{E000}', '\u{F8FF}'),
+    ('\u{FA6E}', '\u{FA6F}'), ('\u{FADA}', '\u{FAFF}'), ('\u{FB07}', '\u{FB12}'), ('\u{FB18}', '\u{FB1C}'),
+    ('\u{FB37}', '\u{FB37}'), ('\u{FB3D}', '\u{FB3D}'), ('\u{FB3F}', '\u{FB3F}'), ('\u{FB42}', '\u{FB42}'),
+    ('\u{FB45}', '\u{FB45}'), ('\u{FBC3}', '\u{FBD2}'), ('\u{FD90}', '\u{FD91}'), ('\u{FDC8}', '\u{FDCE}'),
+    ('\u{FDD0}', '\u{FDEF}'), ('\u{FE1A}', '\u{FE1F}'), ('\u{FE53}', '\u{FE53}'), ('\u{FE67}', '\u{FE67}'),
+    ('\u{FE6C}', '\u{FE6F}'), ('\u{FE75}', '\u{FE75}'), ('\u{FEFD}', '\u{FEFE}'), ('\u{FF00}', '\u{FF00}'),
+    ('\u{FFBF}', '\u{FFC1}'), ('\u{FFC8}', '\u{FFC9}'), ('\u{FFD0}', '\u{FFD1}'), ('\u{FFD8}', '\u{FFD9}'),
+    ('\u{FFDD}', '\u{FFDF}'), ('\u{FFE7}', '\u{FFE7}'), ('\u{FFEF}', '\u{FFF8}'), ('\u{FFFE}', '\u{FFFF}'),
+    ('\u{1000C}', '\u{1000C}'), ('\u{10027}', '\u{10027}'), ('\u{1003B}', '\u{1003B}'), ('\u{1003E}', '\u{1003E}'),
+    ('\u{1004E}', '\u{1004F}'), ('\u{1005E}', '\u{1007F}'), ('\u{100FB}', '\u{100FF}'), ('\u{10103}', '\u{10106}'),
+    ('\u{10134}', '\u{10136}'), ('\u{1018F}', '\u{1018F}'), ('\u{1019D}', '\u{1019F}'), ('\u{101A1}', '\u{101CF}'),
+    ('\u{101FE}', '\u{1027F}'), ('\u{1029D}', '\u{1029F}'), ('\u{102D1}', '\u{102DF}'), ('\u{102FC}', '\u{102FF}'),
+    ('\u{10324}', '\u{1032C}'), ('\u{1034B}', '\u{1034F}'), ('\u{1037B}', '\u{1037F}'), ('\u{1039E}', '\u{1039E}'),
+    ('\u{103C4}', '\u{103C7}'), ('\u{103D6}', '\u{103FF}'), ('\u{1049E}', '\u{1049F}'), ('\u{104AA}', '\u{104AF}'),
+    ('\u{104D4}', '\u{104D7}'), ('\u{104FC}', '\u{104FF}'), ('\u{10528}', '\u{1052F}'), ('\u{10564}', '\u{1056E}'),
+    ('\u{1057B}', '\u{1057B}'), ('\u{1058B}', '\u{1058B}'), ('\u{10593}', '\u{10593}'), ('\u{10596}', '\u{10596}'),
+    ('\u{105A2}', '\u{105A2}'), ('\u{105B2}', '\u{105B2}'), ('\u{105BA}', '\u{105BA}'), ('\u{105BD}', '\u{105FF}'),
+    ('\u{10737}', '\u{1073F}'), ('\u{10756}', '\u{1075F}'), ('\u{10768}', '\u{1077F}'), ('\u{10786}', '\u{10786}'),
+    ('\u{107B1}', '\u{107B1}'), ('\u{107BB}', '\u{107FF}'), ('\u{10806}', '\u{10807}'), ('\u{10809}', '\u{10809}'),
+    ('\u{10836}', '\u{10836}'), ('\u{10839}', '\u{1083B}'), ('\u{1083D}', '\u{1083E}'), ('\u{10856}', '\u{10856}'),
+    ('\u{1089F}', '\u{108A6}'), ('\u{108B0}', '\u{108DF}'), ('\u{108F3}', '\u{108F3}'), ('\u{108F6}', '\u{108FA}'),
+    ('\u{1091C}', '\u{1091E}'), ('\u{1093A}', '\u{1093E}'), ('\u{10940}', '\u{1097F}'), ('\u{109B8}', '\u{109BB}'),
+    ('\u{109D0}', '\u{109D1}'), ('\u{10A04}', '\u{10A04}'), ('\u{10A07}', '\u{10A0B}'), ('\u{10A14}', '\u{10A14}'),
+    ('\u{10A18}', '\u{10A18}'), ('\u{10A36}', '\u{10A37}'), ('\u{10A3B}', '\u{10A3E}'), ('\u{10A49}', '\u{10A4F}'),
+    ('\u{10A59}', '\u{10A5F}'), ('\u{10AA0}', '\u{10ABF}'), ('\u{10AE7}', '\u{10AEA}'), ('\u{10AF7}', '\u{10AFF}'),
+    ('\u{10B36}', '\u{10B38}'), ('\u{10B56}', '\u{10B57}'), ('\u{10B73}', '\u{10B77}'), ('\u{10B92}', '\u{10B98}'),
+    ('\u{10B9D}', '\u{10BA8}'), ('\u{10BB0}', '\u{10BFF}'), ('\u{10C49}', '\u{10C7F}'), ('\u{10CB3}', '\u{10CBF}'),
+    ('\u{10CF3}', '\u{10CF9}'), ('\u{10D28}', '\u{10D2F}'), ('\u{10D3A}', '\u{10E5F}'), ('\u{10E7F}', '\u{10E7F}'),
+    ('\u{10EAA}', '\u{10EAA}'), ('\u{10EAE}', '\u{10EAF}'), ('\u{10EB2}', '\u{10EFF}'), ('\u{10F28}', '\u{10F2F}'),
+    ('\u{10F5A}', '\u{10F6F}'), ('\u{10F8A}', '\u{10FAF}'), ('\u{10FCC}', '\u{10FDF}'), ('\u{10FF7}', '\u{10FFF}'),
+    ('\u{1104E}', '\u{11051}'), ('\u{11076}', '\u{1107E}'), ('\u{110C3}', '\u{110CC}'), ('\u{110CE}', '\u{110CF}'),
+    ('\u{110E9}', '\u{110EF}'), ('\u{110FA}', '\u{110FF}'), ('\u{11135}', '\u{11135}'), ('\u{11148}', '\u{1114F}'),
+    ('\u{11177}', '\u{1117F}'), ('\u{111E0}', '\u{111E0}'), ('\u{111F5}', '\u{111FF}'), ('\u{11212}', '\u{11212}'),
+    ('\u{1123F}', '\u{1127F}'), ('\u{11287}', '\u{11287}'), ('\u{11289}', '\u{11289}'), ('\u{1128E}', '\u{1128E}'),
+    ('\u{1129E}', '\u{1129E}'), ('\u{112AA}', '\u{112AF}'), ('\u{112EB}', '\u{112EF}'), ('\u{112FA}', '\u{112FF}'),
+    ('\u{11304}', '\u{11304}'), ('\u{1130D}', '\u{1130E}'), ('\u{11311}', '\u{11312}'), ('\u{11329}', '\u{11329}'),
+    ('\u{11331}', '\u{11331}'), ('\u{11334}', '\u{11334}'), ('\u{1133A}', '\u{1133A}'), ('\u{11345}', '\u{11346}'),
+    ('\u{11349}', '\u{1134A}'), ('\u{1134E}', '\u{1134F}'), ('\u{11351}', '\u{11356}'), ('\u{11358}', '\u{1135C}'),
+    ('\u{11364}', '\u{11365}'), ('\u{1136D}', '\u{1136F}'), ('\u{11375}', '\u{113FF}'), ('\u{1145C}', '\u{1145C}'),
+    ('\u{11462}', '\u{1147F}'), ('\u{114C8}', '\u{114CF}'), ('\u{114DA}', '\u{1157F}'), ('\u{115B6}', '\u{115B7}'),
+    ('\u{115DE}', '\u{115FF}'), ('\u{11645}', '\u{1164F}'), ('\u{1165A}', '\u{1165F}'), ('\u{1166D}', '\u{1167F}'),
+    ('\u{116BA}', '\u{116BF}'), ('\u{116CA}', '\u{116FF}'), ('\u{1171B}', '\u{1171C}'), ('\u{1172C}', '\u{1172F}'),
+    ('\u{11747}', '\u{117FF}'), ('\u{1183C}', '\u{1189F}'), ('\u{118F3}', '\u{118FE}'), ('\u{11907}', '\u{11908}'),
+    ('\u{1190A}', '\u{1190B}'), ('\u{11914}', '\u{11914}'), ('\u{11917}', '\u{11917}'), ('\u{11936}', '\u{11936}'),
+    ('\u{11939}', '\u{1193A}'), ('\u{11947}', '\u{1194F}'), ('\u{1195A}', '\u{1199F}'), ('\u{119A8}', '\u{119A9}'),
+    ('\u{119D8}', '\u{119D9}'), ('\u{119E5}', '\u{119FF}'), ('\u{11A48}', '\u{11A4F}'), ('\u{11AA3}', '\u{11AAF}'),
+    ('\u{11AF9}', '\u{11BFF}'), ('\u{11C09}', '\u{11C09}'), ('\u{11C37}', '\u{11C37}'), ('\u{11C46}', '\u{11C4F}'),
+    ('\u{11C6D}', '\u{11C6F}'), ('\u{11C90}', '\u{11C91}'), ('\u{11CA8}', '\u{11CA8}'), ('\u{11CB7}', '\u{11CFF}'),
+    ('\u{11D07}', '\u{11D07}'), ('\u{11D0A}', '\u{11D0A}'), ('\u{11D37}', '\u{11D39}'), ('\u{11D3B}', '\u{11D3B}'),
+    ('\u{11D3E}', '\u{11D3E}'), ('\u{11D48}', '\u{11D4F}'), ('\u{11D5A}', '\u{11D5F}'), ('\u{11D66}', '\u{11D66}'),
+    ('\u{11D69}', '\u{11D69}'), ('\u{11D8F}', '\u{11D8F}'), ('\u{11D92}', '\u{11D92}'), ('\u{11D99}', '\u{11D9F}'),
+    ('\u{11DAA}', '\u{11EDF}'), ('\u{11EF9}', '\u{11FAF}'), ('\u{11FB1}', '\u{11FBF}'), ('\u{11FF2}', '\u{11FFE}'),
+    ('\u{1239A}', '\u{123FF}'), ('\u{1246F}', '\u{1246F}'), ('\u{12475}', '\u{1247F}'), ('\u{12544}', '\u{12F8F}'),
+    ('\u{12FF3}', '\u{12FFF}'), ('\u{1342F}', '\u{1342F}'), ('\u{13439}', '\u{143FF}'), ('\u{14647}', '\u{167FF}'),
+    ('\u{16A39}', '\u{16A3F}'), ('\u{16A5F}', '\u{16A5F}'), ('\u{16A6A}', '\u{16A6D}'), ('\u{16ABF}', '\u{16ABF}'),
+    ('\u{16ACA}', '\u{16ACF}'), ('\u{16AEE}', '\u{16AEF}'), ('\u{16AF6}', '\u{16AFF}'), ('\u{16B46}', '\u{16B4F}'),
+    ('\u{16B5A}', '\u{16B5A}'), ('\u{16B62}', '\u{16B62}'), ('\u{16B78}', '\u{16B7C}'), ('\u{16B90}', '\u{16E3F}'),
+    ('\u{16E9B}', '\u{16EFF}'), ('\u{16F4B}', '\u{16F4E}'), ('\u{16F88}', '\u{16F8E}'), ('\u{16FA0}', '\u{16FDF}'),
+    ('\u{16FE5}', '\u{16FEF}'), ('\u{16FF2}', '\u{16FFF}'), ('\u{187F8}', '\u{187FF}'), ('\u{18CD6}', '\u{18CFF}'),
+    ('\u{18D09}', '\u{1AFEF}'), ('\u{1AFF4}', '\u{1AFF4}'), ('\u{1AFFC}', '\u{1AFFC}'), ('\u{1AFFF}', '\u{1AFFF}'),
+    ('\u{1B123}', '\u{1B14F}'), ('\u{1B153}', '\u{1B163}'), ('\u{1B168}', '\u{1B16F}'), ('\u{1B2FC}', '\u{1BBFF}'),
+    ('\u{1BC6B}', '\u{1BC6F}'), ('\u{1BC7D}', '\u{1BC7F}'), ('\u{1BC89}', '\u{1BC8F}'), ('\u{1BC9A}', '\u{1BC9B}'),
+    ('\u{1BCA4}', '\u{1CEFF}'), ('\u{1CF2E}', '\u{1CF2F}'), ('\u{1CF47}', '\u{1CF4F}'), ('\u{1CFC4}', '\u{1CFFF}'),
+    ('\u{1D0F6}', '\u{1D0FF}'), ('\u{1D127}', '\u{1D128}'), ('\u{1D1EB}', '\u{1D1FF}'), ('\u{1D246}', '\u{1D2DF}'),
+    ('\u{1D2F4}', '\u{1D2FF}'), ('\u{1D357}', '\u{1D35F}'), ('\u{1D379}', '\u{1D3FF}'), ('\u{1D455}', '\u{1D455}'),
+    ('\u{1D49D}', '\u{1D49D}'), ('\u{1D4A0}', '\u{1D4A1}'), ('\u{1D4A3}', '\u{1D4A4}'), ('\u{1D4A7}', '\u{1D4A8}'),
+    ('\u{1D4AD}', '\u{1D4AD}'), ('\u{1D4BA}', '\u{1D4BA}'), ('\u{1D4BC}', '\u{1D4BC}'), ('\u{1D4C4}', '\u{1D4C4}'),
+    ('\u{1D506}', '\u{1D506}'), ('\u{1D50B}', '\u{1D50C}'), ('\u{1D515}', '\u{1D515}'), ('\u{1D51D}', '\u{1D51D}'),
+    ('\u{1D53A}', '\u{1D53A}'), ('\u{1D53F}', '\u{1D53F}'), ('\u{1D545}', '\u{1D545}'), ('\u{1D547}', '\u{1D549}'),
+    ('\u{1D551}', '\u{1D551}'), ('\u{1D6A6}', '\u{1D6A7}'), ('\u{1D7CC}', '\u{1D7CD}'), ('\u{1DA8C}', '\u{1DA9A}'),
+    ('\u{1DAA0}', '\u{1DAA0}'), ('\u{1DAB0}', '\u{1DEFF}'), ('\u{1DF1F}', '\u{1DFFF}'), ('\u{1E007}', '\u{1E007}'),
+    ('\u{1E019}', '\u{1E01A}'), ('\u{1E022}', '\u{1E022}'), ('\u{1E025}', '\u{1E025}'), ('\u{1E02B}', '\u{1E0FF}'),
+    ('\u{1E12D}', '\u{1E12F}'), ('\u{1E13E}', '\u{1E13F}'), ('\u{1E14A}', '\u{1E14D}'), ('\u{1E150}', '\u{1E28F}'),
+    ('\u{1E2AF}', '\u{1E2BF}'), ('\u{1E2FA}', '\u{1E2FE}'), ('\u{1E300}', '\u{1E7DF}'), ('\u{1E7E7}', '\u{1E7E7}'),
+    ('\u{1E7EC}', '\u{1E7EC}'), ('\u{1E7EF}', '\u{1E7EF}'), ('\u{1E7FF}', '\u{1E7FF}'), ('\u{1E8C5}', '\u{1E8C6}'),
+    ('\u{1E8D7}', '\u{1E8FF}'), ('\u{1E94C}', '\u{1E94F}'), ('\u{1E95A}', '\u{1E95D}'), ('\u{1E960}', '\u{1EC70}'),
+    ('\u{1ECB5}', '\u{1ED00}'), ('\u{1ED3E}', '\u{1EDFF}'), ('\u{1EE04}', '\u{1EE04}'), ('\u{1EE20}', '\u{1EE20}'),
+    ('\u{1EE23}', '\u{1EE23}'), ('\u{1EE25}', '\u{1EE26}'), ('\u{1EE28}', '\u{1EE28}'), ('\u{1EE33}', '\u{1EE33}'),
+    ('\u{1EE38}', '\u{1EE38}'), ('\u{1EE3A}', '\u{1EE3A}'), ('\u{1EE3C}', '\u{1EE41}'), ('\u{1EE43}', '\u{1EE46}'),
+    ('\u{1EE48}', '\u{1EE48}'), ('\u{1EE4A}', '\u{1EE4A}'), ('\u{1EE4C}', '\u{1EE4C}'), ('\u{1EE50}', '\u{1EE50}'),
+    ('\u{1EE53}', '\u{1EE53}'), ('\u{1EE55}', '\u{1EE56}'), ('\u{1EE58}', '\u{1EE58}'), ('\u{1EE5A}', '\u{1EE5A}'),
+    ('\u{1EE5C}', '\u{1EE5C}'), ('\u{1EE5E}', '\u{1EE5E}'), ('\u{1EE60}', '\u{1EE60}'), ('\u{1EE63}', '\u{1EE63}'),
+    ('\u{1EE65}', '\u{1EE66}'), ('\u{1EE6B}', '\u{1EE6B}'), ('\u{1EE73}', '\u{1EE73}'), ('\u{1EE78}', '\u{1EE78}'),
+    ('\u{1EE7D}', '\u{1EE7D}'), ('\u{1EE7F}', '\u{1EE7F}'), ('\u{1EE8A}', '\u{1EE8A}'), ('\u{1EE9C}', '\u{1EEA0}'),
+    ('\u{1EEA4}', '\u{1EEA4}'), ('\u{1EEAA}', '\u{1EEAA}'), ('\u{1EEBC}', '\u{1EEEF}'), ('\u{1EEF2}', '\u{1EFFF}'),
+    ('\u{1F02C}', '\u{1F02F}'), ('\u{1F094}', '\u{1F09F}'), ('\u{1F0AF}', '\u{1F0B0}'), ('\u{1F0C0}', '\u{1F0C0}'),
+    ('\u{1F0D0}', '\u{1F0D0}'), ('\u{1F0F6}', '\u{1F0FF}'), ('\u{1F1AE}', '\u{1F1E5}'), ('\u{1F203}', '\u{1F20F}'),
+    ('\u{1F23C}', '\u{1F23F}'), ('\u{1F249}', '\u{1F24F}'), ('\u{1F252}', '\u{1F25F}'), ('\u{1F266}', '\u{1F2FF}'),
+    ('\u{1F6D8}', '\u{1F6DC}'), ('\u{1F6ED}', '\u{1F6EF}'), ('\u{1F6FD}', '\u{1F6FF}'), ('\u{1F774}', '\u{1F77F}'),
+    ('\u{1F7D9}', '\u{1F7DF}'), ('\u{1F7EC}', '\u{1F7EF}'), ('\u{1F7F1}', '\u{1F7FF}'), ('\u{1F80C}', '\u{1F80F}'),
+    ('\u{1F848}', '\u{1F84F}'), ('\u{1F85A}', '\u{1F85F}'), ('\u{1F888}', '\u{1F88F}'), ('\u{1F8AE}', '\u{1F8AF}'),
+    ('\u{1F8B2}', '\u{1F8FF}'), ('\u{1FA54}', '\u{1FA5F}'), ('\u{1FA6E}', '\u{1FA6F}'), ('\u{1FA75}', '\u{1FA77}'),
+    ('\u{1FA7D}', '\u{1FA7F}'), ('\u{1FA87}', '\u{1FA8F}'), ('\u{1FAAD}', '\u{1FAAF}'), ('\u{1FABB}', '\u{1FABF}'),
+    ('\u{1FAC6}', '\u{1FACF}'), ('\u{1FADA}', '\u{1FADF}'), ('\u{1FAE8}', '\u{1FAEF}'), ('\u{1FAF7}', '\u{1FAFF}'),
+    ('\u{1FB93}', '\u{1FB93}'), ('\u{1FBCB}', '\u{1FBEF}'), ('\u{1FBFA}', '\u{1FFFF}'), ('\u{2A6E0}', '\u{2A6FF}'),
+    ('\u{2B739}', '\u{2B73F}'), ('\u{2B81E}', '\u{2B81F}'), ('\u{2CEA2}', '\u{2CEAF}'), ('\u{2EBE1}', '\u{2F7FF}'),
+    ('\u{2FA1E}', '\u{2FFFF}'), ('\u{3134B}', '\u{E0000}'), ('\u{E0002}', '\u{E001F}'), ('\u{E0080}', '\u{E00FF}'),
+    ('\u{E01F0}', '\u{10FFFF}'),
+];
+
+const EMOJI: &[(char, char)] = &[
+    ('\u{23}', '\u{23}'), ('\u{2A}', '\u{2A}'), ('\u{30}', '\u{39}'), ('\u{A9}', '\u{A9}'),
+    ('\u{AE}', '\u{AE}'), ('\u{203C}', '\u{203C}'), ('\u{2049}', '\u{2049}'), ('\u{2122}', '\u{2122}'),
+    ('\u{2139}', '\u{2139}'), ('\u{2194}', '\u{2199}'), ('\u{21A9}', '\u{21AA}'), ('\u{231A}', '\u{231B}'),
+    ('\u{2328}', '\u{2328}'), ('\u{23CF}', '\u{23CF}'), ('\u{23E9}', '\u{23F3}'), ('\u{23F8}', '\u{23FA}'),
+    ('\u{24C2}', '\u{24C2}'), ('\u{25AA}', '\u{25AB}'), ('\u{25B6}', '\u{25B6}'), ('\u{25C0}', '\u{25C0}'),
+    ('\u{25FB}', '\u{25FE}'), ('\u{2600}', '\u{2604}'), ('\u{260E}', '\u{260E}'), ('\u{2611}', '\u{2611}'),
+    ('\u{2614}', '\u{2615}'), ('\u{2618}', '\u{2618}'), ('\u{261D}', '\u{261D}'), ('\u{2620}', '\u{2620}'),
+    ('\u{2622}', '\u{2623}'), ('\u{2626}', '\u{2626}'), ('\u{262A}', '\u{262A}'), ('\u{262E}', '\u{262F}'),
+    ('\u{2638}', '\u{263A}'), ('\u{2640}', '\u{2640}'), ('\u{2642}', '\u{2642}'), ('\u{2648}', '\u{2653}'),
+    ('\u{265F}', '\u{2660}'), ('\u{2663}', '\u{2663}'), ('\u{2665}', '\u{2666}'), ('\u{2668}', '\u{2668}'),
+    ('\u{267B}', '\u{267B}'), ('\u{267E}', '\u{267F}'), ('\u{2692}', '\u{2697}'), ('\u{2699}', '\u{2699}'),
+    ('\u{269B}', '\u{269C}'), ('\u{26A0}', '\u{26A1}'), ('\u{26A7}', '\u{26A7}'), ('\u{26AA}', '\u{26AB}'),
+    ('\u{26B0}', '\u{26B1}'), ('\u{26BD}', '\u{26BE}'), ('\u{26C4}', '\u{26C5}'), ('\u{26C8}', '\u{26C8}'),
+    ('\u{26CE}', '\u{26CF}'), ('\u{26D1}', '\u{26D1}'), ('\u{26D3}', '\u{26D4}'), ('\u{26E9}', '\u{26EA}'),
+    ('\u{26F0}', '\u{26F5}'), ('\u{26F7}', '\u{26FA}'), ('\u{26FD}', '\u{26FD}'), ('\u{2702}', '\u{2702}'),
+    ('\u{2705}', '\u{2705}'), ('\u{2708}', '\u{270D}'), ('\u{270F}', '\u{270F}'), ('\u{2712}', '\u{2712}'),
+    ('\u{2714}', '\u{2714}'), ('\u{2716}', '\u{2716}'), ('\u{271D}', '\u{271D}'), ('\u{2721}', '\u{2721}'),
+    ('\u{2728}', '\u{2728}'), ('\u{2733}', '\u{2734}'), ('\u{2744}', '\u{2744}'), ('\u{2747}', '\u{2747}'),
+    ('\u{274C}', '\u{274C}'), ('\u{274E}', '\u{274E}'), ('\u{2753}', '\u{2755}'), ('\u{2757}', '\u{2757}'),
+    ('\u{2763}', '\u{2764}'), ('\u{2795}', '\u{2797}'), ('\u{27A1}', '\u{27A1}'), ('\u{27B0}', '\u{27B0}'),
+    ('\u{27BF}', '\u{27BF}'), ('\u{2934}', '\u{2935}'), ('\u{2B05}', '\u{2B07}'), ('\u{2B1B}', '\u{2B1C}'),
+    ('\u{2B50}', '\u{2B50}'), ('\u{2B55}', '\u{2B55}'), ('\u{3030}', '\u{3030}'), ('\u{303D}', '\u{303D}'),
+    ('\u{3297}', '\u{3297}'), ('\u{3299}', '\u{3299}'), ('\u{1F004}', '\u{1F004}'), ('\u{1F0CF}', '\u{1F0CF}'),
+    ('\u{1F170}', '\u{1F171}'), ('\u{1F17E}', '\u{1F17F}'), ('\u{1F18E}', '\u{1F18E}'), ('\u{1F191}', '\u{1F19A}'),
+    ('\u{1F1E6}', '\u{1F1FF}'), ('\u{1F201}', '\u{1F202}'), ('\u{1F21A}', '\u{1F21A}'), ('\u{1F22F}', '\u{1F22F}'),
+    ('\u{1F232}', '\u{1F23A}'), ('\u{1F250}', '\u{1F251}'), ('\u{1F300}', '\u{1F321}'), ('\u{1F324}', '\u{1F393}'),
+    ('\u{1F396}', '\u{1F397}'), ('\u{1F399}', '\u{1F39B}'), ('\u{1F39E}', '\u{1F3F0}'), ('\u{1F3F3}', '\u{1F3F5}'),
+    ('\u{1F3F7}', '\u{1F4FD}'), ('\u{1F4FF}', '\u{1F53D}'), ('\u{1F549}', '\u{1F54E}'), ('\u{1F550}', '\u{1F567}'),
+    ('\u{1F56F}', '\u{1F570}'), ('\u{1F573}', '\u{1F57A}'), ('\u{1F587}', '\u{1F587}'), ('\u{1F58A}', '\u{1F58D}'),
+    ('\u{1F590}', '\u{1F590}'), ('\u{1F595}', '\u{1F596}'), ('\u{1F5A4}', '\u{1F5A5}'), ('\u{1F5A8}', '\u{1F5A8}'),
+    ('\u{1F5B1}', '\u{1F5B2}'), ('\u{1F5BC}', '\u{1F5BC}'), ('\u{1F5C2}', '\u{1F5C4}'), ('\u{1F5D1}', '\u{1F5D3}'),
+    ('\u{1F5DC}', '\u{1F5DE}'), ('\u{1F5E1}', '\u{1F5E1}'), ('\u{1F5E3}', '\u{1F5E3}'), ('\u{1F5E8}', '\u{1F5E8}'),
+    ('\u{1F5EF}', '\u{1F5EF}'), ('\u{1F5F3}', '\u{1F5F3}'), ('\u{1F5FA}', '\u{1F64F}'), ('\u{1F680}', '\u{1F6C5}'),
+    ('\u{1F6CB}', '\u{1F6D2}'), ('\u{1F6D5}', '\u{1F6D7}'), ('\u{1F6DD}', '\u{1F6E5}'), ('\u{1F6E9}', '\u{1F6E9}'),
+    ('\u{1F6EB}', '\u{1F6EC}'), ('\u{1F6F0}', '\u{1F6F0}'), ('\u{1F6F3}', '\u{1F6FC}'), ('\u{1F7E0}', '\u{1F7EB}'),
+    ('\u{1F7F0}', '\u{1F7F0}'), ('\u{1F90C}', '\u{1F93A}'), ('\u{1F93C}', '\u{1F945}'), ('\u{1F947}', '\u{1F9FF}'),
+    ('\u{1FA70}', '\u{1FA74}'), ('\u{1FA78}', '\u{1FA7C}'), ('\u{1FA80}', '\u{1FA86}'), ('\u{1FA90}', '\u{1FAAC}'),
+    ('\u{1FAB0}', '\u{1FABA}'), ('\u{1FAC0}', '\u{1FAC5}'), ('\u{1FAD0}', '\u{1FAD9}'), ('\u{1FAE0}', '\u{1FAE7}'),
+    ('\u{1FAF0}', '\u{1FAF6}'),
+];
@@ -1,186 +1,322 @@
 use crate::class::Class;
+use crate::regex_error::ErrorKind;
 use crate::regex_rep::RegexRep;
-use crate::regex_step::RegexStep;
-use crate::regex_val::RegexVal;
-use std::collections::VecDeque;
-use std::usize::MAX;
+use crate::syntax::ast::{AstKind, Bracket, BracketItem};
+use crate::unicode::property_ranges;
 
-pub fn handle_backslash(
-    char_iter: &mut std::str::Chars,
-) -> Result<Option<RegexStep>, &'static str> {
-    let mut peekable_iter = char_iter.peekable();
-    if let Some(&next) = peekable_iter.peek() {
-        peekable_iter.next();
-        // Avanzar el iterador después de procesar el backslash
-        if peekable_iter.peek().is_some() {
-            peekable_iter.next();
+/// Parses an escape sequence; the `\` has already been consumed.
+///
+/// `\d`, `\w` and `\s` match the `Digit`, `Word` and `Space` classes and their uppercase forms
+/// match everything else. `\p{name}` (or `\pL` for a one-letter name) matches a Unicode
+/// property and `\P{name}` its complement. `\t`, `\n`, `\r`, `\f` and `\v` are control
+/// characters, `\xHH` and `\u{H...}` are code points in hex, and any other non-alphanumeric
+/// character stands for itself, e.g. `\.` or `\[`.
+///
+/// # Arguments
+///
+/// * `char_iter` - The pattern iterator, positioned after the `\`. Only the characters of the
+///   escape are consumed.
+///
+/// # Returns
+///
+/// The syntax node for the escape, or an error if it is incomplete or unknown.
+pub fn handle_backslash(char_iter: &mut std::str::Chars) -> Result<AstKind, ErrorKind> {
+    let class = |class, negated| AstKind::Class { class, negated };
+    let kind = match char_iter.next() {
+        None => return Err(ErrorKind::TrailingBackslash),
+        Some('d') => class(Class::Digit, false),
+        Some('D') => class(Class::Digit, true),
+        Some('w') => class(Class::Word, false),
+        Some('W') => class(Class::Word, true),
+        Some('s') => class(Class::Space, false),
+        Some('S') => class(Class::Space, true),
+        Some('t') => AstKind::Literal('\t'),
+        Some('n') => AstKind::Literal('\n'),
+        Some('r') => AstKind::Literal('\r'),
+        Some('f') => AstKind::Literal('\x0C'),
+        Some('v') => AstKind::Literal('\x0B'),
+        Some('x') => {
+            let digits: String = char_iter.by_ref().take(2).collect();
+            if digits.len() != 2 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(ErrorKind::InvalidEscape);
+            }
+            AstKind::Literal(code_point(&digits)?)
         }
-        Ok(Some(RegexStep {
-            rep: RegexRep::Exact(1),
-            val: RegexVal::Literal(next),
-        }))
-    } else {
-        Err("'\\' inesperado")
-    }
-}
-
-pub fn check_min_max(min: Option<usize>, max: Option<usize>, counter: usize) -> bool {
-    match min {
-        Some(min) => match max {
-            Some(max) => !(counter < min || counter > max),
-            None => {
-                if counter > min {
-                    return true;
+        Some('u') => {
+            if char_iter.next() != Some('{') {
+                return Err(ErrorKind::InvalidEscape);
+            }
+            let mut digits = String::new();
+            loop {
+                match char_iter.next() {
+                    Some('}') if !digits.is_empty() => break,
+                    Some(c) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+                    _ => return Err(ErrorKind::InvalidEscape),
                 }
-                false
             }
+            AstKind::Literal(code_point(&digits)?)
+        }
+        Some(c @ ('p' | 'P')) => AstKind::Property {
+            name: unicode_property(char_iter)?,
+            negated: c == 'P',
         },
-        None => false,
-    }
+        Some(c) if c.is_ascii_alphanumeric() => return Err(ErrorKind::UnknownEscape),
+        Some(c) => AstKind::Literal(c),
+    };
+    Ok(kind)
 }
 
-pub fn handle_brackets(char_iter: &mut std::str::Chars) -> Result<RegexStep, &'static str> {
-    let mut chars = Vec::new();
-    let mut negate = false;
-    let mut closed = false;
-    let mut is_class = false;
-    let mut val = RegexVal::Literal(' ');
-
-    while let Some(ch) = char_iter.next() {
-        match ch {
-            ']' => {
-                closed = true;
-                break;
-            }
-            '^' => negate = true,
-            '[' => {
-                if char_iter.next() == Some(':') {
-                    let mut class_name = String::new();
-                    for name_ch in char_iter.by_ref() {
-                        if name_ch == ':' {
-                            is_class = true;
-                            break;
-                        }
-                        class_name.push(name_ch);
-                    }
-                    val = match class_name.as_str() {
-                        "alnum" => RegexVal::Class(Class::Alnum),
-                        "alpha" => RegexVal::Class(Class::Alpha),
-                        "digit" => RegexVal::Class(Class::Digit),
-                        "lower" => RegexVal::Class(Class::Lower),
-                        "upper" => RegexVal::Class(Class::Upper),
-                        "space" => RegexVal::Class(Class::Space),
-                        "punct" => RegexVal::Class(Class::Punct),
-                        _ => return Err("Clase de caracteres desconocida"),
-                    };
-                    char_iter.next();
+/// Reads the name of a `\p` or `\P` escape, either a single letter or a `{name}`, and checks
+/// that it is a known property.
+fn unicode_property(char_iter: &mut std::str::Chars) -> Result<String, ErrorKind> {
+    let name = match char_iter.next() {
+        Some('{') => {
+            let mut name = String::new();
+            loop {
+                match char_iter.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(ErrorKind::InvalidProperty),
                 }
             }
-            _ => chars.push(ch),
+            name
         }
+        Some(c) if c.is_ascii_alphabetic() => c.to_string(),
+        _ => return Err(ErrorKind::InvalidProperty),
+    };
+    match property_ranges(&name, false) {
+        Some(_) => Ok(name),
+        None => Err(ErrorKind::UnknownProperty),
     }
+}
 
-    if !closed {
-        return Err("No closing bracket found");
+/// Converts the hex digits of an escape into a character.
+fn code_point(digits: &str) -> Result<char, ErrorKind> {
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or(ErrorKind::InvalidEscape)
+}
+
+/// Parses a bracket expression; the opening `[` has already been consumed.
+///
+/// Follows the POSIX rules: a `^` right after the `[` negates the set, a `]` is a member when it
+/// comes first (after the optional `^`), and a `-` is a member when it comes first or last and
+/// forms a range anywhere else. `[:class:]` adds a character class, `[.c.]` and `[=c=]` stand
+/// for the character `c`. The escapes that stand for a set of characters, `\d`, `\w`, `\s`,
+/// their uppercase forms and `\p{...}`/`\P{...}`, add the same characters as outside brackets,
+/// so `[\d_]` matches a digit or an underscore. Any other backslash is a literal `\`.
+///
+/// # Arguments
+///
+/// * `char_iter` - The pattern iterator, positioned after the `[`.
+///
+/// # Returns
+///
+/// The members of the expression, or an error if it is not closed, has an unknown class or a
+/// range whose end comes before its start.
+pub fn handle_brackets(char_iter: &mut std::str::Chars) -> Result<Bracket, ErrorKind> {
+    let mut items = Vec::new();
+    let negated = char_iter.clone().next() == Some('^');
+    if negated {
+        char_iter.next();
     }
-    if !is_class {
-        val = if negate {
-            RegexVal::NegatedBracket(chars)
-        } else {
-            RegexVal::Bracket(chars)
+
+    let mut first = true;
+    loop {
+        let start = match bracket_member(char_iter, first)? {
+            BracketMember::End => break,
+            BracketMember::Item(item) => {
+                items.push(item);
+                first = false;
+                continue;
+            }
+            BracketMember::Char(c) => c,
         };
+        first = false;
+
+        let mut lookahead = char_iter.clone();
+        if lookahead.next() == Some('-') && !matches!(lookahead.next(), Some(']') | None) {
+            char_iter.next();
+            let end = match bracket_member(char_iter, false)? {
+                BracketMember::Char(c) => c,
+                _ => return Err(ErrorKind::InvalidBracketRange),
+            };
+            if end < start {
+                return Err(ErrorKind::InvalidBracketRange);
+            }
+            items.push(BracketItem::Range(start, end));
+        } else {
+            items.push(BracketItem::Char(start));
+        }
     }
-    Ok(RegexStep {
-        rep: RegexRep::Exact(1),
-        val,
-    })
+
+    Ok(Bracket { negated, items })
 }
 
-pub fn handle_anchoring_start(queue: &mut VecDeque<RegexStep>) -> bool {
-    let mut anchor = false;
-    if let Some(first_step) = queue.front() {
-        if let RegexVal::Literal('^') = first_step.val {
-            anchor = true;
-            queue.pop_front();
+enum BracketMember {
+    /// A character, which may start a range.
+    Char(char),
+    /// A class or a property.
+    Item(BracketItem),
+    /// The closing `]`.
+    End,
+}
+
+/// Reads the next member of a bracket expression.
+fn bracket_member(
+    char_iter: &mut std::str::Chars,
+    first: bool,
+) -> Result<BracketMember, ErrorKind> {
+    let c = char_iter.next().ok_or(ErrorKind::UnclosedBracket)?;
+    let delimiter = match (c, char_iter.clone().next()) {
+        (']', _) if !first => return Ok(BracketMember::End),
+        ('\\', Some(escape @ ('d' | 'D' | 'w' | 'W' | 's' | 'S'))) => {
+            char_iter.next();
+            let class = match escape.to_ascii_lowercase() {
+                'd' => Class::Digit,
+                'w' => Class::Word,
+                _ => Class::Space,
+            };
+            return Ok(BracketMember::Item(BracketItem::Class {
+                class,
+                negated: escape.is_ascii_uppercase(),
+            }));
+        }
+        ('\\', Some(escape @ ('p' | 'P'))) => {
+            char_iter.next();
+            return Ok(BracketMember::Item(BracketItem::Property {
+                name: unicode_property(char_iter)?,
+                negated: escape == 'P',
+            }));
+        }
+        ('[', Some(delimiter @ (':' | '.' | '='))) => delimiter,
+        _ => return Ok(BracketMember::Char(c)),
+    };
+    char_iter.next();
+
+    let mut name = String::new();
+    loop {
+        match char_iter.next() {
+            Some(ch) if ch == delimiter && char_iter.clone().next() == Some(']') => {
+                char_iter.next();
+                break;
+            }
+            Some(ch) => name.push(ch),
+            None => return Err(ErrorKind::UnclosedBracket),
         }
     }
-    anchor
+
+    if delimiter == ':' {
+        return class_by_name(&name).map(|class| {
+            BracketMember::Item(BracketItem::Class {
+                class,
+                negated: false,
+            })
+        });
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(BracketMember::Char(c)),
+        _ => Err(ErrorKind::InvalidCollatingElement),
+    }
 }
 
-pub fn handle_anchoring_end(queue: &mut VecDeque<RegexStep>) -> bool {
-    let mut anchor = false;
-    if let Some(first_step) = queue.back() {
-        if let RegexVal::Literal('$') = first_step.val {
-            anchor = true;
-            queue.pop_back();
-        }
+/// Returns the class called `name` in a `[:name:]` expression.
+fn class_by_name(name: &str) -> Result<Class, ErrorKind> {
+    match name {
+        "alnum" => Ok(Class::Alnum),
+        "alpha" => Ok(Class::Alpha),
+        "blank" => Ok(Class::Blank),
+        "cntrl" => Ok(Class::Cntrl),
+        "digit" => Ok(Class::Digit),
+        "graph" => Ok(Class::Graph),
+        "lower" => Ok(Class::Lower),
+        "print" => Ok(Class::Print),
+        "punct" => Ok(Class::Punct),
+        "space" => Ok(Class::Space),
+        "upper" => Ok(Class::Upper),
+        "word" => Ok(Class::Word),
+        "xdigit" => Ok(Class::Xdigit),
+        _ => Err(ErrorKind::UnknownClass),
     }
-    anchor
 }
 
-pub fn handle_curly(steps: &mut [RegexStep], char_iter: &mut std::str::Chars) -> Option<RegexStep> {
-    let mut min = None;
-    let mut max = None;
+/// Parses the body of a `{min,max}` quantifier; the opening `{` has already been consumed.
+///
+/// # Arguments
+///
+/// * `char_iter` - The pattern iterator, positioned after the `{`. It is left untouched if the
+///   `{` is a literal, and advanced past the `}` otherwise.
+/// * `basic` - Whether the quantifier is written `\{min,max\}`, as in basic patterns.
+///
+/// # Returns
+///
+/// `Some(rep)` for a valid quantifier, `None` if the `{` has to be read as a literal, or an
+/// error if the bounds are inverted or a bound does not fit in a `usize`.
+pub fn handle_curly(
+    char_iter: &mut std::str::Chars,
+    basic: bool,
+) -> Result<Option<RegexRep>, ErrorKind> {
+    let mut lookahead = char_iter.clone();
+    let mut min_str = String::new();
     let mut num_str = String::new();
     let mut after_comma = false;
-    let mut no_comma = true;
-    for ch in char_iter.by_ref() {
-        match ch {
-            ',' => {
-                no_comma = false;
-                if let Ok(num) = num_str.trim().parse::<usize>() {
-                    if !after_comma {
-                        min = Some(num);
-                    } else {
-                        max = Some(num);
-                        break;
-                    }
-                } else {
-                    min = Some(0);
-                }
-                num_str.clear();
+
+    let max = loop {
+        match lookahead.next() {
+            Some(',') if !after_comma => {
+                min_str = std::mem::take(&mut num_str);
                 after_comma = true;
             }
-            '}' => {
-                if let Ok(num) = num_str.trim().parse::<usize>() {
-                    if !after_comma {
-                        min = Some(num);
-                    } else {
-                        max = Some(num);
-                    }
-                    if no_comma {
-                        min = Some(num);
-                        max = Some(num);
-                    }
-                } else {
-                    max = Some(MAX);
+            Some(ch)
+                if (ch == '}' && !basic)
+                    || (ch == '\\' && basic && lookahead.as_str().starts_with('}')) =>
+            {
+                if basic {
+                    lookahead.next();
                 }
-                break;
-            }
-            '0'..='9' => num_str.push(ch),
-            _ => {
-                return Some(RegexStep {
-                    rep: RegexRep::Range {
-                        min: None,
-                        max: None,
-                    },
-                    val: RegexVal::Literal('{'), //placeholder
-                });
+                // Los limites se leen recien aqui: hasta el `}` el `{` todavia puede ser literal.
+                let num = repetition_bound(&num_str)?;
+                if after_comma {
+                    // Sin numero despues de la coma, `max` queda en None: repeticion no acotada.
+                    break num;
+                }
+                return match num {
+                    Some(num) => {
+                        *char_iter = lookahead;
+                        Ok(Some(RegexRep::Exact(num)))
+                    }
+                    None => Ok(None),
+                };
             }
+            Some(ch @ '0'..='9') => num_str.push(ch),
+            _ => return Ok(None),
         }
-    }
+    };
 
-    if let Some(last) = steps.last_mut() {
-        last.rep = RegexRep::Range { min, max };
-    } else {
-        return Some(RegexStep {
-            rep: RegexRep::Range {
-                min: None,
-                max: None,
-            },
-            val: RegexVal::Literal('{'),
-        });
+    *char_iter = lookahead;
+    let min = repetition_bound(&min_str)?;
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Err(ErrorKind::InvalidRepetitionRange);
+        }
     }
+    Ok(Some(RegexRep::Range {
+        min,
+        max,
+        lazy: false,
+    }))
+}
 
-    None
+/// Parses a bound of a `{min,max}` quantifier, `None` if it is empty. A bound too large for a
+/// `usize` is an error rather than a missing bound, which would change what the pattern means.
+fn repetition_bound(digits: &str) -> Result<Option<usize>, ErrorKind> {
+    if digits.is_empty() {
+        return Ok(None);
+    }
+    digits
+        .parse::<usize>()
+        .map(Some)
+        .map_err(|_| ErrorKind::PatternTooLarge)
 }
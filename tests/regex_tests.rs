@@ -1,20 +1,30 @@
+use rgrep::aho_corasick::{AhoCorasick, AhoCorasickBuilder};
+use rgrep::captures::Captures;
+use rgrep::char_set::CharSet;
 use rgrep::class::Class;
-use rgrep::evaluated_step::EvaluatedStep;
-use rgrep::regex::backtrack;
+use rgrep::engine::EngineKind;
+use rgrep::fixed_string::FixedString;
+use rgrep::matcher::Matcher;
+use rgrep::parser::Parser;
+use rgrep::program::Program;
+use rgrep::regex_builder::RegexBuilder;
+use rgrep::regex_error::{ErrorKind, RegexError};
 use rgrep::regex_rep::RegexRep;
-use rgrep::regex_step::{self, Regex, RegexStep};
+use rgrep::regex_set::{RegexSet, RegexSetBuilder};
+use rgrep::regex_step::{Regex, RegexStep};
 use rgrep::regex_val::RegexVal;
-use rgrep::utils::{check_min_max, handle_backslash, handle_brackets, handle_curly};
-use std::collections::VecDeque;
+use rgrep::replace::NoExpand;
+use rgrep::syntax::{parse, parse_with, Ast, AstKind, Dialect, Visitor, VisitorMut};
+use std::borrow::Cow;
 use std::fs::File;
 use std::io::{self, BufRead};
-use std::string;
+use std::time::{Duration, Instant};
 #[test]
 fn test_regex_period() {
     let str_regex = "ab.cd";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -23,7 +33,7 @@ fn test_regex_period() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -31,18 +41,16 @@ fn test_regex_period() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[0]) {
-            Ok(result) => {
-                assert_eq!(result, lines[0])
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[0]) {
+        Ok(result) => {
+            assert_eq!(result, lines[0])
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
 }
 
@@ -51,7 +59,7 @@ fn test_regex_concatenance() {
     let str_regex = "ab.*cd";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -60,7 +68,7 @@ fn test_regex_concatenance() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -68,18 +76,16 @@ fn test_regex_concatenance() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[1]) {
-            Ok(result) => {
-                assert_eq!(result, lines[1])
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[1]) {
+        Ok(result) => {
+            assert_eq!(result, lines[1])
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
 }
 
@@ -88,7 +94,7 @@ fn test_regex_bracket() {
     let str_regex = "a[bc]d";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -97,7 +103,7 @@ fn test_regex_bracket() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -105,18 +111,16 @@ fn test_regex_bracket() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[2]) {
-            Ok(result) => {
-                assert_eq!(result, lines[2])
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[2]) {
+        Ok(result) => {
+            assert_eq!(result, lines[2])
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
 }
 
@@ -125,7 +129,7 @@ fn test_regex_simple_rep() {
     let str_regex = "ab{2,4}cd";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -134,7 +138,7 @@ fn test_regex_simple_rep() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -142,18 +146,16 @@ fn test_regex_simple_rep() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[3]) {
-            Ok(result) => {
-                assert_eq!(result, lines[3])
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[3]) {
+        Ok(result) => {
+            assert_eq!(result, lines[3])
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
 }
 
@@ -162,7 +164,7 @@ fn test_regex_alternance() {
     let str_regex = "abc|de+f";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -171,7 +173,7 @@ fn test_regex_alternance() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -179,19 +181,17 @@ fn test_regex_alternance() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
     let mut fullgrep = String::new();
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[4]) {
-            Ok(result) => {
-                fullgrep.push_str(&result);
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[4]) {
+        Ok(result) => {
+            fullgrep.push_str(&result);
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
     assert_eq!(fullgrep, lines[4]);
 }
@@ -201,7 +201,7 @@ fn test_regex_bracket_2() {
     let str_regex = "la [aeiou] es una vocal";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -210,7 +210,7 @@ fn test_regex_bracket_2() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -218,19 +218,17 @@ fn test_regex_bracket_2() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
     let mut fullgrep = String::new();
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[5]) {
-            Ok(result) => {
-                fullgrep.push_str(&result);
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[5]) {
+        Ok(result) => {
+            fullgrep.push_str(&result);
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
     assert_eq!(fullgrep, lines[5]);
 }
@@ -240,7 +238,7 @@ fn test_regex_negated_bracket() {
     let str_regex = "la [^aeiou] no es una vocal";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -249,7 +247,7 @@ fn test_regex_negated_bracket() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -257,19 +255,17 @@ fn test_regex_negated_bracket() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
     let mut fullgrep = String::new();
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[6]) {
-            Ok(result) => {
-                fullgrep.push_str(&result);
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[6]) {
+        Ok(result) => {
+            fullgrep.push_str(&result);
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
     assert_eq!(fullgrep, lines[6]);
 }
@@ -279,7 +275,7 @@ fn test_regex_class_and_rep() {
     let str_regex = "hola [[:alpha:]]+";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -288,7 +284,7 @@ fn test_regex_class_and_rep() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -296,19 +292,17 @@ fn test_regex_class_and_rep() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
     let mut fullgrep = String::new();
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[7]) {
-            Ok(result) => {
-                fullgrep.push_str(&result);
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[7]) {
+        Ok(result) => {
+            fullgrep.push_str(&result);
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
     assert_eq!(fullgrep, lines[7]);
 }
@@ -318,7 +312,7 @@ fn test_regex_class_2() {
     let str_regex = "[[:digit:]] es un numero";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -327,7 +321,7 @@ fn test_regex_class_2() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -335,19 +329,17 @@ fn test_regex_class_2() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
     let mut fullgrep = String::new();
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[8]) {
-            Ok(result) => {
-                fullgrep.push_str(&result);
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[8]) {
+        Ok(result) => {
+            fullgrep.push_str(&result);
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
     assert_eq!(fullgrep, lines[8]);
 }
@@ -357,7 +349,7 @@ fn test_regex_class_3() {
     let str_regex = "el caracter [[:alnum:]] no es un simbolo";
     let filepath = "unit_test.txt";
 
-    let mut regex_instance = match Regex::new(str_regex) {
+    let regex_instance = match Regex::new(str_regex) {
         Ok(regex_instance) => regex_instance,
         Err(err) => {
             panic!("Error al crear la instancia de Regex: {}", err);
@@ -366,7 +358,7 @@ fn test_regex_class_3() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -374,19 +366,17 @@ fn test_regex_class_3() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
     let mut fullgrep = String::new();
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[9]) {
-            Ok(result) => {
-                fullgrep.push_str(&result);
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[9]) {
+        Ok(result) => {
+            fullgrep.push_str(&result);
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
     assert_eq!(fullgrep, lines[9]);
 }
@@ -405,7 +395,7 @@ fn test_regex_anchor() {
 
     let file = match File::open(filepath) {
         Ok(file) => file,
-        Err(err) => return,
+        Err(_) => return,
     };
     let reader = io::BufReader::new(file);
 
@@ -413,46 +403,21 @@ fn test_regex_anchor() {
     for line_result in reader.lines() {
         let line = match line_result {
             Ok(line) => line,
-            Err(err) => return,
+            Err(_) => return,
         };
         lines.push(line);
     }
 
     let mut fullgrep = String::new();
-    for mut pattern in regex_instance {
-        match pattern.test(&lines[10]) {
-            Ok(result) => {
-                fullgrep.push_str(&result);
-            }
-            Err(err) => println!("Error applying the regular expression pattern: {}", err),
+    match regex_instance.test(&lines[10]) {
+        Ok(result) => {
+            fullgrep.push_str(&result);
         }
+        Err(err) => println!("Error applying the regular expression pattern: {}", err),
     }
     assert_eq!(fullgrep, lines[10]);
 }
 
-#[test]
-fn test_backtrack() {
-    let regex_step = RegexStep {
-        rep: RegexRep::Exact(1),
-        val: RegexVal::Literal('a'),
-    };
-
-    let mut evaluated_steps = Vec::new();
-    evaluated_steps.push(EvaluatedStep {
-        step: regex_step.clone(),
-        size: 3,
-        backtrackeable: true,
-    });
-
-    let mut regex_queue = VecDeque::new();
-    regex_queue.push_front(regex_step.clone());
-
-    assert_eq!(
-        backtrack(&regex_step, &mut evaluated_steps, &mut regex_queue),
-        Some(3)
-    );
-}
-
 #[test]
 fn test_matches_literal() {
     let regex_val = RegexVal::Literal('a');
@@ -471,7 +436,7 @@ fn test_matches_wildcard() {
 
 #[test]
 fn test_matches_bracket() {
-    let regex_val = RegexVal::Bracket(vec!['a', 'e', 'i', 'o', 'u']);
+    let regex_val = RegexVal::Bracket("aeiou".chars().collect());
 
     assert_eq!(regex_val.matches("ouoeuiieuauieo"), 1);
     assert_eq!(regex_val.matches("lkjhgfd"), 0);
@@ -479,7 +444,7 @@ fn test_matches_bracket() {
 
 #[test]
 fn test_matches_negated_bracket() {
-    let regex_val = RegexVal::NegatedBracket(vec!['a', 'e', 'i', 'o', 'u']);
+    let regex_val = RegexVal::Bracket("aeiou".chars().collect::<CharSet>().negate());
 
     assert_eq!(regex_val.matches("ghytr"), 1);
     assert_eq!(regex_val.matches("iiee"), 0);
@@ -495,22 +460,1898 @@ fn test_matches_class() {
 
 #[test]
 fn test_doesnot_match_wrong_anchor() {
-    let mut regex = Regex {
-        steps: vec![
-            RegexStep {
-                rep: RegexRep::Exact(1),
-                val: RegexVal::Literal('f'),
-            },
-            RegexStep {
-                rep: RegexRep::Exact(1),
-                val: RegexVal::Literal('a'),
-            },
-            RegexStep {
-                rep: RegexRep::Exact(1),
-                val: RegexVal::Literal('$'),
-            },
-        ],
-    };
+    let regex = Regex::from_steps(&[
+        RegexStep {
+            rep: RegexRep::Exact(1),
+            val: RegexVal::Literal('f'),
+        },
+        RegexStep {
+            rep: RegexRep::Exact(1),
+            val: RegexVal::Literal('a'),
+        },
+        RegexStep {
+            rep: RegexRep::Exact(1),
+            val: RegexVal::Literal('$'),
+        },
+    ])
+    .unwrap();
 
     assert_eq!(regex.test("fa no").unwrap(), "");
 }
+
+#[test]
+fn test_regex_multiple_wildcards() {
+    let regex = Regex::new("a.*b.*c").unwrap();
+
+    assert_eq!(regex.test("xxaxxbxxcxx").unwrap(), "xxaxxbxxcxx");
+    assert_eq!(regex.test("a b a b").unwrap(), "");
+}
+
+#[test]
+fn test_regex_rep_needs_backtracking() {
+    let regex = Regex::new("ab{2,4}bcd").unwrap();
+
+    assert_eq!(regex.test("abbbcd").unwrap(), "abbbcd");
+    assert_eq!(regex.test("abbcd").unwrap(), "");
+}
+
+#[test]
+fn test_regex_optional_and_plus() {
+    let regex = Regex::new("colou?r+s").unwrap();
+
+    assert_eq!(regex.test("colors").unwrap(), "colors");
+    assert_eq!(regex.test("colourrrs").unwrap(), "colourrrs");
+    assert_eq!(regex.test("colous").unwrap(), "");
+}
+
+#[test]
+fn test_regex_long_line_runs_in_linear_time() {
+    let regex = Regex::new("a.*a.*a.*a.*a.*b").unwrap();
+    let line = "a".repeat(20_000);
+
+    assert_eq!(regex.test(&line).unwrap(), "");
+}
+
+#[test]
+fn test_lazy_dfa_reuses_cached_states() {
+    let regex = Regex::new("ab.*cd").unwrap();
+
+    assert_eq!(
+        regex.test("abrefwgrwegwetrcd").unwrap(),
+        "abrefwgrwegwetrcd"
+    );
+    let built = regex.dfa_stats().states_built;
+    assert!(built > 0);
+
+    assert_eq!(
+        regex.test("abrefwgrwegwetrcd").unwrap(),
+        "abrefwgrwegwetrcd"
+    );
+    assert_eq!(regex.dfa_stats().states_built, built);
+    assert_eq!(regex.dfa_stats().cache_clears, 0);
+}
+
+#[test]
+fn test_lazy_dfa_falls_back_to_nfa_when_over_budget() {
+    let regex = RegexBuilder::new("a.*b.*c")
+        .dfa_cache_capacity(0)
+        .build()
+        .unwrap();
+
+    assert_eq!(regex.test("xxaxxbxxcxx").unwrap(), "xxaxxbxxcxx");
+    assert_eq!(regex.test("a b a b").unwrap(), "");
+    assert_eq!(regex.dfa_stats().nfa_fallbacks, 2);
+}
+
+#[test]
+fn test_lazy_dfa_clears_cache_when_full() {
+    let regex = RegexBuilder::new("[abc]{3,6}d")
+        .dfa_cache_capacity(2 * 1024)
+        .build()
+        .unwrap();
+
+    let line = "abcabcbacbcabbcacbcabbcabacbcad";
+    assert_eq!(regex.test(line).unwrap(), line);
+    assert!(regex.dfa_stats().cache_clears > 0 || regex.dfa_stats().nfa_fallbacks > 0);
+}
+
+#[test]
+fn test_regex_group_with_alternation_and_plus() {
+    let regex = Regex::new("foo(bar|baz)+qux").unwrap();
+
+    assert_eq!(regex.test("foobazbarqux").unwrap(), "foobazbarqux");
+    assert_eq!(regex.test("fooqux").unwrap(), "");
+    assert_eq!(regex.test("foobarbazqu").unwrap(), "");
+}
+
+#[test]
+fn test_regex_nested_alternation() {
+    let regex = RegexBuilder::new("a(b|c(d|e))f")
+        .dfa_cache_capacity(0)
+        .build()
+        .unwrap();
+
+    assert_eq!(regex.test("xacefx").unwrap(), "xacefx");
+    assert_eq!(regex.test("abf").unwrap(), "abf");
+    assert_eq!(regex.test("acf").unwrap(), "");
+}
+
+#[test]
+fn test_regex_curly_on_group() {
+    let regex = Regex::new("x(ab){2,3}y").unwrap();
+
+    assert_eq!(regex.test("xababy").unwrap(), "xababy");
+    assert_eq!(regex.test("xabababy").unwrap(), "xabababy");
+    assert_eq!(regex.test("xaby").unwrap(), "");
+    assert_eq!(regex.test("xababababy").unwrap(), "");
+}
+
+#[test]
+fn test_regex_optional_group_and_empty_alternative() {
+    let regex = Regex::new("a(|b)c(de)?f").unwrap();
+
+    assert_eq!(regex.test("acf").unwrap(), "acf");
+    assert_eq!(regex.test("abcdef").unwrap(), "abcdef");
+    assert_eq!(regex.test("abdf").unwrap(), "");
+}
+
+#[test]
+fn test_regex_anchors_per_alternative() {
+    let regex = Regex::new("^abc|def$").unwrap();
+
+    assert_eq!(regex.test("abcx").unwrap(), "abcx");
+    assert_eq!(regex.test("xdef").unwrap(), "xdef");
+    assert_eq!(regex.test("xabc").unwrap(), "");
+    assert_eq!(regex.test("defx").unwrap(), "");
+}
+
+#[test]
+fn test_regex_unbalanced_parentheses() {
+    assert!(Regex::new("(ab").is_err());
+    assert!(Regex::new("ab)").is_err());
+    assert!(Regex::new("(*a)").is_err());
+}
+
+#[test]
+fn test_captures_numbered_groups() {
+    let regex = Regex::new("(a+)(b|c)(d)?").unwrap();
+    let caps = regex.captures("xxaaacz").unwrap();
+
+    assert_eq!(caps.len(), 4);
+    assert_eq!(caps.get(0).unwrap().as_str(), "aaac");
+    assert_eq!(caps.get(0).unwrap().range(), 2..6);
+    assert_eq!(caps.get(1).unwrap().as_str(), "aaa");
+    assert_eq!(caps.get(2).unwrap().as_str(), "c");
+    assert_eq!(caps.get(3), None);
+    assert!(regex.captures("xyz").is_none());
+}
+
+#[test]
+fn test_captures_named_groups() {
+    let regex = Regex::new("(?P<year>[[:digit:]]{4})-(?<month>[[:digit:]]{2})").unwrap();
+    let caps = regex.captures("fecha: 2023-11-05").unwrap();
+
+    assert_eq!(caps.name("year").unwrap().as_str(), "2023");
+    assert_eq!(caps.name("month").unwrap().range(), 12..14);
+    assert_eq!(caps.get(2).unwrap().as_str(), "11");
+    assert_eq!(caps.name("day"), None);
+    assert_eq!(
+        regex.capture_names().collect::<Vec<_>>(),
+        vec![None, Some("year"), Some("month")]
+    );
+}
+
+#[test]
+fn test_captures_non_capturing_and_repeated_groups() {
+    let regex = Regex::new("(?:ab)+(c|d)+").unwrap();
+    let caps = regex.captures("ababcdc").unwrap();
+
+    assert_eq!(regex.captures_len(), 2);
+    assert_eq!(caps.get(0).unwrap().as_str(), "ababcdc");
+    assert_eq!(caps.get(1).unwrap().as_str(), "c");
+}
+
+#[test]
+fn test_captures_leftmost_first_alternation() {
+    let regex = Regex::new("(a|ab)(c|bcd)").unwrap();
+    let caps = regex.captures("abcd").unwrap();
+
+    assert_eq!(caps.get(0).unwrap().as_str(), "abcd");
+    assert_eq!(caps.get(1).unwrap().as_str(), "a");
+    assert_eq!(caps.get(2).unwrap().as_str(), "bcd");
+}
+
+#[test]
+fn test_captures_invalid_group_names() {
+    assert!(Regex::new("(?P<1a>x)").is_err());
+    assert!(Regex::new("(?P<a>x)(?P<a>y)").is_err());
+    assert!(Regex::new("(?P<a x)").is_err());
+}
+
+#[test]
+fn test_find_returns_match_span() {
+    let regex = Regex::new("b+c").unwrap();
+    let found = regex.find("aabbbcd").unwrap();
+
+    assert_eq!(found.start(), 2);
+    assert_eq!(found.end(), 6);
+    assert_eq!(found.as_str(), "bbbc");
+    assert!(regex.find("aaa").is_none());
+}
+
+#[test]
+fn test_find_at_keeps_anchor_context() {
+    let regex = Regex::new("^ab").unwrap();
+    assert_eq!(regex.find_at("abab", 0).unwrap().range(), 0..2);
+    assert!(regex.find_at("abab", 2).is_none());
+
+    let regex = Regex::new("ab").unwrap();
+    assert_eq!(regex.find_at("abab", 1).unwrap().range(), 2..4);
+}
+
+#[test]
+fn test_find_iter_non_overlapping() {
+    let regex = Regex::new("a[bc]").unwrap();
+    let found: Vec<&str> = regex.find_iter("abacadab").map(|m| m.as_str()).collect();
+
+    assert_eq!(found, vec!["ab", "ac", "ab"]);
+}
+
+#[test]
+fn test_find_iter_empty_matches() {
+    let regex = Regex::new("a*").unwrap();
+    let spans: Vec<_> = regex.find_iter("baaab").map(|m| m.range()).collect();
+
+    assert_eq!(spans, vec![0..0, 1..4, 5..5]);
+}
+
+#[test]
+fn test_replace_first_and_all() {
+    let regex = Regex::new("a+").unwrap();
+
+    assert_eq!(regex.replace("baaac aa", "x"), "bxc aa");
+    assert_eq!(regex.replace_all("baaac aa", "x"), "bxc x");
+    assert_eq!(regex.replacen("a a a a", 2, "o"), "o o a a");
+}
+
+#[test]
+fn test_replace_without_match_borrows_input() {
+    let regex = Regex::new("z+").unwrap();
+
+    assert!(matches!(
+        regex.replace_all("abc", "x"),
+        Cow::Borrowed("abc")
+    ));
+    assert!(matches!(
+        regex.replace_all("abc", |_: &Captures| "x"),
+        Cow::Borrowed("abc")
+    ));
+}
+
+#[test]
+fn test_replace_template_expansion() {
+    let regex = Regex::new("(?P<first>[[:alpha:]]+) (?P<last>[[:alpha:]]+)").unwrap();
+
+    assert_eq!(
+        regex.replace("agustin murseli", "$2, $1"),
+        "murseli, agustin"
+    );
+    assert_eq!(
+        regex.replace("agustin murseli", "${last}_${first}"),
+        "murseli_agustin"
+    );
+    assert_eq!(
+        regex.replace("agustin murseli", "$$1 $last$"),
+        "$1 murseli$"
+    );
+    assert_eq!(regex.replace("agustin murseli", "[$3$nope]"), "[]");
+    assert_eq!(regex.replace("agustin murseli", "${1}x"), "agustinx");
+}
+
+#[test]
+fn test_replace_with_closure_and_no_expand() {
+    let regex = Regex::new("[[:digit:]]+").unwrap();
+
+    let doubled = regex.replace_all("a1 b22", |caps: &Captures| {
+        let n: usize = caps.get(0).unwrap().as_str().parse().unwrap();
+        (n * 2).to_string()
+    });
+    assert_eq!(doubled, "a2 b44");
+    assert_eq!(regex.replace_all("a1 b22", NoExpand("$1")), "a$1 b$1");
+}
+
+#[test]
+fn test_replace_all_empty_matches() {
+    let regex = Regex::new("x*").unwrap();
+
+    assert_eq!(regex.replace_all("abc", "-"), "-a-b-c-");
+}
+
+#[test]
+fn test_regex_accented_input() {
+    let regex = Regex::new("ma.ana").unwrap();
+
+    assert_eq!(regex.test("hasta mañana").unwrap(), "hasta mañana");
+    assert_eq!(regex.test("¿qué pasó?").unwrap(), "");
+}
+
+#[test]
+fn test_regex_bracket_with_accents_on_both_engines() {
+    let regex = Regex::new("canci[óo]n").unwrap();
+    assert_eq!(regex.test("la canción").unwrap(), "la canción");
+
+    let regex = RegexBuilder::new("canci[óo]n")
+        .dfa_cache_capacity(0)
+        .build()
+        .unwrap();
+    assert_eq!(regex.test("la canción").unwrap(), "la canción");
+    assert_eq!(regex.test("la cancien").unwrap(), "");
+}
+
+#[test]
+fn test_find_byte_offsets_on_accented_text() {
+    let regex = Regex::new("[áéíóú]").unwrap();
+    let spans: Vec<_> = regex
+        .find_iter("canción camión")
+        .map(|m| (m.range(), m.as_str()))
+        .collect();
+
+    assert_eq!(spans, vec![(5..7, "ó"), (13..15, "ó")]);
+}
+
+#[test]
+fn test_negated_bracket_and_class_match_whole_code_points() {
+    let regex = Regex::new("[^ ]+").unwrap();
+    assert_eq!(regex.find("¿añejo?").unwrap().as_str(), "¿añejo?");
+
+    let regex = Regex::new("[[:alpha:]]+").unwrap();
+    assert_eq!(regex.find("¿añejo?").unwrap().as_str(), "añejo");
+    assert_eq!(regex.replace_all("él y ñandú", "_"), "_ _ _");
+}
+
+#[test]
+fn test_case_insensitive_builder_option() {
+    let regex = RegexBuilder::new("hola [ñ]and[ú]")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+
+    assert_eq!(regex.test("HOLA ÑANDÚ").unwrap(), "HOLA ÑANDÚ");
+    assert_eq!(regex.test("Hola ñandu").unwrap(), "");
+}
+
+#[test]
+fn test_case_insensitive_unicode_simple_folding() {
+    let regex = RegexBuilder::new("[é]l|k|[σ]")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    let found: Vec<&str> = regex
+        .find_iter("ÉL \u{212A} ς")
+        .map(|m| m.as_str())
+        .collect();
+
+    assert_eq!(found, vec!["ÉL", "\u{212A}", "ς"]);
+}
+
+#[test]
+fn test_case_insensitive_brackets_and_classes() {
+    let regex = RegexBuilder::new("[^aeiou]")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("AEx").unwrap().as_str(), "x");
+
+    let regex = RegexBuilder::new("[[:upper:]]+")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("12 abC").unwrap().as_str(), "abC");
+
+    let regex = Regex::new("[[:lower:]]+").unwrap();
+    assert_eq!(regex.find("ABc").unwrap().as_str(), "c");
+}
+
+#[test]
+fn test_case_insensitive_inline_flags() {
+    let regex = Regex::new("(?i)hola").unwrap();
+    assert!(regex.find("HoLa").is_some());
+
+    let regex = Regex::new("(?i:ab)c").unwrap();
+    assert!(regex.find("ABc").is_some());
+    assert!(regex.find("ABC").is_none());
+
+    let regex = Regex::new("a((?i)b)c").unwrap();
+    assert!(regex.find("aBc").is_some());
+    assert!(regex.find("aBC").is_none());
+
+    let regex = RegexBuilder::new("a(?-i)b")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert!(regex.find("Ab").is_some());
+    assert!(regex.find("AB").is_none());
+
+    assert!(Regex::new("(?x)a").is_err());
+}
+
+#[test]
+fn test_lazy_quantifiers_extract_quoted_strings() {
+    let regex = Regex::new("\".*?\"").unwrap();
+    let found: Vec<&str> = regex
+        .find_iter("name: \"rgrep\", mode: \"fast\"")
+        .map(|m| m.as_str())
+        .collect();
+    assert_eq!(found, vec!["\"rgrep\"", "\"fast\""]);
+
+    let regex = Regex::new("\".*\"").unwrap();
+    assert_eq!(
+        regex
+            .find("name: \"rgrep\", mode: \"fast\"")
+            .unwrap()
+            .as_str(),
+        "\"rgrep\", mode: \"fast\""
+    );
+}
+
+#[test]
+fn test_lazy_quantifiers_prefer_fewest_repetitions() {
+    assert_eq!(
+        Regex::new("a+?").unwrap().find("aaa").unwrap().as_str(),
+        "a"
+    );
+    assert_eq!(Regex::new("a*?").unwrap().find("aaa").unwrap().as_str(), "");
+    assert_eq!(
+        Regex::new("ba??").unwrap().find("baa").unwrap().as_str(),
+        "b"
+    );
+    assert_eq!(
+        Regex::new("a{2,}?").unwrap().find("aaaa").unwrap().as_str(),
+        "aa"
+    );
+    assert_eq!(
+        Regex::new("a{1,3}?")
+            .unwrap()
+            .find("aaaa")
+            .unwrap()
+            .as_str(),
+        "a"
+    );
+    assert_eq!(
+        Regex::new("a{2}?").unwrap().find("aaaa").unwrap().as_str(),
+        "aa"
+    );
+
+    // Una repeticion lazy se extiende si el resto del patron lo necesita.
+    assert_eq!(
+        Regex::new("a+?b").unwrap().find("aaab").unwrap().as_str(),
+        "aaab"
+    );
+}
+
+#[test]
+fn test_lazy_quantifiers_on_groups_and_captures() {
+    let regex = Regex::new("(a|b)*?(b+)").unwrap();
+    let caps = regex.captures("abbb").unwrap();
+    assert_eq!(caps.get(0).unwrap().as_str(), "abbb");
+    assert_eq!(caps.get(1).unwrap().as_str(), "a");
+    assert_eq!(caps.get(2).unwrap().as_str(), "bbb");
+
+    let regex = Regex::new("(a+?)(a*)").unwrap();
+    let caps = regex.captures("aaa").unwrap();
+    assert_eq!(caps.get(1).unwrap().as_str(), "a");
+    assert_eq!(caps.get(2).unwrap().as_str(), "aa");
+
+    let regex = Regex::new("x.*?y").unwrap();
+    assert_eq!(regex.test("x--y--y").unwrap(), "x--y--y");
+    assert_eq!(regex.test("x--").unwrap(), "");
+}
+
+#[test]
+fn test_backreference_finds_repeated_words() {
+    let regex = Regex::new("([[:alpha:]]+) \\1").unwrap();
+    assert_eq!(regex.find("is the the end").unwrap().as_str(), "the the");
+    assert!(regex.find("is the one").is_none());
+
+    let regex = Regex::new("^(ab|c)-\\1$").unwrap();
+    assert_eq!(regex.test("ab-ab").unwrap(), "ab-ab");
+    assert_eq!(regex.test("ab-c").unwrap(), "");
+}
+
+#[test]
+fn test_backreference_by_name_and_captures() {
+    let regex = Regex::new("(?P<quote>[\"'])(.*?)\\k<quote>").unwrap();
+    let caps = regex.captures("say 'it\"s' now").unwrap();
+    assert_eq!(caps.get(0).unwrap().as_str(), "'it\"s'");
+    assert_eq!(caps.name("quote").unwrap().as_str(), "'");
+    assert_eq!(caps.get(2).unwrap().as_str(), "it\"s");
+
+    let regex = Regex::new("(a+)\\1").unwrap();
+    let found: Vec<&str> = regex.find_iter("aaaaa a aa").map(|m| m.as_str()).collect();
+    assert_eq!(found, vec!["aaaa", "aa"]);
+}
+
+#[test]
+fn test_backreference_case_insensitive_and_unset_groups() {
+    let regex = Regex::new("(?i)(ab)\\1").unwrap();
+    assert_eq!(regex.find("xAbaB").unwrap().as_str(), "AbaB");
+    let regex = Regex::new("(ab)(?i:\\1)").unwrap();
+    assert!(regex.find("abAB").is_some());
+    assert!(Regex::new("(ab)\\1").unwrap().find("abAB").is_none());
+
+    // Un grupo que no participo en el match no coincide con nada.
+    let regex = Regex::new("(?:(a)|b)\\1").unwrap();
+    assert_eq!(regex.find("bb aa").unwrap().as_str(), "aa");
+
+    let regex = Regex::new("(a*)*\\1b").unwrap();
+    assert_eq!(regex.find("aab").unwrap().as_str(), "aab");
+}
+
+#[test]
+fn test_backreference_invalid_references() {
+    assert!(Regex::new("(a)\\2").is_err());
+    assert!(Regex::new("\\1(a)").is_ok());
+    assert!(Regex::new("(?P<x>a)\\k<y>").is_err());
+    assert!(Regex::new("\\k<x>(?P<x>a)").is_err());
+    assert!(Regex::new("(a)\\kx").is_err());
+}
+
+#[test]
+fn test_backreference_step_limit() {
+    // La `b` final evita que el prefiltro de literales descarte la linea sin buscar.
+    let line = format!("{}cb", "a".repeat(40));
+    let regex = RegexBuilder::new("(a|a)*\\1b")
+        .backtrack_limit(10_000)
+        .build()
+        .unwrap();
+    let exceeded = Some(RegexError::BacktrackLimitExceeded);
+    assert_eq!(regex.test(&line).err(), exceeded);
+    assert_eq!(regex.try_find(&line).err(), exceeded);
+    assert_eq!(regex.try_captures(&line).err(), exceeded);
+    assert_eq!(regex.try_replace_all(&line, "x").err(), exceeded);
+    assert_eq!(regex.try_is_match(&line).err(), exceeded);
+    let found: Vec<_> = regex.try_find_iter(&line).map(Result::err).collect();
+    assert_eq!(found, vec![exceeded.clone()]);
+    let found: Vec<_> = regex.try_captures_iter(&line).map(Result::err).collect();
+    assert_eq!(found, vec![exceeded.clone()]);
+    // Las versiones sin `try_` tratan el limite como si no hubiera match.
+    assert!(regex.find(&line).is_none());
+    assert_eq!(regex.replace_all(&line, "x"), line);
+
+    // Un error despues de un match termina la iteracion.
+    let line = format!("aab {}cb", "a".repeat(40));
+    let found: Vec<_> = regex.try_find_iter(&line).collect();
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].as_ref().map(|found| found.as_str()), Ok("aab"));
+    assert_eq!(found[1].as_ref().err(), exceeded.as_ref());
+
+    let regex = RegexBuilder::new("(a)\\1")
+        .backtrack_limit(10_000)
+        .build()
+        .unwrap();
+    assert_eq!(regex.test("xaa").unwrap(), "xaa");
+}
+
+#[test]
+fn test_backreference_time_limit() {
+    let line = format!("{}cb", "a".repeat(40));
+    let regex = RegexBuilder::new("(a|a)*\\1b")
+        .backtrack_limit(usize::MAX)
+        .time_limit(Duration::from_millis(20))
+        .build()
+        .unwrap();
+    let started = Instant::now();
+    assert_eq!(regex.test(&line), Err(RegexError::TimeLimitExceeded));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(
+        RegexError::TimeLimitExceeded.to_string(),
+        "backtracking time limit exceeded"
+    );
+
+    // Sin backtracking el limite de tiempo no cambia nada.
+    let regex = RegexBuilder::new("(a|a)*b")
+        .time_limit(Duration::ZERO)
+        .build()
+        .unwrap();
+    assert_eq!(regex.test(&line).unwrap(), line);
+}
+
+#[test]
+fn test_size_and_nest_limits() {
+    // Un limite que no entra en un usize no puede quedar como si no estuviera.
+    let huge = "99999999999999999999999";
+    for (pattern, dialect) in [
+        (format!("a{{1,{}}}", huge), Dialect::Extended),
+        (format!("a{{{},}}", huge), Dialect::Extended),
+        (format!("a{{{}}}", huge), Dialect::Extended),
+        (format!("a\\{{1,{}\\}}", huge), Dialect::Basic),
+        (format!("a\\{{{},\\}}", huge), Dialect::Basic),
+        (format!("a\\{{{}\\}}", huge), Dialect::Basic),
+    ] {
+        let err = RegexBuilder::new(&pattern)
+            .dialect(dialect)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), Some(ErrorKind::PatternTooLarge), "{}", pattern);
+        assert_eq!(err.offset(), Some(1), "{}", pattern);
+    }
+    // Si no cierra, el `{` sigue siendo literal.
+    let literal = format!("a{{{}", huge);
+    assert!(Regex::new(&literal).unwrap().is_match(&literal));
+
+    let err = Regex::new("(a{1000}){1000}").err().unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::PatternTooLarge));
+    assert_eq!(err.span(), Some(0..15));
+    assert_eq!(
+        Regex::new("a{1,1000000}").err().unwrap().kind(),
+        Some(ErrorKind::PatternTooLarge)
+    );
+    assert!(Regex::new("(a{100}){100}").is_ok());
+    // Cada copia de \p{L} lleva su propia lista de rangos.
+    assert_eq!(
+        Regex::new(r"(\p{L}{100}){100}").err().unwrap().kind(),
+        Some(ErrorKind::PatternTooLarge)
+    );
+    assert_eq!(
+        Regex::new(r"(\p{L}{1000}){99}").err().unwrap().kind(),
+        Some(ErrorKind::PatternTooLarge)
+    );
+    // Los slots del PikeVm crecen con la cantidad de grupos.
+    assert!(Regex::new(&"(a)".repeat(100)).is_ok());
+    assert_eq!(
+        Regex::new(&"(a)".repeat(1000)).err().unwrap().kind(),
+        Some(ErrorKind::PatternTooLarge)
+    );
+    assert!(RegexBuilder::new("a{2,3}")
+        .size_limit(10_000)
+        .build()
+        .is_ok());
+    assert!(RegexBuilder::new("a{2,3}").size_limit(100).build().is_err());
+    let err = RegexSet::new(["a", "[ab]{99999,}"]).err().unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::PatternTooLarge));
+
+    let deep = format!("{}a{}", "(".repeat(100_000), ")".repeat(100_000));
+    let err = Regex::new(&deep).err().unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::NestingTooDeep));
+    assert_eq!(err.span(), Some(250..251));
+    let err = Regex::new(&format!("a{}", "*".repeat(1000))).err().unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::NestingTooDeep));
+    assert_eq!(err.span(), Some(251..252));
+
+    let nested = RegexBuilder::new("((a)++)").nest_limit(3).build().unwrap();
+    assert_eq!(nested.test("aa").unwrap(), "aa");
+    let err = RegexBuilder::new("((a)++)")
+        .nest_limit(2)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::NestingTooDeep));
+    assert_eq!(err.span(), Some(5..6));
+    let err = RegexBuilder::new("\\(\\(a\\)\\)")
+        .dialect(Dialect::Basic)
+        .nest_limit(1)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.span(), Some(2..4));
+}
+
+#[test]
+fn test_lookahead_positive_and_negative() {
+    let regex = Regex::new("foo(?=bar)").unwrap();
+    assert_eq!(regex.find("foobaz foobar").unwrap().range(), 7..10);
+
+    let regex = Regex::new("a(?!b)").unwrap();
+    assert_eq!(regex.find("ab ac").unwrap().range(), 3..4);
+
+    let regex = Regex::new("^(?!x)[[:alpha:]]+$").unwrap();
+    assert_eq!(regex.test("port").unwrap(), "port");
+    assert_eq!(regex.test("xport").unwrap(), "");
+}
+
+#[test]
+fn test_lookbehind_positive_and_negative() {
+    let regex = Regex::new("(?<![#])port").unwrap();
+    assert_eq!(regex.test("port: ").unwrap(), "port: ");
+    assert_eq!(regex.test("#port: ").unwrap(), "");
+    assert_eq!(regex.test("# port: ").unwrap(), "# port: ");
+
+    let regex = Regex::new("(?<=ab|c)d").unwrap();
+    let found: Vec<_> = regex.find_iter("abd cd bd").map(|m| m.range()).collect();
+    assert_eq!(found, vec![2..3, 5..6]);
+
+    let regex = Regex::new("(?<=[ó])n").unwrap();
+    assert_eq!(regex.find("cancion canción").unwrap().range(), 15..16);
+}
+
+#[test]
+fn test_lookaround_same_results_on_every_engine() {
+    let patterns = [
+        "foo(?=bar)",
+        "(?<![ab])c",
+        "(?<=a{2})b",
+        "x(?!y|z)",
+        "(?<=(a|bb))c",
+    ];
+    let haystack = "foobar ac cc aab ab xy xz xw bbc";
+    for pattern in patterns {
+        let automaton = RegexBuilder::new(pattern)
+            .engine(EngineKind::Automaton)
+            .build()
+            .unwrap();
+        let backtracking = RegexBuilder::new(pattern)
+            .engine(EngineKind::Backtracking)
+            .build()
+            .unwrap();
+        let expected: Vec<_> = automaton.find_iter(haystack).map(|m| m.range()).collect();
+        let found: Vec<_> = backtracking
+            .find_iter(haystack)
+            .map(|m| m.range())
+            .collect();
+        assert!(!expected.is_empty(), "{}", pattern);
+        assert_eq!(expected, found, "{}", pattern);
+    }
+}
+
+#[test]
+fn test_lookaround_engine_support() {
+    let regex = Regex::new("(?<=a.*)b").unwrap();
+    assert_eq!(regex.find("b xxab").unwrap().range(), 5..6);
+
+    assert!(RegexBuilder::new("(?<=a.*)b")
+        .engine(EngineKind::Automaton)
+        .build()
+        .is_err());
+    assert!(RegexBuilder::new("(a)\\1")
+        .engine(EngineKind::Automaton)
+        .build()
+        .is_err());
+    assert!(RegexBuilder::new("(?<=a{1,3})b")
+        .engine(EngineKind::Automaton)
+        .build()
+        .is_ok());
+
+    // Dentro de un lookaround se ven los grupos capturados antes.
+    let regex = Regex::new("([[:alpha:]])(?=\\1)").unwrap();
+    assert_eq!(regex.find("abccd").unwrap().as_str(), "c");
+}
+
+#[test]
+fn test_lookaround_groups_capture() {
+    let group = |regex: &Regex, haystack: &str, index: usize| {
+        let caps = regex.captures(haystack)?;
+        caps.get(index).map(|found| found.range())
+    };
+    for engine in [EngineKind::Auto, EngineKind::Backtracking] {
+        let build = |pattern: &str| RegexBuilder::new(pattern).engine(engine).build().unwrap();
+        let regex = build("(?=(a))a");
+        assert_eq!(group(&regex, "xa", 1), Some(1..2));
+        let regex = build("(?<=(a|bb))c");
+        assert_eq!(group(&regex, "bbc", 1), Some(0..2));
+        assert_eq!(group(&regex, "ac", 1), Some(0..1));
+        let regex = build("(?<=(b+))c");
+        assert_eq!(group(&regex, "abbc", 1), Some(1..3));
+        // Un lookaround negativo no captura nada.
+        let regex = build("(?!(b))a");
+        assert_eq!(group(&regex, "a", 1), None);
+        // Un camino que falla despues del lookaround no deja sus capturas.
+        let regex = build("(?:(?=(ab))x|a)");
+        assert_eq!(group(&regex, "ab", 0), Some(0..1));
+        assert_eq!(group(&regex, "ab", 1), None);
+    }
+    let regex = Regex::new("(?=(a))a\\1").unwrap();
+    assert_eq!(regex.find("aa").unwrap().range(), 0..2);
+    assert_eq!(group(&regex, "aa", 1), Some(0..1));
+}
+
+#[test]
+fn test_lookaround_invalid_syntax() {
+    assert!(Regex::new("(?=a)*").is_err());
+    assert!(Regex::new("(?<!a").is_err());
+    assert!(Regex::new("(?<=a)b").is_ok());
+}
+
+#[test]
+fn test_anchors_anywhere_in_pattern() {
+    let regex = Regex::new("a|^b").unwrap();
+    assert_eq!(regex.find("cb ba").unwrap().range(), 4..5);
+    assert_eq!(regex.find("bc").unwrap().range(), 0..1);
+
+    let regex = Regex::new("(^|,)ab($|,)").unwrap();
+    assert_eq!(regex.test("ab").unwrap(), "ab");
+    assert_eq!(regex.test("x,ab,y").unwrap(), "x,ab,y");
+    assert_eq!(regex.test("xab").unwrap(), "");
+
+    // Un anclaje que no puede cumplirse hace que el patron no coincida nunca.
+    let regex = Regex::new("a^b").unwrap();
+    assert_eq!(regex.test("ab").unwrap(), "");
+    assert_eq!(regex.test("a^b").unwrap(), "");
+}
+
+#[test]
+fn test_word_boundaries() {
+    let regex = Regex::new("\\bcat\\b").unwrap();
+    let found: Vec<_> = regex
+        .find_iter("cat concat cats cat_ cat")
+        .map(|m| m.range())
+        .collect();
+    assert_eq!(found, vec![0..3, 21..24]);
+
+    let regex = Regex::new("\\Bcat").unwrap();
+    assert_eq!(regex.find("cat concat").unwrap().range(), 7..10);
+
+    // Las letras acentuadas son caracteres de palabra.
+    let regex = Regex::new("\\bn").unwrap();
+    assert!(regex.find("canción").is_none());
+
+    // Una marca combinante queda dentro de la palabra, en `\b`, `\w` y `[[:word:]]`.
+    for engine in [EngineKind::Auto, EngineKind::Backtracking] {
+        let regex = RegexBuilder::new("e\u{301}\\b")
+            .engine(engine)
+            .build()
+            .unwrap();
+        assert!(regex.find("e\u{301}x").is_none());
+        assert_eq!(regex.find("e\u{301} x").unwrap().range(), 0..3);
+    }
+    let regex = Regex::new("\\w+").unwrap();
+    assert_eq!(
+        regex.find("e\u{301}x_\u{203F}y").unwrap().as_str(),
+        "e\u{301}x_\u{203F}y"
+    );
+    assert_eq!(regex.find("\u{bd}\u{2160}").unwrap().as_str(), "\u{2160}");
+    let regex = Regex::new("[[:word:]]+").unwrap();
+    assert_eq!(regex.find("a\u{200D}b").unwrap().as_str(), "a\u{200D}b");
+    assert!(!Regex::new("\\w").unwrap().is_match("\u{bd}"));
+
+    let regex = Regex::new("\\b([[:alpha:]]+) \\1\\b").unwrap();
+    assert_eq!(
+        regex.find("is the end the the one").unwrap().as_str(),
+        "the the"
+    );
+    assert!(regex.find("is the end").is_none());
+}
+
+#[test]
+fn test_gnu_word_start_and_end() {
+    let regex = Regex::new("\\<a").unwrap();
+    let found: Vec<_> = regex.find_iter("a ba ab").map(|m| m.range()).collect();
+    assert_eq!(found, vec![0..1, 5..6]);
+
+    let regex = Regex::new("a\\>").unwrap();
+    let found: Vec<_> = regex.find_iter("a ba ab").map(|m| m.range()).collect();
+    assert_eq!(found, vec![0..1, 3..4]);
+
+    assert!(Regex::new("\\<").unwrap().find("   ").is_none());
+    assert!(Regex::new("\\b*").is_err());
+}
+
+#[test]
+fn test_multi_line_anchors() {
+    let text = "uno\ndos\ntres";
+    let regex = Regex::new("^d").unwrap();
+    assert!(regex.find(text).is_none());
+
+    let regex = Regex::new("(?m)^d[[:alpha:]]+$").unwrap();
+    assert_eq!(regex.find(text).unwrap().as_str(), "dos");
+
+    let regex = RegexBuilder::new("^[[:alpha:]]+$")
+        .multi_line(true)
+        .build()
+        .unwrap();
+    let lines: Vec<&str> = regex.find_iter(text).map(|m| m.as_str()).collect();
+    assert_eq!(lines, vec!["uno", "dos", "tres"]);
+
+    let regex = Regex::new("(?m)\\Ad").unwrap();
+    assert!(regex.find(text).is_none());
+    let regex = Regex::new("(?m)o\\z").unwrap();
+    assert!(regex.find(text).is_none());
+    let regex = Regex::new("(?m)s\\z").unwrap();
+    assert_eq!(regex.find(text).unwrap().range(), 11..12);
+}
+
+#[test]
+fn test_shorthand_classes() {
+    let regex = Regex::new("\\d{3}-\\d{4}").unwrap();
+    assert_eq!(
+        regex.find("call 555-1234 now").unwrap().as_str(),
+        "555-1234"
+    );
+    assert!(regex.find("call 55-1234 now").is_none());
+
+    let regex = Regex::new("\\w+").unwrap();
+    let words: Vec<&str> = regex
+        .find_iter("año_2024, ¿qué?")
+        .map(|m| m.as_str())
+        .collect();
+    assert_eq!(words, vec!["año_2024", "qué"]);
+
+    let regex = Regex::new("\\s\\S+\\s").unwrap();
+    assert_eq!(regex.find("uno\tdos tres").unwrap().as_str(), "\tdos ");
+
+    let regex = Regex::new("\\D+").unwrap();
+    assert_eq!(regex.find("12ab3").unwrap().as_str(), "ab");
+
+    let regex = Regex::new("\\W").unwrap();
+    assert_eq!(regex.find("ñandú_x!").unwrap().as_str(), "!");
+
+    let regex = Regex::new("(?i)\\D").unwrap();
+    assert_eq!(regex.find("12K").unwrap().as_str(), "K");
+}
+
+#[test]
+fn test_escape_sequences() {
+    let regex = Regex::new("\\x1b\\[").unwrap();
+    assert_eq!(regex.find("ok \u{1b}[31m").unwrap().range(), 3..5);
+
+    let regex = Regex::new("caf\\u{e9}").unwrap();
+    assert!(regex.find("un café").is_some());
+
+    let regex = Regex::new("a\\tb\\nc").unwrap();
+    assert!(regex.find("a\tb\nc").is_some());
+
+    let regex = Regex::new("\\u{1F600}").unwrap();
+    assert_eq!(regex.find("hi \u{1F600}").unwrap().range(), 3..7);
+}
+
+#[test]
+fn test_escaped_literal_does_not_swallow_next_char() {
+    let regex = Regex::new("a\\.b").unwrap();
+    assert_eq!(regex.test("a.b").unwrap(), "a.b");
+    assert_eq!(regex.test("axb").unwrap(), "");
+    assert_eq!(regex.test("a.").unwrap(), "");
+
+    let regex = Regex::new("\\(\\d+\\)").unwrap();
+    assert_eq!(regex.find("f(12)").unwrap().as_str(), "(12)");
+}
+
+#[test]
+fn test_invalid_escape_sequences() {
+    assert!(Regex::new("\\").is_err());
+    assert!(Regex::new("\\q").is_err());
+    assert!(Regex::new("\\x4").is_err());
+    assert!(Regex::new("\\xzz").is_err());
+    assert!(Regex::new("\\u{110000}").is_err());
+    assert!(Regex::new("\\u{d800}").is_err());
+    assert!(Regex::new("\\u41").is_err());
+    assert!(Regex::new("\\u{}").is_err());
+}
+
+#[test]
+fn test_bracket_ranges_and_mixed_members() {
+    let regex = Regex::new("[a-z0-9_]+").unwrap();
+    assert_eq!(regex.find("Key: my_var2 = 1").unwrap().as_str(), "ey");
+    let found: Vec<&str> = regex.find_iter("my_var2 = 1").map(|m| m.as_str()).collect();
+    assert_eq!(found, vec!["my_var2", "1"]);
+
+    let regex = Regex::new("[[:digit:]abc]+").unwrap();
+    assert_eq!(regex.find("xx12ab3cz").unwrap().as_str(), "12ab3c");
+
+    let regex = Regex::new("[^[:space:],]+").unwrap();
+    let fields: Vec<&str> = regex
+        .find_iter("uno, dos,tres")
+        .map(|m| m.as_str())
+        .collect();
+    assert_eq!(fields, vec!["uno", "dos", "tres"]);
+
+    let regex = Regex::new("[α-ω]+").unwrap();
+    assert_eq!(regex.find("abc λόγος").unwrap().as_str(), "λ");
+}
+
+#[test]
+fn test_bracket_posix_placement_rules() {
+    // `]` al principio y `-` al principio o al final son miembros literales.
+    let regex = Regex::new("[]a]+").unwrap();
+    assert_eq!(regex.find("x]a]y").unwrap().as_str(), "]a]");
+    let regex = Regex::new("[^]a]+").unwrap();
+    assert_eq!(regex.find("]]bc").unwrap().as_str(), "bc");
+    let regex = Regex::new("[-a]+").unwrap();
+    assert_eq!(regex.find("x-a-y").unwrap().as_str(), "-a-");
+    let regex = Regex::new("[a-]+").unwrap();
+    assert_eq!(regex.find("x-a-y").unwrap().as_str(), "-a-");
+
+    // `^` solo niega al principio.
+    let regex = Regex::new("[a^]+").unwrap();
+    assert_eq!(regex.find("x^a^").unwrap().as_str(), "^a^");
+
+    // La barra invertida no es especial dentro de corchetes.
+    let regex = Regex::new("[\\n]+").unwrap();
+    assert_eq!(regex.find("a\\nb").unwrap().as_str(), "\\n");
+
+    let regex = Regex::new("[[.-.][=a=]]+").unwrap();
+    assert_eq!(regex.find("x-a-y").unwrap().as_str(), "-a-");
+}
+
+#[test]
+fn test_shorthand_classes_in_brackets() {
+    // `\d`, `\w` y `\s` son las mismas clases que fuera de los corchetes, no letras.
+    let regex = Regex::new("[\\d.]+").unwrap();
+    assert_eq!(regex.find("d v1.25d").unwrap().as_str(), "1.25");
+    let regex = Regex::new("[\\w-]+").unwrap();
+    assert_eq!(regex.find("  mi-año_2 ").unwrap().as_str(), "mi-año_2");
+    let regex = Regex::new("[^\\s,]+").unwrap();
+    let fields: Vec<&str> = regex
+        .find_iter("uno, dos\ttres")
+        .map(|m| m.as_str())
+        .collect();
+    assert_eq!(fields, vec!["uno", "dos", "tres"]);
+
+    // Las mayusculas agregan el complemento de la clase.
+    let regex = Regex::new("[\\D5]+").unwrap();
+    assert_eq!(regex.find("1234ab5c67").unwrap().as_str(), "ab5c");
+    let regex = Regex::new("[^\\W_]+").unwrap();
+    assert_eq!(regex.find("__ab_c").unwrap().as_str(), "ab");
+    let regex = Regex::new("[\\S\\s]+").unwrap();
+    assert_eq!(regex.find("a b\nc").unwrap().as_str(), "a b\nc");
+    let regex = RegexBuilder::new("[\\Dx]\\{2\\}")
+        .dialect(Dialect::Basic)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("12ab3").unwrap().as_str(), "ab");
+
+    // Una clase no puede ser el extremo de un rango.
+    assert!(Regex::new("[a-\\d]").is_err());
+}
+
+#[test]
+fn test_bracket_invalid_expressions() {
+    assert!(Regex::new("[z-a]").is_err());
+    assert!(Regex::new("[a-[:digit:]]").is_err());
+    assert!(Regex::new("[[:nope:]]").is_err());
+    assert!(Regex::new("[[:alpha:]").is_err());
+    assert!(Regex::new("[abc").is_err());
+    assert!(Regex::new("[]").is_err());
+    assert!(Regex::new("[[.ab.]]").is_err());
+}
+
+#[test]
+fn test_char_set_lookup() {
+    let set = CharSet::new(
+        vec![('m', 'z'), ('a', 'f'), ('e', 'h'), ('\u{4E00}', '\u{9FFF}')],
+        vec![],
+    );
+    assert_eq!(
+        set.ranges(),
+        &[('a', 'h'), ('m', 'z'), ('\u{4E00}', '\u{9FFF}')]
+    );
+    assert!(set.contains('g') && set.contains('z') && set.contains('中'));
+    assert!(!set.contains('i') && !set.contains('A') && !set.contains('ア'));
+
+    let set = CharSet::new(vec![('a', 'c')], vec![Class::Digit]).negate();
+    assert!(set.is_negated());
+    assert!(set.contains('d') && !set.contains('b') && !set.contains('5'));
+    assert!(set.contains_folded('D') && !set.contains_folded('B'));
+
+    let regex = RegexBuilder::new("[a-c]+")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("xAbCd").unwrap().as_str(), "AbC");
+}
+
+#[test]
+fn test_posix_xdigit_and_word_classes() {
+    let regex = Regex::new("[[:xdigit:]]{7,}").unwrap();
+    assert_eq!(
+        regex.find("commit 3f9a2Bc0d by agent").unwrap().as_str(),
+        "3f9a2Bc0d"
+    );
+    assert!(regex.find("commit 3f9g2bc0d").is_none());
+
+    let regex = Regex::new("[[:word:]]+").unwrap();
+    assert_eq!(regex.find("--año_1--").unwrap().as_str(), "año_1");
+}
+
+#[test]
+fn test_posix_blank_cntrl_graph_print_classes() {
+    let regex = Regex::new("[[:blank:]]+").unwrap();
+    assert_eq!(regex.find("a \t\u{A0}b").unwrap().range(), 1..5);
+    assert!(regex.find("a\nb").is_none());
+
+    let regex = Regex::new("[[:cntrl:]]").unwrap();
+    assert_eq!(regex.find("ok\u{1b}[0m").unwrap().range(), 2..3);
+    assert!(regex.find("ok ok").is_none());
+
+    let regex = Regex::new("[[:graph:]]+").unwrap();
+    let found: Vec<&str> = regex
+        .find_iter("¡hola! qué\ttal")
+        .map(|m| m.as_str())
+        .collect();
+    assert_eq!(found, vec!["¡hola!", "qué", "tal"]);
+
+    let regex = Regex::new("[[:print:]]+").unwrap();
+    let found: Vec<&str> = regex
+        .find_iter("¡hola! qué\ttal")
+        .map(|m| m.as_str())
+        .collect();
+    assert_eq!(found, vec!["¡hola! qué", "tal"]);
+}
+
+#[test]
+fn test_posix_classes_on_ascii_and_unicode() {
+    let cases = [
+        (Class::Alnum, "aZ5é", "_ -"),
+        (Class::Alpha, "aZé", "5_ "),
+        (Class::Blank, " \t\u{3000}", "\nx"),
+        (Class::Cntrl, "\n\u{7f}\u{85}", "a "),
+        (Class::Digit, "09", "a٣"),
+        (Class::Graph, "a~é€", " \t\u{7f}"),
+        (Class::Lower, "aé", "AÉ1"),
+        (Class::Print, "a €", "\t\n"),
+        (Class::Punct, "!~_", "a¿"),
+        (Class::Space, " \t\n\u{2028}", "a_"),
+        (Class::Upper, "AÉ", "aé1"),
+        (Class::Word, "a_9é", "- ."),
+        (Class::Xdigit, "09afAF", "gG٣"),
+    ];
+    for (class, members, others) in cases {
+        let val = RegexVal::Class(class);
+        for c in members.chars() {
+            assert_ne!(val.matches(&c.to_string()), 0, "{:?} {:?}", class, c);
+        }
+        for c in others.chars() {
+            assert_eq!(val.matches(&c.to_string()), 0, "{:?} {:?}", class, c);
+        }
+    }
+}
+
+#[test]
+fn test_unicode_general_categories() {
+    let regex = Regex::new("\\p{L}+").unwrap();
+    let words: Vec<&str> = regex
+        .find_iter("año 2024: λόγος, 漢字!")
+        .map(|m| m.as_str())
+        .collect();
+    assert_eq!(words, vec!["año", "λόγος", "漢字"]);
+
+    let regex = Regex::new("\\p{Lu}\\p{Ll}+").unwrap();
+    assert_eq!(regex.find("el Ñandú corre").unwrap().as_str(), "Ñandú");
+
+    let regex = Regex::new("\\pN+").unwrap();
+    assert_eq!(regex.find("x٣٤½y").unwrap().as_str(), "٣٤½");
+
+    let regex = Regex::new("\\P{L}+").unwrap();
+    assert_eq!(regex.find("abc, 12 def").unwrap().as_str(), ", 12 ");
+
+    let regex = Regex::new("\\p{gc=Uppercase_Letter}").unwrap();
+    assert_eq!(regex.find("abCd").unwrap().as_str(), "C");
+    let regex = Regex::new("(?i)\\p{Lu}+").unwrap();
+    assert_eq!(regex.find("-abCd-").unwrap().as_str(), "abCd");
+}
+
+#[test]
+fn test_unicode_scripts_and_emoji() {
+    let regex = Regex::new("\\p{Greek}+").unwrap();
+    assert_eq!(regex.find("alpha α-ω ΑΒΓ").unwrap().as_str(), "α");
+    let found: Vec<&str> = regex
+        .find_iter("alpha α-ω ΑΒΓ")
+        .map(|m| m.as_str())
+        .collect();
+    assert_eq!(found, vec!["α", "ω", "ΑΒΓ"]);
+
+    let regex = Regex::new("\\p{Han}+").unwrap();
+    assert_eq!(regex.find("log: 错误 en línea").unwrap().as_str(), "错误");
+    let regex = Regex::new("\\p{sc=Hani}").unwrap();
+    assert!(regex.find("sin han").is_none());
+
+    let regex = Regex::new("\\p{Emoji}").unwrap();
+    assert_eq!(regex.find("build ok 🎉!").unwrap().as_str(), "🎉");
+
+    let regex = Regex::new("\\P{Latin}+").unwrap();
+    assert_eq!(regex.find("abcд€f").unwrap().as_str(), "д€");
+}
+
+#[test]
+fn test_unicode_properties_in_brackets() {
+    let regex = Regex::new("[\\p{Greek}[:digit:]]+").unwrap();
+    assert_eq!(regex.find("x αβ12γ y").unwrap().as_str(), "αβ12γ");
+
+    let regex = Regex::new("[^\\p{L}[:space:]]+").unwrap();
+    assert_eq!(regex.find("hola, ¿qué?").unwrap().as_str(), ",");
+
+    let regex = Regex::new("[\\P{L}a]+").unwrap();
+    assert_eq!(regex.find("bca1-2b").unwrap().as_str(), "a1-2");
+}
+
+#[test]
+fn test_unicode_invalid_properties() {
+    assert!(Regex::new("\\p{Klingon}").is_err());
+    assert!(Regex::new("\\p{Greek").is_err());
+    assert!(Regex::new("\\p").is_err());
+    assert!(Regex::new("\\p{sc=Lu}").is_err());
+    assert!(Regex::new("[\\p{Nope}]").is_err());
+    assert!(Regex::new("\\p{ greek }").is_ok());
+}
+
+#[test]
+fn test_error_kind_and_span() {
+    let cases = [
+        ("ab)", ErrorKind::UnmatchedCloseParen, 2..3),
+        ("a(b|c", ErrorKind::UnclosedGroup, 1..2),
+        ("x[a-", ErrorKind::UnclosedBracket, 1..4),
+        ("a|*b", ErrorKind::NothingToRepeat, 2..3),
+        ("a{3,1}", ErrorKind::InvalidRepetitionRange, 1..6),
+        ("[z-a]", ErrorKind::InvalidBracketRange, 0..4),
+        ("[[:foo:]]", ErrorKind::UnknownClass, 0..8),
+        ("a\\q", ErrorKind::UnknownEscape, 1..3),
+        ("\\p{Klingon}", ErrorKind::UnknownProperty, 0..11),
+        ("(?<n>a)(?<n>b)", ErrorKind::DuplicateGroupName, 7..12),
+        ("(a)\\2", ErrorKind::InvalidBackreference, 3..5),
+        ("a\\k<x>", ErrorKind::UnknownGroupName, 1..6),
+    ];
+    for (pattern, kind, span) in cases {
+        let err = Regex::new(pattern).err().unwrap();
+        assert_eq!(err.kind(), Some(kind), "{}", pattern);
+        assert_eq!(err.span(), Some(span.clone()), "{}", pattern);
+        assert_eq!(err.offset(), Some(span.start), "{}", pattern);
+    }
+}
+
+#[test]
+fn test_error_display_underlines_span() {
+    let err = Regex::new("[ñ]u\\q").err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "unknown escape sequence at byte 5\n    [ñ]u\\q\n        ^^"
+    );
+
+    // Un grupo sin cerrar al final del patron se marca en su `(`.
+    let err = Regex::new("ab(").err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "no closing parenthesis found at byte 2\n    ab(\n      ^"
+    );
+
+    let err: Box<dyn std::error::Error> = Box::new(err);
+    assert!(err.to_string().starts_with("no closing parenthesis"));
+}
+
+#[test]
+fn test_error_for_automaton_engine_points_at_construct() {
+    let err = RegexBuilder::new("(a)b\\1")
+        .engine(EngineKind::Automaton)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::UnsupportedBackreference));
+    assert_eq!(err.span(), Some(4..6));
+
+    let err = RegexBuilder::new("x(?<=a+)b")
+        .engine(EngineKind::Automaton)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::UnsupportedLookbehind));
+    assert_eq!(err.span(), Some(1..8));
+}
+
+#[test]
+fn test_syntax_round_trip() {
+    let patterns = [
+        "",
+        "a|",
+        "ab|c(d|e)*f",
+        "(?i)ab(?-i:cd)e",
+        "(?m-i)^a$",
+        "(?-)a",
+        "a*?b+?c??d{2}e{2,}?f{,3}g{1,4}",
+        "a{2}{0,1}(a*){0,1}?b**",
+        "\\d\\W\\s\\p{Greek}\\PL\\p{ sc = latin }",
+        "[]a-c-][^^][a[:digit:]\\p{Lu}][[.-.]-z][\\]",
+        "\\x41\\u{e9}\\t\\n\\.\\[\\{\\}\\\\",
+        "(?P<year>\\d{4})-(?<month>\\d\\d)\\k<year>\\1",
+        "(?=a)(?!b)(?<=c)(?<!d+)",
+        "\\bword\\B\\<\\>\\A\\z",
+        "x{a|.",
+        "[^\\s\\D_]",
+        "Error 404: foo_bar=/@~ñ}",
+    ];
+    for pattern in patterns {
+        let ast = parse(pattern).unwrap();
+        let printed = ast.to_string();
+        let reparsed = parse(&printed).unwrap();
+        assert_eq!(reparsed, ast, "{} printed as {}", pattern, printed);
+        assert_eq!(reparsed.to_string(), printed);
+    }
+}
+
+#[test]
+fn test_syntax_canonical_printing() {
+    let cases = [
+        ("a{1,}b{0,1}c{0,}", "a+b?c{0,}"),
+        ("(?P<n>x)(?im-:y)", "(?<n>x)(?im:y)"),
+        ("[]^-]", "[[.].][.^.][.-.]]"),
+        ("\\pL\\x61", "\\p{L}a"),
+        ("[\\d\\W]", "[[:digit:]\\W]"),
+    ];
+    for (pattern, canonical) in cases {
+        assert_eq!(parse(pattern).unwrap().to_string(), canonical);
+    }
+}
+
+#[test]
+fn test_syntax_spans() {
+    let ast = parse("ab(c|d)*").unwrap();
+    assert_eq!(ast.span, 0..8);
+    let AstKind::Concat(items) = &ast.kind else {
+        panic!("expected a concatenation");
+    };
+    let spans: Vec<_> = items.iter().map(|item| item.span.clone()).collect();
+    assert_eq!(spans, vec![0..1, 1..2, 2..8]);
+    let AstKind::Repetition { ast: group, .. } = &items[2].kind else {
+        panic!("expected a repetition");
+    };
+    assert_eq!(group.span, 2..7);
+
+    // Los arboles se comparan por estructura, no por posiciones.
+    assert_eq!(parse("\\x61").unwrap(), parse("a").unwrap());
+    assert_ne!(parse("a").unwrap(), parse("b").unwrap());
+}
+
+#[test]
+fn test_syntax_visitors() {
+    struct Counter {
+        groups: usize,
+        depth: usize,
+        max_depth: usize,
+    }
+
+    impl Visitor for Counter {
+        fn visit_pre(&mut self, ast: &Ast) {
+            if let AstKind::Group { .. } = ast.kind {
+                self.groups += 1;
+                self.depth += 1;
+                self.max_depth = self.max_depth.max(self.depth);
+            }
+        }
+
+        fn visit_post(&mut self, ast: &Ast) {
+            if let AstKind::Group { .. } = ast.kind {
+                self.depth -= 1;
+            }
+        }
+    }
+
+    let mut counter = Counter {
+        groups: 0,
+        depth: 0,
+        max_depth: 0,
+    };
+    parse("(a(b)|(?:c(d(e))))").unwrap().visit(&mut counter);
+    assert_eq!((counter.groups, counter.max_depth), (5, 4));
+
+    struct Lazify;
+
+    impl VisitorMut for Lazify {
+        fn visit_pre(&mut self, ast: &mut Ast) {
+            if let AstKind::Repetition { rep, .. } = &mut ast.kind {
+                *rep = rep.clone().lazy();
+            }
+        }
+    }
+
+    let mut ast = parse("a*(b+)c").unwrap();
+    ast.visit_mut(&mut Lazify);
+    assert_eq!(ast.to_string(), "a*?(b+?)c");
+    let regex = Regex::new(&ast.to_string()).unwrap();
+    assert_eq!(regex.find("aabbbc").unwrap().as_str(), "aabbbc");
+}
+
+#[test]
+fn test_syntax_errors_match_regex_errors() {
+    for pattern in ["a)", "(a", "[a", "*a", "\\q", "(?<x>a)(?<x>b)", "\\2(a)"] {
+        assert_eq!(
+            parse(pattern).err().unwrap(),
+            Regex::new(pattern).err().unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_every_non_special_character_is_literal() {
+    let cases = [
+        ("Error", "log: Error en linea"),
+        ("404", "GET /index 404"),
+        ("foo_bar", "x = foo_bar;"),
+        ("a=b", "si a=b"),
+        ("/usr/@home", "ruta /usr/@home"),
+        ("año ñandú", "el año ñandú"),
+        ("a}", "{a}"),
+        ("#%&'<>;`~", "#%&'<>;`~"),
+    ];
+    for (pattern, text) in cases {
+        let regex = Regex::new(pattern).unwrap();
+        assert_eq!(regex.find(text).unwrap().as_str(), pattern);
+    }
+    let regex = Regex::new("[A-Z][a-z]+ [0-9]{3}").unwrap();
+    assert_eq!(
+        regex.test("status: Error 500").unwrap(),
+        "status: Error 500"
+    );
+}
+
+fn basic(pattern: &str) -> Regex {
+    RegexBuilder::new(pattern)
+        .dialect(Dialect::Basic)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_basic_dialect_operators() {
+    assert_eq!(
+        basic("\\(ab\\)\\{2\\}").find("xababab").unwrap().as_str(),
+        "abab"
+    );
+    assert_eq!(basic("a\\{1,\\}b").find("caaab").unwrap().as_str(), "aaab");
+    assert_eq!(basic("a\\+b\\?").find("caaab").unwrap().as_str(), "aaab");
+    assert_eq!(
+        basic("gris\\|gato").find("un gato").unwrap().as_str(),
+        "gato"
+    );
+    assert_eq!(basic("\\(a.\\)\\1").find("xabab").unwrap().as_str(), "abab");
+    // Sin barra invertida, los operadores de ERE son literales.
+    let regex = basic("a+b?(c|d){2}");
+    assert_eq!(
+        regex.find("xa+b?(c|d){2}").unwrap().as_str(),
+        "a+b?(c|d){2}"
+    );
+    assert!(regex.find("aab").is_none());
+}
+
+#[test]
+fn test_basic_dialect_context_dependent_characters() {
+    assert_eq!(basic("*a").find("x*a").unwrap().as_str(), "*a");
+    assert_eq!(basic("^*a").find("*a").unwrap().as_str(), "*a");
+    assert_eq!(basic("\\(*a\\)").find("x*a").unwrap().as_str(), "*a");
+    assert_eq!(basic("a^b$c").find("xa^b$c").unwrap().as_str(), "a^b$c");
+    assert!(basic("^ab$").find("xab").is_none());
+    assert_eq!(basic("\\(^a\\)b$").find("ab").unwrap().as_str(), "ab");
+    assert_eq!(basic("a\\}").find("a}").unwrap().as_str(), "a}");
+}
+
+#[test]
+fn test_basic_dialect_errors() {
+    let error = |pattern| {
+        RegexBuilder::new(pattern)
+            .dialect(Dialect::Basic)
+            .build()
+            .err()
+            .unwrap()
+    };
+    let err = error("ab\\{x\\}");
+    assert_eq!(err.kind(), Some(ErrorKind::InvalidRepetition));
+    assert_eq!(err.offset(), Some(2));
+    let err = error("a\\(b");
+    assert_eq!(err.kind(), Some(ErrorKind::UnclosedGroup));
+    assert_eq!(err.span(), Some(1..3));
+    assert_eq!(
+        error("a\\{3,1\\}").kind(),
+        Some(ErrorKind::InvalidRepetitionRange)
+    );
+    assert_eq!(error("a\\)").kind(), Some(ErrorKind::UnmatchedCloseParen));
+    assert!(basic("\\(a\\))").find("a)").is_some());
+}
+
+#[test]
+fn test_basic_and_extended_dialects_share_the_tree() {
+    let cases = [
+        ("\\(a\\)\\{2,3\\}x\\|y*", "(a){2,3}x|y*"),
+        ("a+b?\\+\\?", "a\\+b\\?+{0,1}"),
+        ("(x|y)", "\\(x\\|y\\)"),
+        ("\\(a\\)\\1", "(a)\\1"),
+    ];
+    for (basic, extended) in cases {
+        assert_eq!(
+            parse_with(basic, Dialect::Basic).unwrap(),
+            parse_with(extended, Dialect::Extended).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_fixed_string_takes_every_character_literally() {
+    let needle = FixedString::new("a.b[1]\\d*");
+    let found = needle.find("xx a.b[1]\\d* yy").unwrap();
+    assert_eq!((found.start(), found.as_str()), (3, "a.b[1]\\d*"));
+    assert!(!needle.is_match("acb1d"));
+
+    let ip = FixedString::new("10.0.0.1");
+    let spans: Vec<_> = ip
+        .find_iter("10.0.0.10 10.0.0.1 100.0.0.1")
+        .map(|found| found.range())
+        .collect();
+    assert_eq!(spans, vec![0..8, 10..18]);
+    assert_eq!(FixedString::new("aa").find_iter("aaaaa").count(), 2);
+    assert_eq!(FixedString::new("ñú").find("añúñú").unwrap().range(), 1..5);
+    assert_eq!(FixedString::new("a").find_at("aba", 1).unwrap().start(), 2);
+    assert!(FixedString::new("abc").find("ab").is_none());
+}
+
+#[test]
+fn test_fixed_string_case_insensitive() {
+    let needle = FixedString::new("Ñandú K").case_insensitive(true);
+    assert_eq!(
+        needle.find("un ÑANDÚ \u{212A}").unwrap().as_str(),
+        "ÑANDÚ \u{212A}"
+    );
+    assert!(!FixedString::new("Ñandú").is_match("ÑANDÚ"));
+    assert!(FixedString::new("x.Y")
+        .case_insensitive(true)
+        .is_match("X.y"));
+}
+
+#[test]
+fn test_fixed_string_agrees_with_regex_through_matcher() {
+    fn spans(matcher: &impl Matcher, haystack: &str) -> Vec<(usize, usize)> {
+        matcher
+            .find_iter(haystack)
+            .map(|found| (found.start(), found.end()))
+            .collect()
+    }
+
+    let text = std::fs::read_to_string("words.txt").unwrap();
+    for needle in ["the", "ing", "qu", "zz", "a", ""] {
+        let fixed = FixedString::new(needle);
+        let regex = Regex::new(needle).unwrap();
+        assert_eq!(spans(&fixed, &text), spans(&regex, &text), "{}", needle);
+        assert_eq!(fixed.is_match(&text), Matcher::is_match(&regex, &text));
+    }
+}
+
+#[test]
+fn test_required_literals_extraction() {
+    let cases = [
+        (
+            "ERROR.*timeout",
+            Some("ERROR"),
+            Some("timeout"),
+            Some("timeout"),
+        ),
+        ("[[:digit:]]+ms", None, Some("ms"), Some("ms")),
+        ("a(bc){2}d", Some("abcbcd"), Some("abcbcd"), Some("abcbcd")),
+        ("x+yz", Some("x"), Some("yz"), Some("yz")),
+        ("^\\bfoo(?=bar)$", Some("foo"), Some("foo"), Some("foo")),
+        ("(a)\\1b", Some("a"), Some("b"), Some("a")),
+        ("(?i)error", None, None, None),
+        ("warn|error", None, None, None),
+        ("a*", None, None, None),
+    ];
+    for (pattern, prefix, suffix, required) in cases {
+        let regex = Regex::new(pattern).unwrap();
+        let literals = regex.required_literals();
+        assert_eq!(
+            (literals.prefix(), literals.suffix(), literals.required()),
+            (prefix, suffix, required),
+            "{}",
+            pattern
+        );
+    }
+    let long = Regex::new("a{50000}").unwrap();
+    assert!(long.required_literals().required().unwrap().len() < 1000);
+}
+
+#[test]
+fn test_prefilter_does_not_change_matches() {
+    // Cada par describe el mismo lenguaje, pero los corchetes impiden extraer literales.
+    let pairs = [
+        ("th[a-z]*ing", "[t][h][a-z]*[i][n][g]"),
+        ("(qu)+e", "([q][u])+[e]"),
+        ("\\bst.{2}ed\\b", "\\b[s][t].{2}[e][d]\\b"),
+        ("(?<=r)ing", "(?<=r)[i][n][g]"),
+        ("x{2}", "[x]{2}"),
+    ];
+    let text = std::fs::read_to_string("words.txt").unwrap();
+    for (literal, bracketed) in pairs {
+        let literal = Regex::new(literal).unwrap();
+        let bracketed = Regex::new(bracketed).unwrap();
+        assert!(literal.required_literals().required().is_some());
+        assert!(bracketed.required_literals().required().is_none());
+        let spans = |regex: &Regex| -> Vec<_> {
+            regex.find_iter(&text).map(|found| found.range()).collect()
+        };
+        assert_eq!(spans(&literal), spans(&bracketed));
+        for line in text.lines().take(2000) {
+            assert_eq!(literal.test(line), bracketed.test(line));
+        }
+    }
+}
+
+#[test]
+fn test_aho_corasick_match_semantics() {
+    let first = AhoCorasick::new(["sam", "samwise"]);
+    assert_eq!(first.find("samwise").unwrap().as_str(), "sam");
+    let first = AhoCorasick::new(["samwise", "sam"]);
+    assert_eq!(first.find("samwise").unwrap().as_str(), "samwise");
+    let longest = AhoCorasickBuilder::new()
+        .leftmost_longest(true)
+        .build(["sam", "samwise"]);
+    assert_eq!(longest.find("samwise").unwrap().as_str(), "samwise");
+
+    // Gana el que empieza antes aunque termine despues de otro.
+    let words = AhoCorasick::new(["bc", "abcd", "c"]);
+    let found: Vec<_> = words.find_iter("abcdbc").map(|m| m.as_str()).collect();
+    assert_eq!(found, vec!["abcd", "bc"]);
+    assert_eq!(words.find_pattern_at("abcdbc", 1).unwrap().0, 0);
+    assert_eq!(words.patterns_len(), 3);
+
+    let unicode = AhoCorasickBuilder::new()
+        .case_insensitive(true)
+        .build(["ñandú", "straße"]);
+    let found: Vec<_> = unicode
+        .find_iter("Un ÑANDÚ en la STRAẞE")
+        .map(|m| m.range())
+        .collect();
+    assert_eq!(found, vec![3..10, 17..25]);
+    assert!(AhoCorasick::new(["ñandú"]).find("ÑANDÚ").is_none());
+
+    let with_empty = AhoCorasick::new(["", "a"]);
+    assert_eq!(with_empty.find("a").unwrap().range(), 0..0);
+    assert!(AhoCorasick::new(Vec::<String>::new()).find("abc").is_none());
+}
+
+#[test]
+fn test_regex_literal_alternation_matches_pike_vm() {
+    let text = std::fs::read_to_string("beemovie.txt").unwrap();
+    let mut words: Vec<&str> = text
+        .split(|c: char| !c.is_ascii_lowercase())
+        .filter(|word| word.len() > 2)
+        .collect();
+    words.sort();
+    words.dedup();
+    words.truncate(300);
+
+    let literal = Regex::new(&words.join("|")).unwrap();
+    // Los grupos hacen que la alternativa ya no sea de literales y la resuelva la Pike VM.
+    let grouped: Vec<_> = words.iter().map(|word| format!("(?:{})", word)).collect();
+    let grouped = Regex::new(&grouped.join("|")).unwrap();
+    for line in text.lines().take(500) {
+        let spans = |regex: &Regex| -> Vec<_> {
+            regex.find_iter(line).map(|found| found.range()).collect()
+        };
+        assert_eq!(spans(&literal), spans(&grouped), "{}", line);
+    }
+
+    let insensitive = Regex::new("(?i)cat|dog|").unwrap();
+    assert_eq!(insensitive.find("a DoG").unwrap().range(), 0..0);
+    assert_eq!(insensitive.find_at("a DoG", 1).unwrap().range(), 1..1);
+    assert_eq!(insensitive.find_at("a DoG", 2).unwrap().as_str(), "DoG");
+    assert_eq!(insensitive.test("xyz").unwrap(), "xyz");
+    let pets = Regex::new("cat|dog").unwrap();
+    assert_eq!(pets.test("hotdog").unwrap(), "hotdog");
+    assert_eq!(pets.test("ca t").unwrap(), "");
+    assert_eq!(
+        pets.captures("a cat").unwrap().get(0).unwrap().as_str(),
+        "cat"
+    );
+}
+
+#[test]
+fn test_regex_builder_new_many() {
+    let words = ["cat", "dog", "bird"];
+    let many = RegexBuilder::new_many(words).build().unwrap();
+    let joined = Regex::new(&words.join("|")).unwrap();
+    for line in ["hotdog", "a bird and a cat", "none"] {
+        let spans = |regex: &Regex| -> Vec<_> {
+            regex.find_iter(line).map(|found| found.range()).collect()
+        };
+        assert_eq!(spans(&many), spans(&joined), "{}", line);
+    }
+
+    // Los flags y los grupos de un patron no afectan al siguiente.
+    let regex = RegexBuilder::new_many(["(?i)a", "b"]).build().unwrap();
+    assert!(regex.is_match("A"));
+    assert!(!regex.is_match("B"));
+    let regex = RegexBuilder::new_many(["(x)(y)", "(?P<z>z)\\1"])
+        .build()
+        .unwrap();
+    assert_eq!(regex.captures_len(), 4);
+    let caps = regex.captures("zz").unwrap();
+    assert_eq!(caps.get(3).unwrap().as_str(), "z");
+    assert_eq!(caps.index_of("z"), Some(3));
+    let regex = RegexBuilder::new_many(["a\\{2\\}", "b+"])
+        .dialect(Dialect::Basic)
+        .build()
+        .unwrap();
+    assert!(regex.is_match("aa"));
+    assert!(regex.is_match("b+"));
+    assert!(!regex.is_match("bb"));
+
+    let err = RegexBuilder::new_many(["a", "b[", "c("])
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::UnclosedBracket));
+    assert_eq!(err.pattern(), Some("b["));
+    assert_eq!(err.span(), Some(1..2));
+    let err = RegexBuilder::new_many(["(?<n>a)", "x", "b(?P<n>c)"])
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::DuplicateGroupName));
+    assert_eq!(err.pattern(), Some("b(?P<n>c)"));
+    assert_eq!(err.span(), Some(1..7));
+    assert!(RegexBuilder::new_many(["(?<n>a)", "(?<m>b)"])
+        .build()
+        .is_ok());
+    let err = RegexBuilder::new_many(["a{10}", "b{10}"])
+        .size_limit(3_000)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::PatternTooLarge));
+    assert_eq!(err.pattern(), Some("b{10}"));
+
+    let none = RegexBuilder::new_many(Vec::<&str>::new()).build().unwrap();
+    assert!(!none.is_match(""));
+    assert!(!none.is_match("abc"));
+}
+
+#[test]
+fn test_regex_set_agrees_with_each_regex() {
+    let patterns = [
+        "bee",
+        "(?i)barry",
+        "^[A-Z]+:",
+        "\\bhoney\\b",
+        "[0-9]+",
+        "ing$",
+        "yo(?=u)",
+        "(?<=th)e",
+        "a.*?e",
+        "x|yz|",
+        "zzzz",
+        "b(e+?)|be",
+        "(?P<word>[a-z]+)e+",
+        "(o)(?P<u>u)?",
+        "e*",
+        "y(ou)+",
+    ];
+    let set = RegexSet::new(patterns).unwrap();
+    assert_eq!(set.len(), patterns.len());
+    let regexes: Vec<_> = patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).unwrap())
+        .collect();
+    let text = std::fs::read_to_string("beemovie.txt").unwrap();
+    for line in text.lines().take(300) {
+        let expected: Vec<_> = regexes.iter().map(|regex| regex.find(line)).collect();
+        assert_eq!(set.first_matches(line), expected, "{}", line);
+        let matched: Vec<_> = (0..patterns.len())
+            .filter(|&i| expected[i].is_some())
+            .collect();
+        assert_eq!(set.matches(line).iter().collect::<Vec<_>>(), matched);
+    }
+}
+
+#[test]
+fn test_regex_set_leftmost_start_and_errors() {
+    // `y` termina antes, pero el match mas a la izquierda es `xyz`.
+    let set = RegexSet::new(["xyz|y", "q"]).unwrap();
+    let found = set.first_matches("axyz");
+    assert_eq!(found[0].unwrap().range(), 1..4);
+    assert!(found[1].is_none());
+    assert!(set.is_match("y"));
+    assert!(!set.is_match("abc"));
+
+    let empty = RegexSet::new(Vec::<&str>::new()).unwrap();
+    assert!(empty.is_empty());
+    assert!(!empty.matches("abc").matched_any());
+
+    // Los patrones numeran sus grupos por separado: el grupo 1 de uno no es el del otro.
+    let nodes: Vec<_> = ["(?P<a>x)", "(?P<b>y)(z)"]
+        .iter()
+        .map(|pattern| Parser::new(pattern).parse().unwrap())
+        .collect();
+    let program = Program::compile_set(&nodes);
+    assert_eq!(program.group_names.as_ref(), &[None, None, None]);
+    assert_eq!(program.slot_count(), 6);
+
+    let err = RegexSet::new(["a", "(b)\\1"]).err().unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::UnsupportedBackreference));
+    let err = RegexSet::new(["a", "b[c"]).err().unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::UnclosedBracket));
+}
+
+#[test]
+fn test_regex_set_builder_options() {
+    let patterns = ["(\\w)\\1", "(?<=\\w+)ee", "[0-9]+", "be+"];
+    let err = RegexSet::new(patterns).err().unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::UnsupportedBackreference));
+
+    // Los patrones que necesitan backtracking se buscan aparte y dan el mismo resultado.
+    let regexes: Vec<_> = patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).unwrap())
+        .collect();
+    for engine in [EngineKind::Auto, EngineKind::Backtracking] {
+        let set = RegexSetBuilder::new(patterns)
+            .engine(engine)
+            .build()
+            .unwrap();
+        for line in ["bee 42", "seems good", "nothing", "a 7"] {
+            let expected: Vec<_> = regexes.iter().map(|regex| regex.find(line)).collect();
+            assert_eq!(set.first_matches(line), expected, "{}", line);
+            assert_eq!(set.try_first_matches(line).unwrap(), expected);
+            let matched: Vec<_> = (0..patterns.len())
+                .filter(|&i| expected[i].is_some())
+                .collect();
+            assert_eq!(set.matches(line).iter().collect::<Vec<_>>(), matched);
+        }
+    }
+
+    let set = RegexSetBuilder::new(["(a|a)*\\1b", "c"])
+        .engine(EngineKind::Auto)
+        .backtrack_limit(10_000)
+        .build()
+        .unwrap();
+    let line = format!("{}cb", "a".repeat(40));
+    assert_eq!(
+        set.try_matches(&line).err(),
+        Some(RegexError::BacktrackLimitExceeded)
+    );
+    assert_eq!(
+        set.try_first_matches(&line).err(),
+        Some(RegexError::BacktrackLimitExceeded)
+    );
+    assert_eq!(set.matches(&line).iter().collect::<Vec<_>>(), vec![1]);
+    assert!(set.first_matches(&line)[0].is_none());
+
+    let err = RegexSetBuilder::new(["a", "b{100}"])
+        .size_limit(1_000)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::PatternTooLarge));
+    let err = RegexSetBuilder::new(["((a))"])
+        .nest_limit(1)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::NestingTooDeep));
+    let set = RegexSetBuilder::new(["abc", "a\\{2\\}"])
+        .case_insensitive(true)
+        .dialect(Dialect::Basic)
+        .build()
+        .unwrap();
+    assert_eq!(set.matches("xAAbd").iter().collect::<Vec<_>>(), vec![1]);
+    assert_eq!(set.matches("ABC").iter().collect::<Vec<_>>(), vec![0]);
+
+    let err = Regex::from_steps(&[RegexStep {
+        rep: RegexRep::Exact(1_000_000),
+        val: RegexVal::Wildcard,
+    }])
+    .err()
+    .unwrap();
+    assert_eq!(err.kind(), Some(ErrorKind::PatternTooLarge));
+}
+
+#[test]
+fn test_regex_is_shared_between_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Regex>();
+    assert_send_sync::<RegexSet>();
+
+    let regex = std::sync::Arc::new(Regex::new("[A-Z][a-z]+ [0-9]{3}").unwrap());
+    let text = std::fs::read_to_string("beemovie.txt").unwrap();
+    let lines: Vec<String> = text.lines().take(400).map(String::from).collect();
+    let expected: Vec<_> = lines.iter().map(|line| regex.test(line).unwrap()).collect();
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let regex = std::sync::Arc::clone(&regex);
+            let lines = lines.clone();
+            std::thread::spawn(move || {
+                lines
+                    .iter()
+                    .map(|line| regex.test(line).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), expected);
+    }
+    // Cada hilo devuelve su cache al pool al terminar la busqueda.
+    assert!(regex.dfa_stats().states_built > 0);
+}